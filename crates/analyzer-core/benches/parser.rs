@@ -81,5 +81,31 @@ pub fn criterion_benchmark(c: &mut Criterion) {
 		});
 	});
 
+	group.bench_function("parsing without memoizing terminals", |b| {
+		b.iter(|| {
+			let mut matcher = Parser::from_rules(&rules).unwrap()(black_box(&input).chars().collect::<Vec<_>>().into());
+			for terminal in ["plus", "minus", "n0", "n1", "n2", "n3", "n4", "n5", "n6", "n7", "n8", "n9"] {
+				matcher.set_memoized(terminal, false).unwrap();
+			}
+			matcher._match().unwrap()
+		});
+	});
+
+	group.bench_function("reparsing after an edit", |b| {
+		let tokens: Vec<_> = input.chars().collect();
+		let mut matcher = Parser::from_rules(&rules).unwrap()(tokens.clone().into());
+		matcher._match().unwrap();
+
+		let mid = tokens.len() / 2;
+		let mut digit = '0';
+		b.iter(|| {
+			digit = if digit == '0' { '1' } else { '0' };
+			matcher.apply_edit(mid..mid, &[digit]);
+			let cst = matcher._match().unwrap();
+			matcher.apply_edit(mid..mid + 1, &[]);
+			cst
+		});
+	});
+
 	group.finish()
 }
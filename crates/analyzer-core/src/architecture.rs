@@ -0,0 +1,382 @@
+//! The architecture a program is written against.
+//!
+//! An architecture is described by a header like `v1model.p4`, whose `package` declaration fixes the signatures of
+//! the parsers and controls a program provides. Nothing about a particular architecture is built in beyond the names
+//! of the well-known headers: [`Architecture::new`] checks the `main` instantiation against whichever package it
+//! instantiates, so that another architecture only needs its header to be included.
+
+use std::{collections::HashMap, ops::Range};
+
+use crate::{
+	ast::{self, Direction},
+	symbols::{Namespace, SymbolId, SymbolKind, SymbolTable},
+	syntax::AstNode,
+	types::{Nominal, Type, TypeInfo},
+};
+
+/// The well-known architectures, by the file name of their header.
+const KNOWN: &[(&str, &str)] =
+	&[("v1model.p4", "v1model"), ("psa.p4", "PSA"), ("pna.p4", "PNA"), ("tna.p4", "TNA"), ("t2na.p4", "TNA")];
+
+/// The target of a program, named after the header of its architecture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+	pub name: String,
+	/// The path of the header, as included.
+	pub header: String,
+}
+
+impl Target {
+	/// The target described by an architecture header, e.g. `PSA` for `psa.p4`, or `my_arch` for `my_arch.p4`.
+	pub fn from_header(path: &str) -> Self {
+		let file = path.rsplit(['/', '\\']).next().unwrap_or(path);
+		let name = match KNOWN.iter().find(|(header, _)| *header == file) {
+			Some((_, name)) => name.to_string(),
+			None => file.strip_suffix(".p4").unwrap_or(file).to_string(),
+		};
+		Target { name, header: path.to_string() }
+	}
+
+	/// The target of the first well-known architecture header among the files a program includes.
+	pub fn detect<'a>(includes: impl IntoIterator<Item = &'a str>) -> Option<Self> {
+		includes
+			.into_iter()
+			.find(|path| KNOWN.iter().any(|(header, _)| path.ends_with(header)))
+			.map(Self::from_header)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Architecture {
+	/// The target, if it is known from the headers the program includes, see [`Target`].
+	pub target: Option<Target>,
+	/// The `main` instance.
+	pub main: SymbolId,
+	/// The package `main` is an instance of.
+	pub package: SymbolId,
+	/// The type arguments of the package, given or inferred from the blocks passed to it, in the order of its type
+	/// parameters. The ones that can't be inferred are [`Type::Unknown`].
+	pub type_arguments: Vec<Type>,
+	/// The constructor parameters of the package with the parser, control or other instance passed for each.
+	pub blocks: Vec<(SymbolId, Option<SymbolId>)>,
+	errors: Vec<(Range<usize>, String)>,
+}
+
+impl Architecture {
+	/// Checks the `main` instantiation of a program against the package it instantiates, `None` if there is no
+	/// `main` (e.g. in a header) or it is not an instance of a package.
+	pub fn new(program: &ast::Program, table: &SymbolTable, types: &TypeInfo) -> Option<Self> {
+		let main = table
+			.lookup(SymbolTable::ROOT, "main", Namespace::Value)
+			.filter(|&main| table.symbol(main).kind == SymbolKind::Instance)?;
+		let instance = ast::Instantiation::cast(table.symbol(main).decl.to_node(program.syntax())?)?;
+		let mut checker =
+			Checker { root: program.syntax().clone(), table, types, bindings: HashMap::new(), errors: vec![] };
+
+		let package = match types.symbol_type(main)? {
+			Type::Package(package) => package.clone(),
+			ty if ty.is_unknown() => return None,
+			ty => {
+				let range = table.symbol(main).name_range.clone();
+				checker.errors.push((range, format!("`main` must be an instance of a package, found `{ty}`")));
+				return Some(Architecture {
+					target: None,
+					main,
+					package: main,
+					type_arguments: vec![],
+					blocks: vec![],
+					errors: checker.errors,
+				});
+			}
+		};
+		let decl = checker.node::<ast::PackageDecl>(package.symbol)?;
+
+		let type_parameters = checker.type_parameters(decl.type_parameters());
+		if !package.args.is_empty() {
+			if package.args.len() != type_parameters.len() {
+				let range = instance.ty().map_or(instance.text_range(), |ty| ty.text_range());
+				let message =
+					format!("Expected {} type arguments, found {}", type_parameters.len(), package.args.len());
+				checker.errors.push((range, message));
+			}
+			checker.bindings.extend(type_parameters.iter().copied().zip(package.args.iter().cloned()));
+		}
+
+		let params = decl.parameters();
+		let args = instance.arguments();
+		let optional = |param: &ast::Parameter| {
+			param.default_value().is_some()
+				|| param.annotations().any(|a| a.name().map_or(false, |name| name.text() == "optional"))
+		};
+		let required = params.iter().filter(|param| !optional(param)).count();
+		if args.len() < required || args.len() > params.len() {
+			let expected =
+				if required == params.len() { required.to_string() } else { format!("{required} to {}", params.len()) };
+			checker
+				.errors
+				.push((instance.text_range(), format!("Expected {expected} arguments, found {}", args.len())));
+		}
+
+		let mut blocks = vec![];
+		for (i, param) in params.iter().enumerate() {
+			let Some(symbol) = param.name().and_then(|name| table.declaration_at(name.text_range().start)) else {
+				continue;
+			};
+			let name = param.name().map(|name| name.text());
+			let arg = args.iter().find(|arg| arg.name().map_or(false, |arg| Some(arg.text()) == name));
+			let arg = arg.or_else(|| args.get(i).filter(|arg| arg.name().is_none())).and_then(|arg| arg.value());
+			let block = arg.as_ref().and_then(|arg| checker.argument(symbol, arg));
+			blocks.push((symbol, block));
+		}
+
+		let type_arguments = type_parameters
+			.iter()
+			.map(|parameter| checker.bindings.get(parameter).cloned().unwrap_or(Type::Unknown))
+			.collect();
+		Some(Architecture {
+			target: None,
+			main,
+			package: package.symbol,
+			type_arguments,
+			blocks,
+			errors: checker.errors,
+		})
+	}
+
+	/// The mismatches between the `main` instantiation and the package, or between the blocks passed to it and the
+	/// types of the parameters they are passed for.
+	pub fn errors(&self) -> &[(Range<usize>, String)] { &self.errors }
+}
+
+struct Checker<'a> {
+	root: ast::SyntaxNode,
+	table: &'a SymbolTable,
+	types: &'a TypeInfo,
+	/// The types bound to the type parameters of the package, and of the parser and control types of its parameters.
+	bindings: HashMap<SymbolId, Type>,
+	errors: Vec<(Range<usize>, String)>,
+}
+
+impl Checker<'_> {
+	fn node<N: AstNode<crate::lexer::Token>>(&self, symbol: SymbolId) -> Option<N> {
+		N::cast(self.table.symbol(symbol).decl.to_node(&self.root)?)
+	}
+
+	fn type_parameters(&self, parameters: Option<ast::TypeParameters>) -> Vec<SymbolId> {
+		let names = parameters.map(|parameters| parameters.names().collect::<Vec<_>>()).unwrap_or_default();
+		names.iter().filter_map(|name| self.table.declaration_at(name.text_range().start)).collect()
+	}
+
+	/// Checks the argument passed for a parameter of the package, and returns the parser or control it instantiates.
+	fn argument(&mut self, param: SymbolId, arg: &ast::Expr) -> Option<SymbolId> {
+		let expected = self.types.symbol_type(param).cloned().unwrap_or(Type::Unknown);
+		let found = self.types.type_of(arg.syntax()).cloned().unwrap_or(Type::Unknown);
+		match (&expected, &found) {
+			(Type::Parser(expected), Type::Parser(block)) | (Type::Control(expected), Type::Control(block)) => {
+				self.signature(expected, block.symbol);
+				Some(block.symbol)
+			}
+			_ if expected.is_unknown() || found.is_unknown() => None,
+			_ => {
+				let expected = expected.substitute(&self.bindings);
+				if expected != found {
+					self.errors
+						.push((arg.text_range(), format!("Type mismatch: expected `{expected}`, found `{found}`")));
+				}
+				found.nominal().map(|nominal| nominal.symbol)
+			}
+		}
+	}
+
+	/// Checks the parameters of a parser or control against those of the parser or control type it is passed as.
+	fn signature(&mut self, expected: &Nominal, block: SymbolId) {
+		let (type_parameters, expected_params) = match self.table.symbol(expected.symbol).kind {
+			SymbolKind::ParserType => {
+				let Some(decl) = self.node::<ast::ParserTypeDecl>(expected.symbol) else { return };
+				(decl.type_parameters(), decl.parameters())
+			}
+			SymbolKind::ControlType => {
+				let Some(decl) = self.node::<ast::ControlTypeDecl>(expected.symbol) else { return };
+				(decl.type_parameters(), decl.parameters())
+			}
+			_ => return,
+		};
+		let params = match self.table.symbol(block).kind {
+			SymbolKind::Parser => self.node::<ast::ParserDecl>(block).map(|decl| decl.parameters()),
+			SymbolKind::Control => self.node::<ast::ControlDecl>(block).map(|decl| decl.parameters()),
+			_ => None,
+		};
+		let Some(params) = params else { return };
+
+		let type_parameters = self.type_parameters(type_parameters);
+		self.bindings.extend(type_parameters.into_iter().zip(expected.args.iter().cloned()));
+
+		if params.len() != expected_params.len() {
+			let range = self.table.symbol(block).name_range.clone();
+			let message =
+				format!("Expected {} parameters, as in `{expected}`, found {}", expected_params.len(), params.len());
+			self.errors.push((range, message));
+			return;
+		}
+
+		for (expected_param, param) in expected_params.iter().zip(&params) {
+			let name = param.name().map(|name| name.text()).unwrap_or_default();
+			if expected_param.direction() != param.direction() {
+				let direction = match expected_param.direction() {
+					Some(Direction::In) => "`in`",
+					Some(Direction::Out) => "`out`",
+					Some(Direction::InOut) => "`inout`",
+					None => "directionless",
+				};
+				let message = format!("Parameter `{name}` must be {direction}, as in `{expected}`");
+				self.errors.push((param.text_range(), message));
+			}
+
+			let expected_type = expected_param
+				.name()
+				.and_then(|name| self.table.declaration_at(name.text_range().start))
+				.and_then(|symbol| self.types.symbol_type(symbol).cloned())
+				.map_or(Type::Unknown, |ty| ty.substitute(&self.bindings));
+			let found = param
+				.name()
+				.and_then(|name| self.table.declaration_at(name.text_range().start))
+				.and_then(|symbol| self.types.symbol_type(symbol).cloned())
+				.unwrap_or(Type::Unknown);
+			match expected_type {
+				// the first block to use a type parameter of the package determines its type
+				Type::TypeParameter(parameter) => {
+					self.bindings.insert(parameter.symbol, found);
+				}
+				_ if expected_type.is_unknown() || found.is_unknown() || expected_type == found => (),
+				_ => {
+					let range = param.ty().map_or(param.text_range(), |ty| ty.text_range());
+					let message = format!(
+						"Parameter `{name}` must have type `{expected_type}`, as in `{expected}`, found `{found}`"
+					);
+					self.errors.push((range, message));
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::p4_grammar::parse_and_check;
+	use pretty_assertions::assert_eq;
+
+	const ARCHITECTURE: &str = r#"
+		struct standard_metadata_t { bit<9> ingress_port; }
+		parser Parser<H, M>(packet_in b, out H parsedHdr, inout M meta, inout standard_metadata_t sm);
+		control Ingress<H, M>(inout H hdr, inout M meta, inout standard_metadata_t sm);
+		control Deparser<H>(packet_out b, in H hdr);
+		package Switch<H, M>(Parser<H, M> p, Ingress<H, M> ig, Deparser<H> dep);
+	"#;
+
+	fn check(input: &str) -> (SymbolTable, Option<Architecture>) {
+		let (program, table, types) = parse_and_check(input);
+		let architecture = Architecture::new(&program, &table, &types);
+		(table, architecture)
+	}
+
+	fn errors(input: &str) -> Vec<(&str, String)> {
+		let (_, architecture) = check(input);
+		let errors = architecture.map(|architecture| architecture.errors).unwrap_or_default();
+		errors.into_iter().map(|(range, message)| (&input[range], message)).collect()
+	}
+
+	#[test]
+	fn targets() {
+		assert_eq!(Target::from_header("/usr/share/p4c/p4include/v1model.p4").name, "v1model");
+		assert_eq!(Target::from_header("arch/my_arch.p4").name, "my_arch");
+		assert_eq!(Target::detect(["core.p4", "psa.p4"]).unwrap().name, "PSA");
+		assert_eq!(Target::detect(["core.p4", "headers.p4"]), None);
+	}
+
+	#[test]
+	fn main_instantiation() {
+		let input = format!(
+			"{ARCHITECTURE}{}",
+			r#"
+			struct headers_t { bit<8> x; }
+			struct meta_t { bit<8> y; }
+			parser P(packet_in b, out headers_t hdr, inout meta_t meta, inout standard_metadata_t sm) {
+				state start { transition accept; }
+			}
+			control I(inout headers_t hdr, inout meta_t meta, inout standard_metadata_t sm) { apply {} }
+			control D(packet_out b, in headers_t hdr) { apply {} }
+			Switch(P(), I(), D()) main;
+			"#
+		);
+		assert_eq!(errors(&input), vec![]);
+
+		let (table, architecture) = check(&input);
+		let architecture = architecture.unwrap();
+		assert_eq!(table.symbol(architecture.package).name, "Switch");
+		let types: Vec<_> = architecture.type_arguments.iter().map(|ty| ty.to_string()).collect();
+		assert_eq!(types, ["headers_t", "meta_t"]);
+		let blocks: Vec<_> = architecture
+			.blocks
+			.iter()
+			.map(|&(param, block)| (table.symbol(param).name.as_str(), table.symbol(block.unwrap()).name.as_str()))
+			.collect();
+		assert_eq!(blocks, [("p", "P"), ("ig", "I"), ("dep", "D")]);
+
+		let input = input.replace("Switch(P(), I(), D())", "Switch(dep = D(), p = P(), ig = I())");
+		let (table, architecture) = check(&input);
+		let blocks: Vec<_> = architecture
+			.unwrap()
+			.blocks
+			.iter()
+			.map(|&(param, block)| (table.symbol(param).name.as_str(), table.symbol(block.unwrap()).name.as_str()))
+			.collect();
+		assert_eq!(blocks, [("p", "P"), ("ig", "I"), ("dep", "D")]);
+	}
+
+	#[test]
+	fn mismatched_blocks() {
+		let input = format!(
+			"{ARCHITECTURE}{}",
+			r#"
+			struct headers_t { bit<8> x; }
+			struct meta_t { bit<8> y; }
+			parser P(packet_in b, out headers_t hdr, inout meta_t meta, inout standard_metadata_t sm) {
+				state start { transition accept; }
+			}
+			control I(inout headers_t hdr, in meta_t meta, inout standard_metadata_t sm) { apply {} }
+			control D(packet_out b, in meta_t hdr) { apply {} }
+			control E(packet_out b) { apply {} }
+			Switch(P(), I(), D()) main;
+			Switch<headers_t, headers_t>(P(), I(), E()) main2;
+			"#
+		);
+		assert_eq!(
+			errors(&input),
+			vec![
+				("in meta_t meta", "Parameter `meta` must be `inout`, as in `Ingress<H, M>`".to_string()),
+				(
+					"meta_t",
+					"Parameter `hdr` must have type `headers_t`, as in `Deparser<H>`, found `meta_t`".to_string()
+				),
+			]
+		);
+
+		let input = input.replace("Switch(P(), I(), D()) main;", "").replace("main2", "main");
+		assert_eq!(
+			errors(&input),
+			vec![
+				(
+					"meta_t",
+					"Parameter `meta` must have type `headers_t`, as in `Parser<H, M>`, found `meta_t`".to_string()
+				),
+				("in meta_t meta", "Parameter `meta` must be `inout`, as in `Ingress<H, M>`".to_string()),
+				(
+					"meta_t",
+					"Parameter `meta` must have type `headers_t`, as in `Ingress<H, M>`, found `meta_t`".to_string()
+				),
+				("E", "Expected 2 parameters, as in `Deparser<H>`, found 1".to_string()),
+			]
+		);
+	}
+}
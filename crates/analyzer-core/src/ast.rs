@@ -0,0 +1,1530 @@
+//! Typed views of the syntax tree of a P4 program.
+//!
+//! Each type wraps a [`SyntaxNode`] of a particular kind (see the rules in `p4_grammar.peg`) and provides accessors
+//! for its parts, so that the analyses don't have to know about the shape of the grammar. The accessors return
+//! `None` (or skip the element) when the part is missing, e.g. because the input could not be parsed.
+//!
+//! Positions are [`text_range`](AstNode::text_range)s in the text of the tree, which can be mapped back to the files
+//! they came from with a [`SourceMap`](crate::SourceMap).
+
+use crate::{
+	ast_node,
+	lexer::{Literal, Token},
+	p4_grammar::is_trivia,
+	syntax::{self, AstNode, NodeKind, SyntaxElement},
+};
+
+pub type SyntaxNode = syntax::SyntaxNode<Token>;
+pub type SyntaxToken = syntax::SyntaxToken<Token>;
+
+/// Declares an enum over several typed nodes, which is itself a typed node.
+macro_rules! ast_enum {
+	($(#[$attr:meta])* $name:ident { $($variant:ident($node:ty)),+ $(,)? }) => {
+		$(#[$attr])*
+		#[derive(Debug, Clone, PartialEq, Eq)]
+		pub enum $name {
+			$($variant($node)),+
+		}
+
+		impl AstNode<Token> for $name {
+			fn can_cast(kind: NodeKind) -> bool { $(<$node>::can_cast(kind))||+ }
+
+			fn cast(node: SyntaxNode) -> Option<Self> {
+				$(
+					if <$node>::can_cast(node.kind()) {
+						return <$node>::cast(node).map(Self::$variant);
+					}
+				)+
+				None
+			}
+
+			fn syntax(&self) -> &SyntaxNode {
+				match self {
+					$(Self::$variant(node) => node.syntax()),+
+				}
+			}
+		}
+	};
+}
+
+fn child<N: AstNode<Token>>(node: &SyntaxNode) -> Option<N> { node.children().find_map(N::cast) }
+
+fn children<N: AstNode<Token> + 'static>(node: &SyntaxNode) -> impl Iterator<Item = N> + '_ {
+	node.children().filter_map(N::cast)
+}
+
+/// The tokens of the node itself (i.e. not of its children), without trivia.
+fn tokens(node: &SyntaxNode) -> impl Iterator<Item = SyntaxToken> + '_ {
+	node.child_tokens().filter(|tk| !is_trivia(tk.kind()))
+}
+
+fn has_token(node: &SyntaxNode, text: &str) -> bool { tokens(node).any(|tk| tk.text() == text) }
+
+/// The type referred to by a child of the node, looking through `typeRef` nodes.
+fn type_child(node: &SyntaxNode) -> Option<Type> { node.children().find_map(type_of) }
+
+fn type_of(node: SyntaxNode) -> Option<Type> {
+	if node.kind() == "typeRef" {
+		child(&node)
+	} else {
+		Type::cast(node)
+	}
+}
+
+// Names and annotations
+
+ast_node!(
+	/// A name that is being declared or referred to, e.g. the name of a field or of a state.
+	Name<Token>: "name"
+);
+
+impl Name {
+	pub fn text(&self) -> String { self.0.first_token().map(|tk| tk.text().to_string()).unwrap_or_default() }
+}
+
+ast_node!(Annotation<Token>: "annotation");
+
+impl Annotation {
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	/// The unparsed body of an annotation like `@name("x")`, without the parentheses.
+	pub fn body(&self) -> Option<SyntaxNode> { self.0.child("annotationBody") }
+
+	/// The key-value pairs of a structured annotation like `@a[k = 1]`.
+	pub fn pairs(&self) -> impl Iterator<Item = KvPair> + '_ { children(&self.0) }
+
+	/// The expressions of a structured annotation like `@a[1, 2]`.
+	pub fn expressions(&self) -> impl Iterator<Item = Expr> + '_ { children(&self.0) }
+}
+
+ast_node!(KvPair<Token>: "kvPair");
+
+impl KvPair {
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn value(&self) -> Option<Expr> { child(&self.0) }
+}
+
+// Types
+
+ast_enum!(
+	/// A reference to a type, e.g. the type of a parameter or of a field.
+	Type {
+		Base(BaseType),
+		Name(TypeName),
+		Specialized(SpecializedType),
+		Stack(HeaderStackType),
+		Tuple(TupleType),
+		Void(VoidType),
+		DontCare(DontCareType),
+	}
+);
+
+ast_node!(
+	/// One of the built-in types `bool`, `error`, `match_kind`, `string`, `bit`, `int` and `varbit`, with its width.
+	BaseType<Token>: "baseType"
+);
+
+/// The width of a [`BaseType`], e.g. `8` in `bit<8>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeWidth {
+	Literal(Literal),
+	Expr(Expr),
+}
+
+impl BaseType {
+	/// The name of the type, e.g. `bit`.
+	pub fn keyword(&self) -> String { self.0.first_token().map(|tk| tk.text().to_string()).unwrap_or_default() }
+
+	/// The width of `bit`, `int` and `varbit`. `None` for the other types and for an unsized `int`.
+	pub fn width(&self) -> Option<TypeWidth> {
+		match tokens(&self.0).find_map(|tk| match tk.kind() {
+			Token::Integer(lit) => Some(lit.clone()),
+			_ => None,
+		}) {
+			Some(lit) => Some(TypeWidth::Literal(lit)),
+			None => child(&self.0).map(TypeWidth::Expr),
+		}
+	}
+}
+
+ast_node!(
+	/// A type referred to by its name, e.g. `ethernet_t`.
+	TypeName<Token>: "typeName"
+);
+
+impl TypeName {
+	pub fn text(&self) -> String {
+		tokens(&self.0).find(|tk| tk.text() != ".").map(|tk| tk.text().to_string()).unwrap_or_default()
+	}
+
+	/// Whether the name is prefixed with a dot, i.e. refers to a top-level declaration.
+	pub fn is_global(&self) -> bool { has_token(&self.0, ".") }
+}
+
+ast_node!(
+	/// A generic type with its type arguments, e.g. `register<bit<32>>`.
+	SpecializedType<Token>: "specializedType"
+);
+
+impl SpecializedType {
+	pub fn base(&self) -> Option<TypeName> { child(&self.0) }
+
+	pub fn arguments(&self) -> Vec<Type> { type_arguments(&self.0) }
+}
+
+fn type_arguments(node: &SyntaxNode) -> Vec<Type> {
+	node.child("typeArgumentList")
+		.map(|list| list.children().filter_map(type_of).collect())
+		.unwrap_or_default()
+}
+
+ast_node!(
+	/// A header stack type, e.g. `ipv4_t[2]`.
+	HeaderStackType<Token>: "headerStackType"
+);
+
+impl HeaderStackType {
+	pub fn element(&self) -> Option<Type> { child(&self.0) }
+
+	pub fn size(&self) -> Option<Expr> { child(&self.0) }
+}
+
+ast_node!(TupleType<Token>: "tupleType");
+
+impl TupleType {
+	pub fn elements(&self) -> Vec<Type> { type_arguments(&self.0) }
+}
+
+ast_node!(VoidType<Token>: "voidType");
+ast_node!(
+	/// The `_` type argument, to be inferred.
+	DontCareType<Token>: "dontCareType"
+);
+
+ast_node!(
+	/// The type parameters of a generic declaration, e.g. `<H, M>`.
+	TypeParameters<Token>: "typeParameters"
+);
+
+impl TypeParameters {
+	pub fn names(&self) -> impl Iterator<Item = Name> + '_ { children(&self.0) }
+}
+
+// Parameters and arguments
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+	In,
+	Out,
+	InOut,
+}
+
+ast_node!(Parameter<Token>: "parameter");
+
+impl Parameter {
+	pub fn annotations(&self) -> impl Iterator<Item = Annotation> + '_ { children(&self.0) }
+
+	/// The direction of the parameter, `None` if it is directionless.
+	pub fn direction(&self) -> Option<Direction> {
+		match self.0.child("direction")?.first_token()?.text() {
+			"in" => Some(Direction::In),
+			"out" => Some(Direction::Out),
+			"inout" => Some(Direction::InOut),
+			_ => None,
+		}
+	}
+
+	pub fn ty(&self) -> Option<Type> { type_child(&self.0) }
+
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn default_value(&self) -> Option<Expr> { child(&self.0) }
+}
+
+fn parameters(node: &SyntaxNode, kind: NodeKind) -> Vec<Parameter> {
+	node.child(kind).map(|list| children(&list).collect()).unwrap_or_default()
+}
+
+ast_node!(Argument<Token>: "argument");
+
+impl Argument {
+	/// The name of the parameter, for a named argument like `x = 1`.
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	/// The value of the argument, `None` for a don't-care argument (`_`).
+	pub fn value(&self) -> Option<Expr> { child(&self.0) }
+
+	pub fn is_dont_care(&self) -> bool { has_token(&self.0, "_") }
+}
+
+fn arguments(node: &SyntaxNode) -> Vec<Argument> {
+	node.child("argumentList").map(|list| children(&list).collect()).unwrap_or_default()
+}
+
+// Declarations
+
+ast_node!(
+	/// The whole program, i.e. the root of the syntax tree.
+	Program<Token>: "start"
+);
+
+impl Program {
+	/// The top-level declarations, in order.
+	pub fn declarations(&self) -> impl Iterator<Item = Declaration> + '_ { self.0.children().filter_map(declaration) }
+}
+
+/// Looks through the nodes which only group declarations, e.g. `typeDeclaration`.
+fn declaration(node: SyntaxNode) -> Option<Declaration> {
+	match node.kind() {
+		"declaration" | "typeDeclaration" => node.children().find_map(declaration),
+		_ => Declaration::cast(node),
+	}
+}
+
+ast_enum!(
+	/// A declaration, either at the top level or local to a parser, control or block.
+	Declaration {
+		Constant(ConstantDecl),
+		Variable(VariableDecl),
+		Instantiation(Instantiation),
+		Extern(ExternDecl),
+		Action(ActionDecl),
+		Function(FunctionDecl),
+		Table(TableDecl),
+		Parser(ParserDecl),
+		ParserType(ParserTypeDecl),
+		Control(ControlDecl),
+		ControlType(ControlTypeDecl),
+		Package(PackageDecl),
+		Header(HeaderDecl),
+		HeaderUnion(HeaderUnionDecl),
+		Struct(StructDecl),
+		Enum(EnumDecl),
+		Typedef(TypedefDecl),
+		Error(ErrorDecl),
+		MatchKind(MatchKindDecl),
+		ValueSet(ValueSetDecl),
+	}
+);
+
+impl Declaration {
+	/// The declared name. `error` and `match_kind` declarations have no name of their own.
+	pub fn name(&self) -> Option<Name> {
+		match self {
+			Declaration::Constant(decl) => decl.name(),
+			Declaration::Variable(decl) => decl.name(),
+			Declaration::Instantiation(decl) => decl.name(),
+			Declaration::Extern(decl) => decl.name(),
+			Declaration::Action(decl) => decl.name(),
+			Declaration::Function(decl) => decl.name(),
+			Declaration::Table(decl) => decl.name(),
+			Declaration::Parser(decl) => decl.name(),
+			Declaration::ParserType(decl) => decl.name(),
+			Declaration::Control(decl) => decl.name(),
+			Declaration::ControlType(decl) => decl.name(),
+			Declaration::Package(decl) => decl.name(),
+			Declaration::Header(decl) => decl.name(),
+			Declaration::HeaderUnion(decl) => decl.name(),
+			Declaration::Struct(decl) => decl.name(),
+			Declaration::Enum(decl) => decl.name(),
+			Declaration::Typedef(decl) => decl.name(),
+			Declaration::Error(_) | Declaration::MatchKind(_) => None,
+			Declaration::ValueSet(decl) => decl.name(),
+		}
+	}
+
+	pub fn annotations(&self) -> impl Iterator<Item = Annotation> + '_ { children(self.syntax()) }
+}
+
+ast_node!(ConstantDecl<Token>: "constantDeclaration");
+
+impl ConstantDecl {
+	pub fn ty(&self) -> Option<Type> { type_child(&self.0) }
+
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn value(&self) -> Option<Expr> { child(&self.0) }
+}
+
+ast_node!(VariableDecl<Token>: "variableDeclaration");
+
+impl VariableDecl {
+	pub fn ty(&self) -> Option<Type> { type_child(&self.0) }
+
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn initializer(&self) -> Option<Expr> { child(&self.0) }
+}
+
+ast_node!(
+	/// An instantiation of an extern, parser, control or package, e.g. `counter(1024, CounterType.packets) c;`.
+	Instantiation<Token>: "instantiation"
+);
+
+impl Instantiation {
+	pub fn ty(&self) -> Option<Type> { type_child(&self.0) }
+
+	pub fn arguments(&self) -> Vec<Argument> { arguments(&self.0) }
+
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	/// The declarations of an initializer block, for externs with abstract methods.
+	pub fn initializer(&self) -> Vec<Declaration> {
+		self.0.child("objectInitializer").map(|init| children(&init).collect()).unwrap_or_default()
+	}
+}
+
+ast_node!(
+	/// Either an extern object with its methods, or an extern function.
+	ExternDecl<Token>: "externDeclaration"
+);
+
+impl ExternDecl {
+	pub fn name(&self) -> Option<Name> {
+		match self.function() {
+			Some(function) => function.name(),
+			None => child(&self.0),
+		}
+	}
+
+	/// The prototype of an extern function, `None` for an extern object.
+	pub fn function(&self) -> Option<FunctionPrototype> { child(&self.0) }
+
+	pub fn type_parameters(&self) -> Option<TypeParameters> { child(&self.0) }
+
+	pub fn methods(&self) -> impl Iterator<Item = MethodPrototype> + '_ { children(&self.0) }
+}
+
+ast_node!(MethodPrototype<Token>: "methodPrototype");
+
+impl MethodPrototype {
+	pub fn annotations(&self) -> impl Iterator<Item = Annotation> + '_ { children(&self.0) }
+
+	pub fn is_abstract(&self) -> bool { has_token(&self.0, "abstract") }
+
+	/// The prototype of a method, `None` for a constructor.
+	pub fn function(&self) -> Option<FunctionPrototype> { child(&self.0) }
+
+	pub fn constructor(&self) -> Option<ConstructorPrototype> { child(&self.0) }
+
+	pub fn name(&self) -> Option<Name> {
+		match self.function() {
+			Some(function) => function.name(),
+			None => self.constructor()?.name(),
+		}
+	}
+
+	pub fn parameters(&self) -> Vec<Parameter> {
+		match self.function() {
+			Some(function) => function.parameters(),
+			None => self.constructor().map(|ctor| ctor.parameters()).unwrap_or_default(),
+		}
+	}
+}
+
+ast_node!(FunctionPrototype<Token>: "functionPrototype");
+
+impl FunctionPrototype {
+	pub fn return_type(&self) -> Option<Type> { type_child(&self.0) }
+
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn type_parameters(&self) -> Option<TypeParameters> { child(&self.0) }
+
+	pub fn parameters(&self) -> Vec<Parameter> { parameters(&self.0, "parameterList") }
+}
+
+ast_node!(ConstructorPrototype<Token>: "constructorPrototype");
+
+impl ConstructorPrototype {
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn parameters(&self) -> Vec<Parameter> { parameters(&self.0, "parameterList") }
+}
+
+ast_node!(FunctionDecl<Token>: "functionDeclaration");
+
+impl FunctionDecl {
+	pub fn prototype(&self) -> Option<FunctionPrototype> { child(&self.0) }
+
+	pub fn name(&self) -> Option<Name> { self.prototype()?.name() }
+
+	pub fn parameters(&self) -> Vec<Parameter> { self.prototype().map(|proto| proto.parameters()).unwrap_or_default() }
+
+	pub fn body(&self) -> Option<BlockStatement> { child(&self.0) }
+}
+
+ast_node!(ActionDecl<Token>: "actionDeclaration");
+
+impl ActionDecl {
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn parameters(&self) -> Vec<Parameter> { parameters(&self.0, "parameterList") }
+
+	pub fn body(&self) -> Option<BlockStatement> { child(&self.0) }
+}
+
+ast_node!(
+	/// The declaration of a parser type, either on its own (e.g. in an architecture) or as part of a parser.
+	ParserTypeDecl<Token>: "parserTypeDeclaration"
+);
+
+impl ParserTypeDecl {
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn type_parameters(&self) -> Option<TypeParameters> { child(&self.0) }
+
+	pub fn parameters(&self) -> Vec<Parameter> { parameters(&self.0, "parameterList") }
+}
+
+ast_node!(ParserDecl<Token>: "parserDeclaration");
+
+impl ParserDecl {
+	pub fn declaration(&self) -> Option<ParserTypeDecl> { child(&self.0) }
+
+	pub fn name(&self) -> Option<Name> { self.declaration()?.name() }
+
+	pub fn parameters(&self) -> Vec<Parameter> { self.declaration().map(|decl| decl.parameters()).unwrap_or_default() }
+
+	pub fn constructor_parameters(&self) -> Vec<Parameter> {
+		self.0
+			.child("constructorParameters")
+			.map(|ctor| parameters(&ctor, "parameterList"))
+			.unwrap_or_default()
+	}
+
+	/// The constants, variables, instances and value sets declared before the states.
+	pub fn locals(&self) -> impl Iterator<Item = Declaration> + '_ {
+		children(&self.0).filter(|decl| !matches!(decl, Declaration::ParserType(_)))
+	}
+
+	pub fn states(&self) -> impl Iterator<Item = ParserState> + '_ { children(&self.0) }
+}
+
+ast_node!(ParserState<Token>: "parserState");
+
+impl ParserState {
+	pub fn annotations(&self) -> impl Iterator<Item = Annotation> + '_ { children(&self.0) }
+
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn statements(&self) -> impl Iterator<Item = Statement> + '_ { children(&self.0) }
+
+	/// The transition at the end of the state. A state without one transitions to `reject`.
+	pub fn transition(&self) -> Option<TransitionStatement> { child(&self.0) }
+}
+
+ast_node!(TransitionStatement<Token>: "transitionStatement");
+
+impl TransitionStatement {
+	/// The target of a direct transition, e.g. `accept` in `transition accept;`.
+	pub fn target(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn select(&self) -> Option<SelectExpr> { child(&self.0) }
+}
+
+ast_node!(SelectExpr<Token>: "selectExpression");
+
+impl SelectExpr {
+	/// The expressions the cases are matched against.
+	pub fn expressions(&self) -> impl Iterator<Item = Expr> + '_ { children(&self.0) }
+
+	pub fn cases(&self) -> impl Iterator<Item = SelectCase> + '_ { children(&self.0) }
+}
+
+ast_node!(SelectCase<Token>: "selectCase");
+
+impl SelectCase {
+	pub fn keyset(&self) -> Option<Keyset> { child(&self.0) }
+
+	pub fn state(&self) -> Option<Name> { child(&self.0) }
+}
+
+ast_enum!(
+	/// The set of values matched by a case of a `select`, or by a table entry.
+	Keyset {
+		Tuple(TupleKeyset),
+		Mask(MaskKeyset),
+		Range(RangeKeyset),
+		Default(DefaultKeyset),
+		DontCare(DontCareKeyset),
+		Expr(Expr),
+	}
+);
+
+ast_node!(
+	/// A keyset for several expressions at once, e.g. `(0x800, _)`.
+	TupleKeyset<Token>: "tupleKeysetExpression"
+);
+
+impl TupleKeyset {
+	pub fn elements(&self) -> impl Iterator<Item = Keyset> + '_ { children(&self.0) }
+}
+
+ast_node!(
+	/// `value &&& mask`
+	MaskKeyset<Token>: "maskExpression"
+);
+
+impl MaskKeyset {
+	pub fn value(&self) -> Option<Expr> { children(&self.0).next() }
+
+	pub fn mask(&self) -> Option<Expr> { children(&self.0).nth(1) }
+}
+
+ast_node!(
+	/// `low .. high`
+	RangeKeyset<Token>: "rangeExpression"
+);
+
+impl RangeKeyset {
+	pub fn low(&self) -> Option<Expr> { children(&self.0).next() }
+
+	pub fn high(&self) -> Option<Expr> { children(&self.0).nth(1) }
+}
+
+ast_node!(DefaultKeyset<Token>: "defaultKeyset");
+ast_node!(DontCareKeyset<Token>: "dontCareKeyset");
+
+ast_node!(ValueSetDecl<Token>: "valueSetDeclaration");
+
+impl ValueSetDecl {
+	pub fn element_type(&self) -> Option<Type> { child(&self.0) }
+
+	pub fn size(&self) -> Option<Expr> { child(&self.0) }
+
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+}
+
+ast_node!(
+	/// The declaration of a control type, either on its own (e.g. in an architecture) or as part of a control.
+	ControlTypeDecl<Token>: "controlTypeDeclaration"
+);
+
+impl ControlTypeDecl {
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn type_parameters(&self) -> Option<TypeParameters> { child(&self.0) }
+
+	pub fn parameters(&self) -> Vec<Parameter> { parameters(&self.0, "parameterList") }
+}
+
+ast_node!(ControlDecl<Token>: "controlDeclaration");
+
+impl ControlDecl {
+	pub fn declaration(&self) -> Option<ControlTypeDecl> { child(&self.0) }
+
+	pub fn name(&self) -> Option<Name> { self.declaration()?.name() }
+
+	pub fn parameters(&self) -> Vec<Parameter> { self.declaration().map(|decl| decl.parameters()).unwrap_or_default() }
+
+	pub fn constructor_parameters(&self) -> Vec<Parameter> {
+		self.0
+			.child("constructorParameters")
+			.map(|ctor| parameters(&ctor, "parameterList"))
+			.unwrap_or_default()
+	}
+
+	/// The constants, variables, instances, actions and tables declared before the `apply` block.
+	pub fn locals(&self) -> impl Iterator<Item = Declaration> + '_ {
+		children(&self.0).filter(|decl| !matches!(decl, Declaration::ControlType(_)))
+	}
+
+	pub fn apply(&self) -> Option<BlockStatement> { child(&self.0) }
+}
+
+ast_node!(PackageDecl<Token>: "packageTypeDeclaration");
+
+impl PackageDecl {
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn type_parameters(&self) -> Option<TypeParameters> { child(&self.0) }
+
+	pub fn parameters(&self) -> Vec<Parameter> { parameters(&self.0, "parameterList") }
+}
+
+ast_node!(HeaderDecl<Token>: "headerTypeDeclaration");
+ast_node!(HeaderUnionDecl<Token>: "headerUnionDeclaration");
+ast_node!(StructDecl<Token>: "structTypeDeclaration");
+
+macro_rules! impl_fields {
+	($($name:ident),+) => {
+		$(
+			impl $name {
+				pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+				pub fn type_parameters(&self) -> Option<TypeParameters> { child(&self.0) }
+
+				pub fn fields(&self) -> impl Iterator<Item = StructField> + '_ { children(&self.0) }
+			}
+		)+
+	};
+}
+
+impl_fields!(HeaderDecl, HeaderUnionDecl, StructDecl);
+
+ast_node!(StructField<Token>: "structField");
+
+impl StructField {
+	pub fn annotations(&self) -> impl Iterator<Item = Annotation> + '_ { children(&self.0) }
+
+	pub fn ty(&self) -> Option<Type> { type_child(&self.0) }
+
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+}
+
+ast_node!(EnumDecl<Token>: "enumDeclaration");
+
+/// A member of an enum, with its value if the enum is serializable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumMember {
+	pub name: Name,
+	pub value: Option<Expr>,
+}
+
+impl EnumDecl {
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	/// The underlying type of a serializable enum, e.g. `bit<8>` in `enum bit<8> E { ... }`.
+	pub fn underlying_type(&self) -> Option<Type> { type_child(&self.0) }
+
+	pub fn members(&self) -> Vec<EnumMember> {
+		if self.underlying_type().is_some() {
+			self.0
+				.children()
+				.filter(|node| node.kind() == "specifiedIdentifier")
+				.filter_map(|node| Some(EnumMember { name: child(&node)?, value: child(&node) }))
+				.collect()
+		} else {
+			children(&self.0).skip(1).map(|name| EnumMember { name, value: None }).collect()
+		}
+	}
+}
+
+ast_node!(TypedefDecl<Token>: "typedefDeclaration");
+
+impl TypedefDecl {
+	/// The declared name, which comes after the type.
+	pub fn name(&self) -> Option<Name> { children(&self.0).last() }
+
+	/// Whether this is a `type` declaration, which introduces a new type rather than an alias.
+	pub fn is_new_type(&self) -> bool { has_token(&self.0, "type") }
+
+	pub fn ty(&self) -> Option<Type> { type_child(&self.0) }
+
+	/// A header, header union, struct or enum declared as part of the typedef.
+	pub fn derived(&self) -> Option<Declaration> { child(&self.0) }
+}
+
+ast_node!(ErrorDecl<Token>: "errorDeclaration");
+
+impl ErrorDecl {
+	pub fn members(&self) -> impl Iterator<Item = Name> + '_ { children(&self.0) }
+}
+
+ast_node!(MatchKindDecl<Token>: "matchKindDeclaration");
+
+impl MatchKindDecl {
+	pub fn members(&self) -> impl Iterator<Item = Name> + '_ { children(&self.0) }
+}
+
+// Tables
+
+ast_node!(TableDecl<Token>: "tableDeclaration");
+
+impl TableDecl {
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn key(&self) -> Option<KeyProperty> { child(&self.0) }
+
+	pub fn keys(&self) -> Vec<KeyElement> { self.key().map(|key| key.elements().collect()).unwrap_or_default() }
+
+	pub fn actions_property(&self) -> Option<ActionsProperty> { child(&self.0) }
+
+	pub fn actions(&self) -> Vec<ActionRef> {
+		self.actions_property().map(|actions| actions.actions().collect()).unwrap_or_default()
+	}
+
+	pub fn entries(&self) -> Option<EntriesProperty> { child(&self.0) }
+
+	/// The other properties, e.g. `size` or `default_action`.
+	pub fn properties(&self) -> impl Iterator<Item = TableProperty> + '_ { children(&self.0) }
+
+	pub fn property(&self, name: &str) -> Option<TableProperty> {
+		self.properties().find(|prop| prop.name().map_or(false, |n| n.text() == name))
+	}
+}
+
+ast_node!(KeyProperty<Token>: "keyProperty");
+
+impl KeyProperty {
+	pub fn elements(&self) -> impl Iterator<Item = KeyElement> + '_ { children(&self.0) }
+}
+
+ast_node!(KeyElement<Token>: "keyElement");
+
+impl KeyElement {
+	pub fn expr(&self) -> Option<Expr> { child(&self.0) }
+
+	/// The name of the match kind, e.g. `exact`.
+	pub fn match_kind(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn annotations(&self) -> impl Iterator<Item = Annotation> + '_ { children(&self.0) }
+}
+
+ast_node!(ActionsProperty<Token>: "actionsProperty");
+
+impl ActionsProperty {
+	pub fn actions(&self) -> impl Iterator<Item = ActionRef> + '_ { children(&self.0) }
+}
+
+ast_node!(
+	/// A reference to an action in a table, with the arguments bound by the program, e.g. `set_port(1)`.
+	ActionRef<Token>: "actionRef"
+);
+
+impl ActionRef {
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn is_global(&self) -> bool { has_token(&self.0, ".") }
+
+	/// The arguments, `None` if there are no parentheses.
+	pub fn arguments(&self) -> Option<Vec<Argument>> { self.0.child("argumentList").map(|_| arguments(&self.0)) }
+
+	/// The annotations preceding the action in an `actions` list.
+	pub fn annotations(&self) -> Vec<Annotation> {
+		let mut annotations = vec![];
+		for element in self.0.parent().iter().flat_map(|parent| parent.children_with_tokens()) {
+			match element {
+				SyntaxElement::Node(node) if node == self.0 => break,
+				SyntaxElement::Node(node) => annotations.extend(Annotation::cast(node)),
+				SyntaxElement::Token(tk) if tk.kind() == &Token::Semicolon => annotations.clear(),
+				SyntaxElement::Token(_) => (),
+			}
+		}
+		annotations
+	}
+}
+
+ast_node!(EntriesProperty<Token>: "entriesProperty");
+
+impl EntriesProperty {
+	pub fn is_const(&self) -> bool { has_token(&self.0, "const") }
+
+	pub fn entries(&self) -> impl Iterator<Item = Entry> + '_ { children(&self.0) }
+}
+
+ast_node!(Entry<Token>: "entry");
+
+impl Entry {
+	pub fn priority(&self) -> Option<Expr> {
+		if has_token(&self.0, "priority") {
+			child(&self.0)
+		} else {
+			None
+		}
+	}
+
+	pub fn keyset(&self) -> Option<Keyset> { children(&self.0).nth(self.priority().map_or(0, |_| 1)) }
+
+	pub fn action(&self) -> Option<ActionRef> { child(&self.0) }
+}
+
+ast_node!(TableProperty<Token>: "tableProperty");
+
+impl TableProperty {
+	pub fn is_const(&self) -> bool { has_token(&self.0, "const") }
+
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn value(&self) -> Option<Expr> { child(&self.0) }
+}
+
+// Statements
+
+ast_enum!(
+	Statement {
+		Assignment(AssignmentStatement),
+		MethodCall(MethodCallStatement),
+		Conditional(ConditionalStatement),
+		Empty(EmptyStatement),
+		Block(BlockStatement),
+		Exit(ExitStatement),
+		Return(ReturnStatement),
+		Switch(SwitchStatement),
+		Variable(VariableDecl),
+		Constant(ConstantDecl),
+		Instantiation(Instantiation),
+	}
+);
+
+ast_node!(AssignmentStatement<Token>: "assignmentStatement");
+
+impl AssignmentStatement {
+	pub fn target(&self) -> Option<LValue> { child(&self.0) }
+
+	pub fn value(&self) -> Option<Expr> { child(&self.0) }
+}
+
+ast_node!(
+	/// A call whose result (if any) is discarded, e.g. `packet.extract(hdr.ethernet);` or `t.apply();`.
+	MethodCallStatement<Token>: "methodCallStatement"
+);
+
+impl MethodCallStatement {
+	pub fn callee(&self) -> Option<LValue> { child(&self.0) }
+
+	pub fn type_arguments(&self) -> Vec<Type> { type_arguments(&self.0) }
+
+	pub fn arguments(&self) -> Vec<Argument> { arguments(&self.0) }
+}
+
+ast_node!(
+	/// An expression that can be assigned to, e.g. `hdr.ipv4.ttl` or `meta.flags[3:0]`.
+	LValue<Token>: "lvalue"
+);
+
+impl LValue {
+	/// The name at the start of the l-value, `None` for `this`.
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn is_global(&self) -> bool { tokens(&self.0).next().map_or(false, |tk| tk.text() == ".") }
+
+	pub fn suffixes(&self) -> impl Iterator<Item = Suffix> + '_ { children(&self.0) }
+}
+
+ast_node!(ConditionalStatement<Token>: "conditionalStatement");
+
+impl ConditionalStatement {
+	pub fn condition(&self) -> Option<Expr> { child(&self.0) }
+
+	pub fn then_branch(&self) -> Option<Statement> { children(&self.0).next() }
+
+	pub fn else_branch(&self) -> Option<Statement> { children(&self.0).nth(1) }
+}
+
+ast_node!(EmptyStatement<Token>: "emptyStatement");
+
+ast_node!(BlockStatement<Token>: "blockStatement" | "parserBlockStatement");
+
+impl BlockStatement {
+	pub fn annotations(&self) -> impl Iterator<Item = Annotation> + '_ { children(&self.0) }
+
+	pub fn statements(&self) -> impl Iterator<Item = Statement> + '_ { children(&self.0) }
+}
+
+ast_node!(ExitStatement<Token>: "exitStatement");
+
+ast_node!(ReturnStatement<Token>: "returnStatement");
+
+impl ReturnStatement {
+	pub fn value(&self) -> Option<Expr> { child(&self.0) }
+}
+
+ast_node!(SwitchStatement<Token>: "switchStatement");
+
+impl SwitchStatement {
+	pub fn expr(&self) -> Option<Expr> { child(&self.0) }
+
+	pub fn cases(&self) -> impl Iterator<Item = SwitchCase> + '_ { children(&self.0) }
+}
+
+ast_node!(SwitchCase<Token>: "switchCase");
+
+impl SwitchCase {
+	pub fn label(&self) -> Option<SwitchLabel> { child(&self.0) }
+
+	/// The statements of the case, `None` if it falls through to the next case.
+	pub fn body(&self) -> Option<BlockStatement> { child(&self.0) }
+}
+
+ast_node!(SwitchLabel<Token>: "switchLabel");
+
+impl SwitchLabel {
+	pub fn is_default(&self) -> bool { has_token(&self.0, "default") }
+
+	pub fn expr(&self) -> Option<Expr> { child(&self.0) }
+}
+
+// Expressions
+
+ast_enum!(
+	Expr {
+		Conditional(ConditionalExpr),
+		Binary(BinaryExpr),
+		Cast(CastExpr),
+		Unary(UnaryExpr),
+		Postfix(PostfixExpr),
+		Literal(LiteralExpr),
+		ErrorMember(ErrorMemberExpr),
+		TypeMember(TypeMemberExpr),
+		Name(NameExpr),
+		Paren(ParenExpr),
+		List(ListExpr),
+		Struct(StructExpr),
+	}
+);
+
+ast_node!(
+	/// `condition ? then : else`
+	ConditionalExpr<Token>: "conditionalExpression"
+);
+
+impl ConditionalExpr {
+	pub fn condition(&self) -> Option<Expr> { children(&self.0).next() }
+
+	pub fn then_expr(&self) -> Option<Expr> { children(&self.0).nth(1) }
+
+	pub fn else_expr(&self) -> Option<Expr> { children(&self.0).nth(2) }
+}
+
+ast_node!(
+	/// A chain of binary operators of the same precedence, e.g. `a + b - c`.
+	BinaryExpr<Token>:
+		"logicalOrExpression"
+		| "logicalAndExpression"
+		| "equalityExpression"
+		| "relationalExpression"
+		| "bitwiseOrExpression"
+		| "bitwiseXorExpression"
+		| "bitwiseAndExpression"
+		| "shiftExpression"
+		| "additiveExpression"
+		| "multiplicativeExpression"
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOp {
+	Or,
+	And,
+	Eq,
+	Ne,
+	Lt,
+	Le,
+	Gt,
+	Ge,
+	BitOr,
+	BitXor,
+	BitAnd,
+	Shl,
+	Shr,
+	Concat,
+	SatAdd,
+	SatSub,
+	Add,
+	Sub,
+	Mul,
+	Div,
+	Mod,
+}
+
+impl BinaryOp {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			BinaryOp::Or => "||",
+			BinaryOp::And => "&&",
+			BinaryOp::Eq => "==",
+			BinaryOp::Ne => "!=",
+			BinaryOp::Lt => "<",
+			BinaryOp::Le => "<=",
+			BinaryOp::Gt => ">",
+			BinaryOp::Ge => ">=",
+			BinaryOp::BitOr => "|",
+			BinaryOp::BitXor => "^",
+			BinaryOp::BitAnd => "&",
+			BinaryOp::Shl => "<<",
+			BinaryOp::Shr => ">>",
+			BinaryOp::Concat => "++",
+			BinaryOp::SatAdd => "|+|",
+			BinaryOp::SatSub => "|-|",
+			BinaryOp::Add => "+",
+			BinaryOp::Sub => "-",
+			BinaryOp::Mul => "*",
+			BinaryOp::Div => "/",
+			BinaryOp::Mod => "%",
+		}
+	}
+}
+
+impl BinaryExpr {
+	/// The operands, of which there is one more than there are operators.
+	pub fn operands(&self) -> impl Iterator<Item = Expr> + '_ { children(&self.0) }
+
+	/// The operators between the operands, from left to right.
+	pub fn operators(&self) -> Vec<BinaryOp> {
+		let mut ops = vec![];
+		let mut tokens = tokens(&self.0).peekable();
+		while let Some(tk) = tokens.next() {
+			ops.push(match tk.kind() {
+				Token::DoublePipe => BinaryOp::Or,
+				Token::DoubleAmpersand => BinaryOp::And,
+				Token::DoubleEquals => BinaryOp::Eq,
+				Token::NotEquals => BinaryOp::Ne,
+				Token::OpenChevron => BinaryOp::Lt,
+				Token::LessEquals => BinaryOp::Le,
+				// `>>` is two tokens, which only occur in pairs in a shift
+				Token::CloseChevron if self.0.kind() == "shiftExpression" => {
+					tokens.next();
+					BinaryOp::Shr
+				}
+				Token::CloseChevron => BinaryOp::Gt,
+				Token::GreaterEquals => BinaryOp::Ge,
+				Token::Pipe => BinaryOp::BitOr,
+				Token::Caret => BinaryOp::BitXor,
+				Token::Ampersand => BinaryOp::BitAnd,
+				Token::ShiftLeft => BinaryOp::Shl,
+				Token::DoublePlus => BinaryOp::Concat,
+				Token::SaturatingPlus => BinaryOp::SatAdd,
+				Token::SaturatingMinus => BinaryOp::SatSub,
+				Token::Plus => BinaryOp::Add,
+				Token::Minus => BinaryOp::Sub,
+				Token::Asterisk => BinaryOp::Mul,
+				Token::Slash => BinaryOp::Div,
+				Token::Percent => BinaryOp::Mod,
+				_ => continue,
+			});
+		}
+		ops
+	}
+}
+
+ast_node!(
+	/// `(type) expr`
+	CastExpr<Token>: "castExpression"
+);
+
+impl CastExpr {
+	pub fn ty(&self) -> Option<Type> { type_child(&self.0) }
+
+	pub fn expr(&self) -> Option<Expr> { child(&self.0) }
+}
+
+ast_node!(UnaryExpr<Token>: "unaryExpression");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnaryOp {
+	/// `!`
+	Not,
+	/// `~`
+	BitNot,
+	/// `-`
+	Neg,
+	/// `+`
+	Plus,
+}
+
+impl UnaryExpr {
+	pub fn op(&self) -> Option<UnaryOp> {
+		match tokens(&self.0).next()?.kind() {
+			Token::Exclamation => Some(UnaryOp::Not),
+			Token::Tilde => Some(UnaryOp::BitNot),
+			Token::Minus => Some(UnaryOp::Neg),
+			Token::Plus => Some(UnaryOp::Plus),
+			_ => None,
+		}
+	}
+
+	pub fn operand(&self) -> Option<Expr> { child(&self.0) }
+}
+
+ast_node!(
+	/// An expression followed by member accesses, indices, slices and calls, e.g. `hdr.ipv4.isValid()`.
+	PostfixExpr<Token>: "postfixExpression"
+);
+
+impl PostfixExpr {
+	pub fn base(&self) -> Option<Expr> { child(&self.0) }
+
+	pub fn suffixes(&self) -> impl Iterator<Item = Suffix> + '_ { children(&self.0) }
+}
+
+ast_enum!(
+	Suffix {
+		Member(MemberSuffix),
+		Index(IndexSuffix),
+		Slice(SliceSuffix),
+		Call(CallSuffix),
+	}
+);
+
+ast_node!(
+	/// `.name`
+	MemberSuffix<Token>: "memberSuffix"
+);
+
+impl MemberSuffix {
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+}
+
+ast_node!(
+	/// `[index]`
+	IndexSuffix<Token>: "indexSuffix"
+);
+
+impl IndexSuffix {
+	pub fn index(&self) -> Option<Expr> { child(&self.0) }
+}
+
+ast_node!(
+	/// `[high:low]`
+	SliceSuffix<Token>: "sliceSuffix"
+);
+
+impl SliceSuffix {
+	pub fn high(&self) -> Option<Expr> { children(&self.0).next() }
+
+	pub fn low(&self) -> Option<Expr> { children(&self.0).nth(1) }
+}
+
+ast_node!(
+	/// `<type arguments>(arguments)`
+	CallSuffix<Token>: "callSuffix"
+);
+
+impl CallSuffix {
+	pub fn type_arguments(&self) -> Vec<Type> { type_arguments(&self.0) }
+
+	pub fn arguments(&self) -> Vec<Argument> { arguments(&self.0) }
+}
+
+ast_node!(LiteralExpr<Token>: "literalExpression");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LiteralValue {
+	Integer(Literal),
+	String(String),
+	Bool(bool),
+}
+
+impl LiteralExpr {
+	pub fn value(&self) -> Option<LiteralValue> {
+		let token = tokens(&self.0).next()?;
+		match token.kind() {
+			Token::Integer(lit) => Some(LiteralValue::Integer(lit.clone())),
+			Token::StringLiteral(str) => Some(LiteralValue::String(str.clone())),
+			_ => match token.text() {
+				"true" => Some(LiteralValue::Bool(true)),
+				"false" => Some(LiteralValue::Bool(false)),
+				_ => None,
+			},
+		}
+	}
+}
+
+ast_node!(
+	/// `error.Name`
+	ErrorMemberExpr<Token>: "errorMemberExpression"
+);
+
+impl ErrorMemberExpr {
+	pub fn member(&self) -> Option<Name> { child(&self.0) }
+}
+
+ast_node!(
+	/// A member of a type, e.g. `bit<8>.minSizeInBits` or `E<bit<8>>.a`.
+	TypeMemberExpr<Token>: "typeMemberExpression"
+);
+
+impl TypeMemberExpr {
+	pub fn ty(&self) -> Option<Type> { child(&self.0) }
+
+	pub fn member(&self) -> Option<Name> { child(&self.0) }
+}
+
+ast_node!(
+	/// A reference to a declaration by name, or `this`.
+	NameExpr<Token>: "nameExpression"
+);
+
+impl NameExpr {
+	/// The name, `None` for `this`.
+	pub fn name(&self) -> Option<Name> { child(&self.0) }
+
+	pub fn is_global(&self) -> bool { has_token(&self.0, ".") }
+
+	pub fn is_this(&self) -> bool { has_token(&self.0, "this") }
+}
+
+ast_node!(ParenExpr<Token>: "parenthesizedExpression");
+
+impl ParenExpr {
+	pub fn expr(&self) -> Option<Expr> { child(&self.0) }
+}
+
+ast_node!(
+	/// `{ a, b, c }`
+	ListExpr<Token>: "listExpression"
+);
+
+impl ListExpr {
+	pub fn elements(&self) -> impl Iterator<Item = Expr> + '_ { children(&self.0) }
+}
+
+ast_node!(
+	/// `{ a = 1, b = 2 }`
+	StructExpr<Token>: "structExpression"
+);
+
+impl StructExpr {
+	pub fn fields(&self) -> impl Iterator<Item = KvPair> + '_ { children(&self.0) }
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::p4_grammar::parse;
+	use pretty_assertions::assert_eq;
+
+	fn program(input: &str) -> Program { Program::cast(parse(input)).unwrap() }
+
+	fn names(names: impl IntoIterator<Item = Name>) -> Vec<String> {
+		names.into_iter().map(|name| name.text()).collect()
+	}
+
+	#[test]
+	fn types() {
+		let program = program(
+			r#"
+			header h_t { bit<8> a; varbit<(W * 8)> b; }
+			struct s_t<T> { h_t[4] stack; register<bit<32>> r; tuple<bool, T> t; }
+			enum bit<2> E { A = 1, B = 2 }
+			enum F { X, Y }
+			typedef struct inner_t { int i; } anon;
+			type bit<9> PortId_t;
+			"#,
+		);
+		let decls: Vec<_> = program.declarations().collect();
+		assert_eq!(decls.len(), 6);
+
+		let Declaration::Header(header) = &decls[0] else { panic!() };
+		assert_eq!(header.name().unwrap().text(), "h_t");
+		let fields: Vec<_> = header.fields().collect();
+		assert_eq!(names(fields.iter().filter_map(|f| f.name())), vec!["a", "b"]);
+		let Some(Type::Base(bit)) = fields[0].ty() else { panic!() };
+		assert_eq!(bit.keyword(), "bit");
+		assert_eq!(bit.width(), Some(TypeWidth::Literal(Literal { base: 10, signed: false, width: None, value: 8 })));
+		let Some(Type::Base(varbit)) = fields[1].ty() else { panic!() };
+		assert!(matches!(varbit.width(), Some(TypeWidth::Expr(Expr::Binary(_)))));
+
+		let Declaration::Struct(st) = &decls[1] else { panic!() };
+		assert_eq!(names(st.type_parameters().unwrap().names()), vec!["T"]);
+		let fields: Vec<_> = st.fields().filter_map(|f| f.ty()).collect();
+		let Type::Stack(stack) = &fields[0] else { panic!() };
+		assert_eq!(stack.size().unwrap().syntax().text(), "4");
+		let Some(Type::Name(elem)) = stack.element() else { panic!() };
+		assert_eq!(elem.text(), "h_t");
+		let Type::Specialized(reg) = &fields[1] else { panic!() };
+		assert_eq!(reg.base().unwrap().text(), "register");
+		assert!(matches!(reg.arguments()[..], [Type::Base(_)]));
+		let Type::Tuple(tuple) = &fields[2] else { panic!() };
+		assert_eq!(tuple.elements().len(), 2);
+
+		let Declaration::Enum(e) = &decls[2] else { panic!() };
+		assert!(e.underlying_type().is_some());
+		let members = e.members();
+		assert_eq!(names(members.iter().map(|m| m.name.clone())), vec!["A", "B"]);
+		assert_eq!(members[1].value.as_ref().unwrap().syntax().text(), "2");
+		let Declaration::Enum(f) = &decls[3] else { panic!() };
+		assert_eq!(f.name().unwrap().text(), "F");
+		assert_eq!(names(f.members().into_iter().map(|m| m.name)), vec!["X", "Y"]);
+
+		let Declaration::Typedef(anon) = &decls[4] else { panic!() };
+		assert_eq!(anon.name().unwrap().text(), "anon");
+		assert!(matches!(anon.derived(), Some(Declaration::Struct(_))));
+		let Declaration::Typedef(port) = &decls[5] else { panic!() };
+		assert!(port.is_new_type());
+		assert!(matches!(port.ty(), Some(Type::Base(_))));
+	}
+
+	#[test]
+	fn parsers() {
+		let program = program(
+			r#"
+			parser P(packet_in pkt, out headers_t hdr)(bit<8> n) {
+				bit<8> x = 1;
+				state start {
+					pkt.extract(hdr.eth);
+					transition select(hdr.eth.type, x) {
+						(0x800, _): ipv4;
+						(1 .. 5, 0xf &&& 0x3): reject;
+						default: accept;
+					}
+				}
+				state ipv4 { transition accept; }
+			}
+			"#,
+		);
+		let Some(Declaration::Parser(parser)) = program.declarations().next() else { panic!() };
+		assert_eq!(parser.name().unwrap().text(), "P");
+		let params = parser.parameters();
+		assert_eq!(names(params.iter().filter_map(|p| p.name())), vec!["pkt", "hdr"]);
+		assert_eq!(params.iter().map(|p| p.direction()).collect::<Vec<_>>(), vec![None, Some(Direction::Out)]);
+		assert_eq!(names(parser.constructor_parameters().iter().filter_map(|p| p.name())), vec!["n"]);
+		assert!(matches!(parser.locals().collect::<Vec<_>>()[..], [Declaration::Variable(_)]));
+
+		let states: Vec<_> = parser.states().collect();
+		assert_eq!(names(states.iter().filter_map(|s| s.name())), vec!["start", "ipv4"]);
+		assert!(matches!(states[0].statements().collect::<Vec<_>>()[..], [Statement::MethodCall(_)]));
+		let select = states[0].transition().unwrap().select().unwrap();
+		assert_eq!(select.expressions().count(), 2);
+		let cases: Vec<_> = select.cases().collect();
+		assert_eq!(names(cases.iter().filter_map(|c| c.state())), vec!["ipv4", "reject", "accept"]);
+		let Some(Keyset::Tuple(tuple)) = cases[0].keyset() else { panic!() };
+		assert!(matches!(tuple.elements().collect::<Vec<_>>()[..], [Keyset::Expr(_), Keyset::DontCare(_)]));
+		let Some(Keyset::Tuple(tuple)) = cases[1].keyset() else { panic!() };
+		assert!(matches!(tuple.elements().collect::<Vec<_>>()[..], [Keyset::Range(_), Keyset::Mask(_)]));
+		assert!(matches!(cases[2].keyset(), Some(Keyset::Default(_))));
+		assert_eq!(states[1].transition().unwrap().target().unwrap().text(), "accept");
+	}
+
+	#[test]
+	fn controls_and_tables() {
+		let program = program(
+			r#"
+			control C(inout headers_t hdr) {
+				action set(bit<9> port) { meta.port = port; }
+				table t {
+					key = { hdr.eth.dst: exact @name("dst"); hdr.eth.type: ternary; }
+					actions = { set; @defaultonly NoAction; }
+					const default_action = NoAction();
+					size = 64;
+					const entries = { (1, 2): set(3); priority = 5: (_, 0x800): NoAction(); }
+				}
+				apply {
+					switch (t.apply().action_run) {
+						set: { return; }
+						default: {}
+					}
+					hdr.eth.type = (bit<16>) ~x[7:0] >> 2;
+				}
+			}
+			"#,
+		);
+		let Some(Declaration::Control(control)) = program.declarations().next() else { panic!() };
+		let locals: Vec<_> = control.locals().collect();
+		let Declaration::Action(action) = &locals[0] else { panic!() };
+		assert_eq!(action.name().unwrap().text(), "set");
+		let Declaration::Table(table) = &locals[1] else { panic!() };
+
+		let keys = table.keys();
+		assert_eq!(names(keys.iter().filter_map(|k| k.match_kind())), vec!["exact", "ternary"]);
+		assert_eq!(keys[0].expr().unwrap().syntax().text(), "hdr.eth.dst");
+		assert_eq!(keys[0].annotations().count(), 1);
+		let actions = table.actions();
+		assert_eq!(names(actions.iter().filter_map(|a| a.name())), vec!["set", "NoAction"]);
+		assert_eq!(actions[0].annotations().len(), 0);
+		assert_eq!(names(actions[1].annotations().into_iter().filter_map(|a| a.name())), vec!["defaultonly"]);
+		assert!(actions[0].arguments().is_none());
+		let default_action = table.property("default_action").unwrap();
+		assert!(default_action.is_const());
+		assert!(matches!(default_action.value(), Some(Expr::Postfix(_))));
+		assert_eq!(table.property("size").unwrap().value().unwrap().syntax().text(), "64");
+
+		let entries = table.entries().unwrap();
+		assert!(entries.is_const());
+		let entries: Vec<_> = entries.entries().collect();
+		assert!(entries[0].priority().is_none());
+		assert!(matches!(entries[0].keyset(), Some(Keyset::Tuple(_))));
+		assert_eq!(entries[0].action().unwrap().arguments().unwrap().len(), 1);
+		assert_eq!(entries[1].priority().unwrap().syntax().text(), "5");
+		assert_eq!(entries[1].keyset().unwrap().syntax().text(), "(_, 0x800)");
+
+		let statements: Vec<_> = control.apply().unwrap().statements().collect();
+		let Statement::Switch(switch) = &statements[0] else { panic!() };
+		let Some(Expr::Postfix(expr)) = switch.expr() else { panic!() };
+		assert!(matches!(expr.base(), Some(Expr::Name(_))));
+		assert!(matches!(
+			expr.suffixes().collect::<Vec<_>>()[..],
+			[Suffix::Member(_), Suffix::Call(_), Suffix::Member(_)]
+		));
+		let cases: Vec<_> = switch.cases().collect();
+		assert!(!cases[0].label().unwrap().is_default());
+		assert!(cases[1].label().unwrap().is_default());
+		assert!(matches!(cases[0].body().unwrap().statements().next(), Some(Statement::Return(_))));
+
+		let Statement::Assignment(assignment) = &statements[1] else { panic!() };
+		let target = assignment.target().unwrap();
+		assert_eq!(target.name().unwrap().text(), "hdr");
+		assert_eq!(target.suffixes().count(), 2);
+		let Some(Expr::Binary(shift)) = assignment.value() else { panic!() };
+		assert_eq!(shift.operators(), vec![BinaryOp::Shr]);
+		let Some(Expr::Cast(cast)) = shift.operands().next() else { panic!() };
+		let Some(Expr::Unary(not)) = cast.expr() else { panic!() };
+		assert_eq!(not.op(), Some(UnaryOp::BitNot));
+	}
+
+	#[test]
+	fn externs_and_instantiations() {
+		let program = program(
+			r#"
+			extern register<T> {
+				register(bit<32> size);
+				void read(out T result, in bit<32> index);
+				abstract T compute(in T x);
+			}
+			extern void mark_to_drop(inout standard_metadata_t m);
+			package Switch<H>(Parser<H> p, Control<H> c);
+			Switch(P(), C()) main;
+			"#,
+		);
+		let decls: Vec<_> = program.declarations().collect();
+		let Declaration::Extern(register) = &decls[0] else { panic!() };
+		assert_eq!(register.name().unwrap().text(), "register");
+		assert!(register.function().is_none());
+		let methods: Vec<_> = register.methods().collect();
+		assert!(methods[0].constructor().is_some());
+		assert_eq!(names(methods.iter().filter_map(|m| m.name())), vec!["register", "read", "compute"]);
+		assert_eq!(methods[1].parameters().len(), 2);
+		assert!(matches!(methods[1].function().unwrap().return_type(), Some(Type::Void(_))));
+		assert!(methods[2].is_abstract());
+
+		let Declaration::Extern(drop) = &decls[1] else { panic!() };
+		assert_eq!(drop.name().unwrap().text(), "mark_to_drop");
+		assert_eq!(drop.function().unwrap().parameters()[0].direction(), Some(Direction::InOut));
+
+		let Declaration::Package(package) = &decls[2] else { panic!() };
+		assert_eq!(names(package.parameters().iter().filter_map(|p| p.name())), vec!["p", "c"]);
+
+		let Declaration::Instantiation(main) = &decls[3] else { panic!() };
+		assert_eq!(main.name().unwrap().text(), "main");
+		assert!(matches!(main.ty(), Some(Type::Name(_))));
+		assert_eq!(main.arguments().len(), 2);
+		assert_eq!(decls[3].name().unwrap().text(), "main");
+		assert_eq!(main.text_range(), main.syntax().text_range());
+	}
+
+	#[test]
+	fn expressions() {
+		let program = program(
+			"const bool x = a.b[1] == 8w3 || !c && error.NoError != E.X ? f<bit<8>>(y = 1, _) : {1, 2} == { a = 1 };",
+		);
+		let Some(Declaration::Constant(constant)) = program.declarations().next() else { panic!() };
+		let Some(Expr::Conditional(cond)) = constant.value() else { panic!() };
+		let Some(Expr::Binary(or)) = cond.condition() else { panic!() };
+		assert_eq!(or.operators(), vec![BinaryOp::Or]);
+		let operands: Vec<_> = or.operands().collect();
+		let Expr::Binary(eq) = &operands[0] else { panic!() };
+		assert_eq!(eq.operators(), vec![BinaryOp::Eq]);
+		let Some(Expr::Literal(lit)) = eq.operands().nth(1) else { panic!() };
+		assert_eq!(
+			lit.value(),
+			Some(LiteralValue::Integer(Literal { base: 10, signed: false, width: Some(8), value: 3 }))
+		);
+		let Expr::Binary(and) = &operands[1] else { panic!() };
+		let Some(Expr::Binary(ne)) = and.operands().nth(1) else { panic!() };
+		let Some(Expr::ErrorMember(err)) = ne.operands().next() else { panic!() };
+		assert_eq!(err.member().unwrap().text(), "NoError");
+
+		let Some(Expr::Postfix(call)) = cond.then_expr() else { panic!() };
+		let Some(Suffix::Call(call)) = call.suffixes().next() else { panic!() };
+		assert_eq!(call.type_arguments().len(), 1);
+		let args = call.arguments();
+		assert_eq!(args[0].name().unwrap().text(), "y");
+		assert!(args[1].is_dont_care());
+		assert!(args[1].value().is_none());
+
+		let Some(Expr::Binary(eq)) = cond.else_expr() else { panic!() };
+		assert!(matches!(eq.operands().collect::<Vec<_>>()[..], [Expr::List(_), Expr::Struct(_)]));
+	}
+}
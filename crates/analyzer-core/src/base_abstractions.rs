@@ -33,6 +33,27 @@ pub struct Diagnostic {
 	pub location: std::ops::Range<usize>,
 	pub severity: Severity,
 	pub message: String,
+	/// Other locations that explain the diagnostic, e.g. the steps of a path that leads to it.
+	pub related: Vec<RelatedInformation>,
+	/// Whether the diagnostic reports unused or unreachable code, which editors grey out.
+	pub unnecessary: bool,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct RelatedInformation {
+	pub file: FileId,
+	pub location: std::ops::Range<usize>,
+	pub message: String,
+}
+
+/// An edit of a file that fixes the problem reported by a diagnostic.
+#[derive(Clone, PartialEq, Eq)]
+pub struct QuickFix {
+	pub title: String,
+	pub file: FileId,
+	/// The range replaced by the text, which is empty for an insertion.
+	pub location: std::ops::Range<usize>,
+	pub text: String,
 }
 
 /// Represents an included dependency.
@@ -0,0 +1,292 @@
+//! The values of compile-time constants.
+//!
+//! Integers are arbitrary-precision. Operations on `bit<W>` and `int<W>` wrap around (or saturate, for `|+|` and
+//! `|-|`) the way the P4 specification prescribes, while operations on `int` are exact. The values themselves are
+//! computed alongside the types of the expressions, see [`TypeInfo::value_of`](crate::types::TypeInfo::value_of).
+
+use std::fmt;
+
+use num_bigint::{BigInt, Sign};
+
+use crate::{
+	ast::{BinaryOp, UnaryOp},
+	types::Type,
+};
+
+/// The widest `bit<W>`, `int<W>` and `varbit<W>` types, the largest number of bits an `int` can be shifted left by,
+/// and the highest bit that can be sliced from one.
+///
+/// Without a limit, wrapping a single value to the range of its type or shifting an `int`, whose values have no width
+/// to bound them, could take gigabytes of memory.
+pub const MAX_INT_BITS: u32 = 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Value {
+	/// An integer of type `bit<W>`, `int<W>` or `int`, always in the range of its type.
+	Int(BigInt, Type),
+	Bool(bool),
+	String(String),
+	/// A member of the `error` type.
+	Error(String),
+	/// A member of an enum, with its value if the enum is serializable.
+	Enum {
+		name: String,
+		member: String,
+		value: Option<Box<Value>>,
+	},
+	List(Vec<Value>),
+	Struct(Vec<(String, Value)>),
+}
+
+impl Value {
+	/// An integer of a type, wrapped around to fit the type.
+	pub fn int(value: impl Into<BigInt>, ty: Type) -> Self { Value::Int(wrap(value.into(), &ty), ty) }
+
+	/// The integer value, looking through serializable enum members.
+	pub fn as_int(&self) -> Option<&BigInt> {
+		match self {
+			Value::Int(value, _) => Some(value),
+			Value::Enum { value: Some(value), .. } => value.as_int(),
+			_ => None,
+		}
+	}
+
+	pub fn as_u64(&self) -> Option<u64> { self.as_int().and_then(|value| u64::try_from(value).ok()) }
+
+	pub fn as_bool(&self) -> Option<bool> {
+		match self {
+			Value::Bool(value) => Some(*value),
+			_ => None,
+		}
+	}
+
+	/// Converts the value to a type, as an explicit or implicit cast would.
+	pub fn cast(&self, to: &Type) -> Option<Value> {
+		match (self, to) {
+			(Value::Int(value, _), Type::Bit(_) | Type::Int(_) | Type::InfInt) => {
+				Some(Value::int(value.clone(), to.clone()))
+			}
+			(Value::Int(value, _), Type::Bool) => match u64::try_from(value) {
+				Ok(0) => Some(Value::Bool(false)),
+				Ok(1) => Some(Value::Bool(true)),
+				_ => None,
+			},
+			(Value::Bool(value), Type::Bit(_) | Type::Int(_)) => Some(Value::int(u8::from(*value), to.clone())),
+			(Value::Enum { value: Some(value), .. }, Type::Bit(_) | Type::Int(_) | Type::InfInt) => value.cast(to),
+			(Value::Int(..), Type::Enum(_)) | (Value::Enum { .. }, Type::Enum(_)) => None,
+			(Value::List(values), Type::Tuple(types)) if values.len() == types.len() => {
+				Some(Value::List(values.iter().zip(types).map(|(value, ty)| value.cast(ty)).collect::<Option<_>>()?))
+			}
+			(_, Type::Unknown | Type::TypeParameter(_)) => Some(self.clone()),
+			_ => Some(self.clone()).filter(|value| value.matches(to)),
+		}
+	}
+
+	/// Whether the value is already of a type, so that a cast to it has no effect.
+	fn matches(&self, ty: &Type) -> bool {
+		match (self, ty) {
+			(Value::Int(_, own), _) => own == ty,
+			(Value::Bool(_), Type::Bool) | (Value::String(_), Type::String) | (Value::Error(_), Type::Error) => true,
+			(Value::Enum { name, .. }, Type::Enum(nominal)) => name == &nominal.name,
+			(Value::List(_), _) | (Value::Struct(_), _) => true,
+			_ => false,
+		}
+	}
+}
+
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Value::Int(value, _) => write!(f, "{value}"),
+			Value::Bool(value) => write!(f, "{value}"),
+			Value::String(value) => write!(f, "{value:?}"),
+			Value::Error(member) => write!(f, "error.{member}"),
+			Value::Enum { name, member, value: Some(value) } => write!(f, "{name}.{member} ({value})"),
+			Value::Enum { name, member, value: None } => write!(f, "{name}.{member}"),
+			Value::List(values) => {
+				let values: Vec<_> = values.iter().map(|value| value.to_string()).collect();
+				write!(f, "{{ {} }}", values.join(", "))
+			}
+			Value::Struct(fields) => {
+				let fields: Vec<_> = fields.iter().map(|(name, value)| format!("{name} = {value}")).collect();
+				write!(f, "{{ {} }}", fields.join(", "))
+			}
+		}
+	}
+}
+
+/// The smallest and the largest value of a fixed-width integer type.
+fn bounds(ty: &Type) -> Option<(BigInt, BigInt)> {
+	match ty {
+		Type::Bit(width) => Some((BigInt::from(0), (BigInt::from(1) << *width as usize) - 1)),
+		Type::Int(0) => Some((BigInt::from(0), BigInt::from(0))),
+		Type::Int(width) => {
+			let half = BigInt::from(1) << (*width as usize - 1);
+			Some((-half.clone(), half - 1))
+		}
+		_ => None,
+	}
+}
+
+/// Wraps an integer around to the range of a type, i.e. keeps its lowest `W` bits.
+pub fn wrap(value: BigInt, ty: &Type) -> BigInt {
+	let Some((min, max)) = bounds(ty) else { return value };
+	let modulus = &max - &min + 1;
+	let mut value: BigInt = (value - &min) % &modulus;
+	if value.sign() == Sign::Minus {
+		value += &modulus;
+	}
+	value + min
+}
+
+/// Clamps an integer to the range of a type.
+fn saturate(value: BigInt, ty: &Type) -> BigInt {
+	match bounds(ty) {
+		Some((min, _)) if value < min => min,
+		Some((_, max)) if value > max => max,
+		_ => value,
+	}
+}
+
+/// Applies a unary operator to a value of type `ty`.
+pub fn unary(op: UnaryOp, value: &Value, ty: &Type) -> Option<Value> {
+	match (op, value) {
+		(UnaryOp::Not, Value::Bool(value)) => Some(Value::Bool(!value)),
+		(UnaryOp::Neg, Value::Int(value, _)) => Some(Value::int(-value, ty.clone())),
+		(UnaryOp::Plus, Value::Int(..)) => Some(value.clone()),
+		(UnaryOp::BitNot, Value::Int(value, _)) => Some(Value::int(-value - 1, ty.clone())),
+		_ => None,
+	}
+}
+
+/// Applies a binary operator to two values, where `ty` is the type of the result (for comparisons, the type the
+/// operands are compared as). Errors are those that make the expression invalid, like a division by zero.
+pub fn binary(op: BinaryOp, lhs: &Value, rhs: &Value, ty: &Type) -> Result<Option<Value>, String> {
+	if let (BinaryOp::And | BinaryOp::Or, Some(a), Some(b)) = (op, lhs.as_bool(), rhs.as_bool()) {
+		return Ok(Some(Value::Bool(if op == BinaryOp::And { a && b } else { a || b })));
+	}
+	if let (BinaryOp::Eq | BinaryOp::Ne, Some(a), Some(b)) = (op, lhs.cast(ty), rhs.cast(ty)) {
+		return Ok(Some(Value::Bool((a == b) == (op == BinaryOp::Eq))));
+	}
+	let (Some(a), Some(b)) = (lhs.as_int(), rhs.as_int()) else { return Ok(None) };
+
+	let result = match op {
+		BinaryOp::Lt => return Ok(Some(Value::Bool(a < b))),
+		BinaryOp::Le => return Ok(Some(Value::Bool(a <= b))),
+		BinaryOp::Gt => return Ok(Some(Value::Bool(a > b))),
+		BinaryOp::Ge => return Ok(Some(Value::Bool(a >= b))),
+		BinaryOp::Add => a + b,
+		BinaryOp::Sub => a - b,
+		BinaryOp::Mul => a * b,
+		BinaryOp::Div | BinaryOp::Mod => {
+			if b.sign() == Sign::NoSign {
+				return Err("Division by zero".to_string());
+			}
+			if a.sign() == Sign::Minus || b.sign() == Sign::Minus {
+				return Err(format!("`{}` cannot be applied to negative values", op.as_str()));
+			}
+			if op == BinaryOp::Div {
+				a / b
+			} else {
+				a % b
+			}
+		}
+		BinaryOp::SatAdd => return Ok(Some(Value::Int(saturate(a + b, ty), ty.clone()))),
+		BinaryOp::SatSub => return Ok(Some(Value::Int(saturate(a - b, ty), ty.clone()))),
+		BinaryOp::Shl | BinaryOp::Shr => {
+			if b.sign() == Sign::Minus {
+				return Err("The shift amount cannot be negative".to_string());
+			}
+			let amount = usize::try_from(b).unwrap_or(usize::MAX);
+			// shifting out everything but the sign
+			let amount = match ty.width() {
+				Some(width) => amount.min(width as usize + 1),
+				None if op == BinaryOp::Shr => amount.min(a.bits() as usize + 1),
+				None if amount > MAX_INT_BITS as usize => {
+					return Err(format!("An `int` cannot be shifted left by more than {MAX_INT_BITS} bits"));
+				}
+				None => amount,
+			};
+			if op == BinaryOp::Shl {
+				a << amount
+			} else {
+				a >> amount
+			}
+		}
+		BinaryOp::BitAnd => a & b,
+		BinaryOp::BitOr => a | b,
+		BinaryOp::BitXor => a ^ b,
+		BinaryOp::Concat => {
+			let (Value::Int(_, left), Value::Int(_, right)) = (lhs, rhs) else { return Ok(None) };
+			let (Some(_), Some(width)) = (left.width(), right.width()) else { return Ok(None) };
+			(wrap(a.clone(), &Type::Bit(left.width().unwrap())) << width as usize) | wrap(b.clone(), &Type::Bit(width))
+		}
+		BinaryOp::Eq | BinaryOp::Ne | BinaryOp::And | BinaryOp::Or => return Ok(None),
+	};
+	Ok(Some(Value::int(result, ty.clone())))
+}
+
+/// The bits `high` down to `low` of an integer.
+pub fn slice(value: &Value, high: u32, low: u32) -> Option<Value> {
+	Some(Value::int(value.as_int()? >> low as usize, Type::Bit(high - low + 1)))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use pretty_assertions::assert_eq;
+
+	fn int(value: i64, ty: Type) -> Value { Value::int(value, ty) }
+
+	fn eval(op: BinaryOp, lhs: Value, rhs: Value, ty: Type) -> String {
+		match binary(op, &lhs, &rhs, &ty) {
+			Ok(Some(value)) => value.to_string(),
+			Ok(None) => "?".to_string(),
+			Err(err) => err,
+		}
+	}
+
+	#[test]
+	fn wrap_and_saturate() {
+		assert_eq!(int(256 + 3, Type::Bit(8)), Value::Int(BigInt::from(3), Type::Bit(8)));
+		assert_eq!(int(-1, Type::Bit(8)).to_string(), "255");
+		assert_eq!(int(128, Type::Int(8)).to_string(), "-128");
+		assert_eq!(int(-129, Type::Int(8)).to_string(), "127");
+		assert_eq!(int(1 << 40, Type::InfInt).to_string(), "1099511627776");
+
+		assert_eq!(eval(BinaryOp::Add, int(250, Type::Bit(8)), int(10, Type::Bit(8)), Type::Bit(8)), "4");
+		assert_eq!(eval(BinaryOp::SatAdd, int(250, Type::Bit(8)), int(10, Type::Bit(8)), Type::Bit(8)), "255");
+		assert_eq!(eval(BinaryOp::SatSub, int(5, Type::Bit(8)), int(10, Type::Bit(8)), Type::Bit(8)), "0");
+		assert_eq!(eval(BinaryOp::SatSub, int(-100, Type::Int(8)), int(100, Type::Int(8)), Type::Int(8)), "-128");
+		assert_eq!(eval(BinaryOp::Sub, int(-100, Type::Int(8)), int(100, Type::Int(8)), Type::Int(8)), "56");
+		assert_eq!(eval(BinaryOp::Shl, int(0x81, Type::Bit(8)), int(1, Type::InfInt), Type::Bit(8)), "2");
+		assert_eq!(eval(BinaryOp::Shr, int(-4, Type::Int(8)), int(1, Type::InfInt), Type::Int(8)), "-2");
+		assert_eq!(
+			eval(BinaryOp::Shl, int(1, Type::InfInt), int(100, Type::InfInt), Type::InfInt),
+			"1267650600228229401496703205376"
+		);
+		assert_eq!(
+			eval(BinaryOp::Shl, int(1, Type::InfInt), int(0xffff_ffff, Type::InfInt), Type::InfInt),
+			"An `int` cannot be shifted left by more than 4096 bits"
+		);
+		assert_eq!(eval(BinaryOp::Shr, int(-5, Type::InfInt), int(1 << 40, Type::InfInt), Type::InfInt), "-1");
+	}
+
+	#[test]
+	fn operators() {
+		assert_eq!(eval(BinaryOp::Concat, int(1, Type::Bit(4)), int(-1, Type::Int(4)), Type::Bit(8)), "31");
+		assert_eq!(eval(BinaryOp::Div, int(7, Type::InfInt), int(0, Type::InfInt), Type::InfInt), "Division by zero");
+		assert_eq!(
+			eval(BinaryOp::Mod, int(-7, Type::InfInt), int(2, Type::InfInt), Type::InfInt),
+			"`%` cannot be applied to negative values"
+		);
+		assert_eq!(eval(BinaryOp::Eq, int(3, Type::InfInt), int(3, Type::Bit(8)), Type::Bit(8)), "true");
+		assert_eq!(eval(BinaryOp::Lt, int(-1, Type::Int(8)), int(0, Type::Int(8)), Type::Int(8)), "true");
+		assert_eq!(eval(BinaryOp::And, Value::Bool(true), Value::Bool(false), Type::Bool), "false");
+		assert_eq!(unary(UnaryOp::BitNot, &int(0x0f, Type::Bit(8)), &Type::Bit(8)), Some(int(0xf0, Type::Bit(8))));
+		assert_eq!(slice(&int(0xabcd, Type::Bit(16)), 11, 4), Some(int(0xbc, Type::Bit(8))));
+		assert_eq!(slice(&int(-2, Type::InfInt), 4095, 4090), Some(int(0x3f, Type::Bit(6))));
+		assert_eq!(int(300, Type::InfInt).cast(&Type::Bit(8)), Some(int(44, Type::Bit(8))));
+		assert_eq!(int(2, Type::InfInt).cast(&Type::Bool), None);
+	}
+}
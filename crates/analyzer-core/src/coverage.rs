@@ -0,0 +1,579 @@
+//! The coverage of the cases of `select` expressions and `switch` statements.
+//!
+//! The values of each key are encoded as bits, and each case as the patterns of bits it matches, in the manner of a
+//! ternary match: a value like `0x800` matches one pattern, a mask like `0x800 &&& 0xF00` and `_` leave some bits out,
+//! and a range like `1 .. 5` is split into patterns that each leave out some of the low bits. Subtracting the patterns
+//! of the earlier cases from those of a case gives the values that it can match, which are none if it is shadowed,
+//! and subtracting the patterns of every case from those of the keys gives the values that match no case.
+//!
+//! A `bool`, an enum that isn't serializable, `error` and the `action_run` of a table have as many values as they have
+//! members, and a serializable enum has the values of its underlying type, not just those of its members. Keys wider
+//! than 64 bits, value sets and cases whose values aren't known at compile time can't be analyzed, and are left out.
+
+use std::ops::Range;
+
+use num_bigint::BigInt;
+
+use crate::{
+	ast::{self, Expr, Keyset, SyntaxNode},
+	const_eval::Value,
+	state_graph::matches_everything,
+	symbols::{SymbolKind, SymbolTable},
+	syntax::AstNode,
+	types::{Type, TypeInfo},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+	pub range: Range<usize>,
+	pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+	/// A case that the cases before it match all the values of.
+	Shadowed,
+	/// A `select` without a default case, which rejects the values that match no case.
+	Rejected,
+	/// A `switch` without a default case, which does nothing for the values that match no case.
+	Ignored,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+	pub range: Range<usize>,
+	pub message: String,
+	pub kind: Kind,
+	/// The earlier cases that match the values of a shadowed case.
+	pub related: Vec<Note>,
+}
+
+/// Finds the shadowed cases of the `select`s and `switch`es of a program, and the ones without a default case that
+/// don't cover every value.
+pub fn check(program: &ast::Program, table: &SymbolTable, types: &TypeInfo) -> Vec<Finding> {
+	let root = program.syntax().clone();
+	let mut checker = Checker { root: root.clone(), table, types, findings: vec![] };
+	for node in root.descendants() {
+		if let Some(select) = ast::SelectExpr::cast(node.clone()) {
+			checker.select(&select);
+		} else if let Some(switch) = ast::SwitchStatement::cast(node) {
+			checker.switch(&switch);
+		}
+	}
+	checker.findings.sort_by_key(|finding| finding.range.start);
+	checker.findings
+}
+
+/// The cubes of patterns are given up on past this many, as combining the patterns of several keys, or subtracting
+/// cubes, can make many.
+const LIMIT: usize = 4096;
+
+/// The bits of a key that a case matches: those set in `mask` must equal those of `value`, while the others can be
+/// anything. The bits of `value` outside of `mask` are 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Pattern {
+	value: u128,
+	mask: u128,
+}
+
+impl Pattern {
+	fn new(value: u128, mask: u128) -> Self { Pattern { value: value & mask, mask } }
+}
+
+/// The patterns of the keys of a case, one per key, which match the values that match every one of them.
+type Cube = Vec<Pattern>;
+
+fn intersection(a: &Cube, b: &Cube) -> Option<Cube> {
+	a.iter()
+		.zip(b)
+		.map(|(x, y)| {
+			let conflict = (x.value ^ y.value) & x.mask & y.mask != 0;
+			(!conflict).then(|| Pattern::new(x.value | y.value, x.mask | y.mask))
+		})
+		.collect()
+}
+
+/// The values of `a` that aren't in `b`, as cubes that don't overlap: for each bit that `b` sets and `a` doesn't,
+/// the values of `a` that differ from `b` in that bit and agree with it in the bits before.
+fn difference(a: &Cube, b: &Cube) -> Vec<Cube> {
+	if intersection(a, b).is_none() {
+		return vec![a.clone()];
+	}
+	let mut cubes = vec![];
+	let mut current = a.clone();
+	for key in 0..a.len() {
+		let free = b[key].mask & !current[key].mask;
+		for bit in (0..128).map(|i| 1u128 << i).filter(|bit| free & bit != 0) {
+			let mut other = current.clone();
+			other[key] = Pattern::new(current[key].value | (!b[key].value & bit), current[key].mask | bit);
+			cubes.push(other);
+			current[key] = Pattern::new(current[key].value | (b[key].value & bit), current[key].mask | bit);
+		}
+	}
+	cubes
+}
+
+/// The values of some cubes that aren't in any of some others, `None` if there are too many cubes to tell.
+fn subtract(mut cubes: Vec<Cube>, others: &[Cube]) -> Option<Vec<Cube>> {
+	for other in others {
+		cubes = cubes.iter().flat_map(|cube| difference(cube, other)).collect();
+		if cubes.len() > LIMIT {
+			return None;
+		}
+	}
+	Some(cubes)
+}
+
+/// The patterns matching the values from `low` to `high`, each leaving out as many of the low bits as it can.
+fn range_patterns(mut low: u128, high: u128, width: u32) -> Vec<Pattern> {
+	let all = (1u128 << width) - 1;
+	let mut patterns = vec![];
+	while low <= high {
+		let mut size = 1u128;
+		while size <= all && low % (size * 2) == 0 && low + size * 2 - 1 <= high {
+			size *= 2;
+		}
+		patterns.push(Pattern::new(low, all & !(size - 1)));
+		low += size;
+	}
+	patterns
+}
+
+/// How the values of a key are encoded as bits, by its type.
+#[derive(Debug, Clone)]
+enum Key {
+	Bool,
+	Bit(u32),
+	/// `int<W>`, in two's complement.
+	Int(u32),
+	/// A serializable enum, with the names and values of its members.
+	Serializable(u32, Vec<(String, u128)>),
+	/// An enum that isn't serializable, `error` or the `action_run` of a table, whose members are encoded by their
+	/// position.
+	Members(Vec<String>),
+}
+
+impl Key {
+	fn width(&self) -> u32 {
+		match self {
+			Key::Bool => 1,
+			Key::Bit(width) | Key::Int(width) | Key::Serializable(width, _) => *width,
+			Key::Members(members) => (usize::BITS - members.len().saturating_sub(1).leading_zeros()).max(1),
+		}
+	}
+
+	/// The patterns of all the values of the key.
+	fn values(&self) -> Vec<Pattern> {
+		match self {
+			Key::Members(members) => range_patterns(0, members.len() as u128 - 1, self.width()),
+			_ => vec![Pattern::new(0, 0)],
+		}
+	}
+
+	fn all(&self) -> u128 { (1u128 << self.width()) - 1 }
+
+	fn encode(&self, value: &Value) -> Option<u128> {
+		match (self, value) {
+			(Key::Bool, Value::Bool(value)) => Some(u128::from(*value)),
+			(Key::Members(members), Value::Enum { member, .. } | Value::Error(member)) => {
+				members.iter().position(|name| name.ends_with(&format!(".{member}"))).map(|i| i as u128)
+			}
+			(Key::Members(_) | Key::Bool, _) => None,
+			(_, value) => self.encode_int(value.as_int()?),
+		}
+	}
+
+	fn encode_int(&self, value: &BigInt) -> Option<u128> {
+		let value = match value.sign() {
+			num_bigint::Sign::Minus => (BigInt::from(1u128 << self.width()) + value).try_into().ok()?,
+			_ => u128::try_from(value).ok()?,
+		};
+		Some(value & self.all())
+	}
+
+	/// The value encoded by some bits, as it would be written.
+	fn decode(&self, bits: u128) -> String {
+		match self {
+			Key::Bool => (bits != 0).to_string(),
+			Key::Bit(width) if *width <= 8 => bits.to_string(),
+			Key::Bit(width) => format!("0x{bits:0digits$x}", digits = (*width as usize + 3) / 4),
+			Key::Int(width) if bits >> (width - 1) != 0 => format!("-{}", (1u128 << width) - bits),
+			Key::Int(_) => bits.to_string(),
+			Key::Serializable(width, members) => match members.iter().find(|(_, value)| *value == bits) {
+				Some((name, _)) => name.clone(),
+				None => Key::Bit(*width).decode(bits),
+			},
+			Key::Members(members) => members.get(bits as usize).cloned().unwrap_or_default(),
+		}
+	}
+}
+
+/// Whether a key has any values, which an enum or `error` without members and a table without actions don't.
+fn nonempty(key: &Key) -> bool { !matches!(key, Key::Members(members) if members.is_empty()) }
+
+/// A case of a `select` or `switch`, with the cubes of the values it matches, `None` if they aren't known.
+struct Case {
+	range: Range<usize>,
+	text: String,
+	cubes: Option<Vec<Cube>>,
+}
+
+struct Checker<'a> {
+	root: SyntaxNode,
+	table: &'a SymbolTable,
+	types: &'a TypeInfo,
+	findings: Vec<Finding>,
+}
+
+impl Checker<'_> {
+	fn select(&mut self, select: &ast::SelectExpr) {
+		let keys: Option<Vec<_>> =
+			select.expressions().map(|expr| self.key(self.types.type_of(expr.syntax())?)).collect();
+		let cases: Vec<_> = select
+			.cases()
+			.filter_map(|case| case.keyset())
+			.map(|keyset| {
+				let cubes = keys.as_ref().and_then(|keys| self.keyset(&keyset, keys));
+				Case { range: keyset.text_range(), text: keyset.syntax().text(), cubes }
+			})
+			.collect();
+		let has_default = select.cases().filter_map(|case| case.keyset()).any(|keyset| matches_everything(&keyset));
+		self.cover(keys, &cases, has_default, select.text_range(), Kind::Rejected);
+	}
+
+	fn switch(&mut self, switch: &ast::SwitchStatement) {
+		let key = switch.expr().and_then(|expr| self.key(self.types.type_of(expr.syntax())?));
+		let ty = switch.expr().and_then(|expr| self.types.type_of(expr.syntax()));
+		let action_run = matches!(ty, Some(Type::ActionRun(_)));
+		let labels: Vec<_> = switch.cases().filter_map(|case| case.label()).collect();
+		let cases: Vec<_> = labels
+			.iter()
+			.map(|label| {
+				let cubes = key.as_ref().and_then(|key| {
+					let pattern = match (label.expr(), key) {
+						_ if label.is_default() => Pattern::new(0, 0),
+						// the labels of a switch on `action_run` are the names of actions rather than values
+						(Some(Expr::Name(name)), Key::Members(actions)) if action_run => {
+							let name = name.name()?.text();
+							Pattern::new(actions.iter().position(|action| *action == name)? as u128, key.all())
+						}
+						(Some(expr), _) => Pattern::new(key.encode(self.types.value_of(expr.syntax())?)?, key.all()),
+						(None, _) => return None,
+					};
+					Some(vec![vec![pattern]])
+				});
+				Case { range: label.text_range(), text: label.syntax().text(), cubes }
+			})
+			.collect();
+		let has_default = labels.iter().any(|label| label.is_default());
+		let range = switch.expr().map_or(switch.text_range(), |expr| expr.text_range());
+		self.cover(key.map(|key| vec![key]), &cases, has_default, range, Kind::Ignored);
+	}
+
+	/// Reports the shadowed cases, and the values that match no case if there is no default case.
+	fn cover(&mut self, keys: Option<Vec<Key>>, cases: &[Case], has_default: bool, range: Range<usize>, kind: Kind) {
+		let (statement, outcome) = match kind {
+			Kind::Rejected => ("select", "values that match no case are rejected"),
+			_ => ("switch", "nothing is done for values that match no case"),
+		};
+		let missing = format!("The {statement} has no default case, so {outcome}");
+		let values = keys.as_ref().and_then(|keys| {
+			keys.iter().try_fold(vec![vec![]], |cubes: Vec<Cube>, key| product(&cubes, &key.values()))
+		});
+		let (Some(keys), Some(values)) = (keys, values) else {
+			if !has_default {
+				self.findings.push(Finding { range, message: missing, kind, related: vec![] });
+			}
+			return;
+		};
+
+		let mut earlier: Vec<(&Case, Vec<Cube>)> = vec![];
+		for case in cases {
+			let Some(cubes) = &case.cubes else { continue };
+			let cubes: Vec<_> =
+				cubes.iter().flat_map(|cube| values.iter().filter_map(|value| intersection(cube, value))).collect();
+			let before: Vec<_> = earlier.iter().flat_map(|(_, cubes)| cubes.iter().cloned()).collect();
+			if subtract(cubes.clone(), &before).map_or(false, |rest| rest.is_empty()) {
+				let related = earlier
+					.iter()
+					.filter(|(_, others)| {
+						cubes.iter().any(|cube| others.iter().any(|other| intersection(cube, other).is_some()))
+					})
+					.map(|(other, _)| Note {
+						range: other.range.clone(),
+						message: format!("`{}` matches them first", other.text),
+					})
+					.collect();
+				let message =
+					format!("The case `{}` is never matched, as the cases before it match all its values", case.text);
+				self.findings.push(Finding { range: case.range.clone(), message, kind: Kind::Shadowed, related });
+			}
+			earlier.push((case, cubes));
+		}
+
+		if has_default {
+			return;
+		}
+		let all: Vec<_> = earlier.iter().flat_map(|(_, cubes)| cubes.iter().cloned()).collect();
+		let Some(rest) = subtract(values, &all) else {
+			self.findings.push(Finding { range, message: missing, kind, related: vec![] });
+			return;
+		};
+		// the smallest value that matches no case, if any
+		let Some(example) = rest.iter().map(|cube| cube.iter().map(|pattern| pattern.value).collect::<Vec<_>>()).min()
+		else {
+			return;
+		};
+		// the cases whose values aren't known may match it
+		let message = if cases.iter().any(|case| case.cubes.is_none()) {
+			missing
+		} else {
+			let example: Vec<_> = keys.iter().zip(example).map(|(key, bits)| key.decode(bits)).collect();
+			match &example[..] {
+				[value] => format!("{missing}, e.g. `{value}`"),
+				values => format!("{missing}, e.g. `({})`", values.join(", ")),
+			}
+		};
+		self.findings.push(Finding { range, message, kind, related: vec![] });
+	}
+
+	/// How the values of a type are encoded, `None` if they can't be.
+	fn key(&self, ty: &Type) -> Option<Key> {
+		match ty {
+			Type::Bool => Some(Key::Bool),
+			Type::Bit(width) if (1..=64).contains(width) => Some(Key::Bit(*width)),
+			Type::Int(width) if (1..=64).contains(width) => Some(Key::Int(*width)),
+			Type::Enum(nominal) => {
+				let members: Vec<_> = self.table.members(nominal.symbol).collect();
+				let name = |member| format!("{}.{}", nominal.name, self.table.symbol(member).name);
+				let width = match members.first().and_then(|&member| self.types.symbol_value(member)) {
+					Some(Value::Enum { value: Some(value), .. }) => match &**value {
+						Value::Int(_, Type::Bit(width)) if *width <= 64 => *width,
+						_ => return None,
+					},
+					_ => return Some(Key::Members(members.into_iter().map(name).collect())).filter(nonempty),
+				};
+				let values = members.iter().map(|&member| {
+					let value = self.types.symbol_value(member)?.as_int()?;
+					Some((name(member), Key::Bit(width).encode_int(value)?))
+				});
+				Some(Key::Serializable(width, values.collect::<Option<_>>()?))
+			}
+			Type::Error => {
+				let members = self.table.symbols().filter(|(_, symbol)| symbol.kind == SymbolKind::ErrorMember);
+				let names: Vec<_> = members.map(|(_, symbol)| format!("error.{}", symbol.name)).collect();
+				Some(Key::Members(names)).filter(nonempty)
+			}
+			Type::ActionRun(table) => {
+				let decl = ast::TableDecl::cast(self.table.symbol(table.symbol).decl.to_node(&self.root)?)?;
+				let actions: Vec<_> = decl.actions().iter().filter_map(|action| Some(action.name()?.text())).collect();
+				Some(Key::Members(actions)).filter(nonempty)
+			}
+			_ => None,
+		}
+	}
+
+	/// The cubes of the values a keyset matches, `None` if they aren't known.
+	fn keyset(&self, keyset: &Keyset, keys: &[Key]) -> Option<Vec<Cube>> {
+		let elements: Vec<_> = match keyset {
+			Keyset::Tuple(tuple) => tuple.elements().collect(),
+			keyset if keys.len() == 1 || matches_everything(keyset) => vec![keyset.clone(); keys.len()],
+			_ => return None,
+		};
+		if elements.len() != keys.len() {
+			return None;
+		}
+		let mut cubes = vec![vec![]];
+		for (element, key) in elements.iter().zip(keys) {
+			cubes = product(&cubes, &self.patterns(element, key)?)?;
+		}
+		Some(cubes)
+	}
+
+	/// The patterns of the values a keyset of a single key matches, `None` if they aren't known.
+	fn patterns(&self, keyset: &Keyset, key: &Key) -> Option<Vec<Pattern>> {
+		let value = |expr: Option<Expr>| key.encode(self.types.value_of(expr?.syntax())?);
+		match keyset {
+			Keyset::Default(_) | Keyset::DontCare(_) => Some(vec![Pattern::new(0, 0)]),
+			Keyset::Expr(expr) => Some(vec![Pattern::new(value(Some(expr.clone()))?, key.all())]),
+			Keyset::Mask(mask) => Some(vec![Pattern::new(value(mask.value())?, value(mask.mask())?)]),
+			Keyset::Range(range) => {
+				let (low, high) = (value(range.low())?, value(range.high())?);
+				match key {
+					// the negative values come after the positive ones in two's complement
+					Key::Int(width) => {
+						let sign = 1u128 << (width - 1);
+						let (low, high) = (low ^ sign, high ^ sign);
+						let patterns = range_patterns(low, high, *width);
+						Some(
+							patterns
+								.into_iter()
+								.map(|pattern| Pattern::new(pattern.value ^ sign, pattern.mask))
+								.collect(),
+						)
+					}
+					_ => Some(range_patterns(low, high, key.width())),
+				}
+			}
+			Keyset::Tuple(_) => None,
+		}
+	}
+}
+
+/// Every cube of `cubes` extended with every pattern of the next key, `None` if that makes too many cubes.
+fn product(cubes: &[Cube], patterns: &[Pattern]) -> Option<Vec<Cube>> {
+	if cubes.len().saturating_mul(patterns.len()) > LIMIT {
+		return None;
+	}
+	let cubes = cubes
+		.iter()
+		.flat_map(|cube| {
+			patterns.iter().map(move |&pattern| {
+				let mut cube = cube.clone();
+				cube.push(pattern);
+				cube
+			})
+		})
+		.collect();
+	Some(cubes)
+}
+
+#[cfg(test)]
+mod test {
+	use crate::p4_grammar::parse_and_check;
+	use pretty_assertions::assert_eq;
+
+	const TYPES: &str = r#"
+		header ethernet_t { bit<16> type; }
+		enum bit<8> Color { Red = 1, Green = 2 }
+		enum Mode { On, Off }
+	"#;
+
+	/// A finding with its related locations, by their text.
+	type Reported<'a> = (&'a str, String, Vec<(&'a str, String)>);
+
+	fn check(input: &str) -> Vec<Reported<'_>> {
+		let (program, table, types) = parse_and_check(input);
+		assert_eq!(types.errors(), &[]);
+		super::check(&program, &table, &types)
+			.into_iter()
+			.map(|finding| {
+				let related = finding.related.into_iter().map(|note| (&input[note.range], note.message)).collect();
+				(&input[finding.range], finding.message, related)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn selects() {
+		let input = format!(
+			"{TYPES}{}",
+			r#"
+			parser P(in ethernet_t eth, in bool a, in bool b, in int<8> n, in Color c) {
+				state start {
+					transition select(eth.type) { 0x8100 &&& 0xEFFF: accept; 0x9100: reject; }
+				}
+				state range {
+					transition select(eth.type) { 0x0800 .. 0x0FFF: accept; 0x0806: reject; default: accept; }
+				}
+				state pair {
+					transition select(a, b) { (true, _): accept; (false, true): accept; }
+				}
+				state signed {
+					transition select(n) { -128 .. -1: accept; 0 .. 127: accept; }
+				}
+				state color {
+					transition select(c) { Color.Red: accept; Color.Green: accept; }
+				}
+				state wide {
+					transition select(eth.type, eth.type, eth.type) { (1 .. 0xFFFE, 1 .. 0xFFFE, 1 .. 0xFFFE): accept; }
+				}
+			}
+			"#
+		);
+		assert_eq!(
+			check(&input),
+			vec![
+				(
+					"select(eth.type) { 0x8100 &&& 0xEFFF: accept; 0x9100: reject; }",
+					"The select has no default case, so values that match no case are rejected, e.g. `0x0000`"
+						.to_string(),
+					vec![]
+				),
+				(
+					"0x9100",
+					"The case `0x9100` is never matched, as the cases before it match all its values".to_string(),
+					vec![("0x8100 &&& 0xEFFF", "`0x8100 &&& 0xEFFF` matches them first".to_string())]
+				),
+				(
+					"0x0806",
+					"The case `0x0806` is never matched, as the cases before it match all its values".to_string(),
+					vec![("0x0800 .. 0x0FFF", "`0x0800 .. 0x0FFF` matches them first".to_string())]
+				),
+				(
+					"select(a, b) { (true, _): accept; (false, true): accept; }",
+					"The select has no default case, so values that match no case are rejected, e.g. `(false, false)`"
+						.to_string(),
+					vec![]
+				),
+				(
+					"select(c) { Color.Red: accept; Color.Green: accept; }",
+					"The select has no default case, so values that match no case are rejected, e.g. `0`".to_string(),
+					vec![]
+				),
+				(
+					"select(eth.type, eth.type, eth.type) { (1 .. 0xFFFE, 1 .. 0xFFFE, 1 .. 0xFFFE): accept; }",
+					// the case splits into too many cubes to tell which values it matches
+					"The select has no default case, so values that match no case are rejected".to_string(),
+					vec![]
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn switches() {
+		let input = format!(
+			"{TYPES}{}",
+			r#"
+			control C(in Mode mode) {
+				action a() {}
+				action b() {}
+				table t { actions = { a; b; } }
+				apply {
+					switch (t.apply().action_run) { a: {} }
+					switch (t.apply().action_run) { a: b: {} default: {} }
+					switch (mode) { Mode.On: {} Mode.On: {} }
+				}
+			}
+			"#
+		);
+		assert_eq!(
+			check(&input),
+			vec![
+				(
+					"t.apply().action_run",
+					"The switch has no default case, so nothing is done for values that match no case, e.g. `b`"
+						.to_string(),
+					vec![]
+				),
+				(
+					"default",
+					"The case `default` is never matched, as the cases before it match all its values".to_string(),
+					vec![("a", "`a` matches them first".to_string()), ("b", "`b` matches them first".to_string())]
+				),
+				(
+					"mode",
+					"The switch has no default case, so nothing is done for values that match no case, e.g. `Mode.Off`"
+						.to_string(),
+					vec![]
+				),
+				(
+					"Mode.On",
+					"The case `Mode.On` is never matched, as the cases before it match all its values".to_string(),
+					vec![("Mode.On", "`Mode.On` matches them first".to_string())]
+				),
+			]
+		);
+	}
+}
@@ -0,0 +1,414 @@
+//! Checks of the directions of parameters, i.e. of copy-in/copy-out.
+//!
+//! The arguments of `out` and `inout` parameters are copied back when the call returns, so they must be l-values that
+//! can be written: `in` and directionless parameters and constants are read-only, like the results of expressions.
+//! The same goes for the targets of assignments and the headers made valid or invalid. The directionless parameters of
+//! an action are supplied by the control plane, after the directional ones bound by the program, so they must come
+//! last, and the parameters of constructors can't have a direction at all.
+
+use std::ops::Range;
+
+use crate::{
+	ast::{self, Direction, Expr, Suffix},
+	symbols::{SymbolId, SymbolKind, SymbolTable},
+	syntax::AstNode,
+	types::{Type, TypeInfo},
+};
+
+/// A location related to an error, e.g. the declaration of the parameter an argument is passed to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+	pub range: Range<usize>,
+	pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+	pub range: Range<usize>,
+	pub message: String,
+	pub related: Vec<Note>,
+}
+
+/// Finds the writes of read-only values, the arguments of `out` and `inout` parameters that aren't l-values, and the
+/// parameters declared with a direction where they can't have one, in the order of the program.
+pub fn check(program: &ast::Program, table: &SymbolTable, types: &TypeInfo) -> Vec<Error> {
+	let mut checker = Checker { root: program.syntax().clone(), table, types, errors: vec![] };
+	for node in program.syntax().descendants() {
+		if let Some(action) = ast::ActionDecl::cast(node.clone()) {
+			checker.action(&action);
+		} else if let Some(constructor) = ast::ConstructorPrototype::cast(node.clone()) {
+			checker.constructor(&constructor.parameters());
+		} else if let Some(parser) = ast::ParserDecl::cast(node.clone()) {
+			checker.constructor(&parser.constructor_parameters());
+		} else if let Some(control) = ast::ControlDecl::cast(node.clone()) {
+			checker.constructor(&control.constructor_parameters());
+		} else if let Some(package) = ast::PackageDecl::cast(node.clone()) {
+			checker.constructor(&package.parameters());
+		} else if let Some(assignment) = ast::AssignmentStatement::cast(node.clone()) {
+			if let Some(target) = assignment.target() {
+				let access = checker.access(target.name(), &target.suffixes().collect::<Vec<_>>());
+				checker.write(access, target.text_range());
+			}
+		} else if let Some(call) = ast::MethodCallStatement::cast(node.clone()) {
+			let Some(callee) = call.callee() else { continue };
+			let suffixes: Vec<_> = callee.suffixes().collect();
+			checker.call(callee.name(), &suffixes, &call.arguments(), call.text_range());
+		} else if let Some(postfix) = ast::PostfixExpr::cast(node.clone()) {
+			let base = match postfix.base() {
+				Some(Expr::Name(name)) => name.name(),
+				_ => None,
+			};
+			let suffixes: Vec<_> = postfix.suffixes().collect();
+			for (i, suffix) in suffixes.iter().enumerate() {
+				if let Suffix::Call(call) = suffix {
+					let base = if i == 0 { base.clone() } else { None };
+					checker.call(base, &suffixes[..i], &call.arguments(), suffix.text_range());
+				}
+			}
+		} else if let Some(action) = ast::ActionRef::cast(node) {
+			// the directional parameters of the actions of a table are bound in the `actions` list
+			let Some(symbol) = action.name().and_then(|name| table.resolve(&name)) else { continue };
+			let params = checker.parameters(symbol).into_iter().filter(|param| param.direction().is_some());
+			let params: Vec<_> = params.collect();
+			checker.arguments(&params, &action.arguments().unwrap_or_default());
+		}
+	}
+	checker.errors
+}
+
+/// Whether a value can be written, e.g. by passing it to an `out` parameter.
+enum Access {
+	Writable,
+	NotLValue,
+	/// A parameter or constant that can only be read, with what it is.
+	ReadOnly(SymbolId, &'static str),
+}
+
+struct Checker<'a> {
+	root: ast::SyntaxNode,
+	table: &'a SymbolTable,
+	types: &'a TypeInfo,
+	errors: Vec<Error>,
+}
+
+impl Checker<'_> {
+	fn node<N: AstNode<crate::lexer::Token>>(&self, symbol: SymbolId) -> Option<N> {
+		N::cast(self.table.symbol(symbol).decl.to_node(&self.root)?)
+	}
+
+	/// A note pointing at the declaration of a parameter or constant.
+	fn declared(&self, symbol: SymbolId) -> Note {
+		let symbol = self.table.symbol(symbol);
+		Note { range: symbol.name_range.clone(), message: format!("`{}` is declared here", symbol.name) }
+	}
+
+	/// Checks that the directionless parameters of an action come after the directional ones.
+	fn action(&mut self, action: &ast::ActionDecl) {
+		let params = action.parameters();
+		let Some(first) = params.iter().position(|param| param.direction().is_none()) else { return };
+		let Some(directionless) = params[first].name() else { return };
+		let directional = params[first..].iter().filter(|param| param.direction().is_some());
+		for name in directional.filter_map(|param| param.name()) {
+			let message = format!(
+				"The parameter `{}` has a direction, so it must come before the directionless parameter `{}`",
+				name.text(),
+				directionless.text()
+			);
+			let note = Note {
+				range: directionless.text_range(),
+				message: format!("`{}` is supplied by the control plane", directionless.text()),
+			};
+			self.errors.push(Error { range: name.text_range(), message, related: vec![note] });
+		}
+	}
+
+	/// Checks that the parameters of a constructor (of an extern, parser, control or package) have no direction.
+	fn constructor(&mut self, params: &[ast::Parameter]) {
+		for param in params.iter().filter(|param| param.direction().is_some()) {
+			let name = param.name().map(|name| name.text()).unwrap_or_default();
+			let message = format!("`{name}` is a constructor parameter, which cannot have a direction");
+			self.errors.push(Error { range: param.text_range(), message, related: vec![] });
+		}
+	}
+
+	/// Checks the arguments of a call whose callee is a name followed by suffixes, and the receiver of the built-in
+	/// methods that modify it, like `setValid`.
+	fn call(&mut self, base: Option<ast::Name>, callee: &[Suffix], args: &[ast::Argument], range: Range<usize>) {
+		let name = match callee.last() {
+			None => base.clone(),
+			Some(Suffix::Member(member)) => member.name(),
+			Some(_) => None,
+		};
+		let Some(name) = name else { return };
+		let params = match (self.types.callee(name.syntax()), self.types.type_of(name.syntax())) {
+			(Some(symbol), _) => self.parameters(symbol),
+			(None, Some(Type::BuiltinMethod(receiver, method))) => match (&**receiver, method.as_str()) {
+				(Type::Parser(nominal) | Type::Control(nominal), "apply") => self.apply_parameters(nominal.symbol),
+				(_, "setValid" | "setInvalid" | "push_front" | "pop_front") => {
+					let access = self.access(base, &callee[..callee.len() - 1]);
+					self.write(access, range);
+					return;
+				}
+				_ => return,
+			},
+			_ => return,
+		};
+		self.arguments(&params, args);
+	}
+
+	/// Checks that the arguments of `out` and `inout` parameters can be written.
+	fn arguments(&mut self, params: &[ast::Parameter], args: &[ast::Argument]) {
+		for (i, arg) in args.iter().enumerate() {
+			let param = match arg.name() {
+				Some(name) => params.iter().find(|param| param.name().map_or(false, |p| p.text() == name.text())),
+				None => params.get(i),
+			};
+			let (Some(param), Some(value)) = (param, arg.value()) else { continue };
+			let direction = match param.direction() {
+				Some(Direction::Out) => "out",
+				Some(Direction::InOut) => "inout",
+				_ => continue,
+			};
+			let Some(name) = param.name() else { continue };
+			let declared = Note { range: name.text_range(), message: format!("`{}` is declared here", name.text()) };
+			match self.expr_access(&value) {
+				Access::Writable => (),
+				Access::NotLValue => {
+					let message =
+						format!("The argument of the `{direction}` parameter `{}` must be an l-value", name.text());
+					self.errors.push(Error { range: value.text_range(), message, related: vec![declared] });
+				}
+				Access::ReadOnly(symbol, what) => {
+					let message = format!(
+						"`{}` is {what}, which cannot be passed to the `{direction}` parameter `{}`",
+						self.table.symbol(symbol).name,
+						name.text()
+					);
+					let related = vec![declared, self.declared(symbol)];
+					self.errors.push(Error { range: value.text_range(), message, related });
+				}
+			}
+		}
+	}
+
+	/// Reports a write of a value that can't be written.
+	fn write(&mut self, access: Access, range: Range<usize>) {
+		if let Access::ReadOnly(symbol, what) = access {
+			let message = format!("`{}` is {what}, which cannot be written", self.table.symbol(symbol).name);
+			self.errors.push(Error { range, message, related: vec![self.declared(symbol)] });
+		}
+	}
+
+	fn expr_access(&self, expr: &Expr) -> Access {
+		match expr {
+			Expr::Paren(paren) => paren.expr().map_or(Access::Writable, |inner| self.expr_access(&inner)),
+			Expr::Name(name) => self.access(name.name(), &[]),
+			Expr::Postfix(postfix) => match postfix.base() {
+				Some(Expr::Name(name)) => self.access(name.name(), &postfix.suffixes().collect::<Vec<_>>()),
+				_ => Access::NotLValue,
+			},
+			_ => Access::NotLValue,
+		}
+	}
+
+	/// Whether a name followed by suffixes, e.g. `hdr.ipv4[0].ttl`, can be written.
+	fn access(&self, name: Option<ast::Name>, suffixes: &[Suffix]) -> Access {
+		for (i, suffix) in suffixes.iter().enumerate() {
+			match suffix {
+				Suffix::Call(_) => return Access::NotLValue,
+				Suffix::Member(member) => {
+					// the size of a header stack is only read
+					let stack = match i.checked_sub(1) {
+						Some(previous) => self.types.type_of(suffixes[previous].syntax()),
+						None => name
+							.as_ref()
+							.and_then(|name| self.table.resolve(name))
+							.and_then(|s| self.types.symbol_type(s)),
+					};
+					let member = member.name().map(|name| name.text()).unwrap_or_default();
+					if matches!(stack, Some(Type::Stack(..))) && matches!(member.as_str(), "size" | "lastIndex") {
+						return Access::NotLValue;
+					}
+				}
+				Suffix::Index(_) | Suffix::Slice(_) => (),
+			}
+		}
+
+		let Some(symbol) = name.and_then(|name| self.table.resolve(&name)) else { return Access::Writable };
+		match self.table.symbol(symbol).kind {
+			SymbolKind::Variable => Access::Writable,
+			SymbolKind::Constant => Access::ReadOnly(symbol, "a constant"),
+			SymbolKind::Parameter => match self.node::<ast::Parameter>(symbol).and_then(|param| param.direction()) {
+				Some(Direction::Out | Direction::InOut) => Access::Writable,
+				Some(Direction::In) => Access::ReadOnly(symbol, "an `in` parameter"),
+				None => Access::ReadOnly(symbol, "a directionless parameter"),
+			},
+			_ => Access::NotLValue,
+		}
+	}
+
+	/// The parameters of an action, function or method.
+	fn parameters(&self, symbol: SymbolId) -> Vec<ast::Parameter> {
+		match self.table.symbol(symbol).kind {
+			SymbolKind::Action => self.node::<ast::ActionDecl>(symbol).map(|decl| decl.parameters()),
+			SymbolKind::Function => self.node::<ast::FunctionDecl>(symbol).map(|decl| decl.parameters()),
+			SymbolKind::ExternFunction => {
+				self.node::<ast::ExternDecl>(symbol).and_then(|decl| decl.function()).map(|f| f.parameters())
+			}
+			SymbolKind::Method => self.node::<ast::MethodPrototype>(symbol).map(|decl| decl.parameters()),
+			_ => None,
+		}
+		.unwrap_or_default()
+	}
+
+	fn apply_parameters(&self, symbol: SymbolId) -> Vec<ast::Parameter> {
+		match self.table.symbol(symbol).kind {
+			SymbolKind::Parser => self.node::<ast::ParserDecl>(symbol).map(|decl| decl.parameters()),
+			SymbolKind::ParserType => self.node::<ast::ParserTypeDecl>(symbol).map(|decl| decl.parameters()),
+			SymbolKind::Control => self.node::<ast::ControlDecl>(symbol).map(|decl| decl.parameters()),
+			SymbolKind::ControlType => self.node::<ast::ControlTypeDecl>(symbol).map(|decl| decl.parameters()),
+			_ => None,
+		}
+		.unwrap_or_default()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::p4_grammar::parse_and_check;
+	use pretty_assertions::assert_eq;
+
+	/// An error with its related locations, by their text.
+	type Reported<'a> = (&'a str, String, Vec<(&'a str, String)>);
+
+	fn check(input: &str) -> Vec<Reported<'_>> {
+		let (program, table, types) = parse_and_check(input);
+		let errors = super::check(&program, &table, &types);
+		errors
+			.into_iter()
+			.map(|error| {
+				let related = error.related.into_iter().map(|note| (&input[note.range], note.message)).collect();
+				(&input[error.range], error.message, related)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn arguments() {
+		let input = r#"
+			header h_t { bit<8> f; }
+			extern packet_in { void extract<T>(out T hdr); }
+			extern void update(inout bit<8> value, in bit<8> delta);
+			const bit<8> LIMIT = 8;
+			parser P(packet_in pkt, in h_t meta, out h_t hdr) {
+				state start {
+					pkt.extract(hdr);
+					pkt.extract(meta);
+					transition accept;
+				}
+			}
+			control C(inout h_t hdr, in bit<8> port) {
+				h_t[2] stack;
+				action a(out bit<8> x) { x = 1; }
+				apply {
+					update(hdr.f, 1);
+					update(stack[0].f[3:0] ++ 4w0, 1);
+					update(LIMIT, 1);
+					update(port, 1);
+					update(value = stack.size, delta = 1);
+					a(hdr.f);
+					a(hdr.isValid() ? 8w1 : 8w0);
+				}
+			}
+		"#;
+		let declared = |name: &'static str| (name, format!("`{name}` is declared here"));
+		assert_eq!(
+			check(input),
+			vec![
+				(
+					"meta",
+					"`meta` is an `in` parameter, which cannot be passed to the `out` parameter `hdr`".to_string(),
+					vec![declared("hdr"), declared("meta")]
+				),
+				(
+					"stack[0].f[3:0] ++ 4w0",
+					"The argument of the `inout` parameter `value` must be an l-value".to_string(),
+					vec![declared("value")]
+				),
+				(
+					"LIMIT",
+					"`LIMIT` is a constant, which cannot be passed to the `inout` parameter `value`".to_string(),
+					vec![declared("value"), declared("LIMIT")]
+				),
+				(
+					"port",
+					"`port` is an `in` parameter, which cannot be passed to the `inout` parameter `value`".to_string(),
+					vec![declared("value"), declared("port")]
+				),
+				(
+					"stack.size",
+					"The argument of the `inout` parameter `value` must be an l-value".to_string(),
+					vec![declared("value")]
+				),
+				(
+					"hdr.isValid() ? 8w1 : 8w0",
+					"The argument of the `out` parameter `x` must be an l-value".to_string(),
+					vec![declared("x")]
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn writes_and_declarations() {
+		let input = r#"
+			header h_t { bit<8> f; }
+			extern Counter { Counter(in bit<32> size); }
+			control C(in h_t hdr, inout bit<8> x)(bit<8> seed) {
+				action a(bit<8> port, inout bit<8> y) { y = port; port = 1; }
+				apply {
+					hdr.f = 1;
+					hdr.setInvalid();
+					x = seed;
+					seed = x;
+				}
+			}
+		"#;
+		assert_eq!(
+			check(input),
+			vec![
+				(
+					"in bit<32> size",
+					"`size` is a constructor parameter, which cannot have a direction".to_string(),
+					vec![]
+				),
+				(
+					"y",
+					"The parameter `y` has a direction, so it must come before the directionless parameter `port`"
+						.to_string(),
+					vec![("port", "`port` is supplied by the control plane".to_string())]
+				),
+				(
+					"port",
+					"`port` is a directionless parameter, which cannot be written".to_string(),
+					vec![("port", "`port` is declared here".to_string())]
+				),
+				(
+					"hdr.f",
+					"`hdr` is an `in` parameter, which cannot be written".to_string(),
+					vec![("hdr", "`hdr` is declared here".to_string())]
+				),
+				(
+					"hdr.setInvalid();",
+					"`hdr` is an `in` parameter, which cannot be written".to_string(),
+					vec![("hdr", "`hdr` is declared here".to_string())]
+				),
+				(
+					"seed",
+					"`seed` is a directionless parameter, which cannot be written".to_string(),
+					vec![("seed", "`seed` is declared here".to_string())]
+				),
+			]
+		);
+	}
+}
@@ -0,0 +1,804 @@
+//! Definite assignment of local variables and `out` parameters.
+//!
+//! Local variables and `out` parameters start out with unspecified values. The variables that are assigned on every
+//! path (and on some path) are followed through the bodies of parsers, controls, actions and functions, field by field
+//! for structs, so that reading a struct of which only some fields are assigned is reported as well. Headers are left
+//! out, since reading an invalid header is reported by [`validity`](crate::validity).
+//!
+//! Calling an action, or applying a table, counts as assigning the variables that the actions assign.
+
+use std::{
+	collections::{BTreeSet, HashMap, HashSet},
+	ops::Range,
+};
+
+use crate::{
+	ast::{self, Direction, Expr, Statement, Suffix, SyntaxNode},
+	state_graph::{Label, StateGraph},
+	symbols::{SymbolId, SymbolKind, SymbolTable},
+	syntax::AstNode,
+	types::{Type, TypeInfo},
+};
+
+/// A quick fix, which inserts text after a range of the program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+	pub title: String,
+	pub range: Range<usize>,
+	pub text: String,
+	/// Whether the text is a statement, to be put on a line of its own with the indentation of the code after it.
+	pub statement: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+	pub range: Range<usize>,
+	pub message: String,
+	/// A fix initializing the variable to zero, if it has a numeric or boolean type.
+	pub fix: Option<Fix>,
+}
+
+/// Finds the reads of variables that may be uninitialized, and the `out` parameters that may not be written.
+pub fn check(program: &ast::Program, table: &SymbolTable, types: &TypeInfo) -> Vec<Warning> {
+	let mut analysis = Analysis {
+		root: program.syntax().clone(),
+		table,
+		types,
+		tracked: HashMap::new(),
+		outputs: vec![],
+		report: true,
+		warnings: vec![],
+	};
+	let mut analyzed = HashSet::new();
+	for (id, symbol) in table.symbols() {
+		let analyze =
+			matches!(symbol.kind, SymbolKind::Parser | SymbolKind::Control | SymbolKind::Action | SymbolKind::Function);
+		if analyze && analyzed.insert(symbol.decl.clone()) {
+			analysis.block(id);
+		}
+	}
+	analysis.warnings
+}
+
+/// A field of a variable, e.g. `m.a.b` as the symbol of `m` and `["a", "b"]`. The variable itself has no fields.
+type Field = (SymbolId, Vec<String>);
+
+/// The fields assigned at a point of a program.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Assigned {
+	/// The fields assigned on every path to the point.
+	must: BTreeSet<Field>,
+	/// The fields assigned on some path to the point.
+	may: BTreeSet<Field>,
+}
+
+impl Assigned {
+	/// The fields assigned at a point reached from two others, `None` standing for a point that can't be reached.
+	fn join(a: Option<Assigned>, b: Option<Assigned>) -> Option<Assigned> {
+		match (a, b) {
+			(Some(a), Some(b)) => Some(Assigned {
+				must: a.must.intersection(&b.must).cloned().collect(),
+				may: a.may.union(&b.may).cloned().collect(),
+			}),
+			(a, b) => a.or(b),
+		}
+	}
+}
+
+/// A variable or `out` parameter whose assignments are followed.
+struct Variable {
+	name: String,
+	/// The fields that are assigned separately, e.g. `a` and `b.c` of a struct, or just the variable itself.
+	leaves: Vec<Vec<String>>,
+	fix: Option<Fix>,
+}
+
+impl Variable {
+	fn display(&self, fields: &[String]) -> String {
+		fields.iter().fold(self.name.clone(), |path, field| format!("{path}.{field}"))
+	}
+}
+
+/// What calling an action or applying a table does besides passing the arguments.
+enum Effect {
+	/// Reads an expression, e.g. a key of a table.
+	Read(Expr),
+	Assign(Target),
+}
+
+/// Where a variable is read or written, e.g. `m.a` of `m.a[3:0]`.
+struct Target {
+	symbol: SymbolId,
+	fields: Vec<String>,
+	/// Whether only part of the field is accessed, i.e. an element or a slice.
+	partial: bool,
+}
+
+struct Analysis<'a> {
+	root: SyntaxNode,
+	table: &'a SymbolTable,
+	types: &'a TypeInfo,
+	/// The variables of the body being analyzed.
+	tracked: HashMap<SymbolId, Variable>,
+	/// The `out` parameters of the body being analyzed.
+	outputs: Vec<(SymbolId, Range<usize>)>,
+	/// Whether to report warnings, which is turned off while iterating to a fixed point over the states of a parser.
+	report: bool,
+	warnings: Vec<Warning>,
+}
+
+impl Analysis<'_> {
+	fn warn(&mut self, range: Range<usize>, message: String, fix: Option<Fix>) {
+		if self.report && !self.warnings.iter().any(|warning| warning.range == range && warning.message == message) {
+			self.warnings.push(Warning { range, message, fix });
+		}
+	}
+
+	fn node<N: AstNode<crate::lexer::Token>>(&self, symbol: SymbolId) -> Option<N> {
+		N::cast(self.table.symbol(symbol).decl.to_node(&self.root)?)
+	}
+
+	/// The fields of a value of a type that are assigned separately.
+	fn leaves(&self, ty: &Type, path: &mut Vec<String>, leaves: &mut Vec<Vec<String>>) {
+		match ty {
+			Type::Header(_) | Type::HeaderUnion(_) | Type::Stack(..) | Type::Extern(_) => (),
+			Type::Struct(nominal) => {
+				for field in self.table.members(nominal.symbol) {
+					if let Some(ty) = self.types.symbol_type(field) {
+						path.push(self.table.symbol(field).name.clone());
+						self.leaves(ty, path, leaves);
+						path.pop();
+					}
+				}
+			}
+			_ => leaves.push(path.clone()),
+		}
+	}
+
+	/// Starts following a variable, which is unassigned.
+	fn track(&mut self, symbol: SymbolId, fix: impl FnOnce(&str) -> Option<Fix>) {
+		let Some(ty) = self.types.symbol_type(symbol) else { return };
+		let mut leaves = vec![];
+		self.leaves(ty, &mut vec![], &mut leaves);
+		let name = self.table.symbol(symbol).name.clone();
+		let zero = match ty {
+			ty if ty.is_numeric() => Some("0"),
+			Type::Bool => Some("false"),
+			_ => None,
+		};
+		let fix = zero.and_then(fix);
+		self.tracked.insert(symbol, Variable { name, leaves, fix });
+	}
+
+	/// Analyzes a parser, control, action or function.
+	fn block(&mut self, symbol: SymbolId) {
+		let Some(node) = self.table.symbol(symbol).decl.to_node(&self.root) else { return };
+		let name = self.table.symbol(symbol).name.clone();
+		self.tracked.clear();
+		self.outputs.clear();
+
+		let (params, body) = if let Some(parser) = ast::ParserDecl::cast(node.clone()) {
+			(parser.parameters(), None)
+		} else if let Some(control) = ast::ControlDecl::cast(node.clone()) {
+			(control.parameters(), control.apply())
+		} else if let Some(action) = ast::ActionDecl::cast(node.clone()) {
+			(action.parameters(), action.body())
+		} else if let Some(function) = ast::FunctionDecl::cast(node.clone()) {
+			(function.parameters(), function.body())
+		} else {
+			return;
+		};
+
+		// `out` parameters are assigned to zero by a statement at the start of the body
+		let first = body.as_ref().and_then(|body| body.statements().next()).map(|statement| statement.syntax().clone());
+		let first = first.or_else(|| {
+			let parser = ast::ParserDecl::cast(node.clone())?;
+			let start = parser.states().find(|state| state.name().map_or(false, |name| name.text() == "start"))?;
+			let first = start.statements().next().map(|statement| statement.syntax().clone());
+			first
+		});
+		for param in params.iter().filter(|param| param.direction() == Some(Direction::Out)) {
+			let Some(name) = param.name() else { continue };
+			let Some(param) = self.table.declaration_at(name.text_range().start) else { continue };
+			self.track(param, |zero| {
+				let start = first.as_ref()?.text_range().start;
+				Some(Fix {
+					title: format!("Initialize `{}` to {zero}", name.text()),
+					range: start..start,
+					text: format!("{} = {zero};", name.text()),
+					statement: true,
+				})
+			});
+			self.outputs.push((param, name.text_range()));
+		}
+
+		let mut assigned = Some(Assigned::default());
+		if let Some(parser) = ast::ParserDecl::cast(node.clone()) {
+			for decl in parser.locals() {
+				self.local(&decl, &mut assigned);
+			}
+			let accepted = self.parser(&parser, assigned);
+			if let Some(accepted) = accepted {
+				self.unwritten(&accepted, None, &format!("before `{name}` accepts"));
+			}
+			return;
+		}
+		if let Some(control) = ast::ControlDecl::cast(node) {
+			for decl in control.locals() {
+				self.local(&decl, &mut assigned);
+			}
+		}
+		for statement in body.iter().flat_map(|body| body.statements()) {
+			self.statement(&statement, &mut assigned);
+		}
+		if let Some(assigned) = assigned {
+			self.unwritten(&assigned, None, &format!("before `{name}` returns"));
+		}
+	}
+
+	/// Analyzes the states of a parser until the fields assigned at their starts no longer change, then reports the
+	/// warnings. Returns the fields assigned when the parser accepts.
+	fn parser(&mut self, parser: &ast::ParserDecl, entry: Option<Assigned>) -> Option<Assigned> {
+		let graph = StateGraph::new(parser);
+		let states: Vec<_> = graph
+			.states()
+			.map(|(_, state)| state.decl.as_ref().and_then(|decl| ast::ParserState::cast(decl.to_node(&self.root)?)))
+			.collect();
+		let mut starts: Vec<Option<Assigned>> = vec![None; states.len()];
+		let start = graph.start()?;
+		starts[start.0] = entry;
+
+		let report = std::mem::replace(&mut self.report, false);
+		let mut worklist = vec![start];
+		while let Some(id) = worklist.pop() {
+			let Some(state) = &states[id.0] else { continue };
+			let end = self.state(state, starts[id.0].clone());
+			for edge in graph.edges_from(id) {
+				let Some(to) = edge.to.filter(|&to| to != StateGraph::REJECT) else { continue };
+				if matches!(edge.label, Label::Implicit | Label::NoMatch { .. }) {
+					continue;
+				}
+				let joined = Assigned::join(starts[to.0].clone(), end.clone());
+				if joined != starts[to.0] {
+					starts[to.0] = joined;
+					worklist.push(to);
+				}
+			}
+		}
+		self.report = report;
+
+		for (state, assigned) in states.iter().zip(&starts) {
+			if let (Some(state), Some(_)) = (state, assigned) {
+				self.state(state, assigned.clone());
+			}
+		}
+		starts[StateGraph::ACCEPT.0].take()
+	}
+
+	/// Analyzes the statements and the `select` of a state.
+	fn state(&mut self, state: &ast::ParserState, mut assigned: Option<Assigned>) -> Option<Assigned> {
+		for statement in state.statements() {
+			self.statement(&statement, &mut assigned);
+		}
+		let select = state.transition().and_then(|transition| transition.select());
+		if let (Some(select), Some(current)) = (select, &mut assigned) {
+			for expr in select.expressions() {
+				self.expr(&expr, current);
+			}
+		}
+		assigned
+	}
+
+	fn local(&mut self, decl: &ast::Declaration, assigned: &mut Option<Assigned>) {
+		match decl {
+			ast::Declaration::Variable(variable) => self.statement(&Statement::Variable(variable.clone()), assigned),
+			ast::Declaration::Constant(constant) => self.statement(&Statement::Constant(constant.clone()), assigned),
+			_ => (),
+		}
+	}
+
+	fn statement(&mut self, statement: &Statement, assigned: &mut Option<Assigned>) {
+		let Some(current) = assigned.as_mut() else { return };
+		match statement {
+			Statement::Assignment(assignment) => {
+				if let Some(value) = assignment.value() {
+					self.expr(&value, current);
+				}
+				if let Some(target) = assignment.target() {
+					self.suffix_reads(target.suffixes(), current);
+					if let Some(target) = self.target(target.name(), target.suffixes()) {
+						self.assign(&target, current);
+					}
+				}
+			}
+			Statement::MethodCall(call) => {
+				let Some(callee) = call.callee() else { return };
+				let suffixes: Vec<_> = callee.suffixes().collect();
+				self.call(callee.name(), &suffixes, &call.arguments(), current);
+			}
+			Statement::Conditional(conditional) => {
+				if let Some(condition) = conditional.condition() {
+					self.expr(&condition, current);
+				}
+				let mut then_assigned = Some(current.clone());
+				let mut else_assigned = Some(current.clone());
+				if let Some(branch) = conditional.then_branch() {
+					self.statement(&branch, &mut then_assigned);
+				}
+				if let Some(branch) = conditional.else_branch() {
+					self.statement(&branch, &mut else_assigned);
+				}
+				*assigned = Assigned::join(then_assigned, else_assigned);
+			}
+			Statement::Block(block) => {
+				for statement in block.statements() {
+					self.statement(&statement, assigned);
+				}
+			}
+			Statement::Exit(_) => *assigned = None,
+			Statement::Return(ret) => {
+				if let Some(value) = ret.value() {
+					self.expr(&value, current);
+				}
+				self.unwritten(current, Some(ret.text_range()), "before this `return`");
+				*assigned = None;
+			}
+			Statement::Switch(switch) => {
+				if let Some(expr) = switch.expr() {
+					self.expr(&expr, current);
+				}
+				let entry = current.clone();
+				let mut joined = None;
+				let mut has_default = false;
+				for case in switch.cases() {
+					has_default |= case.label().map_or(false, |label| label.is_default());
+					let Some(body) = case.body() else { continue };
+					let mut branch = Some(entry.clone());
+					for statement in body.statements() {
+						self.statement(&statement, &mut branch);
+					}
+					joined = Assigned::join(joined, branch);
+				}
+				if !has_default {
+					joined = Assigned::join(joined, Some(entry));
+				}
+				*assigned = joined;
+			}
+			Statement::Variable(variable) => {
+				if let Some(value) = variable.initializer() {
+					self.expr(&value, current);
+				}
+				let Some(name) = variable.name() else { return };
+				let Some(symbol) = self.table.declaration_at(name.text_range().start) else { return };
+				self.track(symbol, |zero| {
+					Some(Fix {
+						title: format!("Initialize `{}` to {zero}", name.text()),
+						range: name.text_range(),
+						text: format!(" = {zero}"),
+						statement: false,
+					})
+				});
+				// a variable declared in a parser state is unassigned again whenever the state is entered
+				current.must.retain(|(field, _)| *field != symbol);
+				current.may.retain(|(field, _)| *field != symbol);
+				if variable.initializer().is_some() {
+					self.assign(&Target { symbol, fields: vec![], partial: false }, current);
+				}
+			}
+			Statement::Constant(constant) => {
+				if let Some(value) = constant.value() {
+					self.expr(&value, current);
+				}
+			}
+			Statement::Instantiation(_) | Statement::Empty(_) => (),
+		}
+	}
+
+	/// Checks the reads of an expression, and the assignments of the calls in it.
+	fn expr(&mut self, expr: &Expr, assigned: &mut Assigned) {
+		match expr {
+			Expr::Name(name) => {
+				if let Some(target) = self.target(name.name(), std::iter::empty()) {
+					self.read(&target, name.text_range(), assigned);
+				}
+			}
+			Expr::Postfix(postfix) => {
+				let suffixes: Vec<_> = postfix.suffixes().collect();
+				let base = match postfix.base() {
+					Some(Expr::Name(name)) => name.name(),
+					Some(base) => {
+						self.expr(&base, assigned);
+						None
+					}
+					None => None,
+				};
+				match suffixes.iter().position(|suffix| matches!(suffix, Suffix::Call(_))) {
+					Some(i) => {
+						let Suffix::Call(call) = &suffixes[i] else { unreachable!() };
+						self.call(base, &suffixes[..i], &call.arguments(), assigned);
+						self.suffix_reads(suffixes[i + 1..].iter().cloned(), assigned);
+					}
+					None => {
+						self.suffix_reads(suffixes.iter().cloned(), assigned);
+						if let Some(target) = self.target(base, suffixes.iter().cloned()) {
+							let end =
+								suffixes.last().map_or(postfix.text_range().end, |suffix| suffix.text_range().end);
+							self.read(&target, postfix.text_range().start..end, assigned);
+						}
+					}
+				}
+			}
+			_ => self.children(expr.syntax(), assigned),
+		}
+	}
+
+	/// Checks the expressions within a node.
+	fn children(&mut self, node: &SyntaxNode, assigned: &mut Assigned) {
+		for child in node.children() {
+			match Expr::cast(child.clone()) {
+				Some(expr) => self.expr(&expr, assigned),
+				None => self.children(&child, assigned),
+			}
+		}
+	}
+
+	/// Checks the expressions in the indices and slices of an l-value or expression.
+	fn suffix_reads(&mut self, suffixes: impl Iterator<Item = Suffix>, assigned: &mut Assigned) {
+		for suffix in suffixes.filter(|suffix| matches!(suffix, Suffix::Index(_) | Suffix::Slice(_))) {
+			self.children(suffix.syntax(), assigned);
+		}
+	}
+
+	/// The variable and fields reached from a name by member accesses, e.g. `m.a` of `m.a[3:0]`.
+	fn target(&self, name: Option<ast::Name>, suffixes: impl Iterator<Item = Suffix>) -> Option<Target> {
+		let symbol = self.table.resolve(&name?)?;
+		let mut fields = vec![];
+		for suffix in suffixes {
+			match suffix {
+				Suffix::Member(member) => fields.push(member.name()?.text()),
+				_ => return Some(Target { symbol, fields, partial: true }),
+			}
+		}
+		Some(Target { symbol, fields, partial: false })
+	}
+
+	fn expr_target(&self, expr: &Expr) -> Option<Target> {
+		match expr {
+			Expr::Name(name) => self.target(name.name(), std::iter::empty()),
+			Expr::Postfix(postfix) => match postfix.base()? {
+				Expr::Name(name) => self.target(name.name(), postfix.suffixes()),
+				_ => None,
+			},
+			_ => None,
+		}
+	}
+
+	fn assign(&self, target: &Target, assigned: &mut Assigned) {
+		let Some(variable) = self.tracked.get(&target.symbol) else { return };
+		if target.partial {
+			return;
+		}
+		for leaf in variable.leaves.iter().filter(|leaf| leaf.starts_with(&target.fields)) {
+			assigned.must.insert((target.symbol, leaf.clone()));
+			assigned.may.insert((target.symbol, leaf.clone()));
+		}
+	}
+
+	fn read(&mut self, target: &Target, range: Range<usize>, assigned: &Assigned) {
+		let Some(variable) = self.tracked.get(&target.symbol) else { return };
+		let read: Vec<_> = variable
+			.leaves
+			.iter()
+			.filter(|leaf| leaf.starts_with(&target.fields) || target.fields.starts_with(leaf))
+			.map(|leaf| (target.symbol, leaf.clone()))
+			.collect();
+		let missing: Vec<_> = read.iter().filter(|field| !assigned.must.contains(field)).collect();
+		if missing.is_empty() {
+			return;
+		}
+		let path = variable.display(&target.fields);
+		let (message, fix) = if missing.len() < read.len() {
+			let missing: Vec<_> = missing.iter().map(|(_, leaf)| format!("`{}`", variable.display(leaf))).collect();
+			let message = format!("`{path}` is only partly initialized, {} may be uninitialized", missing.join(", "));
+			(message, None)
+		} else if read.iter().any(|field| assigned.may.contains(field)) {
+			(format!("`{path}` may be uninitialized when it is read"), variable.fix.clone())
+		} else {
+			(format!("`{path}` is uninitialized when it is read"), variable.fix.clone())
+		};
+		self.warn(range, message, fix);
+	}
+
+	/// Reports the `out` parameters that are not written at a `return` statement, or at the end of the body.
+	fn unwritten(&mut self, assigned: &Assigned, range: Option<Range<usize>>, at: &str) {
+		for (symbol, name_range) in self.outputs.clone() {
+			let Some(variable) = self.tracked.get(&symbol) else { continue };
+			let fields: Vec<_> = variable.leaves.iter().map(|leaf| (symbol, leaf.clone())).collect();
+			let missing: Vec<_> = fields.iter().filter(|field| !assigned.must.contains(field)).collect();
+			if missing.is_empty() {
+				continue;
+			}
+			let name = &variable.name;
+			let message = if missing.len() < fields.len() {
+				let missing: Vec<_> = missing.iter().map(|(_, leaf)| format!("`{}`", variable.display(leaf))).collect();
+				format!("`out` parameter `{name}` is only partly written {at}, {} may not be", missing.join(", "))
+			} else if fields.iter().any(|field| assigned.may.contains(field)) {
+				format!("`out` parameter `{name}` may not be written {at}")
+			} else {
+				format!("`out` parameter `{name}` is not written {at}")
+			};
+			let fix = variable.fix.clone();
+			self.warn(range.clone().unwrap_or(name_range), message, fix);
+		}
+	}
+
+	/// Checks the arguments of a call, and assigns the arguments of `out` and `inout` parameters as well as the
+	/// variables assigned by the actions called.
+	fn call(&mut self, base: Option<ast::Name>, callee: &[Suffix], args: &[ast::Argument], assigned: &mut Assigned) {
+		self.suffix_reads(callee.iter().cloned(), assigned);
+		let (params, effects) = self.callee(base, callee);
+		for (i, arg) in args.iter().enumerate() {
+			let Some(value) = arg.value() else { continue };
+			let param = match (&params, arg.name()) {
+				(Some(params), Some(name)) => {
+					params.iter().find(|param| param.name().map_or(false, |p| p.text() == name.text()))
+				}
+				(Some(params), None) => params.get(i),
+				(None, _) => None,
+			};
+			let direction = match param {
+				Some(param) => param.direction(),
+				// the arguments of unknown parameters may be written
+				None if params.is_none() => Some(Direction::Out),
+				None => None,
+			};
+			match direction {
+				Some(Direction::Out) => {
+					if let Expr::Postfix(postfix) = &value {
+						self.suffix_reads(postfix.suffixes(), assigned);
+					}
+				}
+				_ => self.expr(&value, assigned),
+			}
+			if matches!(direction, Some(Direction::Out | Direction::InOut)) {
+				if let Some(target) = self.expr_target(&value) {
+					self.assign(&target, assigned);
+				}
+			}
+		}
+		for effect in effects {
+			match effect {
+				Effect::Read(expr) => self.expr(&expr, assigned),
+				Effect::Assign(target) => self.assign(&target, assigned),
+			}
+		}
+	}
+
+	/// The parameters of the callee of a call, if known, with the keys read and the variables assigned when it is an
+	/// action or the `apply` of a table.
+	fn callee(&self, base: Option<ast::Name>, suffixes: &[Suffix]) -> (Option<Vec<ast::Parameter>>, Vec<Effect>) {
+		// the overload chosen by the type checker, if any
+		let symbol = match suffixes.last() {
+			None => base.and_then(|base| self.types.callee(base.syntax()).or_else(|| self.table.resolve(&base))),
+			Some(Suffix::Member(member)) => match member.name().and_then(|name| self.types.type_of(name.syntax())) {
+				Some(Type::Callable(_)) => member.name().and_then(|name| self.types.callee(name.syntax())),
+				Some(Type::BuiltinMethod(receiver, method)) if method == "apply" => {
+					return match &**receiver {
+						Type::Table(table) => (Some(vec![]), self.table_effects(table.symbol)),
+						Type::Parser(nominal) | Type::Control(nominal) => {
+							(Some(self.apply_parameters(nominal.symbol)), vec![])
+						}
+						_ => (None, vec![]),
+					};
+				}
+				Some(Type::BuiltinMethod(..)) => return (Some(vec![]), vec![]),
+				_ => None,
+			},
+			Some(_) => None,
+		};
+		let Some(symbol) = symbol else { return (None, vec![]) };
+		let params = match self.table.symbol(symbol).kind {
+			SymbolKind::Action => self.node::<ast::ActionDecl>(symbol).map(|decl| decl.parameters()),
+			SymbolKind::Function => self.node::<ast::FunctionDecl>(symbol).map(|decl| decl.parameters()),
+			SymbolKind::ExternFunction => {
+				self.node::<ast::ExternDecl>(symbol).and_then(|decl| decl.function()).map(|f| f.parameters())
+			}
+			SymbolKind::Method => self.node::<ast::MethodPrototype>(symbol).map(|decl| decl.parameters()),
+			_ => None,
+		};
+		let effects = self.action_effects(symbol).into_iter().map(Effect::Assign).collect();
+		(params, effects)
+	}
+
+	fn apply_parameters(&self, symbol: SymbolId) -> Vec<ast::Parameter> {
+		match self.table.symbol(symbol).kind {
+			SymbolKind::Parser => self.node::<ast::ParserDecl>(symbol).map(|decl| decl.parameters()),
+			SymbolKind::ParserType => self.node::<ast::ParserTypeDecl>(symbol).map(|decl| decl.parameters()),
+			SymbolKind::Control => self.node::<ast::ControlDecl>(symbol).map(|decl| decl.parameters()),
+			SymbolKind::ControlType => self.node::<ast::ControlTypeDecl>(symbol).map(|decl| decl.parameters()),
+			_ => None,
+		}
+		.unwrap_or_default()
+	}
+
+	/// The variables assigned by the body of an action.
+	fn action_effects(&self, symbol: SymbolId) -> Vec<Target> {
+		if self.table.symbol(symbol).kind != SymbolKind::Action {
+			return vec![];
+		}
+		let Some(action) = self.node::<ast::ActionDecl>(symbol) else { return vec![] };
+		action
+			.syntax()
+			.descendants()
+			.filter_map(ast::AssignmentStatement::cast)
+			.filter_map(|assignment| assignment.target())
+			.filter_map(|target| self.target(target.name(), target.suffixes()))
+			.collect()
+	}
+
+	/// The keys read when a table is applied, and the variables assigned by its actions.
+	fn table_effects(&self, symbol: SymbolId) -> Vec<Effect> {
+		let Some(table) = self.node::<ast::TableDecl>(symbol) else { return vec![] };
+		let keys = table.keys().into_iter().filter_map(|key| key.expr()).map(Effect::Read);
+		let actions = table
+			.actions()
+			.into_iter()
+			.filter_map(|action| self.table.resolve(&action.name()?))
+			.flat_map(|action| self.action_effects(action))
+			.map(Effect::Assign);
+		keys.chain(actions).collect()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::p4_grammar::parse_and_check;
+	use pretty_assertions::assert_eq;
+
+	fn check(input: &str) -> Vec<(&str, String, Option<String>)> {
+		let (program, table, types) = parse_and_check(input);
+		let warnings = super::check(&program, &table, &types);
+		warnings
+			.into_iter()
+			.map(|warning| {
+				let fix = warning.fix.map(|fix| format!("{}: {}", fix.title, fix.text));
+				(&input[warning.range], warning.message, fix)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn locals() {
+		let input = r#"
+			struct pair_t { bit<8> a; bit<8> b; }
+			action a(in bit<8> c) {
+				bit<8> x;
+				bit<8> y;
+				bool z;
+				pair_t p;
+				if (c == 0) {
+					x = 1;
+					p.a = 1;
+				}
+				y = x;
+				y = y + 1;
+				p.b = (bit<8>) p.a;
+				y = p.b;
+				pair_t q = p;
+				z = !z;
+				bit<8> w;
+				w[3:0] = 1;
+				y = w;
+			}
+		"#;
+		assert_eq!(
+			check(input),
+			vec![
+				(
+					"x",
+					"`x` may be uninitialized when it is read".to_string(),
+					Some("Initialize `x` to 0:  = 0".to_string())
+				),
+				("p.a", "`p.a` may be uninitialized when it is read".to_string(), None),
+				("p", "`p` is only partly initialized, `p.a` may be uninitialized".to_string(), None),
+				(
+					"z",
+					"`z` is uninitialized when it is read".to_string(),
+					Some("Initialize `z` to false:  = false".to_string())
+				),
+				(
+					"w",
+					"`w` is uninitialized when it is read".to_string(),
+					Some("Initialize `w` to 0:  = 0".to_string())
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn out_parameters() {
+		let input = r#"
+			struct pair_t { bit<8> a; bit<8> b; }
+			extern void random(out bit<8> result);
+			bit<8> f(in bool c, out bit<8> x, out pair_t p) {
+				p.a = x;
+				if (c) {
+					return 0;
+				}
+				random(x);
+				return x;
+			}
+			action a(out bit<8> x) {
+				if (x == 0) { exit; }
+			}
+			control C(out bit<8> x) {
+				bit<8> y;
+				action set() { y = 1; }
+				table t { actions = { set; } default_action = set; }
+				apply {
+					t.apply();
+					x = y;
+				}
+			}
+		"#;
+		assert_eq!(
+			check(input),
+			vec![
+				(
+					"x",
+					"`x` is uninitialized when it is read".to_string(),
+					Some("Initialize `x` to 0: x = 0;".to_string())
+				),
+				(
+					"return 0;",
+					"`out` parameter `x` is not written before this `return`".to_string(),
+					Some("Initialize `x` to 0: x = 0;".to_string())
+				),
+				(
+					"return 0;",
+					"`out` parameter `p` is only partly written before this `return`, `p.b` may not be".to_string(),
+					None
+				),
+				(
+					"return x;",
+					"`out` parameter `p` is only partly written before this `return`, `p.b` may not be".to_string(),
+					None
+				),
+				(
+					"x",
+					"`x` is uninitialized when it is read".to_string(),
+					Some("Initialize `x` to 0: x = 0;".to_string())
+				),
+				(
+					"x",
+					"`out` parameter `x` is not written before `a` returns".to_string(),
+					Some("Initialize `x` to 0: x = 0;".to_string())
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn parsers() {
+		let input = r#"
+			parser P(out bit<8> x, out bit<8> y) {
+				bit<8> count;
+				state start {
+					y = 0;
+					transition select(y) { 0: first; default: second; }
+				}
+				state first { count = 1; x = 1; transition next; }
+				state second { x = 2; transition next; }
+				state next { y = count; transition accept; }
+			}
+		"#;
+		assert_eq!(
+			check(input),
+			vec![(
+				"count",
+				"`count` may be uninitialized when it is read".to_string(),
+				Some("Initialize `count` to 0:  = 0".to_string())
+			)]
+		);
+	}
+}
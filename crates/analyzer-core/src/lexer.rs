@@ -13,7 +13,8 @@ pub struct LexedBuffer {
 
 pub struct Lextras {
 	pub db: Option<*const dyn crate::Db>,
-	pub file_id: FileId,
+	/// The file being lexed, only needed for reporting diagnostics.
+	pub file_id: Option<FileId>,
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
@@ -27,6 +28,15 @@ pub struct Literal {
 #[derive(Logos, PartialOrd, Ord, PartialEq, Eq, Debug, Clone)]
 #[logos(extras = Lextras)]
 pub enum Token {
+	#[token("&")]
+	Ampersand,
+
+	#[token("@")]
+	At,
+
+	#[token("^")]
+	Caret,
+
 	#[token("}")]
 	CloseBrace,
 
@@ -52,9 +62,30 @@ pub enum Token {
 	#[token(".")]
 	Dot,
 
+	#[token("&&")]
+	DoubleAmpersand,
+
+	#[token("==")]
+	DoubleEquals,
+
+	#[token("..")]
+	DoubleDot,
+
+	#[token("||")]
+	DoublePipe,
+
+	#[token("++")]
+	DoublePlus,
+
 	#[token("=")]
 	Equals,
 
+	#[token("!")]
+	Exclamation,
+
+	#[token(">=")]
+	GreaterEquals,
+
 	#[token("*")]
 	Asterisk,
 
@@ -114,6 +145,12 @@ pub enum Token {
 	#[token("typedef")]
 	KwTypedef,
 
+	#[token("<=")]
+	LessEquals,
+
+	#[token("!=")]
+	NotEquals,
+
 	#[token("{")]
 	OpenBrace,
 
@@ -126,12 +163,45 @@ pub enum Token {
 	#[token("(")]
 	OpenParen,
 
+	#[token("%")]
+	Percent,
+
+	#[token("|")]
+	Pipe,
+
 	#[regex(r"#\s*\w+", |lex| Lexer(lex).read_directive())]
 	PreprocessorDirective(PreprocessorDirective),
 
+	#[token("?")]
+	Question,
+
+	/// The saturating addition operator, `|+|`.
+	#[token("|+|")]
+	SaturatingPlus,
+
+	/// The saturating subtraction operator, `|-|`.
+	#[token("|-|")]
+	SaturatingMinus,
+
 	#[token(";")]
 	Semicolon,
 
+	/// The `<<` operator. There is no token for `>>`, which is lexed as two [`Token::CloseChevron`]s so that nested
+	/// type arguments like `H<bit<8>>` can be closed.
+	#[token("<<")]
+	ShiftLeft,
+
+	/// A string literal, without the surrounding quotes and with its escape sequences processed.
+	#[regex(r#""([^"\\]|\\.)*""#, |lex| Lexer(lex).read_string())]
+	StringLiteral(String),
+
+	#[token("~")]
+	Tilde,
+
+	/// The ternary match operator, `&&&`.
+	#[token("&&&")]
+	TripleAmpersand,
+
 	#[regex(r"\s\s*")]
 	Whitespace,
 }
@@ -170,6 +240,25 @@ impl<'a, 'b> Lexer<'a, 'b> {
 		Some(lit)
 	}
 
+	fn read_string(self) -> String {
+		let str = self.0.slice();
+		let mut value = String::new();
+		let mut chars = str[1..str.len() - 1].chars();
+		while let Some(ch) = chars.next() {
+			match ch {
+				'\\' => match chars.next() {
+					Some('n') => value.push('\n'),
+					Some('r') => value.push('\r'),
+					Some('t') => value.push('\t'),
+					Some(ch) => value.push(ch),
+					None => (),
+				},
+				ch => value.push(ch),
+			}
+		}
+		value
+	}
+
 	fn read_directive(mut self) -> Option<PreprocessorDirective> {
 		lazy_static! {
 			static ref DIRECTIVE: Regex = Regex::new(r"#\s*(\w+)").unwrap();
@@ -346,10 +435,17 @@ impl<'a, 'b> Lexer<'a, 'b> {
 	}
 
 	fn report(&mut self, severity: Severity, msg: &str) {
-		if let Some(db) = self.0.extras.db.map(|db| unsafe { &*db }) {
+		if let (Some(db), Some(file)) = (self.0.extras.db.map(|db| unsafe { &*db }), self.0.extras.file_id) {
 			Diagnostics::push(
 				db,
-				Diagnostic { file: self.0.extras.file_id, location: self.0.span(), severity, message: msg.to_string() },
+				Diagnostic {
+					file,
+					location: self.0.span(),
+					severity,
+					message: msg.to_string(),
+					related: vec![],
+					unnecessary: false,
+				},
 			);
 		}
 	}
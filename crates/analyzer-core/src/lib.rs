@@ -1,15 +1,41 @@
+pub mod architecture;
+pub mod ast;
 pub mod base_abstractions;
+pub mod const_eval;
+pub mod coverage;
+pub mod directions;
+pub mod initialization;
 pub mod lexer;
+pub mod p4_grammar;
 pub mod parser;
 pub mod preprocessor;
+pub mod state_graph;
+pub mod symbols;
+pub mod syntax;
+pub mod types;
+pub mod unused;
+pub mod validity;
 
-use std::collections::HashMap;
+use std::{
+	collections::{HashMap, HashSet},
+	ops::Range,
+	sync::Arc,
+};
 
+use architecture::{Architecture, Target};
+use cancellation::CancellationToken;
 use logos::Logos;
 
 use base_abstractions::*;
+use const_eval::Value;
 use lexer::*;
+use p4_grammar::*;
+use parser::{trace::Trace, Cancelled, Cst, MemoStatistics, Parser};
 use preprocessor::*;
+use state_graph::{Problem, StateGraph};
+use symbols::{Namespace, Symbol, SymbolTable};
+use syntax::{build_tree, AstNode, GreenNode, SyntaxNode, ERROR};
+use types::{Type, TypeInfo};
 
 // #[derive(Default)]
 #[salsa::db(crate::Jar)]
@@ -38,6 +64,17 @@ pub struct Jar(
 	// gotta include salsa functions as well
 	lex,
 	preprocess,
+	parse,
+	symbol_table,
+	type_check,
+	parser_graphs,
+	target_architecture,
+	header_validity,
+	definite_assignment,
+	unused_code,
+	parameter_directions,
+	case_coverage,
+	analyze,
 );
 
 pub trait Db: salsa::DbWithJar<Jar> {
@@ -54,6 +91,8 @@ impl Db for Database {
 pub struct Analyzer {
 	db: Database,
 	fs: Option<Fs>,
+	/// One parser per file, kept across updates so that reparsing can reuse the memo table.
+	parsers: HashMap<FileId, FileParser>,
 	require_fn: Box<dyn Fn(&str) -> () + 'static>,
 }
 
@@ -65,6 +104,66 @@ pub struct LexedFs {
 #[salsa::input]
 pub struct Fs {
 	fs: HashMap<FileId, Buffer>,
+	/// The latest match of each file by its own parser, see [`Analyzer::reparse`].
+	#[return_ref]
+	matches: HashMap<FileId, Arc<Reparse>>,
+}
+
+/// The match of the tokens of a preprocessed file by the parser the [`Analyzer`] keeps for the file.
+///
+/// The parsers live outside of the database, since their memo tables change with every match, so [`parse`] is given
+/// their matches as an input instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reparse {
+	/// The non-trivia tokens that were matched.
+	input: Vec<Token>,
+	/// The concrete syntax tree of the tokens, `None` if they didn't match the grammar.
+	cst: Option<Cst<Token>>,
+}
+
+/// The parser of a file, with what it takes to turn the edits of the file into edits of the parser's input.
+struct FileParser {
+	parser: Parser<Token>,
+	/// The file and span of each token of the input.
+	sources: Vec<(FileId, Span)>,
+	/// The file itself and the files it includes, whether they were found or not.
+	files: HashSet<FileId>,
+	/// How the files have changed since the input was last updated.
+	changes: Changes,
+}
+
+impl FileParser {
+	fn new() -> Self {
+		Self { parser: new_parser(), sources: vec![], files: HashSet::new(), changes: Changes::Unchanged }
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Changes {
+	Unchanged,
+	/// Only a range of the file itself was edited.
+	Edited(Edit),
+	/// The files may have changed in any way, e.g. replaced as a whole.
+	Replaced,
+}
+
+/// The bytes `start..old_end` of a file that were replaced by `start..new_end`, possibly over several edits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Edit {
+	start: usize,
+	old_end: usize,
+	new_end: usize,
+}
+
+impl Edit {
+	/// The edit followed by a replacement of `range` of the edited contents with `len` bytes.
+	fn then(self, range: Range<usize>, len: usize) -> Edit {
+		Edit {
+			start: self.start.min(range.start),
+			old_end: self.old_end + range.end.saturating_sub(self.new_end),
+			new_end: self.new_end.max(range.end) - range.end + range.start + len,
+		}
+	}
 }
 
 impl Analyzer {
@@ -72,15 +171,276 @@ impl Analyzer {
 		resolver_fn: impl Fn(&str, &str) -> Result<String, String> + 'static,
 		require_fn: impl Fn(&str) -> () + 'static,
 	) -> Self {
-		Self { db: Database::new(resolver_fn), fs: Default::default(), require_fn: Box::new(require_fn) }
+		Self {
+			db: Database::new(resolver_fn),
+			fs: Default::default(),
+			parsers: Default::default(),
+			require_fn: Box::new(require_fn),
+		}
 	}
 
 	fn filesystem(&self) -> HashMap<FileId, Buffer> { self.fs.map(|fs| fs.fs(&self.db)).unwrap_or_default() }
 
-	pub fn update(&mut self, file_id: FileId, input: String) {
+	fn matches(&self) -> HashMap<FileId, Arc<Reparse>> {
+		self.fs.map(|fs| fs.matches(&self.db).clone()).unwrap_or_default()
+	}
+
+	fn set_input(&mut self, file_id: FileId, input: String) {
 		let mut filesystem = self.filesystem();
 		filesystem.insert(file_id, Buffer::new(&self.db, input));
-		self.fs = Fs::new(&self.db, filesystem).into();
+		self.fs = Fs::new(&self.db, filesystem, self.matches()).into();
+	}
+
+	/// Replaces the contents of a file.
+	///
+	/// The file, and the files including it, are parsed again by their next [`Analyzer::reparse`], or from scratch if
+	/// their syntax trees are needed before then.
+	pub fn update(&mut self, file_id: FileId, input: String) {
+		for file_parser in self.parsers.values_mut().filter(|file_parser| file_parser.files.contains(&file_id)) {
+			file_parser.changes = Changes::Replaced;
+		}
+		self.set_input(file_id, input);
+	}
+
+	/// Replaces ranges of bytes of a file, each in the contents left by the previous ones, e.g. for the changes of a
+	/// `didChange` notification.
+	///
+	/// Unlike after an [`Analyzer::update`], the next reparse of the file tells its parser which of its tokens the
+	/// edits replaced, instead of having it compare all of them. Returns `None` if the file is unknown or a range is out
+	/// of bounds.
+	pub fn edit(&mut self, file_id: FileId, edits: &[(Range<usize>, String)]) -> Option<()> {
+		let mut input = self.input(file_id)?.to_string();
+		let mut replaced = vec![];
+		for (range, text) in edits {
+			input.get(range.clone())?;
+			replaced.push((range.clone(), text.len(), has_directives(&input, range.clone())));
+			input.replace_range(range.clone(), text);
+		}
+
+		for (id, file_parser) in self.parsers.iter_mut().filter(|(_, file_parser)| file_parser.files.contains(&file_id))
+		{
+			for (range, len, directives) in replaced.iter().cloned() {
+				file_parser.changes = match file_parser.changes {
+					// the preprocessor may now treat the rest of the file differently
+					_ if *id != file_id || directives => Changes::Replaced,
+					Changes::Unchanged => {
+						Changes::Edited(Edit { start: range.start, old_end: range.end, new_end: range.start + len })
+					}
+					Changes::Edited(edit) => Changes::Edited(edit.then(range, len)),
+					Changes::Replaced => Changes::Replaced,
+				};
+			}
+		}
+		self.set_input(file_id, input);
+		Some(())
+	}
+
+	/// Matches the tokens of a preprocessed file, and of the files that include it, with the parsers of the files, so
+	/// that only the rules affected by the changes since their previous match are evaluated again. [`parse`] then
+	/// builds the syntax trees from these matches.
+	///
+	/// The tokens replaced by the edits of the file itself since its previous reparse (see [`Analyzer::edit`]) are
+	/// handed to [`Parser::apply_edit`], and any other changes are found by [`Parser::update_input`].
+	///
+	/// Returns `false` if a match was cancelled by the token, e.g. once a newer edit has arrived. A file whose match
+	/// was cancelled is parsed from scratch if its syntax tree is needed before its next reparse, but the results of
+	/// the rules the cancelled match completed are kept for the next one.
+	pub fn reparse(&mut self, file_id: FileId, cancel_token: Option<Arc<CancellationToken>>) -> bool {
+		let Some(fs) = self.fs else { return false };
+		let including = self
+			.parsers
+			.iter()
+			.filter(|(id, file_parser)| **id != file_id && file_parser.files.contains(&file_id));
+		let files: Vec<_> = std::iter::once(file_id).chain(including.map(|(id, _)| *id)).collect();
+
+		let mut matches = self.matches();
+		let mut completed = true;
+		for file_id in files {
+			let Some(preprocessed) = preprocess(&self.db, fs, file_id).as_ref() else { continue };
+			let (sources, input): (Vec<_>, Vec<_>) = preprocessed
+				.iter()
+				.filter(|(_, tk, _)| !is_trivia(tk))
+				.map(|(file, tk, span)| ((*file, span.clone()), tk.clone()))
+				.unzip();
+			let includes = preprocess::accumulated::<IncludedDependencies>(&self.db, fs, file_id);
+			let contents = self.buffer(file_id).map_or("", |buffer| buffer.contents(&self.db).as_str());
+
+			let file_parser = self.parsers.entry(file_id).or_insert_with(FileParser::new);
+			let edited = match file_parser.changes {
+				Changes::Edited(edit) if !has_directives(contents, edit.start..edit.new_end) => {
+					edited_tokens(file_id, edit, &file_parser.sources, &sources)
+				}
+				_ => None,
+			};
+			match edited {
+				Some((old, new)) => file_parser.parser.apply_edit(old, &input[new]),
+				None => {
+					file_parser.parser.update_input(&input);
+				}
+			}
+			file_parser.sources = sources;
+			file_parser.files =
+				std::iter::once(file_id).chain(includes.iter().map(|include| include.file_id)).collect();
+			file_parser.changes = Changes::Unchanged;
+
+			file_parser.parser.set_cancellation_token(cancel_token.clone());
+			match file_parser.parser._match_rule("start") {
+				Ok(cst) => {
+					matches.insert(file_id, Arc::new(Reparse { input, cst }));
+				}
+				Err(err) if err.is::<Cancelled>() => completed = false,
+				Err(err) => panic!("{err}"),
+			}
+		}
+
+		fs.set_matches(&mut self.db).to(matches);
+		completed
+	}
+
+	/// The syntax tree of a preprocessed file, or `None` if the file is unknown.
+	pub fn parsed(&self, file_id: FileId) -> Option<&ParsedUnit> { parse(&self.db, self.fs?, file_id).as_ref() }
+
+	/// The declarations of a preprocessed file and the uses of their names, see [`symbol_table`].
+	pub fn symbols(&self, file_id: FileId) -> Option<&SymbolTable> {
+		symbol_table(&self.db, self.fs?, file_id).as_ref()
+	}
+
+	/// The symbol referred to or declared by the name at an offset in a file. The name of a callee refers to the
+	/// overload chosen for the arguments of the call, and the name of a method to the method.
+	///
+	/// The file can be the one being analyzed or one of the files it includes.
+	pub fn resolve(&self, file_id: FileId, offset: usize) -> Option<&Symbol> {
+		let offset = self.parsed(file_id)?.source_map.tree_offset(file_id, offset)?;
+		let symbols = self.symbols(file_id)?;
+		let callee = self.types(file_id).and_then(|types| types.callee_at(offset));
+		callee.or_else(|| symbols.resolve_at(offset)).map(|id| symbols.symbol(id))
+	}
+
+	/// The file and the range of the name of the declaration referred to by the name at an offset in a file, e.g. to
+	/// go to its definition.
+	pub fn definition(&self, file_id: FileId, offset: usize) -> Option<(FileId, Range<usize>)> {
+		let symbol = self.resolve(file_id, offset)?;
+		self.parsed(file_id)?.source_map.locate_range(symbol.name_range.clone())
+	}
+
+	/// The types of the expressions and declarations of a preprocessed file, see [`type_check`].
+	pub fn types(&self, file_id: FileId) -> Option<&TypeInfo> { type_check(&self.db, self.fs?, file_id).as_ref() }
+
+	/// The state machines of the parsers of a preprocessed file, see [`parser_graphs`].
+	pub fn parser_graphs(&self, file_id: FileId) -> Option<&[StateGraph]> {
+		parser_graphs(&self.db, self.fs?, file_id).as_deref()
+	}
+
+	/// The architecture a preprocessed file is written against, see [`target_architecture`].
+	pub fn architecture(&self, file_id: FileId) -> Option<&Architecture> {
+		target_architecture(&self.db, self.fs?, file_id).as_ref()
+	}
+
+	/// The reads of headers that may be invalid in a preprocessed file, see [`header_validity`].
+	pub fn header_validity(&self, file_id: FileId) -> Option<&[validity::Warning]> {
+		header_validity(&self.db, self.fs?, file_id).as_deref()
+	}
+
+	/// The reads of variables that may be uninitialized in a preprocessed file, see [`definite_assignment`].
+	pub fn uninitialized(&self, file_id: FileId) -> Option<&[initialization::Warning]> {
+		definite_assignment(&self.db, self.fs?, file_id).as_deref()
+	}
+
+	/// The misuses of parameter directions in a preprocessed file, see [`parameter_directions`].
+	pub fn direction_errors(&self, file_id: FileId) -> Option<&[directions::Error]> {
+		parameter_directions(&self.db, self.fs?, file_id).as_deref()
+	}
+
+	/// The shadowed cases and the incomplete `select`s and `switch`es of a preprocessed file, see [`case_coverage`].
+	pub fn case_coverage(&self, file_id: FileId) -> Option<&[coverage::Finding]> {
+		case_coverage(&self.db, self.fs?, file_id).as_deref()
+	}
+
+	/// The unused declarations and unreachable code of a preprocessed file and the files it includes, see
+	/// [`unused_code`].
+	pub fn unused(&self, file_id: FileId) -> Option<&[unused::Unused]> {
+		unused_code(&self.db, self.fs?, file_id).as_deref()
+	}
+
+	/// The quick fixes for the warnings of [`definite_assignment`] that overlap a range of a file.
+	pub fn quick_fixes(&self, file_id: FileId, range: Range<usize>) -> Vec<QuickFix> {
+		let (Some(unit), Some(warnings)) = (self.parsed(file_id), self.uninitialized(file_id)) else { return vec![] };
+		let mut fixes: Vec<QuickFix> = vec![];
+		for warning in warnings {
+			let Some(fix) = &warning.fix else { continue };
+			let overlaps = unit.source_map.locate_range(warning.range.clone()).map_or(false, |(file, location)| {
+				file == file_id && location.start <= range.end && range.start <= location.end
+			});
+			let Some((file, location)) = unit.source_map.locate_range(fix.range.clone()).filter(|_| overlaps) else {
+				continue;
+			};
+
+			let offset = if fix.range.is_empty() { location.start } else { location.end };
+			let mut text = fix.text.clone();
+			if fix.statement {
+				// keep the code after the statement where it was, on the next line
+				let input = self.input(file).unwrap_or_default();
+				let line = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+				let indentation = &input[line..offset];
+				text = format!("{text}\n{}", if indentation.trim().is_empty() { indentation } else { "" });
+			}
+			let fix = QuickFix { title: fix.title.clone(), file, location: offset..offset, text };
+			if !fixes.contains(&fix) {
+				fixes.push(fix);
+			}
+		}
+		fixes
+	}
+
+	/// The type of the innermost expression at an offset in a file, or else of the value declared there.
+	pub fn type_at(&self, file_id: FileId, offset: usize) -> Option<&Type> {
+		let offset = self.parsed(file_id)?.source_map.tree_offset(file_id, offset)?;
+		let types = self.types(file_id)?;
+		types.type_at(offset).or_else(|| types.symbol_type(self.symbols(file_id)?.resolve_at(offset)?))
+	}
+
+	/// The signature of the action, function or method called at an offset in a file, with the type parameters bound
+	/// by the call substituted, e.g. `register<bit<32>>.read(out bit<32> result, in bit<32> index)`.
+	pub fn signature_at(&self, file_id: FileId, offset: usize) -> Option<&str> {
+		let offset = self.parsed(file_id)?.source_map.tree_offset(file_id, offset)?;
+		self.types(file_id)?.signature_at(offset)
+	}
+
+	/// The value of the compile-time constant at an offset in a file, i.e. of the innermost expression or of the
+	/// constant declared there.
+	pub fn value_at(&self, file_id: FileId, offset: usize) -> Option<&Value> {
+		let offset = self.parsed(file_id)?.source_map.tree_offset(file_id, offset)?;
+		let types = self.types(file_id)?;
+		match types.type_at(offset) {
+			Some(_) => types.value_at(offset),
+			None => types.symbol_value(self.symbols(file_id)?.resolve_at(offset)?),
+		}
+	}
+
+	/// The tokens of a preprocessed file that are handed to the parser, i.e. without any trivia.
+	fn parser_input(&self, file_id: FileId) -> Option<Vec<Token>> {
+		let preprocessed = preprocess(&self.db, self.fs?, file_id).as_ref()?;
+		Some(preprocessed.iter().map(|(_, tk, _)| tk).filter(|tk| !is_trivia(tk)).cloned().collect())
+	}
+
+	/// Parses a file from scratch and records a [`Trace`] of every rule attempt, for debugging the grammar.
+	///
+	/// Trace positions are indices into the non-trivia tokens of [`Analyzer::preprocessed`].
+	///
+	/// Since the grammar skips the input it can't match as `error` nodes, the match itself always succeeds. Where the
+	/// input stops conforming to the grammar is instead given by [`Trace::furthest_failure_in`] the tokens of the first
+	/// `error` node of the [`Analyzer::parsed`] tree, up to the token that ends it.
+	pub fn parse_trace(&self, file_id: FileId) -> Option<Trace> {
+		let mut parser = new_parser();
+		parser.set_tracing(true);
+		parser.update_input(&self.parser_input(file_id)?);
+		parser._match();
+		parser.trace().cloned()
+	}
+
+	/// Retrieves statistics about the reuse of the memo table by the last reparse of a file.
+	pub fn parse_statistics(&self, file_id: FileId) -> Option<MemoStatistics> {
+		self.parsers.get(&file_id).map(|file_parser| file_parser.parser.statistics())
 	}
 
 	pub fn input(&self, file_id: FileId) -> Option<&str> {
@@ -106,14 +466,17 @@ impl Analyzer {
 		result
 	}
 
+	/// The diagnostics of a file and the files it includes, i.e. of every analysis of the file (see [`analyze`]),
+	/// sorted by file and location.
 	pub fn diagnostics(&self, id: FileId) -> Vec<Diagnostic> {
-		if let Some(buf) = self.filesystem().get(&id) {
-			let mut d = lex::accumulated::<Diagnostics>(&self.db, id, *buf);
-			d.append(&mut preprocess::accumulated::<Diagnostics>(&self.db, self.fs.unwrap(), id));
-			d
-		} else {
-			vec![]
-		}
+		let Some(fs) = self.fs.filter(|_| self.filesystem().contains_key(&id)) else { return vec![] };
+
+		// the diagnostics are accumulated once for every path through the queries that leads to them
+		let mut seen = HashSet::new();
+		let mut diagnostics = analyze::accumulated::<Diagnostics>(&self.db, fs, id);
+		diagnostics.retain(|d| seen.insert((d.file, d.location.clone(), d.message.clone())));
+		diagnostics.sort_by_cached_key(|d| (self.path(d.file), d.location.start, d.location.end, d.message.clone()));
+		diagnostics
 	}
 
 	/// Retrieves the included dependencies for a given source [`FileId`].
@@ -127,7 +490,13 @@ impl Analyzer {
 		let id = FileId::new(&self.db, uri.to_string());
 		let mut filesystem = self.filesystem();
 		filesystem.remove(&id).map(|_| ())?;
-		self.fs = Fs::new(&self.db, filesystem).into();
+		let mut matches = self.matches();
+		matches.remove(&id);
+		self.fs = Fs::new(&self.db, filesystem, matches).into();
+		self.parsers.remove(&id);
+		for file_parser in self.parsers.values_mut().filter(|file_parser| file_parser.files.contains(&id)) {
+			file_parser.changes = Changes::Replaced;
+		}
 		Some(())
 	}
 
@@ -138,6 +507,44 @@ impl Analyzer {
 	pub fn files(&self) -> Vec<String> { self.filesystem().keys().map(|k| k.path(&self.db)).collect() }
 }
 
+fn new_parser() -> Parser<Token> {
+	Parser::from_rules(&*p4_grammar()).expect("the P4 grammar should be well-formed")(Default::default())
+}
+
+/// Whether the lines overlapping a range of a text may hold preprocessor directives.
+fn has_directives(text: &str, range: Range<usize>) -> bool {
+	let start = text[..range.start].rfind('\n').map_or(0, |i| i + 1);
+	let end = text[range.end..].find('\n').map_or(text.len(), |i| range.end + i);
+	text[start..end].contains('#')
+}
+
+/// The range of the input of a parser that an edit of a file replaced, and the range of the tokens replacing it in
+/// the new input, given the file and span of each token of both inputs.
+///
+/// The tokens that end before the edit stay the same, and so do the tokens after it once one of them starts at the
+/// same place, shifted by the edit, since the lexer continues from there as before. Returns `None` if they don't,
+/// e.g. if the edit opened a comment.
+fn edited_tokens(
+	file_id: FileId,
+	edit: Edit,
+	old: &[(FileId, Span)],
+	new: &[(FileId, Span)],
+) -> Option<(Range<usize>, Range<usize>)> {
+	let start = old.iter().position(|(file, span)| *file == file_id && span.end >= edit.start).unwrap_or(old.len());
+	let edited = |end: usize| move |(file, span): &&(FileId, Span)| *file == file_id && span.start <= end;
+	let old_end = start + old[start..].iter().take_while(edited(edit.old_end)).count();
+	let new_end = start + new.get(start..)?.iter().take_while(edited(edit.new_end)).count();
+
+	let expected = match old.get(old_end) {
+		Some((file, span)) if *file == file_id => {
+			Some((*file, span.start + edit.new_end - edit.old_end..span.end + edit.new_end - edit.old_end))
+		}
+		next => next.cloned(),
+	};
+	let aligned = old.len() - old_end == new.len() - new_end && new.get(new_end).cloned() == expected;
+	aligned.then(|| (start..old_end, start..new_end))
+}
+
 // TODO: trait for workspace logic?
 //       - path resolution
 //       - fetching unopened files
@@ -183,6 +590,8 @@ where
 						location,
 						severity: Severity::Error,
 						message: "unexpected token".to_string(),
+						related: vec![],
+						unnecessary: false,
 					};
 
 					Diagnostics::push(db, diagnostic);
@@ -213,7 +622,7 @@ pub fn lex(db: &dyn crate::Db, file_id: FileId, buf: Buffer) -> LexedBuffer {
 	let contents = buf.contents(db);
 	let lexer = {
 		let db = unsafe { std::mem::transmute(db) };
-		Token::lexer_with_extras(contents, Lextras { db: Some(db), file_id })
+		Token::lexer_with_extras(contents, Lextras { db: Some(db), file_id: Some(file_id) })
 	};
 
 	// merge consecutive error tokens and push them as diagnostics
@@ -251,8 +660,471 @@ pub fn preprocess(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Vec<(Fi
 	dbg!(&pp.errors);
 
 	for ((file, location), msg) in pp.errors {
-		Diagnostics::push(db, Diagnostic { file, location, severity: Severity::Error, message: msg });
+		Diagnostics::push(
+			db,
+			Diagnostic { file, location, severity: Severity::Error, message: msg, related: vec![], unnecessary: false },
+		);
 	}
 
 	Some(result)
 }
+
+/// The syntax tree of a preprocessed file, see [`parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedUnit {
+	pub root: GreenNode<Token>,
+	pub source_map: SourceMap,
+}
+
+impl ParsedUnit {
+	pub fn syntax(&self) -> SyntaxNode<Token> { SyntaxNode::new_root(self.root.clone()) }
+
+	/// The typed view of the syntax tree, see [`ast`].
+	pub fn program(&self) -> ast::Program { ast::Program::cast(self.syntax()).expect("the root should be a program") }
+}
+
+/// Maps offsets in the text of a syntax tree back to the files the tokens came from.
+///
+/// A preprocessed file is made up of tokens from the file itself and from the files it includes, so the offsets in
+/// its syntax tree generally don't correspond to offsets in any one file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+	/// The range of each token in the tree, in order, with the file and span it came from.
+	tokens: Vec<(Range<usize>, FileId, Span)>,
+}
+
+impl SourceMap {
+	fn push(&mut self, width: usize, file_id: FileId, span: Span) {
+		let start = self.tokens.last().map(|(range, ..)| range.end).unwrap_or(0);
+		self.tokens.push((start..start + width, file_id, span));
+	}
+
+	/// The file and span of the token containing the given offset in the tree.
+	pub fn locate(&self, offset: usize) -> Option<(FileId, Span)> {
+		let i = self.tokens.partition_point(|(range, ..)| range.end <= offset);
+		let (range, file_id, span) = self.tokens.get(i)?;
+		range.contains(&offset).then(|| (*file_id, span.clone()))
+	}
+
+	/// The file and span covered by a range in the tree.
+	///
+	/// If the range covers tokens from several files, e.g. both sides of an `#include`, the span is cut short at the
+	/// end of the tokens from the file of the first token.
+	pub fn locate_range(&self, range: Range<usize>) -> Option<(FileId, Span)> {
+		let (file_id, span) = self.locate(range.start)?;
+		let end = self.tokens[self.tokens.partition_point(|(r, ..)| r.end <= range.start)..]
+			.iter()
+			.take_while(|(r, f, _)| r.start < range.end && *f == file_id)
+			.map(|(_, _, span)| span.end)
+			.last()
+			.unwrap_or(span.end);
+
+		Some((file_id, span.start..end))
+	}
+
+	/// The offset in the tree of an offset in one of the files, i.e. the reverse of [`SourceMap::locate`].
+	///
+	/// An offset between two tokens maps to the end of the first one. Returns `None` if no token of the file ends up
+	/// at the offset, e.g. if it is inside an `#if` that was skipped.
+	pub fn tree_offset(&self, file_id: FileId, offset: usize) -> Option<usize> {
+		self.tokens
+			.iter()
+			.find(|(_, file, span)| *file == file_id && span.start <= offset && offset <= span.end)
+			.map(|(range, _, span)| range.start + (offset - span.start))
+	}
+}
+
+/// Parses a preprocessed file into a syntax tree.
+///
+/// The tree is built from the latest match of the file's own parser (see [`Analyzer::reparse`]) if it matched the
+/// current tokens of the file, and from a match from scratch otherwise, e.g. if the reparse was cancelled. Input that
+/// doesn't conform to the grammar ends up in `error` nodes, each of which is reported as a diagnostic.
+#[salsa::tracked(return_ref)]
+pub fn parse(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<ParsedUnit> {
+	let preprocessed = preprocess(db, fs, file_id).as_ref()?;
+	let buffers = fs.fs(db);
+
+	let mut source_map = SourceMap::default();
+	let tokens: Vec<_> = preprocessed
+		.iter()
+		.map(|(file_id, tk, span)| {
+			let text = buffers.get(file_id).and_then(|buf| buf.contents(db).get(span.clone())).unwrap_or_default();
+			source_map.push(text.len(), *file_id, span.clone());
+			(tk.clone(), text)
+		})
+		.collect();
+
+	let input: Vec<_> = tokens.iter().map(|(tk, _)| tk).filter(|tk| !is_trivia(tk)).cloned().collect();
+	let cst = match fs.matches(db).get(&file_id) {
+		Some(reparse) if reparse.input == input => reparse.cst.clone()?,
+		_ => {
+			let mut parser = new_parser();
+			parser.update_input(&input);
+			parser._match()?
+		}
+	};
+	let rules = p4_grammar();
+	let unit = ParsedUnit { root: build_tree(&rules, "start", &cst, tokens, is_trivia, is_node), source_map };
+
+	for error in unit.syntax().descendants().filter(|node| node.kind() == ERROR) {
+		let mut tokens = error.tokens().filter(|tk| !is_trivia(tk.kind()));
+		let first = match tokens.next() {
+			Some(tk) => tk,
+			None => continue,
+		};
+		let end = tokens.last().map(|tk| tk.text_range().end).unwrap_or(first.text_range().end);
+
+		if let Some((file, location)) = unit.source_map.locate_range(first.text_range().start..end) {
+			let message = format!("Syntax error: unexpected `{}`", first.text());
+			Diagnostics::push(
+				db,
+				Diagnostic { file, location, severity: Severity::Error, message, related: vec![], unnecessary: false },
+			);
+		}
+	}
+
+	Some(unit)
+}
+
+/// Resolves the names of a preprocessed file, see [`symbols`].
+///
+/// Names declared more than once in the same scope, and names used without a visible declaration, are reported as
+/// diagnostics. The latter only once all included files have been found, as a missing file usually means that most
+/// of its declarations are also missing. Resolving a particular name (see [`Analyzer::resolve`]) is then a lookup in
+/// the table.
+#[salsa::tracked(return_ref)]
+pub fn symbol_table(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<SymbolTable> {
+	let unit = parse(db, fs, file_id).as_ref()?;
+	let table = SymbolTable::new(&unit.program());
+	let report = |range: Range<usize>, message: String| {
+		if let Some((file, location)) = unit.source_map.locate_range(range) {
+			Diagnostics::push(
+				db,
+				Diagnostic { file, location, severity: Severity::Error, message, related: vec![], unnecessary: false },
+			);
+		}
+	};
+
+	for &(_, duplicate) in table.duplicates() {
+		let symbol = table.symbol(duplicate);
+		report(symbol.name_range.clone(), format!("Duplicate declaration of `{}`", symbol.name));
+	}
+
+	let includes = preprocess::accumulated::<IncludedDependencies>(db, fs, file_id);
+	if includes.iter().all(|include| include.is_resolved) {
+		// Undefined states are reported with the other problems of the state machine, see `parser_graphs`.
+		for reference in table.unresolved().filter(|reference| reference.namespace != Namespace::State) {
+			let what = match reference.namespace {
+				Namespace::Value => "name",
+				Namespace::State => "state",
+				Namespace::Error => "error",
+			};
+			report(reference.range.clone(), format!("Undeclared {what} `{}`", reference.name));
+		}
+	}
+
+	Some(table)
+}
+
+/// Type checks a preprocessed file, see [`types`].
+///
+/// The type errors are reported as diagnostics, and the types of the expressions and declarations are kept for
+/// hovers and inlay hints, as are the values of the compile-time constants (see [`const_eval`]).
+#[salsa::tracked(return_ref)]
+pub fn type_check(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<TypeInfo> {
+	let unit = parse(db, fs, file_id).as_ref()?;
+	let table = symbol_table(db, fs, file_id).as_ref()?;
+	let info = TypeInfo::new(&unit.program(), table);
+
+	for (range, message) in info.errors() {
+		if let Some((file, location)) = unit.source_map.locate_range(range.clone()) {
+			Diagnostics::push(
+				db,
+				Diagnostic {
+					file,
+					location,
+					severity: Severity::Error,
+					message: message.clone(),
+					related: vec![],
+					unnecessary: false,
+				},
+			);
+		}
+	}
+
+	Some(info)
+}
+
+/// Builds the state machines of the parsers of a preprocessed file, see [`state_graph`].
+///
+/// Undefined states and parsers without a `start` state are reported as errors, and states that can loop without
+/// consuming input as warnings. Unreachable states are reported with the rest of the dead code by [`unused_code`], and
+/// `select`s without a default case by [`case_coverage`], which knows whether their cases cover every value.
+#[salsa::tracked(return_ref)]
+pub fn parser_graphs(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Vec<StateGraph>> {
+	let unit = parse(db, fs, file_id).as_ref()?;
+	let graphs = StateGraph::all(&unit.program());
+
+	for graph in &graphs {
+		for problem in graph.problems() {
+			let severity = match problem {
+				Problem::NoStart | Problem::UndefinedState(_) => Severity::Error,
+				Problem::Loop(_) => Severity::Warning,
+				Problem::Unreachable(_) => continue,
+			};
+			let range = graph.problem_range(&problem);
+			if let Some((file, location)) = range.and_then(|range| unit.source_map.locate_range(range)) {
+				Diagnostics::push(
+					db,
+					Diagnostic {
+						file,
+						location,
+						severity,
+						message: graph.problem_message(&problem),
+						related: vec![],
+						unnecessary: false,
+					},
+				);
+			}
+		}
+	}
+
+	Some(graphs)
+}
+
+/// Checks the `main` instantiation of a preprocessed file against the package of its architecture, see
+/// [`architecture`](crate::architecture).
+///
+/// The target is named after the header that declares the package, so that any architecture header can be used, or
+/// else after the first well-known architecture header that is included.
+#[salsa::tracked(return_ref)]
+pub fn target_architecture(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Architecture> {
+	let unit = parse(db, fs, file_id).as_ref()?;
+	let table = symbol_table(db, fs, file_id).as_ref()?;
+	let types = type_check(db, fs, file_id).as_ref()?;
+	let mut architecture = Architecture::new(&unit.program(), table, types)?;
+
+	for (range, message) in architecture.errors() {
+		if let Some((file, location)) = unit.source_map.locate_range(range.clone()) {
+			Diagnostics::push(
+				db,
+				Diagnostic {
+					file,
+					location,
+					severity: Severity::Error,
+					message: message.clone(),
+					related: vec![],
+					unnecessary: false,
+				},
+			);
+		}
+	}
+
+	let package = table.symbol(architecture.package).name_range.clone();
+	architecture.target = match unit.source_map.locate_range(package) {
+		Some((header, _)) if header != file_id => Some(Target::from_header(&header.path(db))),
+		_ => {
+			let includes = preprocess::accumulated::<IncludedDependencies>(db, fs, file_id);
+			let paths: Vec<_> = includes.iter().map(|include| include.file_id.path(db)).collect();
+			Target::detect(paths.iter().map(String::as_str))
+		}
+	};
+	Some(architecture)
+}
+
+/// Follows the validity of headers through a preprocessed file, see [`validity`].
+///
+/// Reads of fields of headers that may be invalid, `emit`s of invalid headers and `extract`s past the end of header
+/// stacks are reported as warnings, with the path along which the header is invalid (or the stack is full) as related
+/// information.
+#[salsa::tracked(return_ref)]
+pub fn header_validity(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Vec<validity::Warning>> {
+	let unit = parse(db, fs, file_id).as_ref()?;
+	let table = symbol_table(db, fs, file_id).as_ref()?;
+	let types = type_check(db, fs, file_id).as_ref()?;
+	let warnings = validity::check(&unit.program(), table, types, target_architecture(db, fs, file_id).as_ref());
+
+	for warning in &warnings {
+		let Some((file, location)) = unit.source_map.locate_range(warning.range.clone()) else { continue };
+		let related = warning
+			.path
+			.iter()
+			.filter_map(|step| {
+				let (file, location) = unit.source_map.locate_range(step.range.clone())?;
+				Some(RelatedInformation { file, location, message: step.message.clone() })
+			})
+			.collect();
+		Diagnostics::push(
+			db,
+			Diagnostic {
+				file,
+				location,
+				severity: Severity::Warning,
+				message: warning.message.clone(),
+				related,
+				unnecessary: false,
+			},
+		);
+	}
+
+	Some(warnings)
+}
+
+/// Follows the assignments of local variables and `out` parameters through a preprocessed file, see
+/// [`initialization`].
+///
+/// Reads of variables that may be uninitialized and `out` parameters that may not be written are reported as warnings,
+/// whose fixes are offered by [`Analyzer::quick_fixes`].
+#[salsa::tracked(return_ref)]
+pub fn definite_assignment(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Vec<initialization::Warning>> {
+	let unit = parse(db, fs, file_id).as_ref()?;
+	let table = symbol_table(db, fs, file_id).as_ref()?;
+	let types = type_check(db, fs, file_id).as_ref()?;
+	let warnings = initialization::check(&unit.program(), table, types);
+
+	for warning in &warnings {
+		if let Some((file, location)) = unit.source_map.locate_range(warning.range.clone()) {
+			Diagnostics::push(
+				db,
+				Diagnostic {
+					file,
+					location,
+					severity: Severity::Warning,
+					message: warning.message.clone(),
+					related: vec![],
+					unnecessary: false,
+				},
+			);
+		}
+	}
+
+	Some(warnings)
+}
+
+/// Finds the unused declarations and unreachable code of a preprocessed file and the files it includes, see
+/// [`unused`](crate::unused).
+///
+/// Only the code of the file itself is reported as a diagnostic, since the declarations of included files, e.g. of an
+/// architecture, are usually meant to be used by other programs.
+#[salsa::tracked(return_ref)]
+pub fn unused_code(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Vec<unused::Unused>> {
+	let unit = parse(db, fs, file_id).as_ref()?;
+	let table = symbol_table(db, fs, file_id).as_ref()?;
+	let graphs = parser_graphs(db, fs, file_id).as_ref()?;
+	let unused = unused::check(&unit.program(), table, graphs);
+
+	for item in &unused {
+		match unit.source_map.locate_range(item.range.clone()) {
+			Some((file, location)) if file == file_id => Diagnostics::push(
+				db,
+				Diagnostic {
+					file,
+					location,
+					severity: Severity::Warning,
+					message: item.message.clone(),
+					related: vec![],
+					unnecessary: true,
+				},
+			),
+			_ => (),
+		}
+	}
+
+	Some(unused)
+}
+
+/// Checks the directions of the parameters of a preprocessed file and the arguments passed to them, see
+/// [`directions`](crate::directions).
+///
+/// Each error points at the declarations involved as related information, e.g. at the `out` parameter that an
+/// argument which isn't an l-value is passed to.
+#[salsa::tracked(return_ref)]
+pub fn parameter_directions(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Vec<directions::Error>> {
+	let unit = parse(db, fs, file_id).as_ref()?;
+	let table = symbol_table(db, fs, file_id).as_ref()?;
+	let types = type_check(db, fs, file_id).as_ref()?;
+	let errors = directions::check(&unit.program(), table, types);
+
+	for error in &errors {
+		let Some((file, location)) = unit.source_map.locate_range(error.range.clone()) else { continue };
+		let related = error
+			.related
+			.iter()
+			.filter_map(|note| {
+				let (file, location) = unit.source_map.locate_range(note.range.clone())?;
+				Some(RelatedInformation { file, location, message: note.message.clone() })
+			})
+			.collect();
+		Diagnostics::push(
+			db,
+			Diagnostic {
+				file,
+				location,
+				severity: Severity::Error,
+				message: error.message.clone(),
+				related,
+				unnecessary: false,
+			},
+		);
+	}
+
+	Some(errors)
+}
+
+/// Checks the cases of the `select`s and `switch`es of a preprocessed file, see [`coverage`](crate::coverage).
+///
+/// Cases that earlier ones make unreachable are reported as unnecessary warnings, pointing at those earlier cases as
+/// related information. `select`s without a default case that may reject a value are reported as warnings, while
+/// `switch`es that may do nothing for a value are only reported as information, as that is often intended. Both give
+/// a value that matches no case when they can.
+#[salsa::tracked(return_ref)]
+pub fn case_coverage(db: &dyn crate::Db, fs: Fs, file_id: FileId) -> Option<Vec<coverage::Finding>> {
+	let unit = parse(db, fs, file_id).as_ref()?;
+	let table = symbol_table(db, fs, file_id).as_ref()?;
+	let types = type_check(db, fs, file_id).as_ref()?;
+	let findings = coverage::check(&unit.program(), table, types);
+
+	for finding in &findings {
+		let Some((file, location)) = unit.source_map.locate_range(finding.range.clone()) else { continue };
+		let related = finding
+			.related
+			.iter()
+			.filter_map(|note| {
+				let (file, location) = unit.source_map.locate_range(note.range.clone())?;
+				Some(RelatedInformation { file, location, message: note.message.clone() })
+			})
+			.collect();
+		let severity = match finding.kind {
+			coverage::Kind::Shadowed | coverage::Kind::Rejected => Severity::Warning,
+			coverage::Kind::Ignored => Severity::Info,
+		};
+		Diagnostics::push(
+			db,
+			Diagnostic {
+				file,
+				location,
+				severity,
+				message: finding.message.clone(),
+				related,
+				unnecessary: finding.kind == coverage::Kind::Shadowed,
+			},
+		);
+	}
+
+	Some(findings)
+}
+
+/// Runs every analysis of a preprocessed file, so that their diagnostics can be collected from a single query, see
+/// [`Analyzer::diagnostics`].
+#[salsa::tracked]
+pub fn analyze(db: &dyn crate::Db, fs: Fs, file_id: FileId) {
+	symbol_table(db, fs, file_id);
+	type_check(db, fs, file_id);
+	parser_graphs(db, fs, file_id);
+	target_architecture(db, fs, file_id);
+	header_validity(db, fs, file_id);
+	definite_assignment(db, fs, file_id);
+	unused_code(db, fs, file_id);
+	parameter_directions(db, fs, file_id);
+	case_coverage(db, fs, file_id);
+}
@@ -0,0 +1,321 @@
+//! The grammar used to parse P4 programs.
+//!
+//! The grammar is written in the textual format of [`parser::text`](crate::parser::text), in `p4_grammar.peg`.
+
+use std::{collections::HashMap, rc::Rc};
+
+use logos::Logos;
+
+use crate::{
+	lexer::{Lextras, Token},
+	parser::{text::TextGrammar, Rule, RuleName, TokenMatcher},
+};
+
+/// Words that have a meaning of their own in P4 and therefore cannot be used as identifiers.
+///
+/// Most keywords are lexed as [`Token::Identifier`]s, so they are told apart here. Some of them (e.g. `key`) are
+/// allowed as names anyway, which the grammar spells out.
+const RESERVED: &[&str] = &[
+	"_",
+	"abstract",
+	"actions",
+	"bit",
+	"bool",
+	"const",
+	"default",
+	"entries",
+	"enum",
+	"error",
+	"exit",
+	"extern",
+	"false",
+	"header_union",
+	"in",
+	"inout",
+	"int",
+	"key",
+	"list",
+	"match_kind",
+	"out",
+	"package",
+	"parser",
+	"priority",
+	"select",
+	"string",
+	"struct",
+	"switch",
+	"this",
+	"true",
+	"tuple",
+	"type",
+	"value_set",
+	"varbit",
+	"void",
+];
+
+thread_local! {
+	static RULES: Rc<HashMap<RuleName, Rule<Token>>> = Rc::new(load());
+}
+
+/// The rules of the P4 grammar, starting at `start`.
+///
+/// Matching `start` never fails: input that doesn't conform to the grammar is matched by `error` instead, which
+/// produces a node of that kind in the syntax tree.
+///
+/// The rules are loaded once per thread, and shared by all the callers on that thread.
+pub fn p4_grammar() -> Rc<HashMap<RuleName, Rule<Token>>> { RULES.with(Rc::clone) }
+
+fn load() -> HashMap<RuleName, Rule<Token>> {
+	TextGrammar::new(literal)
+		.with_class(TokenMatcher::new("identifier", |tk| match tk {
+			Token::Identifier(name) => !RESERVED.contains(&name.as_str()),
+			_ => false,
+		}))
+		.with_class(TokenMatcher::new("integer", |tk| matches!(tk, Token::Integer(_))))
+		.with_class(TokenMatcher::new("string", |tk| matches!(tk, Token::StringLiteral(_))))
+		.with_class(TokenMatcher::new("any", |_| true))
+		.parse(include_str!("p4_grammar.peg"))
+		.unwrap_or_else(|err| panic!("p4_grammar.peg:{err}"))
+}
+
+/// Lexes the text of a literal in the grammar, e.g. `"&&&"` or `">>"` (which becomes two tokens).
+fn literal(text: &str) -> Option<Vec<Token>> {
+	Token::lexer_with_extras(text, Lextras { db: None, file_id: None })
+		.filter(|tk| !is_trivia(tk))
+		.map(|tk| if tk == Token::Error { None } else { Some(tk) })
+		.collect()
+}
+
+/// Whether the rule produces a node in the syntax tree, see [`build_tree`](crate::syntax::build_tree).
+///
+/// Rules whose names start with an underscore only exist to structure the grammar, and the rules generated for
+/// literals, token classes and nested expressions have no meaning of their own.
+pub fn is_node(name: RuleName) -> bool { !name.starts_with('_') && !name.contains(['.', '"', '\'', '<']) }
+
+/// Whether the token carries no meaning for the parser, i.e. it is whitespace or a comment.
+pub fn is_trivia(tk: &Token) -> bool { matches!(tk, Token::Whitespace | Token::Comment) }
+
+/// Parses a piece of P4 code on its own, without preprocessing it, e.g. for use in tests.
+#[cfg(test)]
+pub(crate) fn parse(input: &str) -> crate::syntax::SyntaxNode<Token> {
+	use crate::{parser::Parser, syntax::*};
+
+	let rules = p4_grammar();
+	let tokens: Vec<_> = Token::lexer_with_extras(input, Lextras { db: None, file_id: None })
+		.spanned()
+		.map(|(tk, span)| (tk, &input[span]))
+		.collect();
+	let input: Vec<_> = tokens.iter().map(|(tk, _)| tk.clone()).filter(|tk| !is_trivia(tk)).collect();
+	let mut parser = Parser::from_rules(&*rules).unwrap()(input.into());
+	let cst = parser._match().expect("the P4 grammar should match any input");
+
+	SyntaxNode::new_root(build_tree(&rules, "start", &cst, tokens, is_trivia, is_node))
+}
+
+/// Parses a piece of P4 code like [`parse`], then collects its symbols and checks its types, e.g. for the tests of the
+/// analyses that build on them.
+#[cfg(test)]
+pub(crate) fn parse_and_check(
+	input: &str,
+) -> (crate::ast::Program, crate::symbols::SymbolTable, crate::types::TypeInfo) {
+	use crate::syntax::AstNode;
+
+	let program = crate::ast::Program::cast(parse(input)).unwrap();
+	let table = crate::symbols::SymbolTable::new(&program);
+	let types = crate::types::TypeInfo::new(&program, &table);
+	(program, table, types)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::syntax::*;
+	use pretty_assertions::assert_eq;
+
+	fn kinds(node: &SyntaxNode<Token>) -> Vec<NodeKind> { node.children().map(|child| child.kind()).collect() }
+
+	fn errors(node: &SyntaxNode<Token>) -> Vec<String> {
+		node.descendants().filter(|node| node.kind() == ERROR).map(|node| node.text()).collect()
+	}
+
+	#[test]
+	fn declarations() {
+		let root = parse(
+			r#"
+			error { NoError, PacketTooShort }
+			match_kind { exact, ternary, lpm }
+			typedef bit<48> macAddr_t;
+			const bit<16> TYPE_IPV4 = 0x800;
+			enum bit<8> Color { Red = 1, Green = 2 }
+			header ethernet_t { macAddr_t dstAddr; macAddr_t srcAddr; bit<16> etherType; }
+			header_union ip_t { ipv4_t v4; ipv6_t v6; }
+			struct headers { ethernet_t ethernet; ipv4_t[2] ipv4; }
+			extern packet_in {
+				void extract<T>(out T hdr);
+				T lookahead<T>();
+				@noWarn("unused") bit<32> length();
+			}
+			extern void verify(in bool check, in error toSignal);
+			parser Parser<H>(packet_in b, out H parsedHeaders);
+			package V1Switch<H, M>(Parser<H, M> p, Deparser<H> dep);
+			bit<8> max(in bit<8> a, in bit<8> b) { return a > b ? a : b; }
+			V1Switch(MyParser(), MyDeparser()) main;
+			"#,
+		);
+
+		assert_eq!(
+			kinds(&root),
+			vec![
+				"declaration",
+				"declaration",
+				"declaration",
+				"declaration",
+				"declaration",
+				"declaration",
+				"declaration",
+				"declaration",
+				"declaration",
+				"declaration",
+				"declaration",
+				"declaration",
+				"declaration",
+				"declaration",
+			]
+		);
+		let declarations: Vec<_> = root.children().flat_map(|decl| kinds(&decl)).collect();
+		assert_eq!(
+			declarations,
+			vec![
+				"errorDeclaration",
+				"matchKindDeclaration",
+				"typeDeclaration",
+				"constantDeclaration",
+				"typeDeclaration",
+				"typeDeclaration",
+				"typeDeclaration",
+				"typeDeclaration",
+				"externDeclaration",
+				"externDeclaration",
+				"typeDeclaration",
+				"typeDeclaration",
+				"functionDeclaration",
+				"instantiation",
+			]
+		);
+		assert_eq!(errors(&root), Vec::<String>::new());
+	}
+
+	#[test]
+	fn parsers_and_controls() {
+		let root = parse(
+			r#"
+			parser MyParser(packet_in packet, out headers hdr, inout standard_metadata_t standard_metadata) {
+				value_set<bit<16>>(4) pvs;
+				state start {
+					packet.extract(hdr.ethernet);
+					transition select(hdr.ethernet.etherType, hdr.vlan.isValid()) {
+						(TYPE_IPV4, true): parse_ipv4;
+						(0x8100 &&& 0xEFFF, _): parse_vlan;
+						pvs: accept;
+						default: reject;
+					}
+				}
+				state parse_ipv4 { packet.extract(hdr.ipv4); transition accept; }
+			}
+
+			control MyIngress(inout headers hdr, inout standard_metadata_t standard_metadata) {
+				action drop() { mark_to_drop(standard_metadata); }
+				action forward(macAddr_t dstAddr, egressSpec_t port) {
+					standard_metadata.egress_spec = port;
+					hdr.ethernet.srcAddr = hdr.ethernet.dstAddr;
+					hdr.ipv4.ttl = hdr.ipv4.ttl - 1;
+					hdr.ipv4.flags[1:0] = (bit<2>) (port >> 2 | 8w1 << 1);
+				}
+				table ipv4_lpm {
+					key = { hdr.ipv4.dstAddr: lpm; }
+					actions = { forward; drop; @defaultonly NoAction; }
+					size = 1024;
+					const default_action = drop();
+					const entries = { 0x0a000001 &&& 0xffffffff: forward(0x1, 1); }
+				}
+				apply {
+					if (hdr.ipv4.isValid() && !(hdr.ipv4.ttl == 0)) {
+						switch (ipv4_lpm.apply().action_run) {
+							forward: {}
+							default: { exit; }
+						}
+					} else {
+						drop();
+					}
+				}
+			}
+			"#,
+		);
+
+		assert_eq!(errors(&root), Vec::<String>::new());
+		let kinds: Vec<_> = root.descendants().map(|node| node.kind()).collect();
+		for kind in [
+			"parserDeclaration",
+			"valueSetDeclaration",
+			"parserState",
+			"selectCase",
+			"tupleKeysetExpression",
+			"maskExpression",
+			"dontCareKeyset",
+			"defaultKeyset",
+			"controlDeclaration",
+			"actionDeclaration",
+			"tableDeclaration",
+			"keyProperty",
+			"actionsProperty",
+			"tableProperty",
+			"entriesProperty",
+			"sliceSuffix",
+			"castExpression",
+			"shiftExpression",
+			"bitwiseOrExpression",
+			"logicalAndExpression",
+			"switchStatement",
+			"exitStatement",
+			"conditionalStatement",
+		] {
+			assert!(kinds.contains(&kind), "missing {kind}");
+		}
+	}
+
+	#[test]
+	fn expressions() {
+		let root = parse("const bit<8> x = a + b * -c - (d) - 1;");
+		let expr = root.descendants().find(|node| node.kind() == "additiveExpression").unwrap();
+		assert_eq!(
+			kinds(&expr),
+			vec!["nameExpression", "multiplicativeExpression", "parenthesizedExpression", "literalExpression"]
+		);
+		assert_eq!(root.descendants().filter(|node| node.kind() == "castExpression").count(), 0);
+
+		let root = parse("const bool x = a < b && c >= d;");
+		let expr = root.descendants().find(|node| node.kind() == "logicalAndExpression").unwrap();
+		assert_eq!(kinds(&expr), vec!["relationalExpression", "relationalExpression"]);
+	}
+
+	#[test]
+	fn error_recovery() {
+		let root = parse(
+			r#"
+			header h_t { bit<8> f; }
+			control c() {
+				apply {
+					x = ;
+					y = 1;
+				}
+			}
+			struct s_t { bit 8> g; }
+			typedef bit<8> t;
+			"#,
+		);
+
+		assert_eq!(errors(&root), vec!["x =", "struct s_t { bit 8> g; }"]);
+		let declarations: Vec<_> = root.children().flat_map(|decl| kinds(&decl)).collect();
+		assert_eq!(declarations, vec!["typeDeclaration", "controlDeclaration", "typeDeclaration"]);
+	}
+}
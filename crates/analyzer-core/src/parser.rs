@@ -1,28 +1,151 @@
 use anyhow::{anyhow, Result};
+use cancellation::CancellationToken;
 use parking_lot::{RwLock, RwLockReadGuard};
-use std::{collections::HashMap, rc::Rc};
+use std::{collections::HashMap, fmt, rc::Rc, sync::Arc};
+use trace::{Trace, TraceRecorder};
 
-#[derive(Debug, Default)]
+pub mod text;
+pub mod trace;
+
+#[derive(Debug)]
 pub struct Parser<Token: Clone> {
-	rules: Rc<HashMap<RuleName, Rule<Token>>>,
+	grammar: Grammar<Token>,
 	buffer: RwLock<Vec<Token>>,
 	memo_table: Vec<Column<Token>>,
+	statistics: MemoStatistics,
+	/// The rules which can be used to start a match.
+	entry_points: Vec<RuleName>,
+	tracing: bool,
+	/// The rule attempts of the last match, if tracing was enabled.
+	trace: Option<Trace>,
+	/// The maximum number of nested rule applications before a match gives up.
+	max_depth: usize,
+	cancel_token: Option<Arc<CancellationToken>>,
+}
+
+/// The default maximum number of nested rule applications, see [`Parser::set_max_depth`].
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+/// The error returned when a match is cancelled through the [`CancellationToken`] of a [`Parser`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "The match was cancelled.") }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Counters describing how much of the memo table was reused, e.g. after an edit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoStatistics {
+	/// The number of memo table entries dropped by the last [`Parser::apply_edit`].
+	pub invalidated: usize,
+	/// The number of memo table entries that survived the last [`Parser::apply_edit`].
+	pub retained: usize,
+	/// The number of rule applications answered by the memo table during the last match.
+	pub reused: usize,
+	/// The number of rule applications that had to be evaluated during the last match.
+	pub evaluated: usize,
+}
+
+impl MemoStatistics {
+	/// The fraction of rule applications answered by the memo table during the last match.
+	pub fn reuse_ratio(&self) -> f64 {
+		match self.reused + self.evaluated {
+			0 => 0.0,
+			total => self.reused as f64 / total as f64,
+		}
+	}
 }
 
 #[derive(Debug)]
 pub struct Matcher<'a, Token: Clone> {
-	rules: Rc<HashMap<RuleName, Rule<Token>>>,
+	grammar: &'a Grammar<Token>,
 	memo_table: &'a mut Vec<Column<Token>>,
 	input: RwLockReadGuard<'a, Vec<Token>>,
 	pos: usize,
 	max_examined_pos: isize,
+	reused: usize,
+	evaluated: usize,
+	trace: Option<TraceRecorder>,
+	depth: usize,
+	max_depth: usize,
+	/// The number of rule applications cut short by the depth limit, or answered by memo table entries which depend
+	/// on such an application.
+	cutoffs: usize,
+	cancel_token: Option<&'a CancellationToken>,
+	cancelled: bool,
+}
+
+pub type RuleName = &'static str;
+
+/// A dense index identifying a rule of a [`Grammar`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct RuleId(u32);
+
+impl RuleId {
+	fn index(self) -> usize { self.0 as usize }
+}
+
+/// The rules of a [`Parser`], with rule names interned to [`RuleId`]s.
+#[derive(Debug)]
+struct Grammar<Token: Clone> {
+	names: Vec<RuleName>,
+	ids: HashMap<RuleName, RuleId>,
+	rules: Vec<CompiledRule<Token>>,
+	/// Whether the results of a rule are stored in the memo table.
+	memoized: Vec<bool>,
+}
+
+/// A [`Rule`] referring to other rules by their [`RuleId`].
+#[derive(Debug)]
+enum CompiledRule<Token: Clone> {
+	Terminal(Rc<Vec<Token>>),
+	Matcher(TokenMatcher<Token>),
+	Choice(Vec<RuleId>),
+	Sequence(Vec<RuleId>),
+	Repetition(RuleId),
+	OneOrMore(RuleId),
+	Optional(RuleId),
+	Not(RuleId),
+	And(RuleId),
 }
 
-type RuleName = &'static str;
+impl<Token: Clone> Grammar<Token> {
+	fn new(rules: &HashMap<RuleName, Rule<Token>>) -> Self {
+		// sort the names so that the ids do not depend on the iteration order of the map
+		let mut names: Vec<_> = rules.keys().copied().collect();
+		names.sort_unstable();
+		let ids: HashMap<_, _> = names.iter().enumerate().map(|(i, name)| (*name, RuleId(i as u32))).collect();
 
+		let id = |name: &RuleName| ids[name];
+		let rules = names
+			.iter()
+			.map(|name| match &rules[name] {
+				Rule::Terminal(tokens) => CompiledRule::Terminal(tokens.clone()),
+				Rule::Matcher(matcher) => CompiledRule::Matcher(matcher.clone()),
+				Rule::Choice(options) => CompiledRule::Choice(options.iter().map(id).collect()),
+				Rule::Sequence(parts) => CompiledRule::Sequence(parts.iter().map(id).collect()),
+				Rule::Repetition(rule) => CompiledRule::Repetition(id(rule)),
+				Rule::OneOrMore(rule) => CompiledRule::OneOrMore(id(rule)),
+				Rule::Optional(rule) => CompiledRule::Optional(id(rule)),
+				Rule::Not(rule) => CompiledRule::Not(id(rule)),
+				Rule::And(rule) => CompiledRule::And(id(rule)),
+			})
+			.collect();
+
+		Self { memoized: vec![true; names.len()], names, ids, rules }
+	}
+
+	fn name(&self, id: RuleId) -> RuleName { self.names[id.index()] }
+}
+
+/// The memoized results of all the rules applied at a single input position.
 #[derive(Debug, Clone)]
 struct Column<Token: Clone> {
-	memo: HashMap<RuleName, MemoTableEntry<Token>>,
+	/// The entries, sorted by rule.
+	memo: Vec<(RuleId, MemoTableEntry<Token>)>,
 	max_examined_length: isize,
 }
 
@@ -30,16 +153,31 @@ impl<T: Clone> Default for Column<T> {
 	fn default() -> Self { Self { memo: Default::default(), max_examined_length: -1 } }
 }
 
+impl<T: Clone> Column<T> {
+	fn get(&self, rule: RuleId) -> Option<&MemoTableEntry<T>> {
+		self.memo.binary_search_by_key(&rule, |(id, _)| *id).ok().map(|i| &self.memo[i].1)
+	}
+
+	fn insert(&mut self, rule: RuleId, entry: MemoTableEntry<T>) {
+		match self.memo.binary_search_by_key(&rule, |(id, _)| *id) {
+			Ok(i) => self.memo[i].1 = entry,
+			Err(i) => self.memo.insert(i, (rule, entry)),
+		}
+	}
+}
+
 #[derive(Debug, Clone)]
 struct MemoTableEntry<Token: Clone> {
 	existing_match: Option<ExistingMatch<Token>>,
-	examined_length: usize,
+	examined_length: u32,
+	/// Whether the result depends on a rule application cut short by the depth limit.
+	depth_limited: bool,
 }
 
 #[derive(Debug, Clone)]
 struct ExistingMatch<Token: Clone> {
 	cst: Rc<Cst<Token>>,
-	match_length: usize,
+	match_length: u32,
 }
 
 /// The concrete syntax tree type exactly mirrors the structure of the grammar.
@@ -50,24 +188,35 @@ pub enum Cst<Token: Clone> {
 	Choice(RuleName, Rc<Cst<Token>>),
 	Sequence(Vec<Rc<Cst<Token>>>),
 	Repetition(Vec<Rc<Cst<Token>>>),
+	Optional(Option<Rc<Cst<Token>>>),
 	Not(RuleName),
+	And(RuleName),
 }
 
 impl<Token: Clone + PartialEq> Parser<Token> {
 	pub fn from_rules<R: Into<HashMap<RuleName, Rule<Token>>> + Clone>(
 		rules: &R,
+	) -> Result<impl FnOnce(RwLock<Vec<Token>>) -> Parser<Token>> {
+		Self::from_rules_with_entry_points(rules, &["start"])
+	}
+
+	/// Like [`Parser::from_rules`], but any of the given `entry_points` can be used to start a match instead of just
+	/// `start`, e.g. to parse a lone expression. All entry points share the same memo table.
+	pub fn from_rules_with_entry_points<R: Into<HashMap<RuleName, Rule<Token>>> + Clone>(
+		rules: &R,
+		entry_points: &[RuleName],
 	) -> Result<impl FnOnce(RwLock<Vec<Token>>) -> Parser<Token>> {
 		let rules: HashMap<_, _> = rules.clone().into();
-		if !rules.contains_key("start") {
-			return Err(anyhow!("Missing initial non-terminal 'start'"));
+		if let Some(name) = entry_points.iter().find(|name| !rules.contains_key(*name)) {
+			return Err(anyhow!("Missing initial non-terminal '{name}'"));
 		}
 
 		let neighbours = |rule: &Rule<Token>| match rule {
-			Rule::Terminal(_) => vec![],
+			Rule::Terminal(_) | Rule::Matcher(_) => vec![],
 			Rule::Choice(options) => options.clone(),
 			Rule::Sequence(parts) => parts.clone(),
-			Rule::Repetition(rule) => vec![*rule],
-			Rule::Not(rule) => vec![*rule],
+			Rule::Repetition(rule) | Rule::OneOrMore(rule) | Rule::Optional(rule) => vec![*rule],
+			Rule::Not(rule) | Rule::And(rule) => vec![*rule],
 		};
 
 		for (k, rule) in rules.iter() {
@@ -76,86 +225,276 @@ impl<Token: Clone + PartialEq> Parser<Token> {
 			}
 		}
 
-		Ok(move |buffer| Parser { rules: rules.into(), memo_table: vec![], buffer })
+		let grammar = Grammar::new(&rules);
+		let entry_points = entry_points.to_vec();
+		Ok(move |buffer| Parser {
+			grammar,
+			memo_table: vec![],
+			buffer,
+			statistics: Default::default(),
+			entry_points,
+			tracing: false,
+			trace: None,
+			max_depth: DEFAULT_MAX_DEPTH,
+			cancel_token: None,
+		})
+	}
+
+	/// The rules which can be used to start a match.
+	pub fn entry_points(&self) -> &[RuleName] { &self.entry_points }
+
+	/// The current input of the parser.
+	pub fn input(&self) -> RwLockReadGuard<'_, Vec<Token>> { self.buffer.read() }
+
+	/// Statistics about the last edit and match.
+	pub fn statistics(&self) -> MemoStatistics { self.statistics }
+
+	/// Enables or disables recording a [`Trace`] of every rule attempt during subsequent matches.
+	///
+	/// Outcomes answered by the memo table are recorded without their sub-attempts, so to trace a complete parse,
+	/// trace a fresh parser rather than a reparse.
+	pub fn set_tracing(&mut self, enabled: bool) {
+		self.tracing = enabled;
+		if !enabled {
+			self.trace = None;
+		}
+	}
+
+	/// The trace of the last match, if tracing was enabled for it.
+	pub fn trace(&self) -> Option<&Trace> { self.trace.as_ref() }
+
+	/// Sets the maximum number of nested rule applications, [`DEFAULT_MAX_DEPTH`] by default.
+	///
+	/// Deeply nested input, such as thousands of parentheses, would otherwise overflow the stack. When the limit is
+	/// reached, the rule application that would go deeper fails as if the rule didn't match, so that the enclosing
+	/// rules can recover from it like from any other mismatch, e.g. by skipping the input as an error.
+	pub fn set_max_depth(&mut self, max_depth: usize) { self.max_depth = max_depth; }
+
+	/// Sets a token which aborts subsequent matches when cancelled, making them return a [`Cancelled`] error.
+	///
+	/// The memo table stays consistent when a match is cancelled, so the next match can still reuse the results of
+	/// the rules that were completed.
+	pub fn set_cancellation_token(&mut self, cancel_token: Option<Arc<CancellationToken>>) {
+		self.cancel_token = cancel_token;
+	}
+
+	/// The total number of entries in the memo table.
+	pub fn memo_table_size(&self) -> usize { self.memo_table.iter().map(|col| col.memo.len()).sum() }
+
+	/// Sets whether the results of the given rule are stored in the memo table.
+	///
+	/// Memoizing cheap rules, such as single tokens, usually costs more than evaluating them again. Existing entries
+	/// of a rule are evicted when it stops being memoized.
+	pub fn set_memoized(&mut self, rule_name: RuleName, memoized: bool) -> Result<()> {
+		let id = *self.grammar.ids.get(rule_name).ok_or_else(|| anyhow!("Unknown rule '{rule_name}'"))?;
+		self.grammar.memoized[id.index()] = memoized;
+
+		if !memoized {
+			for col in &mut self.memo_table {
+				col.memo.retain(|(rule, _)| *rule != id);
+			}
+		}
+
+		Ok(())
 	}
 
+	/// Match the whole input against `start`, returning `None` if it doesn't match or if the match was cancelled.
+	///
+	/// Panics if `start` is not an entry point of the parser, see [`Parser::from_rules_with_entry_points`].
 	pub fn _match(&mut self) -> Option<Cst<Token>> {
+		match self._match_rule("start") {
+			Ok(cst) => cst,
+			Err(err) if err.is::<Cancelled>() => None,
+			Err(err) => panic!("{err}"),
+		}
+	}
+
+	/// Match the whole input against the given entry point.
+	pub fn _match_rule(&mut self, entry_point: RuleName) -> Result<Option<Cst<Token>>> {
+		let len = self.buffer.read().len();
+		Ok(self._match_at(entry_point, 0)?.filter(|(_, end)| *end == len).map(|(cst, _)| cst))
+	}
+
+	/// Match a prefix of the input against the given entry point, returning the number of tokens consumed.
+	pub fn _match_prefix(&mut self, entry_point: RuleName) -> Result<Option<(Cst<Token>, usize)>> {
+		self._match_at(entry_point, 0)
+	}
+
+	/// Match the input starting at `pos` against the given entry point, returning the position after the match.
+	///
+	/// Fails with [`Cancelled`] if the cancellation token of the parser was cancelled during the match.
+	pub fn _match_at(&mut self, entry_point: RuleName, pos: usize) -> Result<Option<(Cst<Token>, usize)>> {
+		if !self.entry_points.contains(&entry_point) {
+			return Err(anyhow!("'{entry_point}' is not an entry point"));
+		}
+
 		let mut matcher = Matcher {
-			rules: self.rules.clone(),
+			grammar: &self.grammar,
 			memo_table: &mut self.memo_table,
 			input: self.buffer.read(),
-			pos: 0,
+			pos,
 			max_examined_pos: -1,
+			reused: 0,
+			evaluated: 0,
+			trace: self.tracing.then(TraceRecorder::default),
+			depth: 0,
+			max_depth: self.max_depth,
+			cutoffs: 0,
+			cancel_token: self.cancel_token.as_deref(),
+			cancelled: false,
 		};
 
-		matcher
-			.memoized_eval_rule("start")
-			.filter(|_| matcher.pos == matcher.input.len())
-			.map(|rc| (*rc).clone())
+		let rule = self.grammar.ids[entry_point];
+		let result = matcher.memoized_eval_rule(rule).map(|rc| ((*rc).clone(), matcher.pos));
+
+		// whether a rule fails because of the depth limit depends on how deep it was applied, so these results are
+		// only reused within the match
+		if matcher.cutoffs > 0 {
+			for col in matcher.memo_table.iter_mut() {
+				col.memo.retain(|(_, entry)| !entry.depth_limited);
+			}
+		}
+		if matcher.cancelled {
+			return Err(Cancelled.into());
+		}
+
+		self.statistics.reused = matcher.reused;
+		self.statistics.evaluated = matcher.evaluated;
+		self.trace = matcher.trace.map(TraceRecorder::finish);
+		Ok(result)
 	}
 
 	/// Apply an edit operation, replacing the given `range` of tokens with `r`.
 	pub fn apply_edit(&mut self, range: std::ops::Range<usize>, r: &[Token]) {
+		if range.is_empty() && r.is_empty() {
+			self.statistics = MemoStatistics { retained: self.memo_table_size(), ..Default::default() };
+			return;
+		}
+
 		// apply edit to the input
 		self.buffer.write().splice(range.clone(), r.iter().cloned());
 
+		// the memo table only grows as far as the input was examined
+		if self.memo_table.len() < range.end {
+			self.memo_table.resize_with(range.end, Default::default);
+		}
+
 		// adjust the memo table: replace the affected range with empty entries
-		self.memo_table.splice(range.clone(), std::iter::repeat(Default::default()).take(r.len()));
+		let mut invalidated: usize = self
+			.memo_table
+			.splice(range.clone(), std::iter::repeat(Default::default()).take(r.len()))
+			.map(|col| col.memo.len())
+			.sum();
 
 		// invalidate overlapping entries
 		for pos in 0..range.start {
 			if let Some(col) = self.memo_table.get_mut(pos) {
 				if pos as isize + col.max_examined_length > range.start as isize {
-					invalidate_entries_in_column(col, pos, range.start);
+					invalidated += invalidate_entries_in_column(col, pos, range.start);
 				}
 			}
 		}
 
-		fn invalidate_entries_in_column<Tk: Clone>(col: &mut Column<Tk>, pos: usize, start_pos: usize) {
+		self.statistics = MemoStatistics { invalidated, retained: self.memo_table_size(), ..Default::default() };
+
+		fn invalidate_entries_in_column<Tk: Clone>(col: &mut Column<Tk>, pos: usize, start_pos: usize) -> usize {
 			let mut new_max = 0;
-			let mut to_remove = vec![];
-			for (rule_name, entry) in &col.memo {
-				if pos + entry.examined_length > start_pos {
-					// this entry's "input range" overlaps the edit
-					to_remove.push(*rule_name);
-				} else if entry.examined_length > new_max {
-					new_max = entry.examined_length;
+			let len = col.memo.len();
+			col.memo.retain(|(_, entry)| {
+				// remove the entries whose "input range" overlaps the edit
+				let overlaps = pos + entry.examined_length as usize > start_pos;
+				if !overlaps {
+					new_max = new_max.max(entry.examined_length);
 				}
-			}
-
-			for k in to_remove {
-				// remove all the affected memoized entries
-				col.memo.remove(&k);
-			}
+				!overlaps
+			});
 
 			col.max_examined_length = new_max as isize;
+			len - col.memo.len()
 		}
 	}
+
+	/// Replace the whole input with `tokens`, reusing the memo table for the unchanged prefix and suffix.
+	///
+	/// This is the same as an [`apply_edit`](Parser::apply_edit) of the smallest range of tokens that differs between
+	/// the current input and `tokens`. Returns that range (in terms of the previous input).
+	pub fn update_input(&mut self, tokens: &[Token]) -> std::ops::Range<usize> {
+		let (start, end) = {
+			let old = self.buffer.read();
+			let prefix = old.iter().zip(tokens).take_while(|(a, b)| a == b).count();
+			let max_suffix = old.len().min(tokens.len()) - prefix;
+			let suffix = old.iter().rev().zip(tokens.iter().rev()).take(max_suffix).take_while(|(a, b)| a == b).count();
+			(prefix, old.len() - suffix)
+		};
+
+		let replacement_end = tokens.len() - (self.buffer.read().len() - end);
+		self.apply_edit(start..end, &tokens[start..replacement_end]);
+		start..end
+	}
 }
 
 impl<'a, Token: Clone + PartialEq> Matcher<'a, Token> {
 	// originally under the (weird?) RuleApplication abstraction
-	fn memoized_eval_rule(&mut self, rule_name: RuleName) -> Option<Rc<Cst<Token>>> {
-		if let Some(cst) = self.use_memoized_result(rule_name) {
-			Some(cst)
+	fn memoized_eval_rule(&mut self, rule: RuleId) -> Option<Rc<Cst<Token>>> {
+		// once cancelled, every rule fails without being memoized until the match has unwound
+		if self.cancelled {
+			return None;
+		}
+		if self.depth >= self.max_depth {
+			self.cutoffs += 1;
+			return None;
+		}
+		if self.cancel_token.map_or(false, |token| token.is_canceled()) {
+			self.cancelled = true;
+			return None;
+		}
+
+		if let Some(trace) = &mut self.trace {
+			trace.enter(self.grammar.name(rule), self.pos);
+		}
+
+		self.depth += 1;
+		let (result, reused) = self.memoized_eval_rule_untraced(rule);
+		self.depth -= 1;
+
+		if let Some(trace) = &mut self.trace {
+			trace.exit(result.as_ref().map(|_| self.pos), reused);
+		}
+		result
+	}
+
+	/// Evaluates a rule or reuses its memoized result, also returning whether the memo table was used.
+	fn memoized_eval_rule_untraced(&mut self, rule: RuleId) -> (Option<Rc<Cst<Token>>>, bool) {
+		if !self.grammar.memoized[rule.index()] {
+			self.evaluated += 1;
+			return (self.eval_rule(rule), false);
+		}
+
+		if let Some(result) = self.use_memoized_result(rule) {
+			self.reused += 1;
+			(result, true)
 		} else {
+			self.evaluated += 1;
 			let orig_pos = self.pos;
 			let orig_max = self.max_examined_pos;
 			self.max_examined_pos = -1;
 
-			let cst = self.eval_rule(rule_name);
-			self.memoize_result(orig_pos, rule_name, cst.clone());
+			let cutoffs = self.cutoffs;
+			let cst = self.eval_rule(rule);
+			if !self.cancelled {
+				self.memoize_result(orig_pos, rule, cst.clone(), self.cutoffs > cutoffs);
+			}
 
 			self.max_examined_pos = self.max_examined_pos.max(orig_max);
-			cst
+			(cst, false)
 		}
 	}
 
 	// originally a Rule method
-	fn eval_rule(&mut self, rule_name: RuleName) -> Option<Rc<Cst<Token>>> {
-		let rules = self.rules.clone();
-		match &rules[rule_name] {
-			Rule::Terminal(vec) => {
+	fn eval_rule(&mut self, rule: RuleId) -> Option<Rc<Cst<Token>>> {
+		let grammar = self.grammar;
+		match &grammar.rules[rule.index()] {
+			CompiledRule::Terminal(vec) => {
 				for tk in vec.iter() {
 					if !self.consume(tk) {
 						return None;
@@ -164,20 +503,24 @@ impl<'a, Token: Clone + PartialEq> Matcher<'a, Token> {
 
 				Some(Cst::Terminal(vec.clone()).into())
 			}
-			Rule::Choice(options) => {
+			CompiledRule::Matcher(matcher) => {
+				let tk = self.consume_matching(matcher)?;
+				Some(Cst::Terminal(vec![tk].into()).into())
+			}
+			CompiledRule::Choice(options) => {
 				let orig_pos = self.pos;
-				for rule in options {
+				for &option in options {
 					self.pos = orig_pos;
-					if let Some(cst) = self.memoized_eval_rule(rule) {
-						return Some(Cst::Choice(rule, cst).into());
+					if let Some(cst) = self.memoized_eval_rule(option) {
+						return Some(Cst::Choice(grammar.name(option), cst).into());
 					}
 				}
 				None
 			}
-			Rule::Sequence(parts) => {
+			CompiledRule::Sequence(parts) => {
 				let mut matches = vec![];
-				for rule in parts {
-					if let Some(cst) = self.memoized_eval_rule(rule) {
+				for &part in parts {
+					if let Some(cst) = self.memoized_eval_rule(part) {
 						if matches.capacity() == 0 {
 							matches.reserve_exact(parts.len())
 						}
@@ -190,56 +533,88 @@ impl<'a, Token: Clone + PartialEq> Matcher<'a, Token> {
 
 				Some(Cst::Sequence(matches).into())
 			}
-			Rule::Repetition(rule) => {
-				let mut matches = vec![];
-				loop {
-					let orig_pos = self.pos;
-					if let Some(cst) = self.memoized_eval_rule(rule) {
-						matches.push(cst);
-					} else {
-						self.pos = orig_pos;
-						break Some(Cst::Repetition(matches).into());
-					}
+			&CompiledRule::Repetition(rule) => self.eval_repetition(rule, 0),
+			&CompiledRule::OneOrMore(rule) => self.eval_repetition(rule, 1),
+			&CompiledRule::Optional(rule) => {
+				let orig_pos = self.pos;
+				let cst = self.memoized_eval_rule(rule);
+				if cst.is_none() {
+					self.pos = orig_pos;
 				}
+				Some(Cst::Optional(cst).into())
 			}
-			Rule::Not(rule) => {
+			&CompiledRule::Not(rule) => {
 				let orig_pos = self.pos;
 				if self.memoized_eval_rule(rule).is_some() {
 					None
 				} else {
 					self.pos = orig_pos;
-					Some(Cst::Not(rule).into())
+					Some(Cst::Not(grammar.name(rule)).into())
+				}
+			}
+			&CompiledRule::And(rule) => {
+				let orig_pos = self.pos;
+				self.memoized_eval_rule(rule)?;
+				self.pos = orig_pos;
+				Some(Cst::And(grammar.name(rule)).into())
+			}
+		}
+	}
+
+	/// Greedily match `rule` as many times as possible, failing if it matched fewer than `min` times.
+	fn eval_repetition(&mut self, rule: RuleId, min: usize) -> Option<Rc<Cst<Token>>> {
+		let mut matches = vec![];
+		loop {
+			let orig_pos = self.pos;
+			match self.memoized_eval_rule(rule) {
+				// an empty match would otherwise repeat forever
+				Some(cst) if self.pos > orig_pos => matches.push(cst),
+				Some(cst) => {
+					matches.push(cst);
+					break;
+				}
+				None => {
+					self.pos = orig_pos;
+					break;
 				}
 			}
 		}
+
+		if matches.len() < min {
+			return None;
+		}
+
+		Some(Cst::Repetition(matches).into())
 	}
 
-	fn memoize_result(&mut self, pos: usize, rule_name: RuleName, cst: Option<Rc<Cst<Token>>>) {
+	fn memoize_result(&mut self, pos: usize, rule: RuleId, cst: Option<Rc<Cst<Token>>>, depth_limited: bool) {
 		while self.memo_table.len() <= pos {
 			self.memo_table.push(Default::default());
 		}
 
 		let col = &mut self.memo_table[pos];
-		let examined_length = (self.max_examined_pos - pos as isize + 1) as usize;
-		let existing_match = cst.map(|cst| ExistingMatch { cst, match_length: self.pos - pos });
+		let examined_length = (self.max_examined_pos - pos as isize + 1).max(0);
+		let existing_match = cst.map(|cst| ExistingMatch { cst, match_length: (self.pos - pos) as u32 });
 
-		let entry = MemoTableEntry { existing_match, examined_length };
+		let entry = MemoTableEntry { existing_match, examined_length: examined_length as u32, depth_limited };
 
-		col.memo.insert(rule_name, entry);
-		col.max_examined_length = col.max_examined_length.max(examined_length as isize)
+		col.insert(rule, entry);
+		col.max_examined_length = col.max_examined_length.max(examined_length)
 	}
 
-	fn use_memoized_result(&mut self, rule_name: RuleName) -> Option<Rc<Cst<Token>>> {
-		self.memo_table.get(self.pos).and_then(|col| {
-			col.memo.get(rule_name).and_then(|entry| {
-				self.max_examined_pos = self.max_examined_pos.max((self.pos + entry.examined_length - 1) as isize);
+	/// Looks up the result of applying `rule` at the current position: `None` if it is not in the memo table, or
+	/// `Some` memoized match or failure.
+	fn use_memoized_result(&mut self, rule: RuleId) -> Option<Option<Rc<Cst<Token>>>> {
+		let entry = self.memo_table.get(self.pos)?.get(rule)?;
+		self.max_examined_pos = self.max_examined_pos.max(self.pos as isize + entry.examined_length as isize - 1);
+		if entry.depth_limited {
+			self.cutoffs += 1;
+		}
 
-				entry.existing_match.clone().map(|m| {
-					self.pos += m.match_length;
-					m.cst
-				})
-			})
-		})
+		Some(entry.existing_match.clone().map(|m| {
+			self.pos += m.match_length as usize;
+			m.cst
+		}))
 	}
 
 	fn consume(&mut self, tk: &Token) -> bool {
@@ -252,17 +627,62 @@ impl<'a, Token: Clone + PartialEq> Matcher<'a, Token> {
 			false
 		}
 	}
+
+	fn consume_matching(&mut self, matcher: &TokenMatcher<Token>) -> Option<Token> {
+		self.max_examined_pos = self.max_examined_pos.max(self.pos as isize);
+
+		let tk = self.input.get(self.pos).filter(|tk| matcher.matches(tk))?.clone();
+		self.pos += 1;
+		Some(tk)
+	}
 }
 
 #[derive(Debug, Clone)]
 pub enum Rule<Token: Clone> {
+	/// Matches exactly the given sequence of tokens.
 	Terminal(Rc<Vec<Token>>),
+	/// Matches a single token accepted by the [`TokenMatcher`], e.g. any identifier.
+	Matcher(TokenMatcher<Token>),
+	/// Ordered choice: the first alternative that matches wins.
 	Choice(Vec<RuleName>),
 	Sequence(Vec<RuleName>),
+	/// Zero or more repetitions.
 	Repetition(RuleName),
+	/// One or more repetitions, producing a [`Cst::Repetition`].
+	OneOrMore(RuleName),
+	Optional(RuleName),
+	/// Negative lookahead, never consumes input.
 	Not(RuleName),
+	/// Positive lookahead, never consumes input.
+	And(RuleName),
 }
 
+/// A predicate over single tokens, used to match tokens by kind (or any other property) rather than by equality.
+///
+/// The `name` is only used for debugging, e.g. when printing the grammar.
+#[derive(Clone)]
+pub struct TokenMatcher<Token> {
+	name: &'static str,
+	predicate: Rc<dyn Fn(&Token) -> bool>,
+}
+
+impl<Token> TokenMatcher<Token> {
+	pub fn new(name: &'static str, predicate: impl Fn(&Token) -> bool + 'static) -> Self {
+		Self { name, predicate: Rc::new(predicate) }
+	}
+
+	pub fn name(&self) -> &'static str { self.name }
+
+	pub fn matches(&self, tk: &Token) -> bool { (self.predicate)(tk) }
+}
+
+impl<Token> std::fmt::Debug for TokenMatcher<Token> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_tuple("TokenMatcher").field(&self.name).finish()
+	}
+}
+
+/// Builds the right-hand side of a single rule, see [`grammar!`] for the syntax.
 #[macro_export]
 macro_rules! rule_rhs {
 	($lit:literal) => {
@@ -271,20 +691,49 @@ macro_rules! rule_rhs {
 			Rule::Terminal(lit.chars().collect::<Vec<_>>().into())
 		}
 	};
+	([$pat:pat]) => {
+		Rule::Matcher(TokenMatcher::new(stringify!($pat), |tk| matches!(tk, $pat)))
+	};
+	({ $pred:expr }) => {
+		Rule::Matcher(TokenMatcher::new(stringify!($pred), $pred))
+	};
 	($name:ident | $($names:ident)|+) => {
 		Rule::Choice(vec![stringify!($name), $(stringify!($names)),+])
 	};
 	($name:ident, $($names:ident),+) => {
 		Rule::Sequence(vec![stringify!($name), $(stringify!($names)),+])
 	};
+	(not $name:ident) => {
+		Rule::Not(stringify!($name))
+	};
+	(and $name:ident) => {
+		Rule::And(stringify!($name))
+	};
 	($name:ident rep) => {
 		Rule::Repetition(stringify!($name))
 	};
+	($name:ident rep1) => {
+		Rule::OneOrMore(stringify!($name))
+	};
+	($name:ident opt) => {
+		Rule::Optional(stringify!($name))
+	};
 	($expr:expr) => {
 		Rule::Terminal($expr)
 	};
 }
 
+/// Builds a list of named rules, suitable for [`Parser::from_rules`].
+///
+/// Each rule has one of the following forms:
+///
+/// - `name => "abc";` matches the given characters exactly (for `char` tokens),
+/// - `name => expr;` matches the tokens in the `Rc<Vec<Token>>` expression exactly,
+/// - `name => [Token::Identifier(_)];` matches any single token matching the pattern,
+/// - `name => { |tk: &Token| predicate };` matches any single token satisfying the predicate,
+/// - `name => a | b | c;` and `name => a, b, c;` for ordered choice and sequence,
+/// - `name => a rep;`, `name => a rep1;` and `name => a opt;` for zero-or-more, one-or-more and optional,
+/// - `name => not a;` and `name => and a;` for negative and positive lookahead.
 #[macro_export]
 macro_rules! grammar {
 	($($name:ident =>
@@ -300,6 +749,7 @@ macro_rules! grammar {
 #[cfg(test)]
 mod test {
 	use super::*;
+	use cancellation::CancellationTokenSource;
 	use pretty_assertions::{assert_eq, assert_ne};
 
 	#[test]
@@ -365,6 +815,344 @@ mod test {
 		);
 	}
 
+	#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+	enum Tk {
+		Ident(String),
+		Int(i64),
+		Semi,
+	}
+
+	#[test]
+	fn token_kinds() {
+		let mut parser = Parser::from_rules(&grammar! {
+			start => stmt rep1;
+			stmt => ident, value, semi;
+			ident => [Tk::Ident(_)];
+			value => { |tk: &Tk| matches!(tk, Tk::Int(n) if *n >= 0) };
+			semi => [Tk::Semi];
+		})
+		.unwrap()(vec![Tk::Ident("x".to_string()), Tk::Int(1), Tk::Semi].into());
+
+		assert_eq!(
+			parser._match(),
+			Some(Cst::Repetition(vec![Cst::Sequence(vec![
+				Cst::Terminal(vec![Tk::Ident("x".to_string())].into()).into(),
+				Cst::Terminal(vec![Tk::Int(1)].into()).into(),
+				Cst::Terminal(vec![Tk::Semi].into()).into(),
+			])
+			.into()]))
+		);
+
+		let mut parser = Parser::from_rules(&grammar! {
+			start => stmt rep1;
+			stmt => ident, value, semi;
+			ident => [Tk::Ident(_)];
+			value => { |tk: &Tk| matches!(tk, Tk::Int(n) if *n >= 0) };
+			semi => [Tk::Semi];
+		})
+		.unwrap()(vec![Tk::Ident("x".to_string()), Tk::Int(-1), Tk::Semi].into());
+		assert_eq!(parser._match(), None);
+
+		let mut parser = Parser::from_rules(&grammar! {
+			start => stmt rep1;
+			stmt => ident, semi;
+			ident => [Tk::Ident(_)];
+			semi => [Tk::Semi];
+		})
+		.unwrap()(vec![].into());
+		assert_eq!(parser._match(), None);
+	}
+
+	#[test]
+	fn optional_and_lookahead() {
+		let mtch = |input: &str| {
+			Parser::from_rules(&grammar! {
+				start => sign, digits, end;
+				sign => minus opt;
+				digits => digit rep1;
+				end => not digit;
+				digit => n0 | n1;
+				minus => "-";
+				n0 => "0";
+				n1 => "1";
+			})
+			.unwrap()(input.chars().collect::<Vec<_>>().into())
+			._match()
+		};
+
+		assert_eq!(
+			mtch("10"),
+			Some(Cst::Sequence(vec![
+				Cst::Optional(None).into(),
+				Cst::Repetition(vec![
+					Cst::Choice("n1", Cst::Terminal(vec!['1'].into()).into()).into(),
+					Cst::Choice("n0", Cst::Terminal(vec!['0'].into()).into()).into(),
+				])
+				.into(),
+				Cst::Not("digit").into(),
+			]))
+		);
+		assert_eq!(
+			mtch("-1"),
+			Some(Cst::Sequence(vec![
+				Cst::Optional(Some(Cst::Terminal(vec!['-'].into()).into())).into(),
+				Cst::Repetition(vec![Cst::Choice("n1", Cst::Terminal(vec!['1'].into()).into()).into()]).into(),
+				Cst::Not("digit").into(),
+			]))
+		);
+		assert_eq!(mtch("-"), None);
+
+		let mtch = |input: &str| {
+			Parser::from_rules(&grammar! {
+				start => peek, any;
+				peek => and one;
+				any => { |_: &char| true };
+				one => "1";
+			})
+			.unwrap()(input.chars().collect::<Vec<_>>().into())
+			._match()
+		};
+
+		assert_eq!(
+			mtch("1"),
+			Some(Cst::Sequence(vec![Cst::And("one").into(), Cst::Terminal(vec!['1'].into()).into()]))
+		);
+		assert_eq!(mtch("0"), None);
+	}
+
+	#[test]
+	fn empty_repetition_terminates() {
+		let matcher = Parser::from_rules(&grammar! {
+			start => maybe_ones rep;
+			maybe_ones => one rep;
+			one => "1";
+		})
+		.unwrap();
+
+		assert_eq!(
+			matcher("11".chars().collect::<Vec<_>>().into())._match(),
+			Some(Cst::Repetition(vec![
+				Cst::Repetition(vec![Cst::Terminal(vec!['1'].into()).into(), Cst::Terminal(vec!['1'].into()).into(),])
+					.into(),
+				Cst::Repetition(vec![]).into(),
+			]))
+		);
+	}
+
+	#[test]
+	fn update_input() {
+		let rules = grammar! {
+			start => num, rest;
+			rest => plus_num rep;
+			plus_num => plus, num;
+			plus => "+";
+			num => digit rep1;
+			digit => { |ch: &char| ch.is_ascii_digit() };
+		};
+		let tokens = |s: &str| s.chars().collect::<Vec<_>>();
+		let mut parser = Parser::from_rules(&rules).unwrap()(tokens("12+34+56").into());
+		assert!(parser._match().is_some());
+		assert_eq!(parser.statistics().reused, 0);
+
+		// an edit at the very end of the input
+		assert_eq!(parser.update_input(&tokens("12+34+567")), 8..8);
+		let stats = parser.statistics();
+		assert!(stats.invalidated > 0 && stats.retained > 0);
+
+		let result = parser._match();
+		assert!(parser.statistics().reused > 0);
+		assert_eq!(result, Parser::from_rules(&rules).unwrap()(tokens("12+34+567").into())._match());
+
+		assert_eq!(parser.update_input(&tokens("12+4+567")), 3..4);
+		assert_eq!(&*parser.input(), &tokens("12+4+567"));
+		assert_eq!(parser._match(), Parser::from_rules(&rules).unwrap()(tokens("12+4+567").into())._match());
+
+		assert_eq!(parser.update_input(&tokens("12+4+567")), 8..8);
+		assert_eq!(parser.statistics().invalidated, 0);
+		assert!(parser._match().is_some());
+		assert_eq!(parser.statistics().evaluated, 0);
+	}
+
+	#[test]
+	fn unmemoized_rules() {
+		let rules = grammar! {
+			start => num, rest;
+			rest => plus_num rep;
+			plus_num => plus, num;
+			plus => "+";
+			num => digit rep1;
+			digit => { |ch: &char| ch.is_ascii_digit() };
+		};
+		let input = || "12+34+56".chars().collect::<Vec<_>>().into();
+
+		let mut memoized = Parser::from_rules(&rules).unwrap()(input());
+		let expected = memoized._match();
+		assert!(expected.is_some());
+
+		let mut parser = Parser::from_rules(&rules).unwrap()(input());
+		parser.set_memoized("digit", false).unwrap();
+		parser.set_memoized("plus", false).unwrap();
+		assert!(parser.set_memoized("minus", false).is_err());
+		assert_eq!(parser._match(), expected);
+		assert!(parser.memo_table_size() < memoized.memo_table_size());
+
+		// existing entries are evicted
+		let size = memoized.memo_table_size();
+		memoized.set_memoized("digit", false).unwrap();
+		assert!(memoized.memo_table_size() < size);
+
+		// edits still invalidate the entries which examined the digits
+		parser.apply_edit(1..2, &['3']);
+		assert_eq!(
+			parser._match(),
+			Parser::from_rules(&rules).unwrap()("13+34+56".chars().collect::<Vec<_>>().into())._match()
+		);
+	}
+
+	#[test]
+	fn entry_points() {
+		let rules = grammar! {
+			start => statement rep1;
+			statement => expression, semi;
+			expression => num, rest;
+			rest => plus_num rep;
+			plus_num => plus, num;
+			plus => "+";
+			semi => ";";
+			num => digit rep1;
+			digit => { |ch: &char| ch.is_ascii_digit() };
+		};
+		let input = |s: &str| s.chars().collect::<Vec<_>>().into();
+
+		assert!(Parser::from_rules_with_entry_points(&rules, &["expression", "type"]).is_err());
+
+		let mut parser = Parser::from_rules_with_entry_points(&rules, &["start", "expression"]).unwrap()(input("1+2;"));
+		assert_eq!(parser.entry_points(), &["start", "expression"]);
+		assert!(parser._match().is_some());
+		assert!(parser._match_rule("statement").is_err());
+		assert_eq!(parser._match_rule("expression").unwrap(), None);
+
+		// the expression was already parsed as part of the statement
+		let (cst, consumed) = parser._match_prefix("expression").unwrap().unwrap();
+		assert_eq!(consumed, 3);
+		assert_eq!(parser.statistics().evaluated, 0);
+
+		let mut parser = Parser::from_rules_with_entry_points(&rules, &["expression"]).unwrap()(input("1+2"));
+		assert!(parser._match_rule("start").is_err());
+		assert_eq!(parser._match_rule("expression").unwrap(), Some(cst));
+
+		let mut parser = Parser::from_rules_with_entry_points(&rules, &["expression"]).unwrap()(input("1;22+3;"));
+		assert_eq!(parser._match_at("expression", 2).unwrap().map(|(_, end)| end), Some(6));
+		assert_eq!(parser._match_at("expression", 1).unwrap(), None);
+	}
+
+	#[test]
+	fn tracing() {
+		let rules = grammar! {
+			start => statement rep1;
+			statement => assignment | call;
+			assignment => ident, eq, ident, semi;
+			call => ident, lparen, rparen, semi;
+			ident => "x";
+			eq => "=";
+			lparen => "(";
+			rparen => ")";
+			semi => ";";
+		};
+		let mut parser = Parser::from_rules(&rules).unwrap()(Default::default());
+		parser.update_input(&"x(;".chars().collect::<Vec<_>>());
+
+		assert_eq!(parser._match(), None);
+		assert_eq!(parser.trace(), None);
+
+		parser.set_tracing(true);
+		parser.apply_edit(0..3, &"x(;".chars().collect::<Vec<_>>());
+		assert_eq!(parser._match(), None);
+
+		let trace = parser.trace().unwrap();
+		assert_eq!(trace.furthest_failure(), Some(2));
+		let tried: Vec<_> = trace.attempts_at(2).iter().map(|node| node.rule).collect();
+		assert_eq!(tried, ["rparen"]);
+		assert_eq!(trace.furthest_failure_in(0..=1, "call"), Some(1));
+
+		// `call` reuses the `ident` matched by `assignment`
+		let idents: Vec<_> = trace.attempts_at(0).into_iter().filter(|node| node.rule == "ident").collect();
+		assert_eq!(idents.iter().map(|node| node.memoized).collect::<Vec<_>>(), [false, true]);
+		assert!(idents.iter().all(|node| node.end == Some(1)));
+
+		let tree = trace.to_string();
+		assert!(tree.starts_with("start @0 failed\n  statement @0 failed\n    assignment @0 failed\n"));
+		assert!(tree.contains("      ident @0 -> 1 (memo)\n"));
+
+		let json = trace.to_json();
+		assert!(
+			json.starts_with(r#"[{"rule":"start","pos":0,"end":null,"memoized":false,"children":[{"rule":"statement""#)
+		);
+		assert_eq!(json.matches('{').count(), trace.nodes().count());
+
+		parser.set_tracing(false);
+		assert_eq!(parser.trace(), None);
+	}
+
+	#[test]
+	fn depth_limit() {
+		let rules = grammar! {
+			start => item rep1;
+			item => expr | any;
+			expr => parens | num;
+			parens => lparen, expr, rparen;
+			lparen => "(";
+			rparen => ")";
+			num => "1";
+			any => { |_: &char| true };
+		};
+		let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth)).chars().collect::<Vec<_>>();
+		let items = |cst: Option<Cst<char>>| match cst {
+			Some(Cst::Repetition(items)) => {
+				items.iter().map(|item| if let Cst::Choice(option, _) = **item { option } else { "" }).collect()
+			}
+			_ => vec![],
+		};
+
+		// deep enough to overflow the stack without a limit: the outer parentheses are skipped one by one instead,
+		// while the inner ones are still matched
+		let mut parser = Parser::from_rules(&rules).unwrap()(nested(5000).into());
+		let matched = items(parser._match());
+		assert_eq!(&matched[..2], ["any", "any"]);
+		assert!(matched.contains(&"expr"));
+
+		let mut parser = Parser::from_rules(&rules).unwrap()(nested(100).into());
+		parser.set_max_depth(100);
+		assert_eq!(&items(parser._match())[..2], ["any", "any"]);
+
+		// the memo table is still consistent after hitting the limit
+		parser.set_max_depth(DEFAULT_MAX_DEPTH);
+		let cst = parser._match();
+		assert_eq!(items(cst.clone()), ["expr"]);
+		assert_eq!(cst, Parser::from_rules(&rules).unwrap()(nested(100).into())._match());
+	}
+
+	#[test]
+	fn cancellation() {
+		let rules = grammar! {
+			start => digit rep1;
+			digit => { |ch: &char| ch.is_ascii_digit() };
+		};
+		let mut parser = Parser::from_rules(&rules).unwrap()("123".chars().collect::<Vec<_>>().into());
+		let source = CancellationTokenSource::new();
+		parser.set_cancellation_token(Some(source.token().clone()));
+		assert!(parser._match_rule("start").unwrap().is_some());
+
+		source.cancel();
+		parser.apply_edit(1..2, &['4']);
+		assert!(parser._match_rule("start").unwrap_err().is::<Cancelled>());
+		assert_eq!(parser._match(), None);
+
+		parser.set_cancellation_token(None);
+		assert!(parser._match().is_some());
+		assert_eq!(parser.statistics().evaluated, 2);
+	}
+
 	#[test]
 	fn simple_edit() {
 		let buffer = "896-7".chars().collect::<Vec<_>>();
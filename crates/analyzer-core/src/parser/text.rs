@@ -0,0 +1,454 @@
+//! A textual PEG format for grammars, so that they can be loaded at runtime instead of being compiled in with
+//! [`grammar!`](crate::grammar).
+//!
+//! A grammar is a list of rules, each of the form `name = expression ;`. Expressions are built from:
+//!
+//! - `name` — a reference to another rule,
+//! - `"text"` or `'text'` — a literal, converted to tokens by [`TextGrammar`],
+//! - `<class>` — any single token accepted by the [`TokenMatcher`] registered under that name,
+//! - `a b c` — a sequence,
+//! - `a | b` (or `a / b`) — an ordered choice,
+//! - `a*`, `a+`, `a?` — zero or more, one or more and optional,
+//! - `!a`, `&a` — negative and positive lookahead,
+//! - `( ... )` — grouping.
+//!
+//! Comments start with `#` or `//` and run until the end of the line.
+//!
+//! Since a [`Rule`] can only refer to other rules by name, any nested expression is given a rule of its own. Literals
+//! and classes are named after their text (e.g. `"("` or `<ident>`) and nested expressions after their enclosing rule
+//! (e.g. `item.1`), neither of which can clash with the names of the rules in the text.
+
+use super::{Rule, RuleName, TokenMatcher};
+use std::{collections::HashMap, fmt, iter::Peekable, str::CharIndices};
+
+/// A 1-based line and column in the text of a grammar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+	pub line: usize,
+	pub column: usize,
+}
+
+impl fmt::Display for Position {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}:{}", self.line, self.column) }
+}
+
+/// An error in the text of a grammar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarError {
+	pub position: Position,
+	pub message: String,
+}
+
+impl fmt::Display for GrammarError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}: {}", self.position, self.message) }
+}
+
+impl std::error::Error for GrammarError {}
+
+/// Converts the text of a literal into tokens.
+type LiteralFn<Token> = dyn Fn(&str) -> Option<Vec<Token>>;
+
+/// Loads grammars written in the textual format described in the [module documentation](self).
+///
+/// Converting literals into tokens depends on the token type, e.g. splitting them into characters or running a lexer,
+/// so it is supplied by the caller. A literal that cannot be converted is reported as an error.
+pub struct TextGrammar<Token: Clone> {
+	literal: Box<LiteralFn<Token>>,
+	classes: HashMap<&'static str, TokenMatcher<Token>>,
+}
+
+impl<Token: Clone> TextGrammar<Token> {
+	pub fn new(literal: impl Fn(&str) -> Option<Vec<Token>> + 'static) -> Self {
+		Self { literal: Box::new(literal), classes: Default::default() }
+	}
+
+	/// Registers a token class, which can be referred to as `<name>` using the name of the `matcher`.
+	pub fn with_class(mut self, matcher: TokenMatcher<Token>) -> Self {
+		self.classes.insert(matcher.name(), matcher);
+		self
+	}
+
+	/// Parses the text of a grammar into the rules expected by [`Parser::from_rules`](super::Parser::from_rules).
+	pub fn parse(&self, text: &str) -> Result<HashMap<RuleName, Rule<Token>>, GrammarError> {
+		let definitions = Reader::new(text).grammar()?;
+
+		let mut names = HashMap::new();
+		for (name, position, _) in &definitions {
+			if names.insert(name.as_str(), leak(name)).is_some() {
+				return Err(GrammarError { position: *position, message: format!("Rule '{name}' is defined twice") });
+			}
+		}
+
+		let mut lowering = Lowering { grammar: self, names, rules: HashMap::new() };
+		for (name, _, expr) in &definitions {
+			let name = lowering.names[name.as_str()];
+			let mut counter = 0;
+			let rule = lowering.rule(name, &mut counter, expr)?;
+			lowering.rules.insert(name, rule);
+		}
+
+		Ok(lowering.rules)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+	Name(String, Position),
+	Literal(String, Position),
+	Class(String, Position),
+	Sequence(Vec<Expr>),
+	Choice(Vec<Expr>),
+	Repetition(Box<Expr>),
+	OneOrMore(Box<Expr>),
+	Optional(Box<Expr>),
+	Not(Box<Expr>),
+	And(Box<Expr>),
+}
+
+/// Turns parsed expressions into [`Rule`]s, adding rules for nested expressions as needed.
+struct Lowering<'a, Token: Clone> {
+	grammar: &'a TextGrammar<Token>,
+	/// The rules defined in the text, by their names.
+	names: HashMap<&'a str, RuleName>,
+	rules: HashMap<RuleName, Rule<Token>>,
+}
+
+impl<'a, Token: Clone> Lowering<'a, Token> {
+	fn rule(&mut self, name: RuleName, counter: &mut usize, expr: &Expr) -> Result<Rule<Token>, GrammarError> {
+		let mut names =
+			|exprs: &[Expr]| -> Result<Vec<_>, _> { exprs.iter().map(|expr| self.name(name, counter, expr)).collect() };
+
+		Ok(match expr {
+			// an alias is a choice with a single option
+			Expr::Name(..) => Rule::Choice(names(std::slice::from_ref(expr))?),
+			Expr::Literal(text, position) => Rule::Terminal(self.literal(text, *position)?.into()),
+			Expr::Class(class, position) => Rule::Matcher(self.class(class, *position)?),
+			Expr::Sequence(parts) => Rule::Sequence(names(parts)?),
+			Expr::Choice(options) => Rule::Choice(names(options)?),
+			Expr::Repetition(expr) => Rule::Repetition(self.name(name, counter, expr)?),
+			Expr::OneOrMore(expr) => Rule::OneOrMore(self.name(name, counter, expr)?),
+			Expr::Optional(expr) => Rule::Optional(self.name(name, counter, expr)?),
+			Expr::Not(expr) => Rule::Not(self.name(name, counter, expr)?),
+			Expr::And(expr) => Rule::And(self.name(name, counter, expr)?),
+		})
+	}
+
+	/// The name of a rule matching `expr`, which is nested in the rule `parent`.
+	fn name(&mut self, parent: RuleName, counter: &mut usize, expr: &Expr) -> Result<RuleName, GrammarError> {
+		match expr {
+			Expr::Name(name, position) => match self.names.get(name.as_str()) {
+				Some(name) => Ok(*name),
+				None => Err(GrammarError { position: *position, message: format!("Undefined rule '{name}'") }),
+			},
+			Expr::Literal(text, _) => self.shared(format!("{text:?}"), expr),
+			Expr::Class(class, _) => self.shared(format!("<{class}>"), expr),
+			_ => {
+				*counter += 1;
+				let name = leak(&format!("{parent}.{counter}"));
+				let rule = self.rule(parent, counter, expr)?;
+				self.rules.insert(name, rule);
+				Ok(name)
+			}
+		}
+	}
+
+	/// The name of a rule for a literal or class, which is shared by all its uses.
+	fn shared(&mut self, name: String, expr: &Expr) -> Result<RuleName, GrammarError> {
+		if let Some((name, _)) = self.rules.get_key_value(name.as_str()) {
+			return Ok(name);
+		}
+
+		let name = leak(&name);
+		let rule = self.rule(name, &mut 0, expr)?;
+		self.rules.insert(name, rule);
+		Ok(name)
+	}
+
+	fn literal(&self, text: &str, position: Position) -> Result<Vec<Token>, GrammarError> {
+		match (self.grammar.literal)(text) {
+			Some(tokens) if !tokens.is_empty() => Ok(tokens),
+			_ => Err(GrammarError { position, message: format!("Invalid literal {text:?}") }),
+		}
+	}
+
+	fn class(&self, class: &str, position: Position) -> Result<TokenMatcher<Token>, GrammarError> {
+		self.grammar
+			.classes
+			.get(class)
+			.cloned()
+			.ok_or_else(|| GrammarError { position, message: format!("Unknown token class '<{class}>'") })
+	}
+}
+
+/// Rule names have to be `'static`, and grammars are loaded rarely enough for leaking them to be acceptable.
+fn leak(name: &str) -> RuleName { Box::leak(name.to_string().into_boxed_str()) }
+
+/// A recursive descent reader for the text of a grammar.
+struct Reader<'a> {
+	text: &'a str,
+	chars: Peekable<CharIndices<'a>>,
+	position: Position,
+}
+
+impl<'a> Reader<'a> {
+	fn new(text: &'a str) -> Self {
+		Self { text, chars: text.char_indices().peekable(), position: Position { line: 1, column: 1 } }
+	}
+
+	fn grammar(&mut self) -> Result<Vec<(String, Position, Expr)>, GrammarError> {
+		let mut definitions = vec![];
+		self.skip_trivia();
+		while self.peek().is_some() {
+			let position = self.position;
+			let name = self.ident().ok_or_else(|| self.error("Expected a rule name"))?;
+			self.expect('=')?;
+			let expr = self.choice()?;
+			self.expect(';')?;
+			definitions.push((name, position, expr));
+		}
+		Ok(definitions)
+	}
+
+	fn choice(&mut self) -> Result<Expr, GrammarError> {
+		let mut options = vec![self.sequence()?];
+		while self.eat('|') || self.eat('/') {
+			options.push(self.sequence()?);
+		}
+		Ok(if options.len() == 1 { options.pop().unwrap() } else { Expr::Choice(options) })
+	}
+
+	fn sequence(&mut self) -> Result<Expr, GrammarError> {
+		let mut parts = vec![];
+		while let Some(part) = self.prefix()? {
+			parts.push(part);
+		}
+		match parts.len() {
+			0 => Err(self.error("Expected an expression")),
+			1 => Ok(parts.pop().unwrap()),
+			_ => Ok(Expr::Sequence(parts)),
+		}
+	}
+
+	fn prefix(&mut self) -> Result<Option<Expr>, GrammarError> {
+		if self.eat('!') {
+			return Ok(Some(Expr::Not(self.required_prefix()?.into())));
+		}
+		if self.eat('&') {
+			return Ok(Some(Expr::And(self.required_prefix()?.into())));
+		}
+
+		let mut expr = match self.primary()? {
+			Some(expr) => expr,
+			None => return Ok(None),
+		};
+		loop {
+			expr = if self.eat('*') {
+				Expr::Repetition(expr.into())
+			} else if self.eat('+') {
+				Expr::OneOrMore(expr.into())
+			} else if self.eat('?') {
+				Expr::Optional(expr.into())
+			} else {
+				return Ok(Some(expr));
+			};
+		}
+	}
+
+	fn required_prefix(&mut self) -> Result<Expr, GrammarError> {
+		self.prefix()?.ok_or_else(|| self.error("Expected an expression"))
+	}
+
+	fn primary(&mut self) -> Result<Option<Expr>, GrammarError> {
+		let position = self.position;
+		let expr = match self.peek() {
+			Some('(') => {
+				self.bump();
+				self.skip_trivia();
+				let expr = self.choice()?;
+				self.expect(')')?;
+				return Ok(Some(expr));
+			}
+			Some('<') => {
+				self.bump();
+				let class = self.ident().ok_or_else(|| self.error("Expected a token class name"))?;
+				if self.peek() != Some('>') {
+					return Err(self.error("Expected '>'"));
+				}
+				self.bump();
+				Expr::Class(class, position)
+			}
+			Some(quote @ ('"' | '\'')) => {
+				self.bump();
+				Expr::Literal(self.string(quote, position)?, position)
+			}
+			Some(ch) if ch.is_alphabetic() || ch == '_' => Expr::Name(self.ident().unwrap(), position),
+			_ => return Ok(None),
+		};
+		self.skip_trivia();
+		Ok(Some(expr))
+	}
+
+	/// Reads the rest of a literal after its opening `quote`.
+	fn string(&mut self, quote: char, start: Position) -> Result<String, GrammarError> {
+		let mut text = String::new();
+		loop {
+			let position = self.position;
+			match self.bump() {
+				Some(ch) if ch == quote => return Ok(text),
+				Some('\\') => match self.bump() {
+					Some('n') => text.push('\n'),
+					Some('t') => text.push('\t'),
+					Some(ch @ ('\\' | '"' | '\'')) => text.push(ch),
+					_ => return Err(GrammarError { position, message: "Invalid escape sequence".to_string() }),
+				},
+				Some(ch) => text.push(ch),
+				None => return Err(GrammarError { position: start, message: "Unterminated literal".to_string() }),
+			}
+		}
+	}
+
+	fn ident(&mut self) -> Option<String> {
+		let start = self.chars.peek().filter(|(_, ch)| ch.is_alphabetic() || *ch == '_')?.0;
+		let mut end = start;
+		while let Some(ch) = self.peek().filter(|ch| ch.is_alphanumeric() || *ch == '_') {
+			end += ch.len_utf8();
+			self.bump();
+		}
+		let ident = self.text[start..end].to_string();
+		self.skip_trivia();
+		Some(ident)
+	}
+
+	fn expect(&mut self, expected: char) -> Result<(), GrammarError> {
+		if self.eat(expected) {
+			Ok(())
+		} else {
+			let found = match self.peek() {
+				Some(ch) => format!("'{ch}'"),
+				None => "the end of the grammar".to_string(),
+			};
+			Err(self.error(&format!("Expected '{expected}', found {found}")))
+		}
+	}
+
+	/// Moves past the next character, and any trivia after it, if it is `expected`.
+	fn eat(&mut self, expected: char) -> bool {
+		if self.peek() == Some(expected) {
+			self.bump();
+			self.skip_trivia();
+			true
+		} else {
+			false
+		}
+	}
+
+	fn skip_trivia(&mut self) {
+		loop {
+			match self.peek() {
+				Some(ch) if ch.is_whitespace() => {
+					self.bump();
+				}
+				Some('#') => self.skip_line(),
+				Some('/') if self.text[self.offset()..].starts_with("//") => self.skip_line(),
+				_ => return,
+			}
+		}
+	}
+
+	fn skip_line(&mut self) { while !matches!(self.bump(), Some('\n') | None) {} }
+
+	fn peek(&mut self) -> Option<char> { self.chars.peek().map(|(_, ch)| *ch) }
+
+	fn offset(&mut self) -> usize { self.chars.peek().map_or(self.text.len(), |(i, _)| *i) }
+
+	fn bump(&mut self) -> Option<char> {
+		let (_, ch) = self.chars.next()?;
+		if ch == '\n' {
+			self.position = Position { line: self.position.line + 1, column: 1 };
+		} else {
+			self.position.column += 1;
+		}
+		Some(ch)
+	}
+
+	fn error(&self, message: &str) -> GrammarError {
+		GrammarError { position: self.position, message: message.to_string() }
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::parser::{Cst, Parser};
+	use pretty_assertions::assert_eq;
+
+	fn chars() -> TextGrammar<char> {
+		TextGrammar::new(|text| Some(text.chars().collect()))
+			.with_class(TokenMatcher::new("digit", |ch: &char| ch.is_ascii_digit()))
+	}
+
+	fn matches(rules: &HashMap<RuleName, Rule<char>>, input: &str) -> bool {
+		Parser::from_rules(rules).unwrap()(input.chars().collect::<Vec<_>>().into())._match().is_some()
+	}
+
+	#[test]
+	fn sums() {
+		let rules = chars()
+			.parse(
+				r#"
+				# a sum of numbers
+				start = num ("+" num)* ;
+				num = "-"? <digit>+ !<digit> ; // greedy anyway
+				"#,
+			)
+			.unwrap();
+
+		let mut names: Vec<_> = rules.keys().copied().collect();
+		names.sort();
+		assert_eq!(
+			names,
+			["\"+\"", "\"-\"", "<digit>", "num", "num.1", "num.2", "num.3", "start", "start.1", "start.2"]
+		);
+		assert!(matches!(&rules["start"], Rule::Sequence(parts) if parts == &["num", "start.1"]));
+		assert!(matches!(&rules["start.1"], Rule::Repetition("start.2")));
+		assert!(matches!(&rules["<digit>"], Rule::Matcher(matcher) if matcher.name() == "digit"));
+
+		assert!(matches(&rules, "1+-23+4"));
+		assert!(!matches(&rules, "1+"));
+		assert!(!matches(&rules, "+1"));
+	}
+
+	#[test]
+	fn choices_and_lookahead() {
+		let rules = chars()
+			.parse(
+				"start = keyword / ident ;
+				keyword = ('if' | 'else') !letter ;
+				ident = letter+ ;
+				letter = 'a' | 'e' | 'f' | 'i' | 'l' | 's' ;
+				alias = &keyword ident ;",
+			)
+			.unwrap();
+		assert!(matches!(&rules["alias.1"], Rule::And("keyword")));
+
+		let mut parser = Parser::from_rules(&rules).unwrap()("if".chars().collect::<Vec<_>>().into());
+		assert!(matches!(parser._match(), Some(Cst::Choice("keyword", _))));
+		assert!(matches(&rules, "elif"));
+		assert!(matches(&rules, "iff"));
+	}
+
+	#[test]
+	fn errors() {
+		let error = |text: &str| chars().parse(text).unwrap_err().to_string();
+
+		assert_eq!(error("start = a ;"), "1:9: Undefined rule 'a'");
+		assert_eq!(error("start = 'a' ;\nstart = 'b' ;"), "2:1: Rule 'start' is defined twice");
+		assert_eq!(error("start = 'a'"), "1:12: Expected ';', found the end of the grammar");
+		assert_eq!(error("start = ('a' | ) ;"), "1:16: Expected an expression");
+		assert_eq!(error("start = 'a\n;"), "1:9: Unterminated literal");
+		assert_eq!(error("start = <letter> ;"), "1:9: Unknown token class '<letter>'");
+		assert_eq!(error("start = '' ;"), "1:9: Invalid literal \"\"");
+		assert_eq!(error("start 'a' ;"), "1:7: Expected '=', found '''");
+		assert_eq!(error("  = 'a' ;"), "1:3: Expected a rule name");
+	}
+}
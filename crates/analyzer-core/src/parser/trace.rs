@@ -0,0 +1,166 @@
+use super::RuleName;
+use std::{
+	fmt::{self, Display, Write},
+	ops::RangeInclusive,
+};
+
+/// A single attempt at applying a rule while matching, together with the attempts it made in turn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceNode {
+	pub rule: RuleName,
+	/// The input position at which the rule was applied.
+	pub pos: usize,
+	/// The input position after the match, or `None` if the rule failed.
+	pub end: Option<usize>,
+	/// Whether the outcome was answered by the memo table rather than by evaluating the rule.
+	pub memoized: bool,
+	pub children: Vec<TraceNode>,
+}
+
+impl TraceNode {
+	/// Whether the rule matched.
+	pub fn succeeded(&self) -> bool { self.end.is_some() }
+
+	/// Iterates over this node and all of its descendants in pre-order.
+	pub fn descendants(&self) -> impl Iterator<Item = &TraceNode> + '_ {
+		let mut stack = vec![self];
+		std::iter::from_fn(move || {
+			let node = stack.pop()?;
+			stack.extend(node.children.iter().rev());
+			Some(node)
+		})
+	}
+
+	fn write_tree(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+		write!(f, "{:indent$}{} @{} ", "", self.rule, self.pos, indent = depth * 2)?;
+		match self.end {
+			Some(end) => write!(f, "-> {end}")?,
+			None => write!(f, "failed")?,
+		}
+		if self.memoized {
+			write!(f, " (memo)")?;
+		}
+		writeln!(f)?;
+
+		for child in &self.children {
+			child.write_tree(f, depth + 1)?;
+		}
+		Ok(())
+	}
+
+	fn write_json(&self, out: &mut String) {
+		out.push_str("{\"rule\":");
+		write_json_string(out, self.rule);
+		write!(out, ",\"pos\":{},\"end\":", self.pos).unwrap();
+		match self.end {
+			Some(end) => write!(out, "{end}").unwrap(),
+			None => out.push_str("null"),
+		}
+		write!(out, ",\"memoized\":{},\"children\":[", self.memoized).unwrap();
+		for (i, child) in self.children.iter().enumerate() {
+			if i > 0 {
+				out.push(',');
+			}
+			child.write_json(out);
+		}
+		out.push_str("]}");
+	}
+}
+
+/// The rule attempts recorded by a [`Parser`](super::Parser) with tracing enabled during its last match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+	/// The entry point rule applications, usually just one.
+	pub roots: Vec<TraceNode>,
+}
+
+impl Trace {
+	/// Iterates over all recorded rule attempts in pre-order.
+	pub fn nodes(&self) -> impl Iterator<Item = &TraceNode> + '_ { self.roots.iter().flat_map(TraceNode::descendants) }
+
+	/// The furthest input position at which a rule failed to match.
+	///
+	/// When a match fails, this is usually where the input stops conforming to the grammar.
+	pub fn furthest_failure(&self) -> Option<usize> {
+		self.nodes().filter(|node| !node.succeeded()).map(|node| node.pos).max()
+	}
+
+	/// The furthest input position in `range` at which a rule failed, counting only the failed attempts that started at
+	/// its beginning, other than those of the rule `except`, and the attempts they made in turn.
+	///
+	/// For the input skipped by an error recovery rule `except`, and the token it stopped at, this is where the rules
+	/// tried before it stopped matching, rather than where the recovery itself looked for the end of the error.
+	pub fn furthest_failure_in(&self, range: RangeInclusive<usize>, except: RuleName) -> Option<usize> {
+		self.nodes()
+			.filter(|node| node.pos == *range.start() && !node.succeeded() && node.rule != except)
+			.flat_map(TraceNode::descendants)
+			.filter(|node| !node.succeeded() && range.contains(&node.pos))
+			.map(|node| node.pos)
+			.max()
+	}
+
+	/// All the rule attempts made at the given input position, in the order they were made.
+	pub fn attempts_at(&self, pos: usize) -> Vec<&TraceNode> { self.nodes().filter(|node| node.pos == pos).collect() }
+
+	/// Serializes the trace to JSON, as an array of nested rule attempts.
+	pub fn to_json(&self) -> String {
+		let mut out = String::from("[");
+		for (i, root) in self.roots.iter().enumerate() {
+			if i > 0 {
+				out.push(',');
+			}
+			root.write_json(&mut out);
+		}
+		out.push(']');
+		out
+	}
+}
+
+/// Renders the trace as an indented tree, one rule attempt per line.
+impl Display for Trace {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for root in &self.roots {
+			root.write_tree(f, 0)?;
+		}
+		Ok(())
+	}
+}
+
+fn write_json_string(out: &mut String, s: &str) {
+	out.push('"');
+	for ch in s.chars() {
+		match ch {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			ch if ch.is_control() => write!(out, "\\u{:04x}", ch as u32).unwrap(),
+			ch => out.push(ch),
+		}
+	}
+	out.push('"');
+}
+
+/// Builds a [`Trace`] from the enter/exit events of rule applications.
+#[derive(Debug, Default)]
+pub(super) struct TraceRecorder {
+	roots: Vec<TraceNode>,
+	stack: Vec<TraceNode>,
+}
+
+impl TraceRecorder {
+	pub(super) fn enter(&mut self, rule: RuleName, pos: usize) {
+		self.stack.push(TraceNode { rule, pos, end: None, memoized: false, children: vec![] });
+	}
+
+	pub(super) fn exit(&mut self, end: Option<usize>, memoized: bool) {
+		let mut node = self.stack.pop().expect("every exit should have a matching enter");
+		node.end = end;
+		node.memoized = memoized;
+		match self.stack.last_mut() {
+			Some(parent) => parent.children.push(node),
+			None => self.roots.push(node),
+		}
+	}
+
+	pub(super) fn finish(self) -> Trace { Trace { roots: self.roots } }
+}
@@ -0,0 +1,465 @@
+//! The state machines of parsers.
+//!
+//! A [`StateGraph`] has a node for every state of a parser, plus `accept` and `reject`, and an edge for every
+//! transition: one for a direct `transition s;`, one per case of a `transition select(...)`, and the implicit
+//! transitions to `reject` of a state without a transition statement and of a `select` whose cases may all fail to
+//! match. [`StateGraph::problems`] finds the mistakes in the state machine, e.g. states that can never be reached.
+
+use std::ops::Range;
+
+use crate::{
+	ast::{self, Keyset, Statement, Suffix},
+	syntax::{AstNode, NodePtr},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StateId(pub usize);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct State {
+	pub name: String,
+	/// The `parserState` node, `None` for `accept` and `reject`.
+	pub decl: Option<NodePtr>,
+	pub name_range: Option<Range<usize>>,
+	/// Whether the state extracts from (or advances) the packet, or applies another parser which may do so.
+	pub consumes_input: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Label {
+	/// `transition s;`
+	Direct,
+	/// A case of a `select`.
+	Case {
+		keyset: NodePtr,
+		/// The keyset as written, e.g. `(0x800, _)`.
+		text: String,
+		/// Whether the keyset matches everything, i.e. it is `default` or `_`.
+		is_default: bool,
+	},
+	/// The transition to `reject` when no case of a `select` matches.
+	NoMatch { select: NodePtr },
+	/// The transition to `reject` of a state without a transition statement.
+	Implicit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+	pub from: StateId,
+	/// The state transitioned to, `None` if no state of that name is declared.
+	pub to: Option<StateId>,
+	/// The name of the state transitioned to, as written.
+	pub target: String,
+	/// The name of the state transitioned to, or the `select` or state for an implicit transition.
+	pub range: Range<usize>,
+	pub label: Label,
+}
+
+/// A mistake in the state machine of a parser.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+	/// The parser declares no `start` state.
+	NoStart,
+	Unreachable(StateId),
+	/// A transition to a state that isn't declared, by its index in [`StateGraph::edges`].
+	UndefinedState(usize),
+	/// States that can transition to each other in a cycle without extracting anything, so that the parser may never
+	/// terminate. In the order of their declaration.
+	Loop(Vec<StateId>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateGraph {
+	pub name: String,
+	/// The `parserDeclaration` node.
+	pub decl: NodePtr,
+	states: Vec<State>,
+	edges: Vec<Edge>,
+}
+
+impl StateGraph {
+	pub const ACCEPT: StateId = StateId(0);
+	pub const REJECT: StateId = StateId(1);
+
+	/// The state graphs of all the parsers of a program.
+	pub fn all(program: &ast::Program) -> Vec<StateGraph> {
+		program
+			.declarations()
+			.filter_map(|decl| match decl {
+				ast::Declaration::Parser(parser) => Some(StateGraph::new(&parser)),
+				_ => None,
+			})
+			.collect()
+	}
+
+	pub fn new(parser: &ast::ParserDecl) -> Self {
+		let builtin =
+			|name: &str| State { name: name.to_string(), decl: None, name_range: None, consumes_input: false };
+		let mut states = vec![builtin("accept"), builtin("reject")];
+		let declared: Vec<_> = parser.states().collect();
+		for state in &declared {
+			let name = state.name();
+			states.push(State {
+				name: name.as_ref().map(|name| name.text()).unwrap_or_default(),
+				decl: Some(NodePtr::new(state.syntax())),
+				name_range: name.map(|name| name.text_range()),
+				consumes_input: state.statements().any(|statement| consumes_input(&statement)),
+			});
+		}
+
+		let mut graph = StateGraph {
+			name: parser.name().map(|name| name.text()).unwrap_or_default(),
+			decl: NodePtr::new(parser.syntax()),
+			states,
+			edges: vec![],
+		};
+		for (i, state) in declared.iter().enumerate() {
+			graph.add_transitions(StateId(i + 2), state);
+		}
+		graph
+	}
+
+	fn add_transitions(&mut self, from: StateId, state: &ast::ParserState) {
+		let Some(transition) = state.transition() else {
+			let range = state.name().map_or(state.text_range(), |name| name.text_range());
+			let target = "reject".to_string();
+			self.edges.push(Edge { from, to: Some(Self::REJECT), target, range, label: Label::Implicit });
+			return;
+		};
+
+		if let Some(target) = transition.target() {
+			self.add_edge(from, &target, Label::Direct);
+		}
+		if let Some(select) = transition.select() {
+			let mut has_default = false;
+			for case in select.cases() {
+				let (Some(keyset), Some(target)) = (case.keyset(), case.state()) else { continue };
+				let is_default = matches_everything(&keyset);
+				has_default |= is_default;
+				let keyset_ptr = NodePtr::new(keyset.syntax());
+				self.add_edge(
+					from,
+					&target,
+					Label::Case { keyset: keyset_ptr, text: keyset.syntax().text(), is_default },
+				);
+			}
+			if !has_default {
+				self.edges.push(Edge {
+					from,
+					to: Some(Self::REJECT),
+					target: "reject".to_string(),
+					range: select.text_range(),
+					label: Label::NoMatch { select: NodePtr::new(select.syntax()) },
+				});
+			}
+		}
+	}
+
+	fn add_edge(&mut self, from: StateId, target: &ast::Name, label: Label) {
+		let name = target.text();
+		let to = self.find(&name);
+		self.edges.push(Edge { from, to, target: name, range: target.text_range(), label });
+	}
+
+	pub fn state(&self, id: StateId) -> &State { &self.states[id.0] }
+
+	/// All the states, starting with `accept` and `reject`, then in the order of their declaration.
+	pub fn states(&self) -> impl Iterator<Item = (StateId, &State)> + '_ {
+		self.states.iter().enumerate().map(|(i, state)| (StateId(i), state))
+	}
+
+	pub fn edges(&self) -> &[Edge] { &self.edges }
+
+	pub fn edges_from(&self, state: StateId) -> impl Iterator<Item = &Edge> + '_ {
+		self.edges.iter().filter(move |edge| edge.from == state)
+	}
+
+	/// Finds a state by name. If it is declared more than once, the first declaration is used.
+	pub fn find(&self, name: &str) -> Option<StateId> {
+		self.states.iter().position(|state| state.name == name).map(StateId)
+	}
+
+	pub fn start(&self) -> Option<StateId> { self.find("start").filter(|&id| id != Self::ACCEPT && id != Self::REJECT) }
+
+	/// Whether each state can be reached from `start`, by state index.
+	pub fn reachable(&self) -> Vec<bool> {
+		let mut reachable = vec![false; self.states.len()];
+		let mut stack: Vec<_> = self.start().into_iter().collect();
+		while let Some(state) = stack.pop() {
+			if std::mem::replace(&mut reachable[state.0], true) {
+				continue;
+			}
+			stack.extend(self.edges_from(state).filter_map(|edge| edge.to));
+		}
+		reachable
+	}
+
+	/// The groups of states that can loop without consuming input, i.e. the strongly connected components of the graph
+	/// of the states that don't consume input, if they contain a cycle.
+	pub fn loops(&self) -> Vec<Vec<StateId>> {
+		let successors: Vec<Vec<usize>> = (0..self.states.len())
+			.map(|i| {
+				self.edges_from(StateId(i))
+					.filter_map(|edge| edge.to)
+					.filter(|to| !self.states[to.0].consumes_input)
+					.map(|to| to.0)
+					.collect()
+			})
+			.collect();
+		let candidates = (0..self.states.len()).filter(|&i| !self.states[i].consumes_input);
+
+		let mut loops: Vec<Vec<StateId>> = strongly_connected(&successors, candidates)
+			.into_iter()
+			.filter(|component| component.len() > 1 || successors[component[0]].contains(&component[0]))
+			.map(|mut component| {
+				component.sort();
+				component.into_iter().map(StateId).collect()
+			})
+			.collect();
+		loops.sort();
+		loops
+	}
+
+	pub fn problems(&self) -> Vec<Problem> {
+		let mut problems = vec![];
+		if self.start().is_none() {
+			problems.push(Problem::NoStart);
+		}
+		let reachable = self.reachable();
+		if self.start().is_some() {
+			problems.extend(
+				self.states().skip(2).filter(|(id, _)| !reachable[id.0]).map(|(id, _)| Problem::Unreachable(id)),
+			);
+		}
+		for (i, edge) in self.edges.iter().enumerate() {
+			if edge.to.is_none() {
+				problems.push(Problem::UndefinedState(i));
+			}
+		}
+		problems.extend(self.loops().into_iter().map(Problem::Loop));
+		problems
+	}
+
+	/// A description of a problem, for a diagnostic at [`StateGraph::problem_range`].
+	pub fn problem_message(&self, problem: &Problem) -> String {
+		match problem {
+			Problem::NoStart => format!("Parser `{}` has no `start` state", self.name),
+			Problem::Unreachable(state) => format!("State `{}` is unreachable from `start`", self.state(*state).name),
+			Problem::UndefinedState(edge) => format!("Undefined state `{}`", self.edges[*edge].target),
+			Problem::Loop(states) => {
+				let names: Vec<_> = states.iter().map(|&state| format!("`{}`", self.state(state).name)).collect();
+				if let [name] = &names[..] {
+					format!("State {name} can loop without consuming input")
+				} else {
+					format!("States {} can loop without consuming input", names.join(", "))
+				}
+			}
+		}
+	}
+
+	/// Where a problem is reported: at the name of the state or the parser, or at the transition.
+	pub fn problem_range(&self, problem: &Problem) -> Option<Range<usize>> {
+		match problem {
+			Problem::NoStart => Some(self.decl.range.clone()),
+			Problem::Unreachable(state) => self.state(*state).name_range.clone(),
+			Problem::UndefinedState(edge) => Some(self.edges[*edge].range.clone()),
+			Problem::Loop(states) => self.state(states[0]).name_range.clone(),
+		}
+	}
+}
+
+/// Whether a keyset matches every value, like `default`, `_` or `(_, default)`.
+pub(crate) fn matches_everything(keyset: &Keyset) -> bool {
+	match keyset {
+		Keyset::Default(_) | Keyset::DontCare(_) => true,
+		Keyset::Tuple(tuple) => tuple.elements().all(|element| matches_everything(&element)),
+		_ => false,
+	}
+}
+
+/// Whether a statement may consume input, by calling `extract` or `advance` on the packet or by applying a
+/// sub-parser.
+fn consumes_input(statement: &Statement) -> bool {
+	match statement {
+		Statement::MethodCall(call) => call.callee().map_or(false, |callee| {
+			let method = callee.suffixes().filter_map(|suffix| match suffix {
+				Suffix::Member(member) => member.name(),
+				_ => None,
+			});
+			method.last().map_or(false, |name| matches!(name.text().as_str(), "extract" | "advance" | "apply"))
+		}),
+		Statement::Block(block) => block.statements().any(|statement| consumes_input(&statement)),
+		Statement::Conditional(conditional) => conditional
+			.then_branch()
+			.into_iter()
+			.chain(conditional.else_branch())
+			.any(|branch| consumes_input(&branch)),
+		_ => false,
+	}
+}
+
+/// The strongly connected components among some of the nodes of a graph, using Tarjan's algorithm.
+fn strongly_connected(successors: &[Vec<usize>], nodes: impl Iterator<Item = usize>) -> Vec<Vec<usize>> {
+	struct Tarjan<'a> {
+		successors: &'a [Vec<usize>],
+		index: Vec<Option<usize>>,
+		low: Vec<usize>,
+		on_stack: Vec<bool>,
+		stack: Vec<usize>,
+		next: usize,
+		components: Vec<Vec<usize>>,
+	}
+
+	impl Tarjan<'_> {
+		fn visit(&mut self, node: usize) {
+			self.index[node] = Some(self.next);
+			self.low[node] = self.next;
+			self.next += 1;
+			self.stack.push(node);
+			self.on_stack[node] = true;
+
+			for &successor in &self.successors[node] {
+				match self.index[successor] {
+					None => {
+						self.visit(successor);
+						self.low[node] = self.low[node].min(self.low[successor]);
+					}
+					Some(index) if self.on_stack[successor] => self.low[node] = self.low[node].min(index),
+					Some(_) => (),
+				}
+			}
+
+			if Some(self.low[node]) == self.index[node] {
+				let mut component = vec![];
+				while let Some(member) = self.stack.pop() {
+					self.on_stack[member] = false;
+					component.push(member);
+					if member == node {
+						break;
+					}
+				}
+				self.components.push(component);
+			}
+		}
+	}
+
+	let count = successors.len();
+	let mut tarjan = Tarjan {
+		successors,
+		index: vec![None; count],
+		low: vec![0; count],
+		on_stack: vec![false; count],
+		stack: vec![],
+		next: 0,
+		components: vec![],
+	};
+	for node in nodes {
+		if tarjan.index[node].is_none() {
+			tarjan.visit(node);
+		}
+	}
+	tarjan.components
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::p4_grammar::parse;
+	use pretty_assertions::assert_eq;
+
+	fn graph(input: &str) -> StateGraph {
+		let program = ast::Program::cast(parse(input)).unwrap();
+		StateGraph::all(&program).remove(0)
+	}
+
+	fn problems(input: &str) -> Vec<(&str, String)> {
+		let graph = graph(input);
+		graph
+			.problems()
+			.iter()
+			.map(|problem| (&input[graph.problem_range(problem).unwrap()], graph.problem_message(problem)))
+			.collect()
+	}
+
+	#[test]
+	fn edges() {
+		let graph = graph(
+			r#"
+			parser P(packet_in pkt, out headers_t hdr) {
+				state start {
+					pkt.extract(hdr.eth);
+					transition select(hdr.eth.type) {
+						0x800: parse_ipv4;
+						0x86dd &&& 0xffff: parse_ipv6;
+						default: accept;
+					}
+				}
+				state parse_ipv4 { pkt.extract(hdr.ipv4); transition accept; }
+				state parse_ipv6 { }
+			}
+			"#,
+		);
+
+		assert_eq!(graph.name, "P");
+		let names: Vec<_> = graph.states().map(|(_, state)| state.name.as_str()).collect();
+		assert_eq!(names, ["accept", "reject", "start", "parse_ipv4", "parse_ipv6"]);
+		let edges: Vec<_> = graph
+			.edges()
+			.iter()
+			.map(|edge| {
+				let label = match &edge.label {
+					Label::Direct => "direct".to_string(),
+					Label::Case { text, .. } => text.clone(),
+					Label::NoMatch { .. } => "no match".to_string(),
+					Label::Implicit => "implicit".to_string(),
+				};
+				(graph.state(edge.from).name.as_str(), label, edge.to.map(|to| graph.state(to).name.as_str()))
+			})
+			.collect();
+		assert_eq!(
+			edges,
+			vec![
+				("start", "0x800".to_string(), Some("parse_ipv4")),
+				("start", "0x86dd &&& 0xffff".to_string(), Some("parse_ipv6")),
+				("start", "default".to_string(), Some("accept")),
+				("parse_ipv4", "direct".to_string(), Some("accept")),
+				("parse_ipv6", "implicit".to_string(), Some("reject")),
+			]
+		);
+		assert_eq!(graph.reachable(), vec![true, true, true, true, true]);
+		assert_eq!(graph.problems(), vec![]);
+	}
+
+	#[test]
+	fn problems_of_states() {
+		let input = r#"
+			parser P(packet_in pkt, out headers_t hdr) {
+				state start {
+					transition select(hdr.eth.type, hdr.vlan.isValid()) {
+						(0x800, true): skip;
+						(0x8100, _): parse_vlan;
+						(_, false): missing;
+					}
+				}
+				state skip { transition loop; }
+				state loop { transition select(hdr.eth.type) { 1: skip; default: start; } }
+				state parse_vlan { pkt.extract(hdr.vlan); transition parse_vlan; }
+				state unused { transition unused; }
+			}
+		"#;
+		assert_eq!(
+			problems(input),
+			vec![
+				("unused", "State `unused` is unreachable from `start`".to_string()),
+				("missing", "Undefined state `missing`".to_string()),
+				("start", "States `start`, `skip`, `loop` can loop without consuming input".to_string()),
+				("unused", "State `unused` can loop without consuming input".to_string()),
+			]
+		);
+
+		assert_eq!(
+			problems("parser P() { state begin { transition accept; } }")[0].1,
+			"Parser `P` has no `start` state"
+		);
+	}
+}
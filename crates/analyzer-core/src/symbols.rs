@@ -0,0 +1,733 @@
+//! Name resolution, i.e. finding the declaration that each name in a program refers to.
+//!
+//! The declarations of a program are collected into a [`SymbolTable`] of nested [`Scope`]s, which follow the rules
+//! of the P4 specification: a name can only be used after it has been declared (except for parser states, which can
+//! be referred to from anywhere in their parser), and local declarations shadow the declarations of enclosing scopes.
+//!
+//! Names after a `.` (e.g. fields, methods and enum members) depend on the type of the expression before it, so they
+//! are left to the type checker.
+
+use std::{collections::HashSet, ops::Range};
+
+use crate::{
+	ast::{self, SyntaxNode},
+	syntax::{AstNode, NodePtr},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SymbolId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScopeId(pub usize);
+
+/// Names in different namespaces don't clash, e.g. a parser state can have the same name as a variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Namespace {
+	/// Types, and everything that can be used in an expression.
+	Value,
+	/// The states of a parser.
+	State,
+	/// The members of `error`, referred to as `error.Name`.
+	Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+	Constant,
+	Variable,
+	Parameter,
+	TypeParameter,
+	/// An instance of an extern, parser, control or package.
+	Instance,
+	Extern,
+	ExternFunction,
+	/// A method of an extern.
+	Method,
+	Action,
+	Function,
+	Table,
+	Parser,
+	ParserType,
+	Control,
+	ControlType,
+	Package,
+	Header,
+	HeaderUnion,
+	Struct,
+	Enum,
+	Typedef,
+	/// A type introduced with `type`, which is distinct from the type it is defined as.
+	NewType,
+	ValueSet,
+	State,
+	Field,
+	EnumMember,
+	ErrorMember,
+	MatchKind,
+}
+
+impl SymbolKind {
+	/// Whether the symbol can be used as a type, e.g. in the declaration of a variable.
+	pub fn is_type(&self) -> bool {
+		matches!(
+			self,
+			SymbolKind::TypeParameter
+				| SymbolKind::Extern
+				| SymbolKind::Parser
+				| SymbolKind::ParserType
+				| SymbolKind::Control
+				| SymbolKind::ControlType
+				| SymbolKind::Package
+				| SymbolKind::Header
+				| SymbolKind::HeaderUnion
+				| SymbolKind::Struct
+				| SymbolKind::Enum
+				| SymbolKind::Typedef
+				| SymbolKind::NewType
+		)
+	}
+
+	/// Whether several symbols of this kind can share a name, as long as they have different numbers of parameters.
+	pub fn is_overloadable(&self) -> bool {
+		matches!(self, SymbolKind::Function | SymbolKind::ExternFunction | SymbolKind::Method)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+	pub name: String,
+	pub kind: SymbolKind,
+	pub namespace: Namespace,
+	/// The node of the whole declaration, e.g. the `parameter` of a parameter.
+	pub decl: NodePtr,
+	/// The range of the declared name.
+	pub name_range: Range<usize>,
+	/// The scope the symbol is declared in.
+	pub scope: ScopeId,
+	/// The scope of the fields, enum members or methods declared by a header, header union, struct, enum or extern.
+	pub members: Option<ScopeId>,
+	/// The number of parameters of functions, actions and methods.
+	pub arity: Option<usize>,
+}
+
+/// A region of the program in which declarations are visible, e.g. a block or the body of a parser.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scope {
+	/// The enclosing scope, `None` for the top level of the program.
+	pub parent: Option<ScopeId>,
+	pub range: Range<usize>,
+	/// The symbols declared in the scope, in order.
+	pub symbols: Vec<SymbolId>,
+}
+
+/// A use of a name, with the symbol it resolves to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+	pub name: String,
+	pub range: Range<usize>,
+	pub namespace: Namespace,
+	/// `None` if no declaration of the name is visible.
+	pub symbol: Option<SymbolId>,
+}
+
+/// The states every parser can transition to without declaring them.
+pub const BUILTIN_STATES: &[&str] = &["accept", "reject"];
+
+/// The declarations of a program and the uses of their names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolTable {
+	symbols: Vec<Symbol>,
+	scopes: Vec<Scope>,
+	/// In the order they occur in the program.
+	references: Vec<Reference>,
+	/// Pairs of a symbol and a later declaration of the same name in the same scope.
+	duplicates: Vec<(SymbolId, SymbolId)>,
+}
+
+impl SymbolTable {
+	/// The top-level scope of every program.
+	pub const ROOT: ScopeId = ScopeId(0);
+
+	pub fn new(program: &ast::Program) -> Self {
+		let root = program.syntax();
+		let mut builder = Builder {
+			table: SymbolTable {
+				symbols: vec![],
+				scopes: vec![Scope { parent: None, range: root.text_range(), symbols: vec![] }],
+				references: vec![],
+				duplicates: vec![],
+			},
+			scope: Self::ROOT,
+		};
+		builder.visit_children(root);
+
+		let mut table = builder.table;
+		table.references.sort_by_key(|reference| reference.range.start);
+		table
+	}
+
+	pub fn symbol(&self, id: SymbolId) -> &Symbol { &self.symbols[id.0] }
+
+	pub fn symbols(&self) -> impl Iterator<Item = (SymbolId, &Symbol)> + '_ {
+		self.symbols.iter().enumerate().map(|(i, symbol)| (SymbolId(i), symbol))
+	}
+
+	pub fn scope(&self, id: ScopeId) -> &Scope { &self.scopes[id.0] }
+
+	pub fn references(&self) -> &[Reference] { &self.references }
+
+	/// Pairs of a symbol and a later declaration of the same name in the same scope, which is not allowed.
+	pub fn duplicates(&self) -> &[(SymbolId, SymbolId)] { &self.duplicates }
+
+	/// The references to names without a visible declaration.
+	pub fn unresolved(&self) -> impl Iterator<Item = &Reference> + '_ {
+		self.references.iter().filter(|reference| {
+			reference.symbol.is_none()
+				&& !(reference.namespace == Namespace::State && BUILTIN_STATES.contains(&reference.name.as_str()))
+		})
+	}
+
+	/// Finds a symbol declared in the scope or in one of the enclosing scopes, regardless of where in the scope it is
+	/// declared. Of several overloads, the first one is returned.
+	pub fn lookup(&self, scope: ScopeId, name: &str, namespace: Namespace) -> Option<SymbolId> {
+		self.lookup_all(scope, name, namespace).first().copied()
+	}
+
+	/// All the overloads of a name, from the innermost scope that declares it.
+	pub fn lookup_all(&self, scope: ScopeId, name: &str, namespace: Namespace) -> Vec<SymbolId> {
+		let mut scope = Some(scope);
+		while let Some(id) = scope {
+			let found = self.declared_in(id, name, namespace);
+			if !found.is_empty() {
+				return found;
+			}
+			scope = self.scope(id).parent;
+		}
+		vec![]
+	}
+
+	fn declared_in(&self, scope: ScopeId, name: &str, namespace: Namespace) -> Vec<SymbolId> {
+		self.scope(scope)
+			.symbols
+			.iter()
+			.copied()
+			.filter(|&id| self.symbol(id).name == name && self.symbol(id).namespace == namespace)
+			.collect()
+	}
+
+	/// The fields, enum members or methods of a type.
+	pub fn members(&self, symbol: SymbolId) -> impl Iterator<Item = SymbolId> + '_ {
+		self.symbol(symbol)
+			.members
+			.iter()
+			.flat_map(|&scope| self.scope(scope).symbols.iter().copied())
+			.filter(|&id| {
+				matches!(self.symbol(id).kind, SymbolKind::Field | SymbolKind::EnumMember | SymbolKind::Method)
+			})
+	}
+
+	/// The symbol a name refers to, or the symbol it declares.
+	pub fn resolve(&self, name: &ast::Name) -> Option<SymbolId> { self.resolve_at(name.text_range().start) }
+
+	/// The symbol referred to or declared by the name at an offset in the program.
+	pub fn resolve_at(&self, offset: usize) -> Option<SymbolId> {
+		match self.reference_at(offset) {
+			Some(reference) => reference.symbol,
+			None => self.declaration_at(offset),
+		}
+	}
+
+	/// The reference covering an offset, including the offset just after its end.
+	pub fn reference_at(&self, offset: usize) -> Option<&Reference> {
+		let i = self.references.partition_point(|reference| reference.range.end < offset);
+		self.references.get(i).filter(|reference| reference.range.start <= offset)
+	}
+
+	/// The symbol whose declared name covers an offset, including the offset just after its end.
+	pub fn declaration_at(&self, offset: usize) -> Option<SymbolId> {
+		self.symbols()
+			.find(|(_, symbol)| symbol.name_range.start <= offset && offset <= symbol.name_range.end)
+			.map(|(id, _)| id)
+	}
+
+	pub fn references_to(&self, symbol: SymbolId) -> impl Iterator<Item = &Reference> + '_ {
+		self.references.iter().filter(move |reference| reference.symbol == Some(symbol))
+	}
+
+	/// The innermost scope containing an offset.
+	pub fn scope_at(&self, offset: usize) -> ScopeId {
+		// scopes are created in the order they start, so the last one containing the offset is nested in all others
+		let i = self.scopes.iter().rposition(|scope| scope.range.start <= offset && offset <= scope.range.end);
+		ScopeId(i.unwrap_or(0))
+	}
+
+	/// The symbols that can be referred to at an offset, i.e. those declared before it in the scopes containing it
+	/// that aren't shadowed by another declaration, innermost first.
+	pub fn visible_at(&self, offset: usize) -> Vec<SymbolId> {
+		let mut seen = HashSet::new();
+		let mut visible = vec![];
+		let mut scope = Some(self.scope_at(offset));
+		while let Some(id) = scope {
+			for &symbol_id in &self.scope(id).symbols {
+				let symbol = self.symbol(symbol_id);
+				let declared = symbol.name_range.end <= offset || symbol.namespace == Namespace::State;
+				if declared && seen.insert((symbol.namespace, &symbol.name)) {
+					visible.push(symbol_id);
+				}
+			}
+			scope = self.scope(id).parent;
+		}
+		visible
+	}
+}
+
+/// Walks the syntax tree in order, declaring names as it goes, so that each reference is resolved against the
+/// declarations that precede it.
+struct Builder {
+	table: SymbolTable,
+	scope: ScopeId,
+}
+
+impl Builder {
+	/// Runs `f` in a new scope covering the node, and returns the scope.
+	fn scoped(&mut self, node: &SyntaxNode, f: impl FnOnce(&mut Self)) -> ScopeId {
+		let parent = self.scope;
+		self.scope = ScopeId(self.table.scopes.len());
+		self.table.scopes.push(Scope { parent: Some(parent), range: node.text_range(), symbols: vec![] });
+		f(self);
+		std::mem::replace(&mut self.scope, parent)
+	}
+
+	fn define(&mut self, name: Option<ast::Name>, kind: SymbolKind, decl: &SyntaxNode) -> Option<SymbolId> {
+		self.define_in(self.scope, name, kind, Namespace::Value, decl, None)
+	}
+
+	fn define_in(
+		&mut self,
+		scope: ScopeId,
+		name: Option<ast::Name>,
+		kind: SymbolKind,
+		namespace: Namespace,
+		decl: &SyntaxNode,
+		arity: Option<usize>,
+	) -> Option<SymbolId> {
+		let name = name?;
+		let id = SymbolId(self.table.symbols.len());
+		let symbol = Symbol {
+			name: name.text(),
+			kind,
+			namespace,
+			decl: NodePtr::new(decl),
+			name_range: name.text_range(),
+			scope,
+			members: None,
+			arity,
+		};
+
+		let previous = self.table.declared_in(scope, &symbol.name, namespace).into_iter().find(|&previous| {
+			let previous = self.table.symbol(previous);
+			!(kind.is_overloadable() && previous.kind.is_overloadable() && previous.arity != arity)
+		});
+		if let Some(previous) = previous {
+			self.table.duplicates.push((previous, id));
+		}
+
+		self.table.symbols.push(symbol);
+		self.table.scopes[scope.0].symbols.push(id);
+		Some(id)
+	}
+
+	fn define_function(&mut self, name: Option<ast::Name>, kind: SymbolKind, decl: &SyntaxNode, arity: usize) {
+		self.define_in(self.scope, name, kind, Namespace::Value, decl, Some(arity));
+	}
+
+	fn set_members(&mut self, symbol: Option<SymbolId>, scope: ScopeId) {
+		if let Some(id) = symbol {
+			self.table.symbols[id.0].members = Some(scope);
+		}
+	}
+
+	fn reference(&mut self, name: Option<ast::Name>, namespace: Namespace, is_global: bool) {
+		if let Some(name) = name {
+			self.reference_text(name.text(), name.text_range(), namespace, is_global);
+		}
+	}
+
+	/// Resolves a name in the current scope, or only among the top-level declarations if it is prefixed with a dot.
+	fn reference_text(&mut self, name: String, range: Range<usize>, namespace: Namespace, is_global: bool) {
+		let symbol = if is_global {
+			self.table.declared_in(SymbolTable::ROOT, &name, namespace).first().copied()
+		} else {
+			self.table.lookup(self.scope, &name, namespace)
+		};
+		self.table.references.push(Reference { name, range, namespace, symbol });
+	}
+
+	fn visit_children(&mut self, node: &SyntaxNode) {
+		for child in node.children() {
+			self.visit(&child);
+		}
+	}
+
+	/// Visits the type parameters of a function prototype before the rest of it, as the return type can refer to them.
+	fn visit_signature(&mut self, prototype: &SyntaxNode) {
+		if let Some(type_parameters) = prototype.child("typeParameters") {
+			self.visit(&type_parameters);
+		}
+		for child in prototype.children().filter(|child| child.kind() != "typeParameters") {
+			self.visit(&child);
+		}
+	}
+
+	fn visit(&mut self, node: &SyntaxNode) {
+		use SymbolKind::*;
+
+		match node.kind() {
+			// the declared names are handled by the declarations themselves, and the names of members, named arguments
+			// and table properties are not resolved in scope
+			"name" | "annotation" | crate::syntax::ERROR => (),
+
+			"constantDeclaration" | "variableDeclaration" | "valueSetDeclaration" => {
+				// the initializer can't refer to the name being declared
+				self.visit_children(node);
+				let kind = match node.kind() {
+					"constantDeclaration" => Constant,
+					"variableDeclaration" => Variable,
+					_ => ValueSet,
+				};
+				self.define(node.child("name").and_then(ast::Name::cast), kind, node);
+			}
+			"instantiation" => {
+				let instance = ast::Instantiation::cast(node.clone()).unwrap();
+				for child in node.children().filter(|child| child.kind() != "objectInitializer") {
+					self.visit(&child);
+				}
+				self.define(instance.name(), Instance, node);
+				if let Some(initializer) = node.child("objectInitializer") {
+					self.scoped(&initializer, |this| this.visit_children(&initializer));
+				}
+			}
+			"parameter" => {
+				self.visit_children(node);
+				self.define(ast::Parameter::cast(node.clone()).unwrap().name(), Parameter, node);
+			}
+			"typeParameters" => {
+				for name in ast::TypeParameters::cast(node.clone()).unwrap().names() {
+					self.define(Some(name), TypeParameter, node);
+				}
+			}
+
+			"externDeclaration" => {
+				let decl = ast::ExternDecl::cast(node.clone()).unwrap();
+				match decl.function() {
+					Some(function) => {
+						self.define_function(function.name(), ExternFunction, node, function.parameters().len());
+						self.scoped(node, |this| this.visit_signature(function.syntax()));
+					}
+					None => {
+						let id = self.define(decl.name(), Extern, node);
+						let members = self.scoped(node, |this| this.visit_children(node));
+						self.set_members(id, members);
+					}
+				}
+			}
+			"methodPrototype" => {
+				let method = ast::MethodPrototype::cast(node.clone()).unwrap();
+				match method.function() {
+					Some(function) => {
+						self.define_function(function.name(), Method, node, function.parameters().len());
+						self.scoped(node, |this| this.visit_signature(function.syntax()));
+					}
+					None => {
+						self.scoped(node, |this| this.visit_children(node));
+					}
+				}
+			}
+			"functionDeclaration" => {
+				let function = ast::FunctionDecl::cast(node.clone()).unwrap();
+				self.define_function(function.name(), Function, node, function.parameters().len());
+				self.scoped(node, |this| {
+					if let Some(prototype) = function.prototype() {
+						this.visit_signature(prototype.syntax());
+					}
+					if let Some(body) = function.body() {
+						this.visit(body.syntax());
+					}
+				});
+			}
+			"actionDeclaration" => {
+				let action = ast::ActionDecl::cast(node.clone()).unwrap();
+				self.define_function(action.name(), Action, node, action.parameters().len());
+				self.scoped(node, |this| this.visit_children(node));
+			}
+			"tableDeclaration" => {
+				self.define(node.child("name").and_then(ast::Name::cast), Table, node);
+				self.visit_children(node);
+			}
+
+			"parserDeclaration" => {
+				let parser = ast::ParserDecl::cast(node.clone()).unwrap();
+				self.define(parser.name(), Parser, node);
+				self.scoped(node, |this| {
+					// states can be referred to before they are declared
+					for state in parser.states() {
+						this.define_in(this.scope, state.name(), State, Namespace::State, state.syntax(), None);
+					}
+					this.visit_block_declaration(node, "parserTypeDeclaration");
+				});
+			}
+			"controlDeclaration" => {
+				let control = ast::ControlDecl::cast(node.clone()).unwrap();
+				self.define(control.name(), Control, node);
+				self.scoped(node, |this| this.visit_block_declaration(node, "controlTypeDeclaration"));
+			}
+			"parserTypeDeclaration" | "controlTypeDeclaration" | "packageTypeDeclaration" => {
+				let kind = match node.kind() {
+					"parserTypeDeclaration" => ParserType,
+					"controlTypeDeclaration" => ControlType,
+					_ => Package,
+				};
+				self.define(node.child("name").and_then(ast::Name::cast), kind, node);
+				self.scoped(node, |this| this.visit_children(node));
+			}
+			"parserState" | "blockStatement" | "parserBlockStatement" => {
+				self.scoped(node, |this| this.visit_children(node));
+			}
+
+			"headerTypeDeclaration" | "headerUnionDeclaration" | "structTypeDeclaration" => {
+				let kind = match node.kind() {
+					"headerTypeDeclaration" => Header,
+					"headerUnionDeclaration" => HeaderUnion,
+					_ => Struct,
+				};
+				let id = self.define(node.child("name").and_then(ast::Name::cast), kind, node);
+				let members = self.scoped(node, |this| this.visit_children(node));
+				self.set_members(id, members);
+			}
+			"structField" => {
+				self.visit_children(node);
+				self.define(ast::StructField::cast(node.clone()).unwrap().name(), Field, node);
+			}
+			"enumDeclaration" => {
+				let decl = ast::EnumDecl::cast(node.clone()).unwrap();
+				let id = self.define(decl.name(), Enum, node);
+				// the values of the members are evaluated outside of the enum
+				self.visit_children(node);
+				let members = self.scoped(node, |this| {
+					for member in decl.members() {
+						this.define(Some(member.name), EnumMember, node);
+					}
+				});
+				self.set_members(id, members);
+			}
+			"typedefDeclaration" => {
+				let typedef = ast::TypedefDecl::cast(node.clone()).unwrap();
+				self.visit_children(node);
+				self.define(typedef.name(), if typedef.is_new_type() { NewType } else { Typedef }, node);
+			}
+			"errorDeclaration" => {
+				// all `error` declarations add to the same type
+				for member in ast::ErrorDecl::cast(node.clone()).unwrap().members() {
+					self.define_in(SymbolTable::ROOT, Some(member), ErrorMember, Namespace::Error, node, None);
+				}
+			}
+			"matchKindDeclaration" => {
+				for member in ast::MatchKindDecl::cast(node.clone()).unwrap().members() {
+					self.define_in(SymbolTable::ROOT, Some(member), MatchKind, Namespace::Value, node, None);
+				}
+			}
+
+			"nameExpression" => {
+				let expr = ast::NameExpr::cast(node.clone()).unwrap();
+				self.reference(expr.name(), Namespace::Value, expr.is_global());
+			}
+			"typeName" => {
+				// a type name is a token rather than a `name` node
+				let ty = ast::TypeName::cast(node.clone()).unwrap();
+				if let Some(name) = node.child_tokens().find(|tk| tk.text() == ty.text()) {
+					self.reference_text(ty.text(), name.text_range(), Namespace::Value, ty.is_global());
+				}
+			}
+			"lvalue" => {
+				let lvalue = ast::LValue::cast(node.clone()).unwrap();
+				self.reference(lvalue.name(), Namespace::Value, lvalue.is_global());
+				self.visit_children(node);
+			}
+			"actionRef" => {
+				let action = ast::ActionRef::cast(node.clone()).unwrap();
+				self.reference(action.name(), Namespace::Value, action.is_global());
+				self.visit_children(node);
+			}
+			"keyElement" => {
+				self.visit_children(node);
+				self.reference(ast::KeyElement::cast(node.clone()).unwrap().match_kind(), Namespace::Value, true);
+			}
+			"errorMemberExpression" => {
+				let member = ast::ErrorMemberExpr::cast(node.clone()).unwrap().member();
+				self.reference(member, Namespace::Error, true);
+			}
+			"transitionStatement" => {
+				self.visit_children(node);
+				self.reference(ast::TransitionStatement::cast(node.clone()).unwrap().target(), Namespace::State, false);
+			}
+			"selectCase" => {
+				self.visit_children(node);
+				self.reference(ast::SelectCase::cast(node.clone()).unwrap().state(), Namespace::State, false);
+			}
+
+			_ => self.visit_children(node),
+		}
+	}
+
+	/// Visits a parser or a control, whose type declaration introduces its type parameters and parameters into the
+	/// same scope as the rest of its body.
+	fn visit_block_declaration(&mut self, node: &SyntaxNode, type_declaration: &str) {
+		for child in node.children() {
+			if child.kind() == type_declaration {
+				self.visit_children(&child);
+			} else {
+				self.visit(&child);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::p4_grammar::parse;
+	use pretty_assertions::assert_eq;
+
+	fn table(input: &str) -> SymbolTable { SymbolTable::new(&ast::Program::cast(parse(input)).unwrap()) }
+
+	/// The references as `name -> kind@offset` of the declared name, or `name -> ?` if they are unresolved.
+	fn resolved(table: &SymbolTable) -> Vec<String> {
+		table
+			.references()
+			.iter()
+			.map(|reference| match reference.symbol {
+				Some(id) => {
+					let symbol = table.symbol(id);
+					format!("{} -> {:?}@{}", reference.name, symbol.kind, symbol.name_range.start)
+				}
+				None => format!("{} -> ?", reference.name),
+			})
+			.collect()
+	}
+
+	#[test]
+	fn scopes() {
+		let input = r#"
+			const bit<8> x = 1;
+			typedef bit<8> t;
+			action a(t x) { bit<8> y = x; { bit<8> x = y; x = .x; } }
+			bit<8> f<T>(in T v) { return x; }
+			extern T g<T>(in T v);
+		"#;
+		let table = table(input);
+		let at = |needle: &str| input.find(needle).unwrap();
+
+		assert_eq!(
+			resolved(&table),
+			vec![
+				format!("t -> Typedef@{}", at("t;")),
+				format!("x -> Parameter@{}", at("x) {")),
+				format!("y -> Variable@{}", at("y = x")),
+				format!("x -> Variable@{}", at("x = y")),
+				format!("x -> Constant@{}", at("x = 1")),
+				format!("T -> TypeParameter@{}", at("T>(in T v) {")),
+				format!("x -> Constant@{}", at("x = 1")),
+				format!("T -> TypeParameter@{}", at("T>(in T v);")),
+				format!("T -> TypeParameter@{}", at("T>(in T v);")),
+			]
+		);
+		assert_eq!(table.unresolved().count(), 0);
+		assert_eq!(table.duplicates(), &[]);
+
+		let visible: Vec<_> = table.visible_at(at("x = y")).into_iter().map(|id| &table.symbol(id).name).collect();
+		assert_eq!(visible, vec!["y", "x", "t", "a"]);
+		let visible: Vec<_> = table.visible_at(at("= .x;")).into_iter().map(|id| &table.symbol(id).name).collect();
+		assert_eq!(visible, vec!["x", "y", "t", "a"]);
+	}
+
+	#[test]
+	fn parsers_and_controls() {
+		let input = r#"
+			error { NoError }
+			error { Missing }
+			match_kind { exact }
+			header h_t { bit<8> f; }
+			parser P(out h_t h) {
+				state start { transition select(h.f) { 1: next; default: reject; } }
+				state next { verify(false, error.Missing); transition accept; }
+			}
+			control C(inout h_t h) {
+				action drop() {}
+				table t { key = { h.f: exact; } actions = { drop; } default_action = drop(); }
+				apply { t.apply(); h.g = 1; }
+			}
+		"#;
+		let table = table(input);
+
+		let names: Vec<_> = table.references().iter().map(|reference| reference.name.as_str()).collect();
+		assert_eq!(
+			names,
+			vec![
+				"h_t", "h", "next", "reject", "verify", "Missing", "accept", "h_t", "h", "exact", "drop", "drop", "t",
+				"h"
+			]
+		);
+		let unresolved: Vec<_> = table.unresolved().map(|reference| reference.name.as_str()).collect();
+		assert_eq!(unresolved, vec!["verify"]);
+
+		let state = table.resolve_at(input.find("next; default").unwrap()).unwrap();
+		assert_eq!(table.symbol(state).kind, SymbolKind::State);
+		assert_eq!(table.symbol(state).name_range.start, input.find("next {").unwrap());
+		let error = table.resolve_at(input.find("Missing);").unwrap()).unwrap();
+		assert_eq!(table.symbol(error).namespace, Namespace::Error);
+		assert_eq!(table.symbol(error).scope, SymbolTable::ROOT);
+
+		let header = table.lookup(SymbolTable::ROOT, "h_t", Namespace::Value).unwrap();
+		let fields: Vec<_> = table.members(header).map(|id| &table.symbol(id).name).collect();
+		assert_eq!(fields, vec!["f"]);
+		let drop = table.resolve_at(input.find("drop() {}").unwrap()).unwrap();
+		assert_eq!(table.references_to(drop).count(), 2);
+		let control_scope = table.scope_at(input.find("action drop").unwrap());
+		assert_eq!(
+			table.lookup(control_scope, "h", Namespace::Value).map(|id| table.symbol(id).kind),
+			Some(SymbolKind::Parameter)
+		);
+	}
+
+	#[test]
+	fn duplicates() {
+		let table = table(
+			r#"
+			extern void f(in bit<8> a);
+			extern void f(in bit<8> a, in bit<8> b);
+			extern void f(in bit<8> c);
+			struct s { bit<8> a; bit<8> a; }
+			const bit<8> s = 1;
+			parser P() {
+				state start { transition accept; }
+				state start { transition reject; }
+			}
+			"#,
+		);
+
+		let duplicates: Vec<_> = table
+			.duplicates()
+			.iter()
+			.map(|&(previous, duplicate)| (table.symbol(previous).kind, table.symbol(duplicate).kind))
+			.collect();
+		assert_eq!(
+			duplicates,
+			vec![
+				(SymbolKind::ExternFunction, SymbolKind::ExternFunction),
+				(SymbolKind::Field, SymbolKind::Field),
+				(SymbolKind::Struct, SymbolKind::Constant),
+				(SymbolKind::State, SymbolKind::State),
+			]
+		);
+	}
+}
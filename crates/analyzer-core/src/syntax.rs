@@ -0,0 +1,622 @@
+//! A lossless, immutable syntax tree built on top of the results of the [`Parser`](crate::parser::Parser).
+//!
+//! The design follows [rowan](https://github.com/rust-analyzer/rowan): the tree is split into a *green* layer, which
+//! holds the actual (position-independent) structure and text and is shared via reference counting, and a *red*
+//! layer of [`SyntaxNode`]s and [`SyntaxToken`]s, which are created on demand and add parent pointers and absolute
+//! offsets. Unlike a [`Cst`], the tree keeps every token of the input (including whitespace and comments), so the
+//! text of the root node is always exactly the text that was parsed.
+//!
+//! Nodes are only created for the rules selected when building the tree, every other rule is flattened into its
+//! parent. The kind of a node is the name of the rule that produced it.
+
+use std::{collections::HashMap, fmt, ops::Range, sync::Arc};
+
+use crate::parser::{Cst, Rule, RuleName};
+
+/// The kind of a syntax node, i.e. the name of the grammar rule that produced it.
+pub type NodeKind = RuleName;
+
+/// The kind of the nodes holding input that a grammar skips over as erroneous, i.e. the nodes of its `error` rule.
+pub const ERROR: NodeKind = "error";
+
+#[derive(Debug)]
+struct GreenNodeData<Token> {
+	kind: NodeKind,
+	width: usize,
+	children: Vec<GreenElement<Token>>,
+}
+
+/// An immutable, position-independent node of the syntax tree. Cloning is cheap.
+#[derive(Debug)]
+pub struct GreenNode<Token>(Arc<GreenNodeData<Token>>);
+
+#[derive(Debug, PartialEq, Eq)]
+struct GreenTokenData<Token> {
+	kind: Token,
+	text: String,
+}
+
+/// An immutable token of the syntax tree, holding both the token and its source text. Cloning is cheap.
+#[derive(Debug)]
+pub struct GreenToken<Token>(Arc<GreenTokenData<Token>>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GreenElement<Token> {
+	Node(GreenNode<Token>),
+	Token(GreenToken<Token>),
+}
+
+impl<Token> Clone for GreenNode<Token> {
+	fn clone(&self) -> Self { Self(self.0.clone()) }
+}
+
+impl<Token: PartialEq> PartialEq for GreenNode<Token> {
+	fn eq(&self, other: &Self) -> bool {
+		Arc::ptr_eq(&self.0, &other.0)
+			|| (self.0.kind == other.0.kind && self.0.width == other.0.width && self.0.children == other.0.children)
+	}
+}
+
+impl<Token: Eq> Eq for GreenNode<Token> {}
+
+impl<Token> GreenNode<Token> {
+	pub fn new(kind: NodeKind, children: Vec<GreenElement<Token>>) -> Self {
+		let width = children.iter().map(|c| c.width()).sum();
+		Self(Arc::new(GreenNodeData { kind, width, children }))
+	}
+
+	pub fn kind(&self) -> NodeKind { self.0.kind }
+
+	/// The length of the text covered by this node, in bytes.
+	pub fn width(&self) -> usize { self.0.width }
+
+	pub fn children(&self) -> &[GreenElement<Token>] { &self.0.children }
+}
+
+impl<Token> Clone for GreenToken<Token> {
+	fn clone(&self) -> Self { Self(self.0.clone()) }
+}
+
+impl<Token: PartialEq> PartialEq for GreenToken<Token> {
+	fn eq(&self, other: &Self) -> bool { Arc::ptr_eq(&self.0, &other.0) || self.0 == other.0 }
+}
+
+impl<Token: Eq> Eq for GreenToken<Token> {}
+
+impl<Token> GreenToken<Token> {
+	pub fn new(kind: Token, text: String) -> Self { Self(Arc::new(GreenTokenData { kind, text })) }
+
+	pub fn kind(&self) -> &Token { &self.0.kind }
+
+	pub fn text(&self) -> &str { &self.0.text }
+
+	pub fn width(&self) -> usize { self.0.text.len() }
+}
+
+impl<Token> GreenElement<Token> {
+	pub fn width(&self) -> usize {
+		match self {
+			GreenElement::Node(node) => node.width(),
+			GreenElement::Token(token) => token.width(),
+		}
+	}
+}
+
+struct NodeData<Token> {
+	green: GreenNode<Token>,
+	parent: Option<SyntaxNode<Token>>,
+	/// The index of this node in the children of its parent.
+	index: usize,
+	offset: usize,
+}
+
+/// A node of the syntax tree, with a parent pointer and an absolute offset. Cloning is cheap.
+pub struct SyntaxNode<Token>(Arc<NodeData<Token>>);
+
+/// A token of the syntax tree, with a parent pointer and an absolute offset.
+pub struct SyntaxToken<Token> {
+	green: GreenToken<Token>,
+	parent: SyntaxNode<Token>,
+	index: usize,
+	offset: usize,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum SyntaxElement<Token> {
+	Node(SyntaxNode<Token>),
+	Token(SyntaxToken<Token>),
+}
+
+impl<Token> Clone for SyntaxNode<Token> {
+	fn clone(&self) -> Self { Self(self.0.clone()) }
+}
+
+impl<Token> PartialEq for SyntaxNode<Token> {
+	fn eq(&self, other: &Self) -> bool {
+		Arc::ptr_eq(&self.0.green.0, &other.0.green.0) && self.0.offset == other.0.offset
+	}
+}
+
+impl<Token> Eq for SyntaxNode<Token> {}
+
+impl<Token> Clone for SyntaxToken<Token> {
+	fn clone(&self) -> Self {
+		Self { green: self.green.clone(), parent: self.parent.clone(), index: self.index, offset: self.offset }
+	}
+}
+
+impl<Token> PartialEq for SyntaxToken<Token> {
+	fn eq(&self, other: &Self) -> bool { self.parent == other.parent && self.index == other.index }
+}
+
+impl<Token> Eq for SyntaxToken<Token> {}
+
+impl<Token> SyntaxNode<Token> {
+	pub fn new_root(green: GreenNode<Token>) -> Self {
+		Self(Arc::new(NodeData { green, parent: None, index: 0, offset: 0 }))
+	}
+
+	pub fn green(&self) -> &GreenNode<Token> { &self.0.green }
+
+	pub fn kind(&self) -> NodeKind { self.0.green.kind() }
+
+	/// The byte range covered by this node, relative to the start of the root.
+	pub fn text_range(&self) -> Range<usize> { self.0.offset..self.0.offset + self.0.green.width() }
+
+	pub fn parent(&self) -> Option<SyntaxNode<Token>> { self.0.parent.clone() }
+
+	/// This node, followed by its parent, grandparent and so on.
+	pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode<Token>> {
+		std::iter::successors(Some(self.clone()), |node| node.parent())
+	}
+
+	pub fn children_with_tokens(&self) -> impl Iterator<Item = SyntaxElement<Token>> + '_ {
+		let mut offset = self.0.offset;
+		self.0.green.children().iter().enumerate().map(move |(index, child)| {
+			let child_offset = offset;
+			offset += child.width();
+			match child {
+				GreenElement::Node(green) => SyntaxElement::Node(SyntaxNode(Arc::new(NodeData {
+					green: green.clone(),
+					parent: Some(self.clone()),
+					index,
+					offset: child_offset,
+				}))),
+				GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+					green: green.clone(),
+					parent: self.clone(),
+					index,
+					offset: child_offset,
+				}),
+			}
+		})
+	}
+
+	pub fn children(&self) -> impl Iterator<Item = SyntaxNode<Token>> + '_ {
+		self.children_with_tokens().filter_map(SyntaxElement::into_node)
+	}
+
+	/// The first child node of the given kind.
+	pub fn child(&self, kind: NodeKind) -> Option<SyntaxNode<Token>> { self.children().find(|c| c.kind() == kind) }
+
+	/// The direct child tokens of this node.
+	pub fn child_tokens(&self) -> impl Iterator<Item = SyntaxToken<Token>> + '_ {
+		self.children_with_tokens().filter_map(SyntaxElement::into_token)
+	}
+
+	/// All the nodes of this subtree (including this node) in preorder.
+	pub fn descendants(&self) -> impl Iterator<Item = SyntaxNode<Token>> {
+		let mut stack = vec![self.clone()];
+		std::iter::from_fn(move || {
+			let node = stack.pop()?;
+			let len = stack.len();
+			stack.extend(node.children());
+			stack[len..].reverse();
+			Some(node)
+		})
+	}
+
+	/// All the tokens of this subtree, in source order.
+	pub fn tokens(&self) -> impl Iterator<Item = SyntaxToken<Token>> {
+		let mut stack = vec![SyntaxElement::Node(self.clone())];
+		std::iter::from_fn(move || loop {
+			match stack.pop()? {
+				SyntaxElement::Token(token) => return Some(token),
+				SyntaxElement::Node(node) => {
+					let len = stack.len();
+					stack.extend(node.children_with_tokens());
+					stack[len..].reverse();
+				}
+			}
+		})
+	}
+
+	pub fn first_token(&self) -> Option<SyntaxToken<Token>> { self.tokens().next() }
+
+	pub fn last_token(&self) -> Option<SyntaxToken<Token>> { self.tokens().last() }
+
+	/// The token covering the given offset, if any.
+	pub fn token_at_offset(&self, offset: usize) -> Option<SyntaxToken<Token>> {
+		let mut node = self.clone();
+		loop {
+			let child = node.children_with_tokens().find(|c| c.text_range().contains(&offset))?;
+			match child {
+				SyntaxElement::Token(token) => return Some(token),
+				SyntaxElement::Node(child) => node = child,
+			}
+		}
+	}
+
+	/// The exact source text covered by this node.
+	pub fn text(&self) -> String {
+		let mut text = String::with_capacity(self.0.green.width());
+		self.tokens().for_each(|tk| text.push_str(tk.text()));
+		text
+	}
+}
+
+impl<Token: fmt::Debug> SyntaxNode<Token> {
+	/// Renders the whole subtree, one element per line, e.g. for use in tests.
+	pub fn debug_tree(&self) -> String {
+		fn go<Token: fmt::Debug>(buf: &mut String, element: SyntaxElement<Token>, depth: usize) {
+			use std::fmt::Write;
+
+			let _ = writeln!(buf, "{:indent$}{:?}", "", element, indent = depth * 2);
+			if let SyntaxElement::Node(node) = element {
+				for child in node.children_with_tokens() {
+					go(buf, child, depth + 1);
+				}
+			}
+		}
+
+		let mut buf = String::new();
+		go(&mut buf, SyntaxElement::Node(self.clone()), 0);
+		buf
+	}
+}
+
+impl<Token> SyntaxToken<Token> {
+	pub fn green(&self) -> &GreenToken<Token> { &self.green }
+
+	pub fn kind(&self) -> &Token { self.green.kind() }
+
+	pub fn text(&self) -> &str { self.green.text() }
+
+	pub fn text_range(&self) -> Range<usize> { self.offset..self.offset + self.green.width() }
+
+	pub fn parent(&self) -> SyntaxNode<Token> { self.parent.clone() }
+
+	/// The token following this one in source order, if any.
+	pub fn next_token(&self) -> Option<SyntaxToken<Token>> {
+		let root = self.parent.ancestors().last()?;
+		root.token_at_offset(self.text_range().end)
+	}
+}
+
+impl<Token> SyntaxElement<Token> {
+	pub fn text_range(&self) -> Range<usize> {
+		match self {
+			SyntaxElement::Node(node) => node.text_range(),
+			SyntaxElement::Token(token) => token.text_range(),
+		}
+	}
+
+	pub fn into_node(self) -> Option<SyntaxNode<Token>> {
+		match self {
+			SyntaxElement::Node(node) => Some(node),
+			SyntaxElement::Token(_) => None,
+		}
+	}
+
+	pub fn into_token(self) -> Option<SyntaxToken<Token>> {
+		match self {
+			SyntaxElement::Node(_) => None,
+			SyntaxElement::Token(token) => Some(token),
+		}
+	}
+}
+
+impl<Token> fmt::Debug for SyntaxNode<Token> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}@{:?}", self.kind(), self.text_range()) }
+}
+
+impl<Token: fmt::Debug> fmt::Debug for SyntaxToken<Token> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{:?}@{:?} {:?}", self.kind(), self.text_range(), self.text())
+	}
+}
+
+impl<Token: fmt::Debug> fmt::Debug for SyntaxElement<Token> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			SyntaxElement::Node(node) => node.fmt(f),
+			SyntaxElement::Token(token) => token.fmt(f),
+		}
+	}
+}
+
+impl<Token> fmt::Display for SyntaxNode<Token> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { self.tokens().try_for_each(|tk| f.write_str(tk.text())) }
+}
+
+/// Identifies a node by its kind and range, without keeping the tree alive, e.g. to store it in a query result.
+///
+/// The node can be found again in any tree with the same structure, see [`NodePtr::to_node`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NodePtr {
+	pub kind: NodeKind,
+	pub range: Range<usize>,
+}
+
+impl NodePtr {
+	pub fn new<Token>(node: &SyntaxNode<Token>) -> Self { Self { kind: node.kind(), range: node.text_range() } }
+
+	/// Finds the node in the tree rooted at `root`.
+	pub fn to_node<Token>(&self, root: &SyntaxNode<Token>) -> Option<SyntaxNode<Token>> {
+		let mut node = root.clone();
+		loop {
+			if node.kind() == self.kind && node.text_range() == self.range {
+				return Some(node);
+			}
+			let next = node.children().find(|child| {
+				let range = child.text_range();
+				// empty nodes contain any empty range at their position, so only descend into the one that matches
+				range.start <= self.range.start
+					&& self.range.end <= range.end
+					&& (!range.is_empty() || child.kind() == self.kind)
+			});
+			node = next?;
+		}
+	}
+}
+
+/// A typed view of a [`SyntaxNode`] of a particular kind.
+pub trait AstNode<Token>: Sized {
+	fn can_cast(kind: NodeKind) -> bool;
+
+	fn cast(node: SyntaxNode<Token>) -> Option<Self>;
+
+	fn syntax(&self) -> &SyntaxNode<Token>;
+
+	/// The byte range covered by the node, see [`SyntaxNode::text_range`].
+	fn text_range(&self) -> Range<usize> { self.syntax().text_range() }
+}
+
+/// Declares a typed wrapper around [`SyntaxNode`]s of the given kind(s), implementing [`AstNode`].
+///
+/// ```ignore
+/// ast_node!(Expression<char>: "expression");
+/// ast_node!(Literal<char>: "number" | "string");
+/// ```
+#[macro_export]
+macro_rules! ast_node {
+	($(#[$attr:meta])* $name:ident<$tk:ty>: $($kind:literal)|+) => {
+		$(#[$attr])*
+		#[derive(Debug, Clone, PartialEq, Eq)]
+		pub struct $name($crate::syntax::SyntaxNode<$tk>);
+
+		impl $crate::syntax::AstNode<$tk> for $name {
+			fn can_cast(kind: $crate::syntax::NodeKind) -> bool { matches!(kind, $($kind)|+) }
+
+			fn cast(node: $crate::syntax::SyntaxNode<$tk>) -> Option<Self> {
+				if Self::can_cast(node.kind()) {
+					Some(Self(node))
+				} else {
+					None
+				}
+			}
+
+			fn syntax(&self) -> &$crate::syntax::SyntaxNode<$tk> { &self.0 }
+		}
+	};
+}
+
+/// Builds a [`GreenNode`] from the [`Cst`] produced by matching `start`.
+///
+/// `tokens` must be the complete input, including the trivia (whitespace, comments, ...) identified by `is_trivia`
+/// which was filtered out before parsing. Trivia is attached to the outermost node that starts after it, so the
+/// leading whitespace of a declaration belongs to its parent. `is_node` selects the rules which produce a node, every
+/// other rule is flattened into the closest enclosing node. The root node always has the kind `start`.
+///
+/// Any input left over after the tokens matched by `cst` (e.g. after a prefix match) is added to the root node, so
+/// the text of the tree is always the whole input.
+pub fn build_tree<Token: Clone + PartialEq, S: AsRef<str>>(
+	rules: &HashMap<RuleName, Rule<Token>>,
+	start: RuleName,
+	cst: &Cst<Token>,
+	tokens: impl IntoIterator<Item = (Token, S)>,
+	is_trivia: impl Fn(&Token) -> bool,
+	is_node: impl Fn(RuleName) -> bool,
+) -> GreenNode<Token> {
+	let mut builder = TreeBuilder {
+		rules,
+		tokens: (Box::new(tokens.into_iter().map(|(tk, text)| (tk, text.as_ref().to_string())))
+			as Box<dyn Iterator<Item = _>>)
+			.peekable(),
+		is_trivia: &is_trivia,
+		is_node: &is_node,
+		stack: vec![(start, vec![])],
+		trivia: vec![],
+	};
+
+	builder.walk_children(start, cst);
+
+	// everything left over belongs to the root
+	builder.flush_trivia();
+	while let Some((tk, text)) = builder.tokens.next() {
+		builder.push_token(tk, text);
+	}
+
+	let (kind, children) = builder.stack.pop().unwrap();
+	debug_assert!(builder.stack.is_empty());
+	GreenNode::new(kind, children)
+}
+
+struct TreeBuilder<'a, Token: Clone> {
+	rules: &'a HashMap<RuleName, Rule<Token>>,
+	tokens: std::iter::Peekable<Box<dyn Iterator<Item = (Token, String)> + 'a>>,
+	is_trivia: &'a dyn Fn(&Token) -> bool,
+	is_node: &'a dyn Fn(RuleName) -> bool,
+	stack: Vec<(NodeKind, Vec<GreenElement<Token>>)>,
+	trivia: Vec<(Token, String)>,
+}
+
+impl<'a, Token: Clone + PartialEq> TreeBuilder<'a, Token> {
+	fn walk(&mut self, rule_name: RuleName, cst: &Cst<Token>) {
+		if (self.is_node)(rule_name) {
+			self.collect_trivia();
+			self.flush_trivia();
+			self.stack.push((rule_name, vec![]));
+			self.walk_children(rule_name, cst);
+			let (kind, children) = self.stack.pop().unwrap();
+			self.push(GreenElement::Node(GreenNode::new(kind, children)));
+		} else {
+			self.walk_children(rule_name, cst);
+		}
+	}
+
+	fn walk_children(&mut self, rule_name: RuleName, cst: &Cst<Token>) {
+		match (&self.rules[rule_name], cst) {
+			(_, Cst::Terminal(tokens)) => {
+				for tk in tokens.iter() {
+					self.consume(tk);
+				}
+			}
+			(_, Cst::Choice(option, cst)) => self.walk(option, cst),
+			(Rule::Sequence(parts), Cst::Sequence(csts)) => {
+				for (part, cst) in parts.iter().zip(csts) {
+					self.walk(part, cst);
+				}
+			}
+			(Rule::Repetition(rule) | Rule::OneOrMore(rule), Cst::Repetition(csts)) => {
+				for cst in csts {
+					self.walk(rule, cst);
+				}
+			}
+			(Rule::Optional(rule), Cst::Optional(Some(cst))) => self.walk(rule, cst),
+			(_, Cst::Optional(None) | Cst::Not(_) | Cst::And(_)) => (),
+			_ => unreachable!("the rule {rule_name} produced a mismatched syntax tree"),
+		}
+	}
+
+	/// Moves past the next non-trivia token, which must be `expected`.
+	fn consume(&mut self, expected: &Token) {
+		self.collect_trivia();
+		self.flush_trivia();
+		if let Some((tk, text)) = self.tokens.next() {
+			debug_assert!(tk == *expected, "the token stream does not match the syntax tree");
+			self.push_token(tk, text);
+		}
+	}
+
+	/// Moves past any trivia preceding the next token.
+	fn collect_trivia(&mut self) {
+		while let Some((tk, text)) = self.tokens.next_if(|(tk, _)| (self.is_trivia)(tk)) {
+			self.trivia.push((tk, text));
+		}
+	}
+
+	fn flush_trivia(&mut self) {
+		for (tk, text) in std::mem::take(&mut self.trivia) {
+			self.push_token(tk, text);
+		}
+	}
+
+	fn push_token(&mut self, tk: Token, text: String) { self.push(GreenElement::Token(GreenToken::new(tk, text))) }
+
+	fn push(&mut self, element: GreenElement<Token>) { self.stack.last_mut().unwrap().1.push(element) }
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{grammar, parser::*, rule_rhs};
+	use pretty_assertions::assert_eq;
+
+	ast_node!(Sum<char>: "start");
+	ast_node!(Number<char>: "num");
+
+	impl Sum {
+		fn operands(&self) -> impl Iterator<Item = Number> + '_ { self.syntax().children().filter_map(Number::cast) }
+	}
+
+	fn parse(input: &str) -> SyntaxNode<char> {
+		let rules = grammar! {
+			start => num, rest;
+			rest => plus_num rep;
+			plus_num => plus, num;
+			plus => "+";
+			num => digit rep1;
+			digit => { |ch: &char| ch.is_ascii_digit() };
+		};
+		let tokens: Vec<_> = input.chars().filter(|ch| !ch.is_whitespace()).collect();
+		let mut parser = Parser::from_rules(&rules).unwrap()(tokens.into());
+		let cst = parser._match().unwrap();
+
+		let green = build_tree(
+			&rules.into(),
+			"start",
+			&cst,
+			input.chars().map(|ch| (ch, ch.to_string())),
+			|ch| ch.is_whitespace(),
+			|rule| rule == "num",
+		);
+		SyntaxNode::new_root(green)
+	}
+
+	#[test]
+	fn lossless() {
+		let input = " 12 +3+ 45 ";
+		let root = parse(input);
+
+		assert_eq!(root.text(), input);
+		assert_eq!(root.to_string(), input);
+		assert_eq!(root.text_range(), 0..input.len());
+		assert_eq!(
+			root.debug_tree(),
+			r#"start@0..11
+  ' '@0..1 " "
+  num@1..3
+    '1'@1..2 "1"
+    '2'@2..3 "2"
+  ' '@3..4 " "
+  '+'@4..5 "+"
+  num@5..6
+    '3'@5..6 "3"
+  '+'@6..7 "+"
+  ' '@7..8 " "
+  num@8..10
+    '4'@8..9 "4"
+    '5'@9..10 "5"
+  ' '@10..11 " "
+"#
+		);
+	}
+
+	#[test]
+	fn typed_nodes() {
+		let root = parse("1 + 23");
+		let sum = Sum::cast(root.clone()).unwrap();
+		assert!(Number::cast(root.clone()).is_none());
+		let operands: Vec<_> = sum.operands().map(|n| n.syntax().text()).collect();
+		assert_eq!(operands, vec!["1", "23"]);
+
+		let token = root.token_at_offset(5).unwrap();
+		assert_eq!(token.text(), "3");
+		assert_eq!(token.parent().kind(), "num");
+		assert_eq!(token.parent().ancestors().map(|n| n.kind()).collect::<Vec<_>>(), vec!["num", "start"]);
+		assert_eq!(root.descendants().map(|n| n.kind()).collect::<Vec<_>>(), vec!["start", "num", "num"]);
+		assert_eq!(root.first_token().unwrap().next_token().unwrap().text(), " ");
+	}
+
+	#[test]
+	fn thread_safe() {
+		fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+		let root = parse("1+2");
+		assert_send_sync(&root);
+		let green = root.green().clone();
+		let text = std::thread::spawn(move || SyntaxNode::new_root(green).text()).join().unwrap();
+		assert_eq!(text, "1+2");
+	}
+}
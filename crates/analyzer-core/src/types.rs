@@ -0,0 +1,2547 @@
+//! Type checking, following the typing rules of the P4 specification.
+//!
+//! The [`TypeInfo`] of a program records the type of every expression and of every declared value, and the type
+//! errors found along the way. Integer literals without a width have the arbitrary-precision type `int`, which is
+//! implicitly cast to the type of the other operand or of the value it is assigned to; the recorded type of such an
+//! expression is the type it was cast to, e.g. `bit<8>` for the `1` in `x + 1` if `x` is a `bit<8>`.
+//!
+//! The values of the expressions that are compile-time constants are computed along with their types (see
+//! [`const_eval`](crate::const_eval)), so that they can be checked where the specification requires a constant, e.g.
+//! for the size of a header stack.
+//!
+//! Anything whose type can't be determined (e.g. because a name is undeclared) has the type [`Type::Unknown`], which
+//! is compatible with every other type, so that a single mistake isn't reported over and over.
+
+use std::{
+	collections::{HashMap, HashSet},
+	fmt,
+	ops::Range,
+};
+
+use num_bigint::{BigInt, Sign};
+
+use crate::{
+	ast::{self, BinaryOp, Expr, Keyset, Statement, Suffix, SyntaxNode, UnaryOp},
+	const_eval::{self, Value},
+	symbols::{ScopeId, SymbolId, SymbolKind, SymbolTable},
+	syntax::{AstNode, NodePtr},
+};
+
+/// A declared type, e.g. a header, together with its type arguments.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Nominal {
+	pub symbol: SymbolId,
+	pub name: String,
+	pub args: Vec<Type>,
+}
+
+impl fmt::Display for Nominal {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.name)?;
+		if !self.args.is_empty() {
+			write!(f, "<{}>", list(&self.args))?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Type {
+	Bool,
+	String,
+	Error,
+	MatchKind,
+	Void,
+	/// `bit<W>`
+	Bit(u32),
+	/// `int<W>`
+	Int(u32),
+	/// `varbit<W>`
+	VarBit(u32),
+	/// The arbitrary-precision `int`, e.g. of integer literals without a width.
+	InfInt,
+	Header(Nominal),
+	HeaderUnion(Nominal),
+	Struct(Nominal),
+	Enum(Nominal),
+	/// A type declared with `type`, which is distinct from the type it is defined as.
+	NewType(Nominal),
+	Extern(Nominal),
+	Parser(Nominal),
+	Control(Nominal),
+	Package(Nominal),
+	TypeParameter(Nominal),
+	/// A header stack, with its size if it is known.
+	Stack(Box<Type>, Option<u64>),
+	Tuple(Vec<Type>),
+	/// The type of a list expression like `{ a, b }`, which can be cast to a tuple, header or struct.
+	List(Vec<Type>),
+	/// The type of a structure-valued expression like `{ a = 1, b = 2 }`.
+	StructLiteral(Vec<(String, Type)>),
+	/// The type of a value set, whose elements are matched in a `select`.
+	Set(Box<Type>),
+	/// A table, whose `apply` method returns a [`Type::TableResult`].
+	Table(Nominal),
+	/// The result of applying a table, with the members `hit`, `miss` and `action_run`.
+	TableResult(Nominal),
+	/// The type of `action_run` of a table, i.e. an enumeration of the actions of the table.
+	ActionRun(Nominal),
+	/// An action, function or method, which can only be called.
+	Callable(Nominal),
+	/// A built-in method like `isValid` of a header, with the type of its receiver.
+	BuiltinMethod(Box<Type>, String),
+	/// A type used as a value, e.g. `E` in `E.A` or `MyParser` in `MyParser()`.
+	Type(Box<Type>),
+	Unknown,
+}
+
+impl Type {
+	/// Whether the type is compatible with every type, because it is unknown or a type parameter.
+	pub fn is_unknown(&self) -> bool { matches!(self, Type::Unknown | Type::TypeParameter(_)) }
+
+	/// Whether the type is `bit<W>`, `int<W>` or `int`.
+	pub fn is_numeric(&self) -> bool { matches!(self, Type::Bit(_) | Type::Int(_) | Type::InfInt) }
+
+	/// The width in bits of `bit<W>`, `int<W>` and `varbit<W>`.
+	pub fn width(&self) -> Option<u32> {
+		match self {
+			Type::Bit(width) | Type::Int(width) | Type::VarBit(width) => Some(*width),
+			_ => None,
+		}
+	}
+
+	pub fn nominal(&self) -> Option<&Nominal> {
+		match self {
+			Type::Header(nominal)
+			| Type::HeaderUnion(nominal)
+			| Type::Struct(nominal)
+			| Type::Enum(nominal)
+			| Type::NewType(nominal)
+			| Type::Extern(nominal)
+			| Type::Parser(nominal)
+			| Type::Control(nominal)
+			| Type::Package(nominal)
+			| Type::TypeParameter(nominal)
+			| Type::Table(nominal)
+			| Type::TableResult(nominal)
+			| Type::ActionRun(nominal)
+			| Type::Callable(nominal) => Some(nominal),
+			_ => None,
+		}
+	}
+
+	/// Whether a type parameter occurs in the type, e.g. in `T[4]` or `register<T>`.
+	pub fn is_generic(&self) -> bool {
+		match self {
+			Type::TypeParameter(_) => true,
+			Type::Stack(element, _) | Type::Set(element) | Type::Type(element) => element.is_generic(),
+			Type::Tuple(types) | Type::List(types) => types.iter().any(Type::is_generic),
+			_ => self.nominal().map_or(false, |nominal| nominal.args.iter().any(Type::is_generic)),
+		}
+	}
+
+	/// Replaces the type parameters bound to types, e.g. `T` by `bit<32>` in the methods of a `register<bit<32>>`. The
+	/// bound types are substituted in turn, so a type parameter can be bound to another.
+	pub fn substitute(&self, bindings: &HashMap<SymbolId, Type>) -> Type {
+		let nominal = |nominal: &Nominal| Nominal {
+			symbol: nominal.symbol,
+			name: nominal.name.clone(),
+			args: nominal.args.iter().map(|arg| arg.substitute(bindings)).collect(),
+		};
+		let types = |types: &[Type]| types.iter().map(|ty| ty.substitute(bindings)).collect();
+		match self {
+			Type::TypeParameter(parameter) => match bindings.get(&parameter.symbol) {
+				Some(bound) if bound != self => bound.substitute(bindings),
+				_ => self.clone(),
+			},
+			Type::Header(n) => Type::Header(nominal(n)),
+			Type::HeaderUnion(n) => Type::HeaderUnion(nominal(n)),
+			Type::Struct(n) => Type::Struct(nominal(n)),
+			Type::Extern(n) => Type::Extern(nominal(n)),
+			Type::Parser(n) => Type::Parser(nominal(n)),
+			Type::Control(n) => Type::Control(nominal(n)),
+			Type::Package(n) => Type::Package(nominal(n)),
+			Type::Stack(element, size) => Type::Stack(Box::new(element.substitute(bindings)), *size),
+			Type::Tuple(elements) => Type::Tuple(types(elements)),
+			Type::List(elements) => Type::List(types(elements)),
+			Type::Set(element) => Type::Set(Box::new(element.substitute(bindings))),
+			Type::Type(inner) => Type::Type(Box::new(inner.substitute(bindings))),
+			_ => self.clone(),
+		}
+	}
+}
+
+fn list(types: &[Type]) -> String { types.iter().map(|ty| ty.to_string()).collect::<Vec<_>>().join(", ") }
+
+impl fmt::Display for Type {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Type::Bool => f.write_str("bool"),
+			Type::String => f.write_str("string"),
+			Type::Error => f.write_str("error"),
+			Type::MatchKind => f.write_str("match_kind"),
+			Type::Void => f.write_str("void"),
+			Type::Bit(width) => write!(f, "bit<{width}>"),
+			Type::Int(width) => write!(f, "int<{width}>"),
+			Type::VarBit(width) => write!(f, "varbit<{width}>"),
+			Type::InfInt => f.write_str("int"),
+			Type::Stack(element, Some(size)) => write!(f, "{element}[{size}]"),
+			Type::Stack(element, None) => write!(f, "{element}[]"),
+			Type::Tuple(elements) => write!(f, "tuple<{}>", list(elements)),
+			Type::List(elements) => write!(f, "{{ {} }}", list(elements)),
+			Type::StructLiteral(fields) => {
+				let fields: Vec<_> = fields.iter().map(|(name, ty)| format!("{name} = {ty}")).collect();
+				write!(f, "{{ {} }}", fields.join(", "))
+			}
+			Type::Set(element) => write!(f, "set<{element}>"),
+			Type::TableResult(table) => write!(f, "apply_result({table})"),
+			Type::ActionRun(table) => write!(f, "action_list({table})"),
+			Type::BuiltinMethod(_, name) => f.write_str(name),
+			Type::Type(ty) => write!(f, "type {ty}"),
+			Type::Unknown => f.write_str("_"),
+			_ => write!(f, "{}", self.nominal().unwrap()),
+		}
+	}
+}
+
+/// The types of the expressions and declarations of a program.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeInfo {
+	expressions: HashMap<NodePtr, Type>,
+	symbols: HashMap<SymbolId, Type>,
+	values: HashMap<NodePtr, Value>,
+	constants: HashMap<SymbolId, Value>,
+	/// The declarations chosen among the overloads of the called actions, functions and methods, by the name of the
+	/// callee.
+	callees: HashMap<NodePtr, SymbolId>,
+	/// The specialized signatures of the called actions, functions and methods, by the name of the callee.
+	signatures: HashMap<NodePtr, String>,
+	errors: Vec<(Range<usize>, String)>,
+}
+
+impl TypeInfo {
+	pub fn new(program: &ast::Program, table: &SymbolTable) -> Self {
+		let mut checker = Checker {
+			root: program.syntax().clone(),
+			table,
+			info: TypeInfo::default(),
+			owners: table.symbols().filter_map(|(id, symbol)| Some((symbol.members?, id))).collect(),
+			resolving: HashSet::new(),
+			return_type: None,
+			in_parser: false,
+		};
+		for decl in program.declarations() {
+			checker.declaration(&decl);
+		}
+
+		// the declarations that are never referred to still have a type to show
+		for (id, symbol) in table.symbols() {
+			if matches!(
+				symbol.kind,
+				SymbolKind::Constant
+					| SymbolKind::Variable
+					| SymbolKind::Parameter
+					| SymbolKind::Instance
+					| SymbolKind::Field | SymbolKind::ValueSet
+			) {
+				checker.symbol_type(id);
+			}
+		}
+		checker.info
+	}
+
+	/// The type of an expression (or of a part of one, like a [`ast::LValue`]).
+	pub fn type_of(&self, node: &SyntaxNode) -> Option<&Type> { self.expressions.get(&NodePtr::new(node)) }
+
+	/// The type of a constant, variable, parameter, instance or field.
+	pub fn symbol_type(&self, symbol: SymbolId) -> Option<&Type> { self.symbols.get(&symbol) }
+
+	/// The value of an expression that is a compile-time constant.
+	pub fn value_of(&self, node: &SyntaxNode) -> Option<&Value> { self.values.get(&NodePtr::new(node)) }
+
+	/// The value of a constant, enum member or error.
+	pub fn symbol_value(&self, symbol: SymbolId) -> Option<&Value> { self.constants.get(&symbol) }
+
+	/// The type of the innermost expression containing an offset.
+	pub fn type_at(&self, offset: usize) -> Option<&Type> { self.expression_at(offset).map(|(_, ty)| ty) }
+
+	/// The value of the innermost expression containing an offset, if it is a compile-time constant.
+	pub fn value_at(&self, offset: usize) -> Option<&Value> {
+		self.expression_at(offset).and_then(|(ptr, _)| self.values.get(ptr))
+	}
+
+	/// The action, function or method a call refers to, by the name of the callee (e.g. `read` in `r.read(x, 0)`),
+	/// which is the overload chosen for the arguments if there are several.
+	pub fn callee(&self, name: &SyntaxNode) -> Option<SymbolId> { self.callees.get(&NodePtr::new(name)).copied() }
+
+	/// The action, function or method called by the name of a callee at an offset.
+	pub fn callee_at(&self, offset: usize) -> Option<SymbolId> { at(&self.callees, offset).copied() }
+
+	/// The signature of the action, function or method called by the name of a callee at an offset, e.g. at `read`
+	/// in `r.read(x, 0)`, with the type parameters bound by the call substituted.
+	pub fn signature_at(&self, offset: usize) -> Option<&str> {
+		at(&self.signatures, offset).map(|signature| signature.as_str())
+	}
+
+	fn expression_at(&self, offset: usize) -> Option<(&NodePtr, &Type)> {
+		self.expressions
+			.iter()
+			.filter(|(ptr, _)| ptr.range.start <= offset && offset < ptr.range.end)
+			.min_by_key(|(ptr, _)| ptr.range.len())
+	}
+
+	pub fn errors(&self) -> &[(Range<usize>, String)] { &self.errors }
+}
+
+/// The value of the innermost node containing an offset.
+fn at<T>(map: &HashMap<NodePtr, T>, offset: usize) -> Option<&T> {
+	map.iter()
+		.filter(|(ptr, _)| ptr.range.start <= offset && offset < ptr.range.end)
+		.min_by_key(|(ptr, _)| ptr.range.len())
+		.map(|(_, value)| value)
+}
+
+/// Whether a parameter can be left without an argument, because it has a default value or is `@optional`.
+fn optional(param: &ast::Parameter) -> bool {
+	param.default_value().is_some()
+		|| param.annotations().any(|a| a.name().map_or(false, |name| name.text() == "optional"))
+}
+
+/// Whether the arguments of a call fit a list of parameters: every argument is matched to a different parameter, by
+/// position or by name, and the parameters left without an argument are optional.
+fn accepts(params: &[ast::Parameter], args: &[ast::Argument]) -> bool {
+	let mut given = vec![false; params.len()];
+	for (i, arg) in args.iter().enumerate() {
+		let position = match arg.name() {
+			Some(name) => params.iter().position(|param| param.name().map_or(false, |p| p.text() == name.text())),
+			None => (i < params.len()).then_some(i),
+		};
+		match position {
+			Some(position) if !given[position] => given[position] = true,
+			_ => return false,
+		}
+	}
+	params.iter().zip(given).all(|(param, given)| given || optional(param))
+}
+
+/// The name of the callee of a call, e.g. `read` for the `.read` of `r.read(x, 0)`, or `f` for the `f` of `f(x)`.
+fn callee_name(node: &SyntaxNode) -> Option<SyntaxNode> {
+	let name = if let Some(member) = ast::MemberSuffix::cast(node.clone()) {
+		member.name()
+	} else if let Some(expr) = ast::NameExpr::cast(node.clone()) {
+		expr.name()
+	} else {
+		let lvalue = ast::LValue::cast(node.clone())?;
+		match lvalue.suffixes().last() {
+			Some(Suffix::Member(member)) => member.name(),
+			Some(_) => None,
+			None => lvalue.name(),
+		}
+	};
+	name.map(|name| name.syntax().clone())
+}
+
+struct Checker<'a> {
+	root: SyntaxNode,
+	table: &'a SymbolTable,
+	info: TypeInfo,
+	/// The symbol of each header, struct, enum, ... by the scope of its members.
+	owners: HashMap<ScopeId, SymbolId>,
+	/// The typedefs and constants being resolved, to stop at cycles.
+	resolving: HashSet<SymbolId>,
+	/// The return type of the function or action being checked, `None` in a parser or control.
+	return_type: Option<Type>,
+	/// Whether the declarations being checked are in a parser.
+	in_parser: bool,
+}
+
+impl<'a> Checker<'a> {
+	/// Reports an error, unless it was already reported, e.g. for a parameter type that is resolved at every call.
+	fn error(&mut self, range: Range<usize>, message: String) {
+		let error = (range, message);
+		if !self.info.errors.contains(&error) {
+			self.info.errors.push(error);
+		}
+	}
+
+	fn record(&mut self, node: &SyntaxNode, ty: Type) -> Type {
+		self.info.expressions.insert(NodePtr::new(node), ty.clone());
+		ty
+	}
+
+	fn record_value(&mut self, node: &SyntaxNode, value: Option<Value>) {
+		match value {
+			Some(value) => self.info.values.insert(NodePtr::new(node), value),
+			None => self.info.values.remove(&NodePtr::new(node)),
+		};
+	}
+
+	fn value(&self, node: &SyntaxNode) -> Option<Value> { self.info.values.get(&NodePtr::new(node)).cloned() }
+
+	/// Records the type an expression is implicitly cast to, e.g. `bit<8>` for an integer literal.
+	fn coerce(&mut self, node: &SyntaxNode, ty: Type) {
+		let value = self.value(node).and_then(|value| value.cast(&ty));
+		self.record(node, ty);
+		self.record_value(node, value);
+	}
+
+	fn node<N: AstNode<crate::lexer::Token>>(&self, symbol: SymbolId) -> Option<N> {
+		N::cast(self.table.symbol(symbol).decl.to_node(&self.root)?)
+	}
+
+	fn nominal(&self, symbol: SymbolId, args: Vec<Type>) -> Nominal {
+		Nominal { symbol, name: self.table.symbol(symbol).name.clone(), args }
+	}
+
+	// Types
+
+	fn resolve_type(&mut self, ty: Option<ast::Type>) -> Type {
+		let Some(ty) = ty else { return Type::Unknown };
+		match &ty {
+			ast::Type::Base(base) => {
+				let width = base.width().map(|width| self.width(&width, base.text_range()));
+				match (base.keyword().as_str(), width) {
+					("bool", _) => Type::Bool,
+					("string", _) => Type::String,
+					("error", _) => Type::Error,
+					("match_kind", _) => Type::MatchKind,
+					("bit", None) => Type::Bit(1),
+					("int", None) => Type::InfInt,
+					("bit", Some(Some(width))) => Type::Bit(width),
+					("int", Some(Some(width))) => Type::Int(width),
+					("varbit", Some(Some(width))) => Type::VarBit(width),
+					_ => Type::Unknown,
+				}
+			}
+			ast::Type::Name(name) => self.named_type(name, vec![]),
+			ast::Type::Specialized(specialized) => {
+				let args: Vec<_> =
+					specialized.arguments().into_iter().map(|arg| self.resolve_type(Some(arg))).collect();
+				let count = args.len();
+				let ty = match specialized.base() {
+					Some(name) => self.named_type(&name, args),
+					None => Type::Unknown,
+				};
+				if let Type::Extern(nominal) = &ty {
+					let parameters = self.node::<ast::ExternDecl>(nominal.symbol).map(|decl| decl.type_parameters());
+					let expected = self.type_parameters(parameters.flatten()).len();
+					if expected != count {
+						self.error(
+							specialized.text_range(),
+							format!("Expected {expected} type arguments, found {count}"),
+						);
+					}
+				}
+				ty
+			}
+			ast::Type::Stack(stack) => {
+				let element = self.resolve_type(stack.element());
+				let size = stack.size().and_then(|size| self.constant(&size));
+				Type::Stack(Box::new(element), size.and_then(|size| u64::try_from(&size).ok()))
+			}
+			ast::Type::Tuple(tuple) => {
+				Type::Tuple(tuple.elements().into_iter().map(|ty| self.resolve_type(Some(ty))).collect())
+			}
+			ast::Type::Void(_) => Type::Void,
+			ast::Type::DontCare(_) => Type::Unknown,
+		}
+	}
+
+	/// The width of the `bit<W>`, `int<W>` or `varbit<W>` in `range`, `None` if it isn't known. Widths above
+	/// [`const_eval::MAX_INT_BITS`] are reported and aren't known either.
+	fn width(&mut self, width: &ast::TypeWidth, range: Range<usize>) -> Option<u32> {
+		let width = match width {
+			ast::TypeWidth::Literal(lit) => BigInt::from(lit.value),
+			ast::TypeWidth::Expr(expr) => self.constant(expr)?,
+		};
+		if width > BigInt::from(const_eval::MAX_INT_BITS) {
+			self.error(range, format!("A type cannot be wider than {} bits", const_eval::MAX_INT_BITS));
+			return None;
+		}
+		u32::try_from(&width).ok()
+	}
+
+	/// The value of an expression that must be a compile-time constant, which is reported if it isn't one.
+	fn require_constant(&mut self, expr: &Expr) -> Option<Value> {
+		let ty = match self.info.expressions.get(&NodePtr::new(expr.syntax())) {
+			Some(ty) => ty.clone(),
+			None => self.infer(expr, None),
+		};
+		let value = self.value(expr.syntax());
+		if value.is_none() && !ty.is_unknown() {
+			self.error(expr.text_range(), "Not a compile-time constant".to_string());
+		}
+		value
+	}
+
+	/// The value of an integer expression that must be a compile-time constant.
+	fn constant(&mut self, expr: &Expr) -> Option<BigInt> { self.require_constant(expr)?.as_int().cloned() }
+
+	fn named_type(&mut self, name: &ast::TypeName, args: Vec<Type>) -> Type {
+		let Some(symbol) = self.table.reference_at(name.text_range().end).and_then(|reference| reference.symbol) else {
+			return Type::Unknown;
+		};
+		match self.type_of_symbol(symbol, args) {
+			Some(ty) => ty,
+			None => {
+				self.error(name.text_range(), format!("`{}` is not a type", name.text()));
+				Type::Unknown
+			}
+		}
+	}
+
+	/// The type declared by a symbol, `None` if it doesn't declare one.
+	fn type_of_symbol(&mut self, symbol: SymbolId, args: Vec<Type>) -> Option<Type> {
+		let nominal = self.nominal(symbol, args);
+		Some(match self.table.symbol(symbol).kind {
+			SymbolKind::Header => Type::Header(nominal),
+			SymbolKind::HeaderUnion => Type::HeaderUnion(nominal),
+			SymbolKind::Struct => Type::Struct(nominal),
+			SymbolKind::Enum => Type::Enum(nominal),
+			SymbolKind::NewType => Type::NewType(nominal),
+			SymbolKind::Extern => Type::Extern(nominal),
+			SymbolKind::Parser | SymbolKind::ParserType => Type::Parser(nominal),
+			SymbolKind::Control | SymbolKind::ControlType => Type::Control(nominal),
+			SymbolKind::Package => Type::Package(nominal),
+			SymbolKind::TypeParameter => Type::TypeParameter(nominal),
+			SymbolKind::Typedef => self.typedef(symbol),
+			_ => return None,
+		})
+	}
+
+	/// The type a typedef stands for.
+	fn typedef(&mut self, symbol: SymbolId) -> Type {
+		if !self.resolving.insert(symbol) {
+			let range = self.table.symbol(symbol).name_range.clone();
+			self.error(range, format!("`{}` is defined in terms of itself", self.table.symbol(symbol).name));
+			return Type::Unknown;
+		}
+		let ty = match self.node::<ast::TypedefDecl>(symbol) {
+			Some(typedef) => match typedef.derived() {
+				Some(derived) => derived
+					.name()
+					.and_then(|name| self.table.declaration_at(name.text_range().start))
+					.and_then(|derived| self.type_of_symbol(derived, vec![]))
+					.unwrap_or(Type::Unknown),
+				None => self.resolve_type(typedef.ty()),
+			},
+			None => Type::Unknown,
+		};
+		self.resolving.remove(&symbol);
+		ty
+	}
+
+	/// The underlying type of a serializable enum.
+	fn underlying_type(&mut self, ty: &Type) -> Option<Type> {
+		let Type::Enum(nominal) = ty else { return None };
+		let decl = self.node::<ast::EnumDecl>(nominal.symbol)?;
+		decl.underlying_type().map(|ty| self.resolve_type(Some(ty)))
+	}
+
+	/// The type of a value, or of the thing a name refers to.
+	fn symbol_type(&mut self, symbol: SymbolId) -> Type {
+		if let Some(ty) = self.info.symbols.get(&symbol) {
+			return ty.clone();
+		}
+		let ty = match self.table.symbol(symbol).kind {
+			SymbolKind::Constant => self.node::<ast::ConstantDecl>(symbol).and_then(|decl| decl.ty()),
+			SymbolKind::Variable => self.node::<ast::VariableDecl>(symbol).and_then(|decl| decl.ty()),
+			SymbolKind::Parameter => self.node::<ast::Parameter>(symbol).and_then(|decl| decl.ty()),
+			SymbolKind::Instance => self.node::<ast::Instantiation>(symbol).and_then(|decl| decl.ty()),
+			SymbolKind::Field => self.node::<ast::StructField>(symbol).and_then(|decl| decl.ty()),
+			SymbolKind::ValueSet => {
+				let element = self.node::<ast::ValueSetDecl>(symbol).and_then(|decl| decl.element_type());
+				let ty = Type::Set(Box::new(self.resolve_type(element)));
+				self.info.symbols.insert(symbol, ty.clone());
+				return ty;
+			}
+			kind => {
+				return match kind {
+					SymbolKind::Action | SymbolKind::Function | SymbolKind::ExternFunction | SymbolKind::Method => {
+						Type::Callable(self.nominal(symbol, vec![]))
+					}
+					SymbolKind::Table => Type::Table(self.nominal(symbol, vec![])),
+					SymbolKind::EnumMember => {
+						let owner = self.owners.get(&self.table.symbol(symbol).scope).copied();
+						owner.map_or(Type::Unknown, |owner| Type::Enum(self.nominal(owner, vec![])))
+					}
+					SymbolKind::ErrorMember => Type::Error,
+					SymbolKind::MatchKind => Type::MatchKind,
+					SymbolKind::State => Type::Unknown,
+					_ => self.type_of_symbol(symbol, vec![]).map_or(Type::Unknown, |ty| Type::Type(Box::new(ty))),
+				};
+			}
+		};
+		let ty = self.resolve_type(ty);
+		self.info.symbols.insert(symbol, ty.clone());
+		ty
+	}
+
+	/// The value of a constant, enum member or error, if it is known.
+	fn symbol_value(&mut self, symbol: SymbolId) -> Option<Value> {
+		if let Some(value) = self.info.constants.get(&symbol) {
+			return Some(value.clone());
+		}
+		let table = self.table;
+		let declared = table.symbol(symbol);
+		let value = match declared.kind {
+			SymbolKind::Constant => {
+				let decl = self.node::<ast::ConstantDecl>(symbol)?;
+				let expr = decl.value()?;
+				if !self.info.expressions.contains_key(&NodePtr::new(expr.syntax())) {
+					// the constant is used before its declaration is checked, e.g. in the width of a type
+					if !self.resolving.insert(symbol) {
+						return None;
+					}
+					let ty = self.resolve_type(decl.ty());
+					self.check(&expr, &ty);
+					self.resolving.remove(&symbol);
+				}
+				self.value(expr.syntax())?
+			}
+			SymbolKind::EnumMember => {
+				let owner = *self.owners.get(&declared.scope)?;
+				let decl = self.node::<ast::EnumDecl>(owner)?;
+				let member = decl.members().into_iter().find(|member| member.name.text() == declared.name)?;
+				let value = match (member.value, decl.underlying_type()) {
+					(Some(expr), Some(underlying)) => {
+						if !self.info.expressions.contains_key(&NodePtr::new(expr.syntax())) {
+							let underlying = self.resolve_type(Some(underlying));
+							self.check(&expr, &underlying);
+						}
+						Some(Box::new(self.value(expr.syntax())?))
+					}
+					_ => None,
+				};
+				Value::Enum { name: table.symbol(owner).name.clone(), member: declared.name.clone(), value }
+			}
+			SymbolKind::ErrorMember => Value::Error(declared.name.clone()),
+			_ => return None,
+		};
+		self.info.constants.insert(symbol, value.clone());
+		Some(value)
+	}
+
+	/// The minimum and maximum size in bits of a value of a type, e.g. for `minSizeInBits`.
+	fn size_in_bits(&mut self, ty: &Type) -> Option<(u64, u64)> {
+		Some(match ty {
+			Type::Bool => (1, 1),
+			Type::Bit(width) | Type::Int(width) => (*width as u64, *width as u64),
+			Type::VarBit(width) => (0, *width as u64),
+			Type::Enum(_) => {
+				let underlying = self.underlying_type(ty)?;
+				self.size_in_bits(&underlying)?
+			}
+			Type::Header(nominal) | Type::Struct(nominal) => {
+				let mut size: (u64, u64) = (0, 0);
+				for (_, field) in self.fields(nominal.symbol) {
+					let (min, max) = self.size_in_bits(&field)?;
+					size = (size.0.checked_add(min)?, size.1.checked_add(max)?);
+				}
+				size
+			}
+			Type::HeaderUnion(nominal) => {
+				let mut sizes = vec![];
+				for (_, field) in self.fields(nominal.symbol) {
+					sizes.push(self.size_in_bits(&field)?);
+				}
+				(sizes.iter().map(|size| size.0).min()?, sizes.iter().map(|size| size.1).max()?)
+			}
+			Type::Stack(element, Some(count)) => {
+				let (min, max) = self.size_in_bits(element)?;
+				(min.checked_mul(*count)?, max.checked_mul(*count)?)
+			}
+			Type::Type(inner) => self.size_in_bits(inner)?,
+			_ => return None,
+		})
+	}
+
+	/// Finds a member of a header, struct, enum or extern by name.
+	fn members_named(&self, owner: SymbolId, name: &str) -> Vec<SymbolId> {
+		self.table.members(owner).filter(|&id| self.table.symbol(id).name == name).collect()
+	}
+
+	// Compatibility
+
+	/// Whether a value of type `found` can be used where `expected` is required, possibly after an implicit cast.
+	fn assignable(&mut self, found: &Type, expected: &Type) -> bool {
+		if found == expected || found.is_unknown() || expected.is_unknown() {
+			return true;
+		}
+		match (found, expected) {
+			(Type::InfInt, Type::Bit(_) | Type::Int(_)) => true,
+			(Type::Enum(_), _) => self.underlying_type(found).map_or(false, |underlying| &underlying == expected),
+			(Type::List(elements) | Type::Tuple(elements), Type::Tuple(expected)) => {
+				elements.len() == expected.len()
+					&& elements.iter().zip(expected).all(|(found, expected)| self.assignable(found, expected))
+			}
+			(Type::List(elements), Type::Stack(element, size)) => {
+				size.map_or(true, |size| elements.len() as u64 == size)
+					&& elements.iter().all(|found| self.assignable(found, element))
+			}
+			(Type::List(elements), Type::Header(nominal) | Type::Struct(nominal)) => {
+				let fields = self.fields(nominal.symbol);
+				elements.len() == fields.len()
+					&& elements.iter().zip(&fields).all(|(found, (_, expected))| self.assignable(found, expected))
+			}
+			(Type::StructLiteral(values), Type::Header(nominal) | Type::Struct(nominal)) => {
+				let fields = self.fields(nominal.symbol);
+				values.len() == fields.len()
+					&& values.iter().all(|(name, found)| {
+						fields
+							.iter()
+							.find(|(field, _)| field == name)
+							.map_or(false, |(_, expected)| self.assignable(found, expected))
+					})
+			}
+			_ => false,
+		}
+	}
+
+	fn fields(&mut self, owner: SymbolId) -> Vec<(String, Type)> {
+		let fields: Vec<_> = self.table.members(owner).collect();
+		fields.into_iter().map(|id| (self.table.symbol(id).name.clone(), self.symbol_type(id))).collect()
+	}
+
+	/// The common type of two operands, implicitly casting `int` (and serializable enums) if needed.
+	fn unify(&mut self, a: &Type, b: &Type) -> Option<Type> {
+		match (a, b) {
+			_ if a == b => Some(a.clone()),
+			(Type::Unknown, _) | (_, Type::Unknown) => Some(Type::Unknown),
+			(Type::TypeParameter(_), _) => Some(b.clone()),
+			(_, Type::TypeParameter(_)) => Some(a.clone()),
+			(Type::InfInt, Type::Bit(_) | Type::Int(_)) => Some(b.clone()),
+			(Type::Bit(_) | Type::Int(_), Type::InfInt) => Some(a.clone()),
+			(Type::Enum(_), _) => {
+				let underlying = self.underlying_type(a)?;
+				self.unify(&underlying, b)
+			}
+			(_, Type::Enum(_)) => {
+				let underlying = self.underlying_type(b)?;
+				self.unify(a, &underlying)
+			}
+			_ => None,
+		}
+	}
+
+	/// Checks an expression against the type it is required to have.
+	fn check(&mut self, expr: &Expr, expected: &Type) {
+		let found = self.infer(expr, Some(expected));
+		if !self.assignable(&found, expected) {
+			self.error(expr.text_range(), format!("Type mismatch: expected `{expected}`, found `{found}`"));
+		} else if found == Type::InfInt && matches!(expected, Type::Bit(_) | Type::Int(_)) {
+			// the width of the constant is inferred from the context
+			self.coerce(expr.syntax(), expected.clone());
+		}
+	}
+
+	// Declarations and statements
+
+	fn declaration(&mut self, decl: &ast::Declaration) {
+		match decl {
+			ast::Declaration::Constant(constant) => {
+				let ty = self.resolve_type(constant.ty());
+				if let Some(value) = constant.value() {
+					self.check(&value, &ty);
+					self.require_constant(&value);
+				}
+				if let Some(symbol) = constant.name().and_then(|name| self.table.resolve(&name)) {
+					self.symbol_value(symbol);
+				}
+			}
+			ast::Declaration::Variable(variable) => {
+				let ty = self.resolve_type(variable.ty());
+				if let Some(value) = variable.initializer() {
+					self.check(&value, &ty);
+				}
+			}
+			ast::Declaration::Instantiation(instance) => {
+				let ty = self.resolve_type(instance.ty());
+				let args = instance.arguments();
+				let mut bindings = HashMap::new();
+				match self.constructor(&ty, &args, instance.text_range(), &mut bindings) {
+					Some(params) => {
+						self.arguments(&params, &args, instance.text_range(), &mut bindings);
+						self.specialize(instance, &ty, &bindings);
+					}
+					None => {
+						for value in args.iter().filter_map(|arg| arg.value()) {
+							self.infer(&value, None);
+						}
+					}
+				}
+				for decl in instance.initializer() {
+					self.declaration(&decl);
+				}
+			}
+			ast::Declaration::Action(action) => {
+				let outer = self.return_type.replace(Type::Void);
+				if let Some(body) = action.body() {
+					self.block(&body);
+				}
+				self.return_type = outer;
+			}
+			ast::Declaration::Function(function) => {
+				let return_type = self.resolve_type(function.prototype().and_then(|proto| proto.return_type()));
+				let outer = self.return_type.replace(return_type);
+				if let Some(body) = function.body() {
+					self.block(&body);
+				}
+				self.return_type = outer;
+			}
+			ast::Declaration::Table(table) => self.table(table),
+			ast::Declaration::Parser(parser) => {
+				let outer = std::mem::replace(&mut self.in_parser, true);
+				for decl in parser.locals() {
+					self.declaration(&decl);
+				}
+				for state in parser.states() {
+					for statement in state.statements() {
+						self.statement(&statement);
+					}
+					if let Some(select) = state.transition().and_then(|transition| transition.select()) {
+						self.select(&select);
+					}
+				}
+				self.in_parser = outer;
+			}
+			ast::Declaration::Control(control) => {
+				for decl in control.locals() {
+					self.declaration(&decl);
+				}
+				if let Some(apply) = control.apply() {
+					self.block(&apply);
+				}
+			}
+			ast::Declaration::Enum(decl) => {
+				let underlying = decl.underlying_type().map(|ty| self.resolve_type(Some(ty)));
+				for member in decl.members() {
+					if let (Some(value), Some(underlying)) = (&member.value, &underlying) {
+						self.check(value, underlying);
+						self.require_constant(value);
+					}
+					if let Some(symbol) = self.table.resolve(&member.name) {
+						self.symbol_value(symbol);
+					}
+				}
+			}
+			ast::Declaration::ValueSet(value_set) => {
+				if let Some(size) = value_set.size() {
+					self.constant(&size);
+				}
+			}
+			ast::Declaration::Typedef(typedef) => {
+				if let Some(derived) = typedef.derived() {
+					self.declaration(&derived);
+				}
+			}
+			ast::Declaration::Extern(_)
+			| ast::Declaration::ParserType(_)
+			| ast::Declaration::ControlType(_)
+			| ast::Declaration::Package(_)
+			| ast::Declaration::Header(_)
+			| ast::Declaration::HeaderUnion(_)
+			| ast::Declaration::Struct(_)
+			| ast::Declaration::Error(_)
+			| ast::Declaration::MatchKind(_) => (),
+		}
+	}
+
+	fn table(&mut self, table: &ast::TableDecl) {
+		let mut key_types = vec![];
+		for key in table.keys() {
+			key_types.push(match key.expr() {
+				Some(expr) => self.infer(&expr, None),
+				None => Type::Unknown,
+			});
+			let Some(match_kind) = key.match_kind() else { continue };
+			let Some(symbol) = self.table.resolve(&match_kind) else { continue };
+			if self.table.symbol(symbol).kind != SymbolKind::MatchKind {
+				self.error(match_kind.text_range(), format!("`{}` is not a match kind", match_kind.text()));
+			}
+		}
+
+		// the directional parameters of the actions are bound in the list, the others by the control plane
+		let mut actions = vec![];
+		for action in table.actions() {
+			let Some(symbol) = self.action(&action.name()) else { continue };
+			let (params, _) = self.signature(symbol);
+			let directional: Vec<_> =
+				params.unwrap_or_default().into_iter().filter(|param| param.direction().is_some()).collect();
+			let args = action.arguments().unwrap_or_default();
+			self.arguments(&directional, &args, action.text_range(), &mut HashMap::new());
+			actions.push((symbol, action));
+		}
+
+		let entries = table.entries().into_iter().flat_map(|entries| entries.entries().collect::<Vec<_>>());
+		let ty = if key_types.len() == 1 { key_types.remove(0) } else { Type::Tuple(key_types) };
+		for entry in entries {
+			if let Some(keyset) = entry.keyset() {
+				self.keyset(&keyset, &ty);
+			}
+			if let Some(priority) = entry.priority() {
+				self.check(&priority, &Type::InfInt);
+			}
+			let Some(action) = entry.action() else { continue };
+			let args = action.arguments().unwrap_or_default();
+			if let Some(symbol) = self.action(&action.name()) {
+				let (params, _) = self.signature(symbol);
+				self.arguments(&params.unwrap_or_default(), &args, action.text_range(), &mut HashMap::new());
+			}
+			self.listed_action(table, &actions, action.name(), &args, action.text_range());
+		}
+
+		for property in table.properties() {
+			let Some(value) = property.value() else { continue };
+			self.infer(&value, None);
+			match property.name().map(|name| name.text()).as_deref() {
+				Some("size") => {
+					let size = self.constant(&value);
+					if size.map_or(false, |size| size.sign() != Sign::Plus) {
+						self.error(value.text_range(), "The size of a table must be positive".to_string());
+					}
+				}
+				Some("default_action") => {
+					let (name, args) = match &value {
+						Expr::Name(name) => (name.name(), vec![]),
+						Expr::Postfix(postfix) => match (postfix.base(), &postfix.suffixes().collect::<Vec<_>>()[..]) {
+							(Some(Expr::Name(name)), [Suffix::Call(call)]) => (name.name(), call.arguments()),
+							_ => (None, vec![]),
+						},
+						_ => (None, vec![]),
+					};
+					match name {
+						Some(name) => self.listed_action(table, &actions, Some(name), &args, value.text_range()),
+						None => self.error(value.text_range(), "Expected an action".to_string()),
+					}
+				}
+				_ => (),
+			}
+		}
+	}
+
+	/// The action referred to by a name in a table, if it is one.
+	fn action(&mut self, name: &Option<ast::Name>) -> Option<SymbolId> {
+		let name = name.as_ref()?;
+		let symbol = self.table.resolve(name)?;
+		if self.table.symbol(symbol).kind != SymbolKind::Action {
+			self.error(name.text_range(), format!("`{}` is not an action", name.text()));
+			return None;
+		}
+		Some(symbol)
+	}
+
+	/// Checks that the action of an entry or of the `default_action` is in the `actions` of a table, and that it binds
+	/// the same directional arguments. The arguments are checked against the parameters by the caller.
+	fn listed_action(
+		&mut self,
+		table: &ast::TableDecl,
+		actions: &[(SymbolId, ast::ActionRef)],
+		name: Option<ast::Name>,
+		args: &[ast::Argument],
+		range: Range<usize>,
+	) {
+		let Some(symbol) = self.action(&name) else { return };
+		let Some((_, listed)) = actions.iter().find(|(action, _)| *action == symbol) else {
+			let table = table.name().map(|name| name.text()).unwrap_or_default();
+			let name = self.table.symbol(symbol).name.clone();
+			self.error(range, format!("`{name}` is not one of the actions of table `{table}`"));
+			return;
+		};
+
+		// constants are compared by value, so that e.g. `1` matches a constant `PORT` of 1, anything else by its text
+		let bound = listed.arguments().unwrap_or_default();
+		for (arg, bound) in args.iter().zip(&bound) {
+			let value = |arg: &ast::Argument| arg.value().and_then(|value| self.value(value.syntax()));
+			let same = match (value(arg), value(bound)) {
+				(Some(a), Some(b)) => match (a.as_int(), b.as_int()) {
+					(Some(a), Some(b)) => a == b,
+					_ => a == b,
+				},
+				_ => arg.syntax().text() == bound.syntax().text(),
+			};
+			if !same {
+				let expected = bound.syntax().text();
+				self.error(arg.text_range(), format!("Expected `{expected}`, as bound in the actions of the table"));
+			}
+		}
+	}
+
+	fn block(&mut self, block: &ast::BlockStatement) {
+		for statement in block.statements() {
+			self.statement(&statement);
+		}
+	}
+
+	fn statement(&mut self, statement: &Statement) {
+		match statement {
+			Statement::Assignment(assignment) => {
+				let Some(target) = assignment.target() else { return };
+				let ty = self.lvalue(&target);
+				if let Some(value) = assignment.value() {
+					self.check(&value, &ty);
+				}
+			}
+			Statement::MethodCall(call) => {
+				let Some(callee) = call.callee() else { return };
+				let callee_type = self.lvalue(&callee);
+				let node = callee.suffixes().last().map_or_else(|| callee.syntax().clone(), |s| s.syntax().clone());
+				self.call(&callee_type, &node, &call.type_arguments(), &call.arguments(), call.text_range(), None);
+			}
+			Statement::Conditional(conditional) => {
+				if let Some(condition) = conditional.condition() {
+					self.check(&condition, &Type::Bool);
+				}
+				for branch in conditional.then_branch().into_iter().chain(conditional.else_branch()) {
+					self.statement(&branch);
+				}
+			}
+			Statement::Block(block) => self.block(block),
+			Statement::Return(ret) => match (ret.value(), self.return_type.clone()) {
+				(Some(value), Some(Type::Void) | None) => {
+					self.infer(&value, None);
+					self.error(value.text_range(), "A value cannot be returned here".to_string());
+				}
+				(Some(value), Some(ty)) => self.check(&value, &ty),
+				(None, Some(ty)) if ty != Type::Void && !ty.is_unknown() => {
+					self.error(ret.text_range(), format!("Expected a return value of type `{ty}`"));
+				}
+				(None, _) => (),
+			},
+			Statement::Switch(switch) => {
+				let ty = match switch.expr() {
+					Some(expr) => self.infer(&expr, None),
+					None => Type::Unknown,
+				};
+				for label in switch.cases().filter_map(|case| case.label()?.expr()) {
+					match &ty {
+						Type::ActionRun(table) => {
+							self.infer(&label, None);
+							self.action_label(table, &label);
+						}
+						_ => self.check(&label, &ty),
+					}
+				}
+				for body in switch.cases().filter_map(|case| case.body()) {
+					self.block(&body);
+				}
+			}
+			Statement::Variable(variable) => self.declaration(&ast::Declaration::Variable(variable.clone())),
+			Statement::Constant(constant) => self.declaration(&ast::Declaration::Constant(constant.clone())),
+			Statement::Instantiation(instance) => self.declaration(&ast::Declaration::Instantiation(instance.clone())),
+			Statement::Empty(_) | Statement::Exit(_) => (),
+		}
+	}
+
+	/// Checks that the label of a case of `switch (t.apply().action_run)` is one of the actions of the table.
+	fn action_label(&mut self, table: &Nominal, label: &Expr) {
+		let Some(decl) = self.node::<ast::TableDecl>(table.symbol) else { return };
+		let name = match label {
+			Expr::Name(name) => name.name(),
+			_ => None,
+		};
+		let symbol = name.as_ref().and_then(|name| self.table.resolve(name));
+		let listed = decl.actions().iter().any(|action| {
+			action
+				.name()
+				.and_then(|name| self.table.resolve(&name))
+				.map_or(false, |action| Some(action) == symbol)
+		});
+		if !listed && (name.is_none() || symbol.is_some()) {
+			self.error(label.text_range(), format!("Expected one of the actions of table `{}`", table.name));
+		}
+	}
+
+	fn select(&mut self, select: &ast::SelectExpr) {
+		let mut types: Vec<_> = select.expressions().map(|expr| self.infer(&expr, None)).collect();
+		let ty = if types.len() == 1 { types.remove(0) } else { Type::Tuple(types) };
+		for keyset in select.cases().filter_map(|case| case.keyset()) {
+			self.keyset(&keyset, &ty);
+		}
+	}
+
+	/// Checks that a keyset can match values of a type.
+	fn keyset(&mut self, keyset: &Keyset, ty: &Type) {
+		match keyset {
+			Keyset::Tuple(tuple) => {
+				let elements: Vec<_> = tuple.elements().collect();
+				match ty {
+					Type::Tuple(types) if types.len() == elements.len() => {
+						for (element, ty) in elements.iter().zip(types) {
+							self.keyset(element, ty);
+						}
+					}
+					_ if ty.is_unknown() => (),
+					_ => {
+						let expected = match ty {
+							Type::Tuple(types) => types.len(),
+							_ => 1,
+						};
+						let message = format!("Expected {expected} values in the keyset, found {}", elements.len());
+						self.error(tuple.text_range(), message);
+					}
+				}
+			}
+			Keyset::Mask(mask) => {
+				for expr in mask.value().into_iter().chain(mask.mask()) {
+					self.check(&expr, ty);
+				}
+			}
+			Keyset::Range(range) => {
+				for expr in range.low().into_iter().chain(range.high()) {
+					self.check(&expr, ty);
+				}
+			}
+			Keyset::Default(_) | Keyset::DontCare(_) => (),
+			Keyset::Expr(expr) => match self.infer(expr, Some(ty)) {
+				Type::Set(element) => {
+					if !self.assignable(&element, ty) && !self.assignable(ty, &element) {
+						let message = format!("Type mismatch: expected `{ty}`, found `set<{element}>`");
+						self.error(expr.text_range(), message);
+					}
+				}
+				found => {
+					if let (Type::Tuple(types), false) = (ty, matches!(found, Type::Tuple(_) | Type::List(_))) {
+						let message = format!("Expected {} values in the keyset, found 1", types.len());
+						self.error(expr.text_range(), message);
+					} else if !self.assignable(&found, ty) {
+						self.error(expr.text_range(), format!("Type mismatch: expected `{ty}`, found `{found}`"));
+					} else if found == Type::InfInt && matches!(ty, Type::Bit(_) | Type::Int(_)) {
+						self.coerce(expr.syntax(), ty.clone());
+					}
+				}
+			},
+		}
+	}
+
+	// Expressions
+
+	fn lvalue(&mut self, lvalue: &ast::LValue) -> Type {
+		let mut ty = match lvalue.name() {
+			Some(name) => self.name(&name),
+			None => Type::Unknown,
+		};
+		for suffix in lvalue.suffixes() {
+			ty = self.suffix(ty, None, lvalue.syntax(), &suffix, None);
+		}
+		self.record(lvalue.syntax(), ty)
+	}
+
+	fn name(&mut self, name: &ast::Name) -> Type {
+		match self.table.resolve(name) {
+			Some(symbol) => self.symbol_type(symbol),
+			None => Type::Unknown,
+		}
+	}
+
+	/// Infers the type of an expression. `expected` is the type it is required to have, if known, which determines
+	/// the types of list and structure-valued expressions.
+	fn infer(&mut self, expr: &Expr, expected: Option<&Type>) -> Type {
+		let ty = match expr {
+			Expr::Literal(lit) => match lit.value() {
+				Some(ast::LiteralValue::Integer(lit)) => match lit.width {
+					Some(width) if width > const_eval::MAX_INT_BITS => {
+						let message =
+							format!("An integer literal cannot be wider than {} bits", const_eval::MAX_INT_BITS);
+						self.error(expr.text_range(), message);
+						Type::Unknown
+					}
+					Some(width) if lit.signed => Type::Int(width),
+					Some(width) => Type::Bit(width),
+					None => Type::InfInt,
+				},
+				Some(ast::LiteralValue::String(_)) => Type::String,
+				Some(ast::LiteralValue::Bool(_)) => Type::Bool,
+				None => Type::Unknown,
+			},
+			Expr::Name(name) => match name.name() {
+				Some(name) => self.name(&name),
+				None => Type::Unknown,
+			},
+			Expr::ErrorMember(_) => Type::Error,
+			Expr::TypeMember(member) => {
+				let ty = self.resolve_type(member.ty());
+				match member.member() {
+					Some(name) => self.member(Type::Type(Box::new(ty)), None, &name),
+					None => Type::Unknown,
+				}
+			}
+			Expr::Paren(paren) => match paren.expr() {
+				Some(inner) => self.infer(&inner, expected),
+				None => Type::Unknown,
+			},
+			Expr::List(list) => {
+				let elements: Vec<_> = list.elements().collect();
+				let expected: Vec<Option<Type>> = match expected {
+					Some(Type::Tuple(types)) => types.iter().cloned().map(Some).collect(),
+					Some(Type::Stack(element, _)) => vec![Some(*element.clone()); elements.len()],
+					Some(Type::Header(nominal) | Type::Struct(nominal)) => {
+						self.fields(nominal.symbol).into_iter().map(|(_, ty)| Some(ty)).collect()
+					}
+					_ => vec![],
+				};
+				let types = elements
+					.iter()
+					.enumerate()
+					.map(|(i, element)| match expected.get(i).cloned().flatten() {
+						Some(ty) if expected.len() == elements.len() => {
+							self.check(element, &ty);
+							self.info.expressions[&NodePtr::new(element.syntax())].clone()
+						}
+						_ => self.infer(element, None),
+					})
+					.collect();
+				Type::List(types)
+			}
+			Expr::Struct(expr) => {
+				let fields = match expected {
+					Some(Type::Header(nominal) | Type::Struct(nominal)) => self.fields(nominal.symbol),
+					_ => vec![],
+				};
+				let types = expr
+					.fields()
+					.filter_map(|pair| {
+						let name = pair.name()?.text();
+						let value = pair.value()?;
+						let ty = match fields.iter().find(|(field, _)| field == &name) {
+							Some((_, ty)) => {
+								self.check(&value, ty);
+								self.info.expressions[&NodePtr::new(value.syntax())].clone()
+							}
+							None => self.infer(&value, None),
+						};
+						Some((name, ty))
+					})
+					.collect();
+				Type::StructLiteral(types)
+			}
+			Expr::Conditional(conditional) => {
+				if let Some(condition) = conditional.condition() {
+					self.check(&condition, &Type::Bool);
+				}
+				let then_type = conditional.then_expr().map_or(Type::Unknown, |e| self.infer(&e, expected));
+				let else_type = conditional.else_expr().map_or(Type::Unknown, |e| self.infer(&e, expected));
+				match self.unify(&then_type, &else_type) {
+					Some(ty) => ty,
+					None => {
+						let message = format!("The branches have different types, `{then_type}` and `{else_type}`");
+						self.error(conditional.text_range(), message);
+						Type::Unknown
+					}
+				}
+			}
+			Expr::Binary(binary) => self.binary(binary),
+			Expr::Unary(unary) => {
+				let operand = unary.operand().map_or(Type::Unknown, |e| self.infer(&e, expected));
+				let op = unary.op();
+				let valid = match op {
+					Some(UnaryOp::Not) => operand == Type::Bool,
+					Some(UnaryOp::BitNot) => matches!(operand, Type::Bit(_) | Type::Int(_)),
+					Some(UnaryOp::Neg | UnaryOp::Plus) => operand.is_numeric(),
+					None => true,
+				};
+				if !valid && !operand.is_unknown() {
+					let op = unary.syntax().first_token().map(|tk| tk.text().to_string()).unwrap_or_default();
+					self.error(unary.text_range(), format!("Operator `{op}` cannot be applied to `{operand}`"));
+					Type::Unknown
+				} else {
+					operand
+				}
+			}
+			Expr::Cast(cast) => {
+				let ty = self.resolve_type(cast.ty());
+				let found = cast.expr().map_or(Type::Unknown, |e| self.infer(&e, Some(&ty)));
+				if !self.castable(&found, &ty) {
+					self.error(cast.text_range(), format!("Cannot cast `{found}` to `{ty}`"));
+				}
+				ty
+			}
+			Expr::Postfix(postfix) => {
+				let mut ty = postfix.base().map_or(Type::Unknown, |e| self.infer(&e, None));
+				let mut value = postfix.base().and_then(|base| self.value(base.syntax()));
+				let mut base = postfix.base().map_or_else(|| postfix.syntax().clone(), |base| base.syntax().clone());
+				let mut suffixes = postfix.suffixes().peekable();
+				while let Some(suffix) = suffixes.next() {
+					// only the type of the last suffix is that of the whole expression
+					let expected = expected.filter(|_| suffixes.peek().is_none());
+					ty = self.suffix(ty, value, &base, &suffix, expected);
+					value = self.value(suffix.syntax());
+					base = suffix.syntax().clone();
+				}
+				ty
+			}
+		};
+		let value = self.evaluate(expr, &ty);
+		self.record_value(expr.syntax(), value);
+		self.record(expr.syntax(), ty)
+	}
+
+	/// The value of an expression whose operands have been checked, if it is a compile-time constant.
+	fn evaluate(&mut self, expr: &Expr, ty: &Type) -> Option<Value> {
+		match expr {
+			Expr::Literal(lit) => match lit.value()? {
+				ast::LiteralValue::Integer(lit) => Some(Value::int(lit.value, ty.clone())),
+				ast::LiteralValue::String(value) => Some(Value::String(value)),
+				ast::LiteralValue::Bool(value) => Some(Value::Bool(value)),
+			},
+			Expr::Name(name) => {
+				let symbol = self.table.resolve(&name.name()?)?;
+				self.symbol_value(symbol)
+			}
+			Expr::ErrorMember(member) => Some(Value::Error(member.member()?.text())),
+			Expr::TypeMember(member) => self.value(member.member()?.syntax()),
+			Expr::Paren(paren) => self.value(paren.expr()?.syntax()),
+			Expr::List(list) => {
+				let values = list.elements().map(|element| self.value(element.syntax())).collect::<Option<_>>()?;
+				Some(Value::List(values))
+			}
+			Expr::Struct(expr) => {
+				let fields = expr
+					.fields()
+					.map(|pair| Some((pair.name()?.text(), self.value(pair.value()?.syntax())?)))
+					.collect::<Option<_>>()?;
+				Some(Value::Struct(fields))
+			}
+			Expr::Conditional(conditional) => {
+				let condition = self.value(conditional.condition()?.syntax())?.as_bool()?;
+				let branch = if condition { conditional.then_expr() } else { conditional.else_expr() };
+				self.value(branch?.syntax())?.cast(ty)
+			}
+			// computed operator by operator, see `binary`
+			Expr::Binary(_) => self.value(expr.syntax()),
+			Expr::Unary(unary) => const_eval::unary(unary.op()?, &self.value(unary.operand()?.syntax())?, ty),
+			Expr::Cast(cast) => {
+				let value = self.value(cast.expr()?.syntax())?;
+				let representation = match ty {
+					Type::Enum(_) => self.underlying_type(ty)?,
+					Type::NewType(nominal) => {
+						let underlying = self.node::<ast::TypedefDecl>(nominal.symbol)?.ty();
+						self.resolve_type(underlying)
+					}
+					_ => ty.clone(),
+				};
+				value.cast(&representation)
+			}
+			Expr::Postfix(postfix) => match postfix.suffixes().last() {
+				Some(suffix) => self.value(suffix.syntax()),
+				None => self.value(postfix.base()?.syntax()),
+			},
+		}
+	}
+
+	fn binary(&mut self, binary: &ast::BinaryExpr) -> Type {
+		let operands: Vec<_> = binary.operands().collect();
+		let Some(first) = operands.first() else { return Type::Unknown };
+		let mut ty = self.infer(first, None);
+		let mut value = self.value(first.syntax());
+		for (op, operand) in binary.operators().into_iter().zip(&operands[1..]) {
+			let rhs = self.infer(operand, None);
+			let lhs = ty;
+			ty = self.binary_op(op, &lhs, &rhs, operand, binary.text_range());
+
+			// comparisons are evaluated in the common type of the operands
+			let operand_type = match ty {
+				Type::Bool if !matches!(op, BinaryOp::And | BinaryOp::Or) => {
+					self.unify(&lhs, &rhs).unwrap_or(Type::Unknown)
+				}
+				_ => ty.clone(),
+			};
+			let rhs = self.value(operand.syntax());
+			value = match (value, rhs) {
+				_ if ty.is_unknown() => None,
+				(Some(lhs), Some(rhs)) => match const_eval::binary(op, &lhs, &rhs, &operand_type) {
+					Ok(value) => value,
+					Err(message) => {
+						self.error(binary.text_range(), message);
+						None
+					}
+				},
+				_ => None,
+			};
+		}
+		self.record_value(binary.syntax(), value);
+		ty
+	}
+
+	fn binary_op(&mut self, op: BinaryOp, lhs: &Type, rhs: &Type, operand: &Expr, range: Range<usize>) -> Type {
+		if lhs.is_unknown() || rhs.is_unknown() {
+			return match op {
+				BinaryOp::Or
+				| BinaryOp::And
+				| BinaryOp::Eq
+				| BinaryOp::Ne
+				| BinaryOp::Lt
+				| BinaryOp::Le
+				| BinaryOp::Gt
+				| BinaryOp::Ge => Type::Bool,
+				_ => Type::Unknown,
+			};
+		}
+
+		let result = match op {
+			BinaryOp::Or | BinaryOp::And => (lhs == &Type::Bool && rhs == &Type::Bool).then_some(Type::Bool),
+			BinaryOp::Eq | BinaryOp::Ne => self.unify(lhs, rhs).map(|_| Type::Bool),
+			BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+				self.unify(lhs, rhs).filter(|ty| ty.is_numeric()).map(|_| Type::Bool)
+			}
+			BinaryOp::Shl | BinaryOp::Shr => {
+				// the shift amount is unsigned, and doesn't need to have the same width
+				let amount = matches!(rhs, Type::Bit(_) | Type::InfInt)
+					|| self.underlying_type(rhs).map_or(false, |ty| matches!(ty, Type::Bit(_)));
+				(lhs.is_numeric() && amount).then(|| lhs.clone())
+			}
+			BinaryOp::Concat => match (lhs, rhs) {
+				(Type::Bit(a) | Type::Int(a), Type::Bit(b) | Type::Int(b)) => {
+					a.checked_add(*b).map(|width| match lhs {
+						Type::Int(_) => Type::Int(width),
+						_ => Type::Bit(width),
+					})
+				}
+				_ => None,
+			},
+			BinaryOp::SatAdd | BinaryOp::SatSub => {
+				self.unify(lhs, rhs).filter(|ty| matches!(ty, Type::Bit(_) | Type::Int(_)))
+			}
+			BinaryOp::Div | BinaryOp::Mod => {
+				self.unify(lhs, rhs).filter(|ty| matches!(ty, Type::Bit(_) | Type::InfInt))
+			}
+			BinaryOp::BitOr | BinaryOp::BitXor | BinaryOp::BitAnd | BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul => {
+				self.unify(lhs, rhs).filter(|ty| ty.is_numeric())
+			}
+		};
+
+		match result {
+			Some(ty) => {
+				if rhs == &Type::InfInt && ty != Type::InfInt && !matches!(op, BinaryOp::Shl | BinaryOp::Shr) {
+					if let Some(fixed) = self.unify(lhs, rhs) {
+						self.coerce(operand.syntax(), fixed);
+					}
+				}
+				ty
+			}
+			None => {
+				let message = format!("Operator `{}` cannot be applied to `{lhs}` and `{rhs}`", op.as_str());
+				self.error(range, message);
+				Type::Unknown
+			}
+		}
+	}
+
+	/// Whether an explicit cast from one type to another is allowed.
+	fn castable(&mut self, from: &Type, to: &Type) -> bool {
+		if self.assignable(from, to) {
+			return true;
+		}
+		match (from, to) {
+			(Type::Bit(_) | Type::Int(_), Type::Bit(_) | Type::Int(_)) => {
+				// changing both the signedness and the width at once isn't allowed
+				matches!((from, to), (Type::Bit(_), Type::Bit(_)) | (Type::Int(_), Type::Int(_)))
+					|| from.width() == to.width()
+			}
+			(Type::Bit(1), Type::Bool) | (Type::Bool, Type::Bit(1)) | (Type::InfInt, Type::Bool) => true,
+			(Type::NewType(nominal), _) | (_, Type::NewType(nominal)) => {
+				let underlying = self.node::<ast::TypedefDecl>(nominal.symbol).map(|decl| decl.ty());
+				let underlying = self.resolve_type(underlying.flatten());
+				let other = if from == &Type::NewType(nominal.clone()) { to } else { from };
+				underlying.is_unknown() || self.castable(other, &underlying) || self.castable(&underlying, other)
+			}
+			(_, Type::Enum(_)) => self.underlying_type(to).map_or(false, |underlying| self.castable(from, &underlying)),
+			(Type::Enum(_), _) => self.underlying_type(from).map_or(false, |underlying| self.castable(&underlying, to)),
+			_ => false,
+		}
+	}
+
+	/// The type of a suffix applied to a value of type `ty`, whose value is `value` if it is a compile-time constant.
+	/// `base` is the node the suffix is applied to, e.g. the member access of a method that is called.
+	fn suffix(
+		&mut self,
+		ty: Type,
+		value: Option<Value>,
+		base: &SyntaxNode,
+		suffix: &Suffix,
+		expected: Option<&Type>,
+	) -> Type {
+		let mut result_value = None;
+		let result = match suffix {
+			Suffix::Member(member) => match member.name() {
+				Some(name) => {
+					let result = self.member(ty, value, &name);
+					result_value = self.value(name.syntax());
+					result
+				}
+				None => Type::Unknown,
+			},
+			Suffix::Index(index) => {
+				if let Some(index) = index.index() {
+					let found = self.infer(&index, None);
+					if !found.is_numeric() && !found.is_unknown() {
+						self.error(index.text_range(), format!("Type mismatch: expected an integer, found `{found}`"));
+					}
+				}
+				let constant = index.index().and_then(|index| self.value(index.syntax())).and_then(|i| i.as_u64());
+				if let (Some(Value::List(values)), Some(i)) = (&value, constant) {
+					result_value = values.get(i as usize).cloned();
+				}
+				match ty {
+					Type::Stack(element, size) => {
+						let index = index.index().and_then(|index| self.value(index.syntax())?.as_int().cloned());
+						if let (Some(i), Some(size)) = (index, size) {
+							if i.sign() == Sign::Minus || i >= BigInt::from(size) {
+								let message = format!(
+									"Index {i} is out of bounds of `{}`",
+									Type::Stack(element.clone(), Some(size))
+								);
+								self.error(suffix.text_range(), message);
+							}
+						}
+						*element
+					}
+					Type::Tuple(elements) => match index.index().and_then(|index| self.constant(&index)) {
+						Some(i) => {
+							usize::try_from(&i).ok().and_then(|i| elements.get(i).cloned()).unwrap_or(Type::Unknown)
+						}
+						None => Type::Unknown,
+					},
+					ty if ty.is_unknown() => Type::Unknown,
+					ty => {
+						self.error(suffix.text_range(), format!("`{ty}` cannot be indexed"));
+						Type::Unknown
+					}
+				}
+			}
+			Suffix::Slice(slice) => {
+				let high = slice.high().and_then(|high| self.constant(&high));
+				let low = slice.low().and_then(|low| self.constant(&low));
+				let bounds =
+					high.zip(low).and_then(|(high, low)| Some((u32::try_from(&high).ok()?, u32::try_from(&low).ok()?)));
+				match (&ty, bounds) {
+					(Type::Bit(_) | Type::Int(_) | Type::InfInt, Some((high, low)))
+						if high >= low && high < ty.width().unwrap_or(const_eval::MAX_INT_BITS) =>
+					{
+						result_value = value.and_then(|value| const_eval::slice(&value, high, low));
+						Type::Bit(high - low + 1)
+					}
+					(Type::Bit(_) | Type::Int(_) | Type::InfInt, Some((high, low))) => {
+						self.error(suffix.text_range(), format!("Invalid slice `[{high}:{low}]` of `{ty}`"));
+						Type::Unknown
+					}
+					(Type::Bit(_) | Type::Int(_) | Type::InfInt, None) => Type::Unknown,
+					(ty, _) if ty.is_unknown() => Type::Unknown,
+					(ty, _) => {
+						self.error(suffix.text_range(), format!("`{ty}` cannot be sliced"));
+						Type::Unknown
+					}
+				}
+			}
+			Suffix::Call(call) => {
+				if let Type::BuiltinMethod(receiver, method) = &ty {
+					let size = self.size_in_bits(receiver);
+					result_value = match (size, method.as_str()) {
+						(Some((min, _)), "minSizeInBits") => Some(Value::int(min, Type::InfInt)),
+						(Some((_, max)), "maxSizeInBits") => Some(Value::int(max, Type::InfInt)),
+						(Some((min, _)), "minSizeInBytes") => Some(Value::int((min + 7) / 8, Type::InfInt)),
+						(Some((_, max)), "maxSizeInBytes") => Some(Value::int((max + 7) / 8, Type::InfInt)),
+						_ => None,
+					};
+				}
+				self.call(&ty, base, &call.type_arguments(), &call.arguments(), suffix.text_range(), expected)
+			}
+		};
+		self.record_value(suffix.syntax(), result_value);
+		self.record(suffix.syntax(), result)
+	}
+
+	fn member(&mut self, ty: Type, value: Option<Value>, name: &ast::Name) -> Type {
+		let member = name.text();
+		let member_value = match (&ty, value) {
+			(_, Some(Value::Struct(fields))) => fields.into_iter().find(|(field, _)| field == &member).map(|(_, v)| v),
+			(Type::Header(nominal) | Type::Struct(nominal), Some(Value::List(values))) => {
+				let position = self.table.members(nominal.symbol).position(|id| self.table.symbol(id).name == member);
+				position.and_then(|i| values.get(i).cloned())
+			}
+			(Type::Type(inner), _) => match &**inner {
+				Type::Enum(nominal) => {
+					let found = self.members_named(nominal.symbol, &member).first().copied();
+					found.and_then(|id| self.symbol_value(id))
+				}
+				_ => None,
+			},
+			(Type::Stack(_, Some(size)), _) if member == "size" => Some(Value::int(*size, Type::Bit(32))),
+			_ => None,
+		};
+		self.record_value(name.syntax(), member_value);
+		if matches!(ty, Type::Stack(..)) && matches!(member.as_str(), "next" | "last" | "lastIndex") && !self.in_parser
+		{
+			self.error(name.text_range(), format!("`{member}` of a header stack can only be used in a parser"));
+		}
+		let found = match &ty {
+			Type::Header(nominal) | Type::HeaderUnion(nominal) | Type::Struct(nominal) => {
+				match self.members_named(nominal.symbol, &member).first() {
+					Some(&field) => Some(self.symbol_type(field)),
+					None => match (&ty, member.as_str()) {
+						(Type::Header(_) | Type::HeaderUnion(_), "isValid" | "setValid" | "setInvalid") => {
+							Some(Type::BuiltinMethod(Box::new(ty.clone()), member.clone()))
+						}
+						(_, "minSizeInBits" | "minSizeInBytes" | "maxSizeInBits" | "maxSizeInBytes") => {
+							Some(Type::BuiltinMethod(Box::new(ty.clone()), member.clone()))
+						}
+						_ => None,
+					},
+				}
+			}
+			Type::Stack(element, _) => match member.as_str() {
+				"next" | "last" => Some(*element.clone()),
+				"size" | "lastIndex" => Some(Type::Bit(32)),
+				"push_front" | "pop_front" => Some(Type::BuiltinMethod(Box::new(ty.clone()), member.clone())),
+				_ => None,
+			},
+			Type::Extern(nominal) => self
+				.members_named(nominal.symbol, &member)
+				.first()
+				.map(|&method| Type::Callable(Nominal { args: nominal.args.clone(), ..self.nominal(method, vec![]) })),
+			Type::Table(_) | Type::Parser(_) | Type::Control(_) if member == "apply" => {
+				Some(Type::BuiltinMethod(Box::new(ty.clone()), member.clone()))
+			}
+			Type::TableResult(table) => match member.as_str() {
+				"hit" | "miss" => Some(Type::Bool),
+				"action_run" => Some(Type::ActionRun(table.clone())),
+				_ => None,
+			},
+			Type::Type(inner) => match &**inner {
+				Type::Enum(nominal) => self.members_named(nominal.symbol, &member).first().map(|_| *inner.clone()),
+				_ => match member.as_str() {
+					"minSizeInBits" | "minSizeInBytes" | "maxSizeInBits" | "maxSizeInBytes" => {
+						Some(Type::BuiltinMethod(Box::new(ty.clone()), member.clone()))
+					}
+					_ => None,
+				},
+			},
+			ty if ty.is_unknown() => Some(Type::Unknown),
+			_ => None,
+		};
+
+		match found {
+			Some(found) => self.record(name.syntax(), found),
+			None => {
+				let owner = match &ty {
+					Type::Type(inner) => inner.to_string(),
+					ty => ty.to_string(),
+				};
+				self.error(name.text_range(), format!("`{owner}` has no member `{member}`"));
+				Type::Unknown
+			}
+		}
+	}
+
+	/// Checks the arguments of a call, and returns the type of its result. `node` is the callee, e.g. the `.read` of
+	/// `r.read(x, 0)`, for which the declaration called and its signature are recorded. `expected` is the type the
+	/// context expects of the result, which binds the type parameters that the arguments leave unbound.
+	fn call(
+		&mut self,
+		callee: &Type,
+		node: &SyntaxNode,
+		type_args: &[ast::Type],
+		args: &[ast::Argument],
+		range: Range<usize>,
+		expected: Option<&Type>,
+	) -> Type {
+		let mut bindings = HashMap::new();
+		let mut method = None;
+		let mut type_parameters = vec![];
+		let (params, result) = match callee {
+			Type::Callable(nominal) => 'callable: {
+				let Some(symbol) = self.overload(nominal.symbol, args, range.clone()) else {
+					break 'callable (None, Type::Unknown);
+				};
+				// the type parameters of an extern are bound by the type of its instance
+				if let Some(owner) = self.owner(symbol) {
+					let parameters = self.node::<ast::ExternDecl>(owner).and_then(|decl| decl.type_parameters());
+					bindings.extend(self.type_parameters(parameters).into_iter().zip(nominal.args.iter().cloned()));
+				}
+				let parameters = self.prototype(symbol).and_then(|prototype| prototype.type_parameters());
+				let parameters = self.type_parameters(parameters);
+				self.type_arguments(&parameters, type_args, range.clone(), &mut bindings);
+				type_parameters = parameters;
+				method = Some((symbol, nominal.args.clone()));
+				self.signature(symbol)
+			}
+			Type::BuiltinMethod(receiver, method) => match (&**receiver, method.as_str()) {
+				(_, "isValid") => (None, Type::Bool),
+				(_, "setValid" | "setInvalid") => (None, Type::Void),
+				(Type::Stack(..), "push_front" | "pop_front") => {
+					match args {
+						[count] => {
+							if let Some(count) = count.value() {
+								self.check(&count, &Type::InfInt);
+								// the elements are shifted by a count known at compile time
+								self.require_constant(&count);
+							}
+						}
+						_ => self.error(range.clone(), format!("Expected 1 arguments, found {}", args.len())),
+					}
+					(None, Type::Void)
+				}
+				(Type::Table(table), "apply") => (Some(vec![]), Type::TableResult(table.clone())),
+				(Type::Parser(nominal) | Type::Control(nominal), "apply") => {
+					(Some(self.apply_parameters(nominal.symbol)), Type::Void)
+				}
+				_ => (None, Type::InfInt),
+			},
+			// constructing an instance, e.g. of a parser passed to a package
+			Type::Type(ty) => (self.constructor(ty, args, range.clone(), &mut bindings), *ty.clone()),
+			ty if ty.is_unknown() => (None, Type::Unknown),
+			ty => {
+				self.error(range.clone(), format!("`{ty}` is not callable"));
+				(None, Type::Unknown)
+			}
+		};
+
+		let arity = match &params {
+			Some(params) => self.arguments(params, args, range.clone(), &mut bindings),
+			None => {
+				for value in args.iter().filter_map(|arg| arg.value()) {
+					if !self.info.expressions.contains_key(&NodePtr::new(value.syntax())) {
+						self.infer(&value, None);
+					}
+				}
+				true
+			}
+		};
+		if let Some((method, owner_args)) = method {
+			self.packet_method(method, args, range.clone());
+			// e.g. the `T` of `bit<8> x = any();`, which is bound by the type of `x`
+			if let Some(expected) = expected.filter(|ty| !ty.is_unknown() && **ty != Type::InfInt) {
+				self.bind(&result, expected, range.clone(), &mut bindings);
+			}
+			let unbound = type_parameters.iter().filter(|parameter| !bindings.contains_key(parameter));
+			let unbound: Vec<_> = unbound.map(|&parameter| self.table.symbol(parameter).name.clone()).collect();
+			// the type arguments can't be inferred from arguments that are missing
+			for parameter in unbound.iter().filter(|_| arity) {
+				let name = &self.table.symbol(method).name;
+				self.error(range.clone(), format!("Cannot infer the type argument `{parameter}` of `{name}`"));
+			}
+			let params = params.unwrap_or_default();
+			let mut signature = match result.substitute(&bindings) {
+				Type::Void => String::new(),
+				result => format!("{result} "),
+			};
+			if let Some(owner) = self.owner(method) {
+				let args = owner_args.iter().map(|arg| arg.substitute(&bindings)).collect();
+				signature += &format!("{}.", self.nominal(owner, args));
+			}
+			signature += &self.prototype_text(method, &params, &bindings);
+			if let Some(name) = callee_name(node) {
+				self.info.callees.insert(NodePtr::new(&name), method);
+				self.info.signatures.insert(NodePtr::new(&name), signature);
+			}
+		}
+		result.substitute(&bindings)
+	}
+
+	/// The name and parameters of an action, function or method with the type parameters bound substituted, e.g.
+	/// `read(out bit<32> result, in bit<32> index)`.
+	fn prototype_text(
+		&mut self,
+		symbol: SymbolId,
+		params: &[ast::Parameter],
+		bindings: &HashMap<SymbolId, Type>,
+	) -> String {
+		let params: Vec<_> = params
+			.iter()
+			.map(|param| {
+				let direction = match param.direction() {
+					Some(ast::Direction::In) => "in ",
+					Some(ast::Direction::Out) => "out ",
+					Some(ast::Direction::InOut) => "inout ",
+					None => "",
+				};
+				let ty = self.resolve_type(param.ty()).substitute(bindings);
+				let name = param.name().map(|name| name.text()).unwrap_or_default();
+				format!("{direction}{ty} {name}")
+			})
+			.collect();
+		format!("{}({})", self.table.symbol(symbol).name, params.join(", "))
+	}
+
+	/// Chooses the declaration called among the overloads of an action, function or method, i.e. the declarations
+	/// with the same name in the same scope, by the number and the names of the arguments. `None` if no overload or
+	/// more than one fits the arguments, which is reported with the candidates.
+	fn overload(&mut self, symbol: SymbolId, args: &[ast::Argument], range: Range<usize>) -> Option<SymbolId> {
+		let declared = self.table.symbol(symbol);
+		let candidates: Vec<_> = self
+			.table
+			.scope(declared.scope)
+			.symbols
+			.iter()
+			.copied()
+			.filter(|&id| self.table.symbol(id).name == declared.name && self.table.symbol(id).kind == declared.kind)
+			.collect();
+		// the arguments of a call without overloads are checked against the parameters
+		if candidates.len() <= 1 {
+			return Some(symbol);
+		}
+
+		let mut listed = vec![];
+		for &candidate in &candidates {
+			let params = self.signature(candidate).0.unwrap_or_default();
+			let text = self.prototype_text(candidate, &params, &HashMap::new());
+			listed.push((params, text));
+		}
+		self.choose("overload", &declared.name, &listed, args, range).map(|i| candidates[i])
+	}
+
+	/// Chooses the one of several candidates, given by their parameters and prototypes, whose parameters fit the
+	/// arguments of a call. `None` if none or more than one fits, which is reported with the candidates.
+	fn choose(
+		&mut self,
+		what: &str,
+		name: &str,
+		candidates: &[(Vec<ast::Parameter>, String)],
+		args: &[ast::Argument],
+		range: Range<usize>,
+	) -> Option<usize> {
+		let matching: Vec<_> = (0..candidates.len()).filter(|&i| accepts(&candidates[i].0, args)).collect();
+		let (message, listed) = match &matching[..] {
+			[chosen] => return Some(*chosen),
+			[] => (format!("No {what} of `{name}` matches {} arguments", args.len()), (0..candidates.len()).collect()),
+			_ => (format!("The call of `{name}` is ambiguous"), matching),
+		};
+		let listed: Vec<_> = listed.into_iter().map(|i| format!("`{}`", candidates[i].1)).collect();
+		self.error(range, format!("{message}, candidates: {}", listed.join(", ")));
+		None
+	}
+
+	/// The extern a method belongs to.
+	fn owner(&self, method: SymbolId) -> Option<SymbolId> {
+		if self.table.symbol(method).kind != SymbolKind::Method {
+			return None;
+		}
+		self.owners.get(&self.table.symbol(method).scope).copied()
+	}
+
+	/// Gives an instance of a generic extern without type arguments, e.g. of `Counter<T>`, the type with the type
+	/// parameters bound by the arguments of its constructor, which then bind them in the calls of its methods.
+	fn specialize(&mut self, instance: &ast::Instantiation, ty: &Type, bindings: &HashMap<SymbolId, Type>) {
+		let Type::Extern(nominal) = ty else { return };
+		let Some(decl) = self.node::<ast::ExternDecl>(nominal.symbol) else { return };
+		let parameters = self.type_parameters(decl.type_parameters());
+		if !nominal.args.is_empty() || parameters.is_empty() {
+			return;
+		}
+		let range = instance.ty().map_or(instance.text_range(), |ty| ty.text_range());
+		let mut args = vec![];
+		for parameter in parameters {
+			args.push(match bindings.get(&parameter) {
+				Some(ty) => ty.clone(),
+				None => {
+					let name = &self.table.symbol(parameter).name;
+					let message = format!("Cannot infer the type argument `{name}` of `{}`", nominal.name);
+					self.error(range.clone(), message);
+					Type::Unknown
+				}
+			});
+		}
+		if let Some(symbol) = instance.name().and_then(|name| self.table.resolve(&name)) {
+			self.info.symbols.insert(symbol, Type::Extern(Nominal { args, ..nominal.clone() }));
+		}
+	}
+
+	/// The symbols of the type parameters of a generic declaration.
+	fn type_parameters(&self, parameters: Option<ast::TypeParameters>) -> Vec<SymbolId> {
+		let names = parameters.map(|parameters| parameters.names().collect::<Vec<_>>()).unwrap_or_default();
+		names.iter().filter_map(|name| self.table.declaration_at(name.text_range().start)).collect()
+	}
+
+	/// Binds the type parameters of a call to its explicit type arguments, if there are any.
+	fn type_arguments(
+		&mut self,
+		parameters: &[SymbolId],
+		args: &[ast::Type],
+		range: Range<usize>,
+		bindings: &mut HashMap<SymbolId, Type>,
+	) {
+		if args.is_empty() {
+			return;
+		}
+		if args.len() != parameters.len() {
+			self.error(range, format!("Expected {} type arguments, found {}", parameters.len(), args.len()));
+		}
+		for (&parameter, arg) in parameters.iter().zip(args) {
+			let ty = self.resolve_type(Some(arg.clone()));
+			bindings.insert(parameter, ty);
+		}
+	}
+
+	/// Checks the misuses of `packet_in.extract` and `packet_out.emit` that their signatures don't rule out.
+	fn packet_method(&mut self, method: SymbolId, args: &[ast::Argument], range: Range<usize>) {
+		let Some(owner) = self.owner(method) else { return };
+		let method = self.table.symbol(method).name.as_str();
+		let (expected, allowed): (&str, fn(&Type) -> bool) = match (self.table.symbol(owner).name.as_str(), method) {
+			("packet_in", "extract") => {
+				if !self.in_parser {
+					self.error(range.clone(), "`extract` can only be called in a parser".to_string());
+				}
+				("a header", |ty| matches!(ty, Type::Header(_)))
+			}
+			("packet_out", "emit") => {
+				if self.in_parser {
+					self.error(range.clone(), "`emit` cannot be called in a parser".to_string());
+				}
+				("a header, header stack, header union or struct", |ty| {
+					matches!(ty, Type::Header(_) | Type::HeaderUnion(_) | Type::Stack(..) | Type::Struct(_))
+				})
+			}
+			_ => return,
+		};
+		let Some(value) = args.first().and_then(|arg| arg.value()) else { return };
+		let ty = self.info.expressions.get(&NodePtr::new(value.syntax())).cloned().unwrap_or(Type::Unknown);
+		if !allowed(&ty) && !ty.is_unknown() {
+			self.error(value.text_range(), format!("`{method}` expects {expected}, found `{ty}`"));
+		}
+	}
+
+	/// The prototype of a function or method.
+	fn prototype(&self, symbol: SymbolId) -> Option<ast::FunctionPrototype> {
+		match self.table.symbol(symbol).kind {
+			SymbolKind::Function => self.node::<ast::FunctionDecl>(symbol).and_then(|decl| decl.prototype()),
+			SymbolKind::ExternFunction => self.node::<ast::ExternDecl>(symbol).and_then(|decl| decl.function()),
+			SymbolKind::Method => self.node::<ast::MethodPrototype>(symbol).and_then(|decl| decl.function()),
+			_ => None,
+		}
+	}
+
+	/// The parameters and the return type of an action, function or method.
+	fn signature(&mut self, symbol: SymbolId) -> (Option<Vec<ast::Parameter>>, Type) {
+		if self.table.symbol(symbol).kind == SymbolKind::Action {
+			let params = self.node::<ast::ActionDecl>(symbol).map(|action| action.parameters());
+			return (params, Type::Void);
+		}
+		match self.prototype(symbol) {
+			Some(prototype) => (Some(prototype.parameters()), self.resolve_type(prototype.return_type())),
+			None => (None, Type::Unknown),
+		}
+	}
+
+	/// The parameters of the constructor of an extern, parser or control called with the given arguments, `None` for
+	/// other types. The constructor of an extern is chosen among its overloads like the overloads of a function (see
+	/// [`Checker::overload`]), `None` if none or more than one fits. The type parameters of an extern are bound to the
+	/// type arguments of the type.
+	fn constructor(
+		&mut self,
+		ty: &Type,
+		args: &[ast::Argument],
+		range: Range<usize>,
+		bindings: &mut HashMap<SymbolId, Type>,
+	) -> Option<Vec<ast::Parameter>> {
+		match ty {
+			Type::Extern(nominal) => {
+				let decl = self.node::<ast::ExternDecl>(nominal.symbol)?;
+				bindings
+					.extend(self.type_parameters(decl.type_parameters()).into_iter().zip(nominal.args.iter().cloned()));
+				let mut constructors: Vec<_> =
+					decl.methods().filter_map(|method| method.constructor()).map(|ctor| ctor.parameters()).collect();
+				// the arguments of a constructor without overloads are checked against the parameters
+				if constructors.len() <= 1 {
+					return Some(constructors.pop().unwrap_or_default());
+				}
+				let candidates: Vec<_> = constructors
+					.into_iter()
+					.map(|params| {
+						let text = self.prototype_text(nominal.symbol, &params, bindings);
+						(params, text)
+					})
+					.collect();
+				let chosen = self.choose("constructor", &nominal.name, &candidates, args, range)?;
+				Some(candidates[chosen].0.clone())
+			}
+			Type::Parser(nominal) if self.table.symbol(nominal.symbol).kind == SymbolKind::Parser => {
+				self.node::<ast::ParserDecl>(nominal.symbol).map(|decl| decl.constructor_parameters())
+			}
+			Type::Control(nominal) if self.table.symbol(nominal.symbol).kind == SymbolKind::Control => {
+				self.node::<ast::ControlDecl>(nominal.symbol).map(|decl| decl.constructor_parameters())
+			}
+			_ => None,
+		}
+	}
+
+	/// The parameters of the `apply` method of a parser or control.
+	fn apply_parameters(&self, symbol: SymbolId) -> Vec<ast::Parameter> {
+		match self.table.symbol(symbol).kind {
+			SymbolKind::Parser => self.node::<ast::ParserDecl>(symbol).map(|decl| decl.parameters()),
+			SymbolKind::ParserType => self.node::<ast::ParserTypeDecl>(symbol).map(|decl| decl.parameters()),
+			SymbolKind::Control => self.node::<ast::ControlDecl>(symbol).map(|decl| decl.parameters()),
+			SymbolKind::ControlType => self.node::<ast::ControlTypeDecl>(symbol).map(|decl| decl.parameters()),
+			_ => None,
+		}
+		.unwrap_or_default()
+	}
+
+	/// Checks the arguments of a call against the parameters, by position or by name, and returns whether the number
+	/// of arguments is right. Every parameter without a default value that isn't `@optional` needs an argument.
+	///
+	/// The type parameters that are still unbound in the types of the parameters are bound to the types of the
+	/// arguments, e.g. `T` to `bit<8>` for `log(x)` if `x` is a `bit<8>`.
+	fn arguments(
+		&mut self,
+		params: &[ast::Parameter],
+		args: &[ast::Argument],
+		range: Range<usize>,
+		bindings: &mut HashMap<SymbolId, Type>,
+	) -> bool {
+		let required = params.iter().filter(|param| !optional(param)).count();
+		let arity = args.len() >= required && args.len() <= params.len();
+		if !arity {
+			let expected =
+				if required == params.len() { required.to_string() } else { format!("{required} to {}", params.len()) };
+			self.error(range.clone(), format!("Expected {expected} arguments, found {}", args.len()));
+		}
+
+		let mut given = vec![false; params.len()];
+		for (i, arg) in args.iter().enumerate() {
+			let position = match arg.name() {
+				Some(name) => {
+					let position =
+						params.iter().position(|param| param.name().map_or(false, |p| p.text() == name.text()));
+					if position.is_none() {
+						self.error(name.text_range(), format!("No parameter named `{}`", name.text()));
+					}
+					position
+				}
+				None => (i < params.len()).then_some(i),
+			};
+			if let Some(position) = position {
+				if std::mem::replace(&mut given[position], true) {
+					let name = params[position].name().map(|name| name.text()).unwrap_or_default();
+					self.error(arg.text_range(), format!("`{name}` is given more than once"));
+				}
+			}
+			let param = position.map(|position| &params[position]);
+			let Some(value) = arg.value() else { continue };
+			match param {
+				Some(param) => {
+					let ty = self.resolve_type(param.ty()).substitute(bindings);
+					if !ty.is_generic() {
+						self.check(&value, &ty);
+						continue;
+					}
+					let found = self.infer(&value, None);
+					self.bind(&ty, &found, value.text_range(), bindings);
+					let expected = ty.substitute(bindings);
+					if !self.assignable(&found, &expected) {
+						self.error(
+							value.text_range(),
+							format!("Type mismatch: expected `{expected}`, found `{found}`"),
+						);
+					}
+				}
+				None => {
+					self.infer(&value, None);
+				}
+			}
+		}
+
+		// with named arguments, the right number of arguments can still leave out a required parameter
+		if arity {
+			for (param, _) in params.iter().zip(given).filter(|(param, given)| !given && !optional(param)) {
+				let name = param.name().map(|name| name.text()).unwrap_or_default();
+				self.error(range.clone(), format!("Missing an argument for `{name}`"));
+			}
+		}
+		arity
+	}
+
+	/// Binds the unbound type parameters in the type of a parameter to the matching parts of the type of the argument,
+	/// e.g. `T` to `bit<8>` for a parameter of type `T[4]` and an argument of type `bit<8>[4]`.
+	fn bind(&mut self, param: &Type, arg: &Type, range: Range<usize>, bindings: &mut HashMap<SymbolId, Type>) {
+		match (param, arg) {
+			(Type::TypeParameter(parameter), _) if !bindings.contains_key(&parameter.symbol) => {
+				let ty = match arg {
+					// a type argument needs a width, which an integer literal alone doesn't give
+					Type::InfInt => {
+						let message = format!("Cannot infer `{}` from an integer without a width", parameter.name);
+						self.error(range, message);
+						Type::Unknown
+					}
+					_ => arg.clone(),
+				};
+				bindings.insert(parameter.symbol, ty);
+			}
+			(Type::Stack(param, _), Type::Stack(arg, _)) | (Type::Set(param), Type::Set(arg)) => {
+				self.bind(param, arg, range, bindings)
+			}
+			(Type::Tuple(params), Type::Tuple(args) | Type::List(args)) if params.len() == args.len() => {
+				for (param, arg) in params.iter().zip(args) {
+					self.bind(param, arg, range.clone(), bindings);
+				}
+			}
+			_ => match (param.nominal(), arg.nominal()) {
+				(Some(param), Some(arg)) if param.symbol == arg.symbol && param.args.len() == arg.args.len() => {
+					for (param, arg) in param.args.iter().zip(&arg.args) {
+						self.bind(param, arg, range.clone(), bindings);
+					}
+				}
+				_ => (),
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::p4_grammar::parse_and_check;
+	use pretty_assertions::assert_eq;
+
+	fn check(input: &str) -> (SymbolTable, TypeInfo) {
+		let (_, table, info) = parse_and_check(input);
+		(table, info)
+	}
+
+	fn errors(input: &str) -> Vec<(&str, String)> {
+		let (_, info) = check(input);
+		info.errors().iter().map(|(range, message)| (&input[range.clone()], message.clone())).collect()
+	}
+
+	fn type_at(input: &str, needle: &str) -> String {
+		let (_, info) = check(input);
+		info.type_at(input.find(needle).unwrap()).map(|ty| ty.to_string()).unwrap_or_default()
+	}
+
+	const HEADERS: &str = r#"
+		typedef bit<16> etherType_t;
+		header ethernet_t { bit<48> dst; bit<48> src; etherType_t type; }
+		header ipv4_t { bit<8> ttl; bit<32> dst; }
+		struct headers_t { ethernet_t eth; ipv4_t[2] ipv4; }
+		enum bit<8> Color { Red = 1, Green = 2 }
+		enum Mode { On, Off }
+	"#;
+
+	#[test]
+	fn widths_and_implicit_casts() {
+		let input = format!(
+			"{HEADERS}{}",
+			r#"
+			control C(inout headers_t hdr) {
+				apply {
+					bit<8> small = 1;
+					bit<16> large = hdr.eth.type + 1;
+					small = large;
+					small = hdr.ipv4[0].ttl |+| small;
+					large = small ++ small;
+					int<8> signed = -1;
+					large = large + small;
+					small = (bit<8>) large;
+					signed = (int<8>) large;
+					bool b = hdr.eth == 1;
+					b = hdr.eth.isValid() && hdr.ipv4[1].ttl > 0;
+					small = hdr.eth.src[7:0];
+					bit<8> c = Color.Red;
+				}
+			}
+			"#
+		);
+
+		assert_eq!(
+			errors(&input),
+			vec![
+				("large", "Type mismatch: expected `bit<8>`, found `bit<16>`".to_string()),
+				("large + small", "Operator `+` cannot be applied to `bit<16>` and `bit<8>`".to_string()),
+				("(int<8>) large", "Cannot cast `bit<16>` to `int<8>`".to_string()),
+				("hdr.eth == 1", "Operator `==` cannot be applied to `ethernet_t` and `int`".to_string()),
+			]
+		);
+		assert_eq!(type_at(&input, "1;\n\t\t\t\t\tsmall = large"), "bit<16>");
+		assert_eq!(type_at(&input, "small ++"), "bit<8>");
+		assert_eq!(type_at(&input, "small ++ small;"), "bit<8>");
+		assert_eq!(type_at(&input, "ttl |+|"), "bit<8>");
+		assert_eq!(type_at(&input, "[7:0]"), "bit<8>");
+		assert_eq!(type_at(&input, "type + 1"), "bit<16>");
+		assert_eq!(type_at(&input, "Red;"), "Color");
+	}
+
+	#[test]
+	fn declarations() {
+		let input = format!(
+			"{HEADERS}{}",
+			r#"
+			const bit<4> four = 4;
+			const bool yes = four;
+			extern void log<T>(in T value);
+			extern Checksum { Checksum(); bit<16> get(); void add<T>(in T data); }
+			const tuple<bit<8>, bool> pair = { 1, true };
+			const headers_t init = { { 1, 2, 3 }, { { 1, 2 }, { 3, 4 } } };
+			action a(bit<8> x) { return x; }
+			bit<8> f(in bit<8> x) { return; }
+			parser P(packet_in pkt, out headers_t hdr) {
+				Checksum() ck;
+				state start {
+					log(hdr.eth.type);
+					bit<8> sum = ck.get();
+					ck.add(hdr.eth, 1);
+					transition select(hdr.eth.type, Mode.On) {
+						(0x800, Mode.On): accept;
+						(0x86dd, 1): reject;
+						0x806: reject;
+						default: accept;
+					}
+				}
+			}
+			"#
+		);
+
+		assert_eq!(
+			errors(&input),
+			vec![
+				("four", "Type mismatch: expected `bool`, found `bit<4>`".to_string()),
+				("x", "A value cannot be returned here".to_string()),
+				("return;", "Expected a return value of type `bit<8>`".to_string()),
+				("ck.get()", "Type mismatch: expected `bit<8>`, found `bit<16>`".to_string()),
+				("ck.add(hdr.eth, 1);", "Expected 1 arguments, found 2".to_string()),
+				("1", "Type mismatch: expected `Mode`, found `int`".to_string()),
+				("0x806", "Expected 2 values in the keyset, found 1".to_string()),
+			]
+		);
+
+		let (table, info) = check(&input);
+		let pair = table.lookup(SymbolTable::ROOT, "pair", crate::symbols::Namespace::Value).unwrap();
+		assert_eq!(info.symbol_type(pair).unwrap().to_string(), "tuple<bit<8>, bool>");
+		let hdr = table.resolve_at(input.find("hdr.eth.type, Mode").unwrap()).unwrap();
+		assert_eq!(info.symbol_type(hdr).unwrap().to_string(), "headers_t");
+		let fields = table.lookup(SymbolTable::ROOT, "headers_t", crate::symbols::Namespace::Value).unwrap();
+		let ipv4 = table.members(fields).nth(1).unwrap();
+		assert_eq!(info.symbol_type(ipv4).unwrap().to_string(), "ipv4_t[2]");
+	}
+
+	#[test]
+	fn constants() {
+		let input = r#"
+			const bit<8> W = 4 + 4;
+			const int<8> NEG = -((int<8>) W);
+			const bit<8> WRAP = 200 + 100;
+			const bit<8> SAT = 8w200 |+| 100;
+			const bit<4> HIGH = (W << 2)[7:4];
+			const bool B = W > 7 && !(W == 9);
+			header h_t { bit<(W)> f; bit<(W + W)> g; varbit<16> v; }
+			enum bit<16> Kind { A = 0x800, B = (bit<16>) W * 2 }
+			const bit<16> K = Kind.B;
+			const int TUPLE_SIZE = h_t.minSizeInBits() + h_t.maxSizeInBytes();
+			const bit<8> DIV = W / 0;
+			const int SHIFTED = 1 << 0xffffffff;
+			const bit<6> SLICED = TUPLE_SIZE[4294967295:4294967290];
+			const bit<0xFFFFFFFF> HUGE = 1;
+			const bit<8> HUGE_LITERAL = 4294967295w1;
+			struct s_t { h_t[W - 6] stack; }
+			control C(inout s_t s, in bit<8> x) {
+				const bit<8> local = x;
+				table t { key = { x: exact; } actions = {} size = x; }
+				apply {
+					s.stack[0].f = s.stack[0].f[8:1] ++ s.stack[0].g[3:0];
+					bit<32> n = s.stack.size;
+				}
+			}
+		"#;
+
+		let (table, info) = check(input);
+		let value = |name: &str| {
+			let symbol = table.lookup(SymbolTable::ROOT, name, crate::symbols::Namespace::Value).unwrap();
+			info.symbol_value(symbol).map(|value| value.to_string()).unwrap_or_default()
+		};
+		assert_eq!(
+			["W", "NEG", "WRAP", "SAT", "HIGH", "B", "K", "TUPLE_SIZE", "DIV", "HUGE"].map(value),
+			["8", "-8", "44", "255", "2", "true", "Kind.B (16)", "29", "", "1"]
+		);
+
+		let h_t = table.lookup(SymbolTable::ROOT, "h_t", crate::symbols::Namespace::Value).unwrap();
+		let fields: Vec<_> = table.members(h_t).map(|id| info.symbol_type(id).unwrap().to_string()).collect();
+		assert_eq!(fields, ["bit<8>", "bit<16>", "varbit<16>"]);
+		assert_eq!(type_at(input, "stack; }"), "");
+		assert_eq!(info.value_at(input.find("size;").unwrap()).unwrap().to_string(), "2");
+
+		assert_eq!(
+			errors(input),
+			vec![
+				("W / 0", "Division by zero".to_string()),
+				("W / 0", "Not a compile-time constant".to_string()),
+				("1 << 0xffffffff", "An `int` cannot be shifted left by more than 4096 bits".to_string()),
+				("1 << 0xffffffff", "Not a compile-time constant".to_string()),
+				("[4294967295:4294967290]", "Invalid slice `[4294967295:4294967290]` of `int`".to_string()),
+				("bit<0xFFFFFFFF>", "A type cannot be wider than 4096 bits".to_string()),
+				("4294967295w1", "An integer literal cannot be wider than 4096 bits".to_string()),
+				("x", "Not a compile-time constant".to_string()),
+				("x", "Not a compile-time constant".to_string()),
+				("[8:1]", "Invalid slice `[8:1]` of `bit<8>`".to_string()),
+			]
+		);
+	}
+
+	#[test]
+	fn tables() {
+		let input = format!(
+			"{HEADERS}{}",
+			r#"
+			match_kind { exact, ternary }
+			const bit<8> PORT = 1;
+			action drop() {}
+			action forward(in bit<8> port, bit<16> mark) {}
+			action unused() {}
+			control C(inout headers_t hdr) {
+				table t {
+					key = { hdr.eth.type: exact; hdr.eth.dst: PORT; hdr.eth.src: ternary; }
+					actions = { drop; forward(PORT); PORT; }
+					const entries = {
+						(0x800, 1, _): forward(PORT, 7);
+						(0x806, 1): drop();
+						(0x86dd, 1, 2 &&& 3): unused();
+						(1, 1, 1): forward(2, 0);
+						(2, 1, 1): forward(1, 0);
+					}
+					default_action = forward(PORT);
+					size = 0;
+				}
+				table u { key = { hdr.eth.type: exact; } actions = { forward; } default_action = drop(); }
+				apply {
+					switch (t.apply().action_run) {
+						drop: {}
+						unused: {}
+						default: {}
+					}
+				}
+			}
+			"#
+		);
+
+		assert_eq!(
+			errors(&input),
+			vec![
+				("PORT", "`PORT` is not a match kind".to_string()),
+				("PORT", "`PORT` is not an action".to_string()),
+				("(0x806, 1)", "Expected 3 values in the keyset, found 2".to_string()),
+				("unused()", "`unused` is not one of the actions of table `t`".to_string()),
+				("2", "Expected `PORT`, as bound in the actions of the table".to_string()),
+				("(PORT)", "Expected 2 arguments, found 1".to_string()),
+				("0", "The size of a table must be positive".to_string()),
+				("forward", "Expected 1 arguments, found 0".to_string()),
+				("drop()", "`drop` is not one of the actions of table `u`".to_string()),
+				("unused", "Expected one of the actions of table `t`".to_string()),
+			]
+		);
+	}
+
+	#[test]
+	fn externs() {
+		let input = format!(
+			"{HEADERS}{}",
+			r#"
+			extern packet_in { void extract<T>(out T hdr); void extract<T>(out T hdr, in bit<32> size); }
+			extern packet_out { void emit<T>(in T hdr); }
+			extern register<T> { register(bit<32> size); void read(out T result, in bit<32> index); }
+			extern Checksum16 { Checksum16(); bit<16> get<D>(in D data); }
+			extern Hash { Hash(bit<8> algo); Hash(bit<8> algo, bit<8> seed = 0); Hash(bool fast, bit<8> algo); }
+			parser P(packet_in pkt, out headers_t hdr, packet_out out_pkt) {
+				register<bit<8>, bit<8>>(16) twice;
+				state start {
+					pkt.extract(hdr.eth);
+					pkt.extract(hdr.eth.type);
+					pkt.extract<ethernet_t>(hdr.eth, 32, 1);
+					out_pkt.emit(hdr.eth);
+					transition accept;
+				}
+			}
+			control C(inout headers_t hdr, packet_in pkt) {
+				register<bit<16>>(true) r;
+				register<bit<16>>() r2;
+				Checksum16() ck;
+				Hash(fast = true, algo = 1) h;
+				Hash(1) h2;
+				Hash(seed = 1) h3;
+				apply {
+					bit<8> x;
+					r.read(x, 0);
+					bit<16> sum = ck.get(hdr.eth);
+					bit<8> small = ck.get<ethernet_t, bool>(hdr.eth);
+					pkt.extract(hdr.eth);
+				}
+			}
+			"#
+		);
+
+		assert_eq!(
+			errors(&input),
+			vec![
+				("register<bit<8>, bit<8>>", "Expected 1 type arguments, found 2".to_string()),
+				("hdr.eth.type", "`extract` expects a header, found `bit<16>`".to_string()),
+				(
+					"pkt.extract<ethernet_t>(hdr.eth, 32, 1);",
+					"No overload of `extract` matches 3 arguments, candidates: `extract(out T hdr)`, `extract(out T \
+					 hdr, in bit<32> size)`"
+						.to_string(),
+				),
+				("out_pkt.emit(hdr.eth);", "`emit` cannot be called in a parser".to_string()),
+				("true", "Type mismatch: expected `bit<32>`, found `bool`".to_string()),
+				("register<bit<16>>() r2;", "Expected 1 arguments, found 0".to_string()),
+				(
+					"Hash(1) h2;",
+					"The call of `Hash` is ambiguous, candidates: `Hash(bit<8> algo)`, `Hash(bit<8> algo, bit<8> seed)`"
+						.to_string(),
+				),
+				(
+					"Hash(seed = 1) h3;",
+					"No constructor of `Hash` matches 1 arguments, candidates: `Hash(bit<8> algo)`, `Hash(bit<8> algo, \
+					 bit<8> seed)`, `Hash(bool fast, bit<8> algo)`"
+						.to_string(),
+				),
+				("x", "Type mismatch: expected `bit<16>`, found `bit<8>`".to_string()),
+				("<ethernet_t, bool>(hdr.eth)", "Expected 1 type arguments, found 2".to_string()),
+				("ck.get<ethernet_t, bool>(hdr.eth)", "Type mismatch: expected `bit<8>`, found `bit<16>`".to_string()),
+				("pkt.extract(hdr.eth);", "`extract` can only be called in a parser".to_string()),
+			]
+		);
+	}
+
+	#[test]
+	fn generics() {
+		let input = format!(
+			"{HEADERS}{}",
+			r#"
+			extern register<T> { register(bit<32> size); void read(out T result, in bit<32> index); }
+			extern Counter<T> { Counter(bit<32> size, T initial); T get(in bit<32> index); }
+			extern void log<T>(in T value);
+			extern T any<T>();
+			extern bit<16> hash<D>(in D data, in D salt);
+			extern void swap<T>(inout T[2] stack);
+			control C(inout headers_t hdr) {
+				register<bit<32>>(16) r;
+				Counter(16, 8w0) c;
+				Counter(16, 0) d;
+				apply {
+					bit<32> x;
+					r.read(x, 0);
+					bit<8> y = c.get(1);
+					log(hdr.eth);
+					log(1);
+					bit<8> z = any();
+					bit<8> w = any<bit<8>>();
+					any();
+					hash(hdr.eth.src, hdr.eth.type);
+					swap(hdr.ipv4);
+				}
+			}
+			"#
+		);
+
+		assert_eq!(type_at(&input, "c.get"), "Counter<bit<8>>");
+		assert_eq!(type_at(&input, "get(1)"), "get<bit<8>>");
+		let (_, info) = check(&input);
+		let signature = |needle: &str| info.signature_at(input.find(needle).unwrap()).map(|s| s.to_string());
+		assert_eq!(
+			signature("read(x"),
+			Some("register<bit<32>>.read(out bit<32> result, in bit<32> index)".to_string())
+		);
+		assert_eq!(signature("get(1)"), Some("bit<8> Counter<bit<8>>.get(in bit<32> index)".to_string()));
+		assert_eq!(signature("log(hdr"), Some("log(in ethernet_t value)".to_string()));
+		assert_eq!(signature("swap("), Some("swap(inout ipv4_t[2] stack)".to_string()));
+		assert_eq!(
+			errors(&input),
+			vec![
+				("0", "Cannot infer `T` from an integer without a width".to_string()),
+				("1", "Cannot infer `T` from an integer without a width".to_string()),
+				("any();", "Cannot infer the type argument `T` of `any`".to_string()),
+				("hdr.eth.type", "Type mismatch: expected `bit<48>`, found `bit<16>`".to_string()),
+			]
+		);
+	}
+
+	#[test]
+	fn overloads() {
+		let input = format!(
+			"{HEADERS}{}",
+			r#"
+			extern void f(in bit<8> x);
+			extern void f(in bit<8> x, in bit<8> y);
+			extern void g(in bit<8> x, in bit<8> y = 1);
+			extern void g(in bit<8> x, in bit<8> y, @optional in bit<8> z, in bit<8> w = 2);
+			extern void h(in bit<8> a, in bit<8> b);
+			control C() {
+				apply {
+					f(1);
+					f(y = 2, x = 1);
+					f(x = 1, z = 3);
+					g(1, 2, 3);
+					g(1, 2);
+					h(b = 1, c = 2);
+					h(1, a = 2);
+				}
+			}
+			"#
+		);
+
+		assert_eq!(
+			errors(&input),
+			vec![
+				(
+					"f(x = 1, z = 3);",
+					"No overload of `f` matches 2 arguments, candidates: `f(in bit<8> x)`, `f(in bit<8> x, in bit<8> \
+					 y)`"
+					.to_string(),
+				),
+				(
+					"g(1, 2);",
+					"The call of `g` is ambiguous, candidates: `g(in bit<8> x, in bit<8> y)`, `g(in bit<8> x, in \
+					 bit<8> y, in bit<8> z, in bit<8> w)`"
+						.to_string(),
+				),
+				("c", "No parameter named `c`".to_string()),
+				("h(b = 1, c = 2);", "Missing an argument for `a`".to_string()),
+				("a = 2", "`a` is given more than once".to_string()),
+				("h(1, a = 2);", "Missing an argument for `b`".to_string()),
+			]
+		);
+
+		// the overload chosen for the call is recorded, e.g. for going to its definition
+		let (table, info) = check(&input);
+		let callee = |needle: &str| info.callee_at(input.find(needle).unwrap()).map(|id| table.symbol(id).arity);
+		assert_eq!(callee("f(1)"), Some(Some(1)));
+		assert_eq!(callee("f(y = 2"), Some(Some(2)));
+		assert_eq!(callee("g(1, 2, 3)"), Some(Some(4)));
+		assert_eq!(callee("g(1, 2)"), None);
+	}
+
+	#[test]
+	fn header_stacks() {
+		let input = format!(
+			"{HEADERS}{}",
+			r#"
+			extern packet_in { void extract<T>(out T hdr); }
+			parser P(packet_in pkt, out headers_t hdr) {
+				state start {
+					pkt.extract(hdr.ipv4.next);
+					bit<32> i = hdr.ipv4.lastIndex;
+					hdr.ipv4[2].ttl = 1;
+					transition accept;
+				}
+			}
+			control C(inout headers_t hdr, in bit<8> n) {
+				apply {
+					hdr.ipv4[1].ttl = hdr.ipv4.last.ttl;
+					hdr.ipv4[-1].ttl = 0;
+					hdr.ipv4.push_front(1);
+					hdr.ipv4.pop_front(n);
+					hdr.ipv4.pop_front();
+				}
+			}
+			"#
+		);
+
+		assert_eq!(
+			errors(&input),
+			vec![
+				("[2]", "Index 2 is out of bounds of `ipv4_t[2]`".to_string()),
+				("last", "`last` of a header stack can only be used in a parser".to_string()),
+				("[-1]", "Index -1 is out of bounds of `ipv4_t[2]`".to_string()),
+				("n", "Type mismatch: expected `int`, found `bit<8>`".to_string()),
+				("n", "Not a compile-time constant".to_string()),
+				("hdr.ipv4.pop_front();", "Expected 1 arguments, found 0".to_string()),
+			]
+		);
+	}
+}
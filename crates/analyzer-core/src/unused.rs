@@ -0,0 +1,199 @@
+//! Unused declarations and unreachable code, which editors grey out.
+//!
+//! A declaration is unused if no name refers to it: actions that are neither called nor listed by a table, tables
+//! that are never applied, variables, constants, instances and typedefs, and the parameters of actions and functions.
+//! The parameters of parsers and controls are left out, since they are dictated by the architecture, and so are the
+//! directionless parameters of the actions a table lists, since they are given by the control plane. The statements
+//! after a `return` or `exit`, and the parser states that can't be reached from `start`, are unreachable.
+
+use std::ops::Range;
+
+use crate::{
+	ast::{self, Statement, SyntaxNode},
+	state_graph::{Problem, StateGraph},
+	symbols::{SymbolKind, SymbolTable},
+	syntax::AstNode,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unused {
+	pub range: Range<usize>,
+	pub message: String,
+}
+
+/// Finds the unused declarations and unreachable code of a program, in the order of the program.
+pub fn check(program: &ast::Program, table: &SymbolTable, graphs: &[StateGraph]) -> Vec<Unused> {
+	let root = program.syntax();
+	let mut unused = vec![];
+	// the directionless parameters of the actions a table lists are given by the control plane
+	let listed: Vec<_> = root
+		.descendants()
+		.filter_map(ast::ActionRef::cast)
+		.filter_map(|action| table.resolve(&action.name()?))
+		.filter_map(|action| table.symbol(action).decl.to_node(root))
+		.collect();
+
+	for (id, symbol) in table.symbols() {
+		if table.references_to(id).next().is_some() {
+			continue;
+		}
+		let what = match symbol.kind {
+			SymbolKind::Action => "Action",
+			SymbolKind::Table => {
+				let message = format!("Table `{}` is never applied", symbol.name);
+				unused.push(Unused { range: symbol.name_range.clone(), message });
+				continue;
+			}
+			SymbolKind::Variable => "Variable",
+			SymbolKind::Constant => "Constant",
+			SymbolKind::Typedef | SymbolKind::NewType => "Type",
+			// `main` and the other instances at the top level are used by the target
+			SymbolKind::Instance if table.scope(symbol.scope).parent.is_some() => "Instance",
+			SymbolKind::Parameter => {
+				let param = symbol.decl.to_node(root);
+				if !has_body(param.clone()) || control_plane(param, &listed) {
+					continue;
+				}
+				"Parameter"
+			}
+			_ => continue,
+		};
+		let message = format!("{what} `{}` is never used", symbol.name);
+		unused.push(Unused { range: symbol.name_range.clone(), message });
+	}
+
+	for graph in graphs {
+		for problem in graph.problems() {
+			if let (Problem::Unreachable(_), Some(range)) = (&problem, graph.problem_range(&problem)) {
+				unused.push(Unused { range, message: graph.problem_message(&problem) });
+			}
+		}
+	}
+
+	let mut unreachable: Vec<Range<usize>> = vec![];
+	for block in root.descendants().filter_map(ast::BlockStatement::cast) {
+		let range = block.syntax().text_range();
+		if unreachable.iter().any(|outer| outer.start <= range.start && range.end <= outer.end) {
+			continue;
+		}
+		let statements: Vec<_> = block.statements().collect();
+		let Some(end) = statements.iter().position(terminates) else { continue };
+		if let (Some(first), Some(last)) = (statements.get(end + 1), statements.last()) {
+			unreachable.push(first.syntax().text_range().start..last.syntax().text_range().end);
+		}
+	}
+	unused.extend(unreachable.into_iter().map(|range| Unused { range, message: "Unreachable code".to_string() }));
+
+	unused.sort_by_key(|unused| unused.range.start);
+	unused
+}
+
+/// Whether a parameter belongs to an action or function, rather than to a prototype or a parser or control.
+fn has_body(param: Option<SyntaxNode>) -> bool {
+	let Some(owner) = param.and_then(|param| param.ancestors().nth(2)) else { return false };
+	ast::ActionDecl::can_cast(owner.kind())
+		|| owner.parent().map_or(false, |parent| ast::FunctionDecl::can_cast(parent.kind()))
+}
+
+/// Whether a parameter is a directionless parameter of an action listed by a table.
+fn control_plane(param: Option<SyntaxNode>, listed: &[SyntaxNode]) -> bool {
+	let Some(param) = param else { return false };
+	ast::Parameter::cast(param.clone()).map_or(false, |param| param.direction().is_none())
+		&& param.ancestors().nth(2).map_or(false, |owner| listed.contains(&owner))
+}
+
+/// Whether no statement after a statement can run, since every path through it ends with a `return` or `exit`.
+fn terminates(statement: &Statement) -> bool {
+	match statement {
+		Statement::Return(_) | Statement::Exit(_) => true,
+		Statement::Block(block) => block.statements().any(|statement| terminates(&statement)),
+		Statement::Conditional(conditional) => {
+			conditional.then_branch().map_or(false, |branch| terminates(&branch))
+				&& conditional.else_branch().map_or(false, |branch| terminates(&branch))
+		}
+		Statement::Switch(switch) => {
+			let cases: Vec<_> = switch.cases().collect();
+			// a case without a body falls through to the next one
+			cases.iter().any(|case| case.label().map_or(false, |label| label.is_default()))
+				&& cases.iter().filter_map(|case| case.body()).all(|body| body.statements().any(|s| terminates(&s)))
+		}
+		_ => false,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::p4_grammar::parse_and_check;
+	use pretty_assertions::assert_eq;
+
+	fn check(input: &str) -> Vec<(&str, String)> {
+		let (program, table, _) = parse_and_check(input);
+		let graphs = StateGraph::all(&program);
+		let unused = super::check(&program, &table, &graphs);
+		unused.into_iter().map(|unused| (&input[unused.range], unused.message)).collect()
+	}
+
+	#[test]
+	fn declarations() {
+		let input = r#"
+			typedef bit<8> byte_t;
+			typedef bit<9> port_t;
+			const port_t CPU = 64;
+			const bit<8> UNUSED = 1;
+			extern void log(in bit<8> x);
+			parser P(out port_t p) {
+				state start { p = CPU; transition accept; }
+				state orphan { transition accept; }
+			}
+			control C(inout bit<8> x, in bit<8> y) {
+				bit<8> tmp;
+				action used(in bit<8> u, bit<8> v, bit<8> w) { x = v; }
+				action called(bit<8> c) {}
+				action unused_action() {}
+				table t { key = { x: exact; } actions = { used(y); } }
+				table never { actions = { used(y); } }
+				apply { t.apply(); called(1); }
+			}
+			C() main;
+		"#;
+		assert_eq!(
+			check(input),
+			vec![
+				("byte_t", "Type `byte_t` is never used".to_string()),
+				("UNUSED", "Constant `UNUSED` is never used".to_string()),
+				("orphan", "State `orphan` is unreachable from `start`".to_string()),
+				("tmp", "Variable `tmp` is never used".to_string()),
+				("u", "Parameter `u` is never used".to_string()),
+				("c", "Parameter `c` is never used".to_string()),
+				("unused_action", "Action `unused_action` is never used".to_string()),
+				("never", "Table `never` is never applied".to_string()),
+			]
+		);
+	}
+
+	#[test]
+	fn unreachable_statements() {
+		let input = r#"
+			control C(inout bit<8> x) {
+				apply {
+					if (x == 0) {
+						return;
+						x = 1;
+					} else {
+						exit;
+					}
+					x = 2;
+					x = 3;
+				}
+			}
+		"#;
+		assert_eq!(
+			check(input),
+			vec![
+				("x = 1;", "Unreachable code".to_string()),
+				("x = 2;\n\t\t\t\t\tx = 3;", "Unreachable code".to_string()),
+			]
+		);
+	}
+}
@@ -0,0 +1,1012 @@
+//! The validity of headers along the paths through parsers, controls, actions and functions.
+//!
+//! Reading a field of an invalid header gives an unspecified value. The validity of each header is followed through
+//! the statements: `extract` and `setValid` make a header valid, `setInvalid` makes it invalid, and so does each
+//! branch of an `if` on `isValid()`. Only one member of a header union is valid at a time, so making one valid makes
+//! the others invalid. The headers of the `out` parameters of a parser and header variables start out
+//! invalid, while the validity of the other headers is unknown. The parsers and controls passed to the package of
+//! `main` are analyzed in order, each starting with the validity of the headers at the end of the previous one.
+//!
+//! Emitting an invalid header does nothing, which is how optional headers are deparsed, so an `emit` is only
+//! reported if the header is invalid on every path to it.
+//!
+//! The next index of each header stack is followed through the states of a parser too, as `extract`ing `stack.next`
+//! advances it, and an `extract` past the end of the stack on some path is reported along with that path.
+
+use std::{
+	collections::{BTreeMap, HashSet},
+	ops::Range,
+};
+
+use crate::{
+	architecture::Architecture,
+	ast::{self, BinaryOp, Direction, Expr, Statement, Suffix, SyntaxNode, UnaryOp},
+	state_graph::{Label, StateGraph},
+	symbols::{SymbolId, SymbolKind, SymbolTable},
+	syntax::AstNode,
+	types::{Type, TypeInfo},
+};
+
+/// A step of a path through a program, e.g. a transition of a parser.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+	pub range: Range<usize>,
+	pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+	pub range: Range<usize>,
+	pub message: String,
+	/// The steps of a path along which the header is invalid.
+	pub path: Vec<Step>,
+}
+
+/// Finds the reads of fields of headers that may be invalid, and the `emit`s of headers that are invalid.
+pub fn check(
+	program: &ast::Program,
+	table: &SymbolTable,
+	types: &TypeInfo,
+	architecture: Option<&Architecture>,
+) -> Vec<Warning> {
+	let mut analysis =
+		Analysis { root: program.syntax().clone(), table, types, report: true, exits: None, warnings: vec![] };
+	let mut analyzed = HashSet::new();
+
+	// the headers passed from block to block, by the type of the parameter they are passed in
+	let mut passed: Vec<(Type, String, Facts)> = vec![];
+	for block in architecture.iter().flat_map(|architecture| &architecture.blocks).filter_map(|(_, block)| *block) {
+		let decl = table.symbol(block).decl.clone();
+		let Some(node) = decl.to_node(&analysis.root) else { continue };
+		let (name, params) = match (ast::ParserDecl::cast(node.clone()), ast::ControlDecl::cast(node)) {
+			(Some(parser), _) => (parser.name(), parser.parameters()),
+			(_, Some(control)) => (control.name(), control.parameters()),
+			_ => continue,
+		};
+		if !analyzed.insert(decl) {
+			continue;
+		}
+		let name = name.map(|name| name.text()).unwrap_or_default();
+
+		let mut entry = Facts::default();
+		for param in params.iter().filter(|param| param.direction() != Some(Direction::Out)) {
+			let Some((symbol, ty)) = analysis.parameter(param) else { continue };
+			let Some((_, from, facts)) = passed.iter().find(|(passed, ..)| *passed == ty) else { continue };
+			let param_name = table.symbol(symbol).name.clone();
+			let step = Step {
+				range: table.symbol(symbol).name_range.clone(),
+				message: format!("`{param_name}` is passed on from `{from}` to `{name}`"),
+			};
+			entry.headers.extend(facts.rooted(&param_name, step).headers);
+		}
+
+		let exit = analysis.block(block, entry);
+		for param in params.iter().filter(|param| param.direction().map_or(false, |d| d != Direction::In)) {
+			let Some((symbol, ty)) = analysis.parameter(param) else { continue };
+			let facts = exit.relative(&table.symbol(symbol).name);
+			passed.retain(|(passed, ..)| *passed != ty);
+			passed.push((ty, name.clone(), facts));
+		}
+	}
+
+	for (id, symbol) in table.symbols() {
+		let analyze =
+			matches!(symbol.kind, SymbolKind::Parser | SymbolKind::Control | SymbolKind::Action | SymbolKind::Function);
+		if analyze && analyzed.insert(symbol.decl.clone()) {
+			analysis.block(id, Facts::default());
+		}
+	}
+
+	analysis.warnings
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Validity {
+	Valid,
+	Invalid,
+	/// Valid on some paths and invalid on others, or of unknown validity on some paths and invalid on others.
+	Maybe,
+}
+
+#[derive(Debug, Clone)]
+struct Fact {
+	validity: Validity,
+	/// How the header came to be invalid, unless it is valid.
+	path: Vec<Step>,
+}
+
+/// The next index of a header stack while parsing, which `extract`ing `stack.next` advances.
+#[derive(Debug, Clone)]
+struct Next {
+	/// The lowest and highest index on the paths to a point, at most the size of the stack.
+	min: u64,
+	max: u64,
+	size: u64,
+	/// The path along which the index is the highest.
+	path: Vec<Step>,
+}
+
+/// The validity of the headers at a point of a program, by the path of the header, e.g. `hdr.ipv4`. The validity of
+/// the headers that are missing is unknown, and they are not reported. The same goes for the next indices of the
+/// header stacks.
+#[derive(Debug, Clone, Default)]
+struct Facts {
+	headers: BTreeMap<String, Fact>,
+	next: BTreeMap<String, Next>,
+}
+
+impl Facts {
+	fn set(&mut self, header: String, validity: Validity, path: Vec<Step>) {
+		self.headers.insert(header, Fact { validity, path });
+	}
+
+	/// Forgets the validity of a header or of the headers in a struct, e.g. when it is passed to a method that may
+	/// change it.
+	fn forget(&mut self, prefix: &str) {
+		self.headers.retain(|header, _| !within(header, prefix));
+		self.next.retain(|stack, _| !within(stack, prefix));
+	}
+
+	/// Adds a step to the paths of the headers that may be invalid, and of the next indices.
+	fn step(&mut self, step: &Step) {
+		for fact in self.headers.values_mut().filter(|fact| fact.validity != Validity::Valid) {
+			fact.path.push(step.clone());
+		}
+		for next in self.next.values_mut() {
+			next.path.push(step.clone());
+		}
+	}
+
+	/// Whether two sets of facts give the same validities and indices, regardless of the paths.
+	fn same(&self, other: &Facts) -> bool {
+		self.headers.len() == other.headers.len()
+			&& self
+				.headers
+				.iter()
+				.all(|(header, fact)| other.headers.get(header).map_or(false, |f| f.validity == fact.validity))
+			&& self.next.len() == other.next.len()
+			&& self
+				.next
+				.iter()
+				.all(|(stack, next)| other.next.get(stack).map_or(false, |n| (n.min, n.max) == (next.min, next.max)))
+	}
+
+	/// The facts at a point reached from two others, `None` standing for a point that can't be reached.
+	fn join(a: Option<Facts>, b: Option<Facts>) -> Option<Facts> {
+		let (a, b) = match (a, b) {
+			(Some(a), Some(b)) => (a, b),
+			(a, b) => return a.or(b),
+		};
+		let mut joined = Facts::default();
+		for header in a.headers.keys().chain(b.headers.keys()) {
+			let fact = match (a.headers.get(header), b.headers.get(header)) {
+				(Some(x), Some(y)) if x.validity == y.validity => x.clone(),
+				(Some(x), Some(y)) => {
+					let invalid = if x.validity == Validity::Valid { y } else { x };
+					Fact { validity: Validity::Maybe, path: invalid.path.clone() }
+				}
+				(Some(x), None) | (None, Some(x)) if x.validity != Validity::Valid => {
+					Fact { validity: Validity::Maybe, path: x.path.clone() }
+				}
+				_ => continue,
+			};
+			joined.headers.insert(header.clone(), fact);
+		}
+		for (stack, x) in &a.next {
+			let Some(y) = b.next.get(stack) else { continue };
+			let highest = if x.max >= y.max { x } else { y };
+			let next = Next { min: x.min.min(y.min), ..highest.clone() };
+			joined.next.insert(stack.clone(), next);
+		}
+		Some(joined)
+	}
+
+	/// Raises the highest next indices that are higher than in `before` straight to the sizes of their stacks.
+	fn widen(&mut self, before: &Facts) {
+		for (stack, next) in &mut self.next {
+			if before.next.get(stack).map_or(false, |n| next.max > n.max) {
+				next.max = next.size;
+			}
+		}
+	}
+
+	/// The facts about the headers within a parameter, relative to it, e.g. `ipv4` for `hdr.ipv4`.
+	fn relative(&self, param: &str) -> Facts {
+		let prefix = format!("{param}.");
+		let headers = self
+			.headers
+			.iter()
+			.filter_map(|(header, fact)| Some((header.strip_prefix(&prefix)?.to_string(), fact.clone())));
+		Facts { headers: headers.collect(), next: BTreeMap::new() }
+	}
+
+	/// The facts relative to a parameter (see [`Facts::relative`]) about the headers within another.
+	fn rooted(&self, param: &str, step: Step) -> Facts {
+		let headers = self.headers.iter().map(|(header, fact)| (format!("{param}.{header}"), fact.clone()));
+		let mut facts = Facts { headers: headers.collect(), next: BTreeMap::new() };
+		facts.step(&step);
+		facts
+	}
+}
+
+/// The name and the suffixes of an expression that reaches a value by member accesses, e.g. `hdr.ipv4`.
+fn parts(expr: &Expr) -> Option<(Option<ast::Name>, Vec<Suffix>)> {
+	match expr {
+		Expr::Name(name) => Some((name.name(), vec![])),
+		Expr::Postfix(postfix) => match postfix.base()? {
+			Expr::Name(name) => Some((name.name(), postfix.suffixes().collect())),
+			_ => None,
+		},
+		_ => None,
+	}
+}
+
+/// Whether a header path is the path of a header or struct, or within it.
+fn within(header: &str, prefix: &str) -> bool {
+	header.strip_prefix(prefix).map_or(false, |rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+struct Analysis<'a> {
+	root: SyntaxNode,
+	table: &'a SymbolTable,
+	types: &'a TypeInfo,
+	/// Whether to report warnings, which is turned off while iterating to a fixed point over the states of a parser.
+	report: bool,
+	/// The facts at the `exit` and `return` statements of the body being analyzed.
+	exits: Option<Facts>,
+	warnings: Vec<Warning>,
+}
+
+impl Analysis<'_> {
+	fn warn(&mut self, range: Range<usize>, message: String, path: Vec<Step>) {
+		if self.report && !self.warnings.iter().any(|warning| warning.range == range && warning.message == message) {
+			self.warnings.push(Warning { range, message, path });
+		}
+	}
+
+	fn parameter(&self, param: &ast::Parameter) -> Option<(SymbolId, Type)> {
+		let symbol = self.table.declaration_at(param.name()?.text_range().start)?;
+		Some((symbol, self.types.symbol_type(symbol)?.clone()))
+	}
+
+	/// The paths of the headers in a value of a type, e.g. `hdr.eth` and `hdr.ipv4` for `hdr`.
+	fn headers(&self, ty: &Type, path: &str, headers: &mut Vec<String>) {
+		match ty {
+			Type::Header(_) => headers.push(path.to_string()),
+			Type::Struct(nominal) | Type::HeaderUnion(nominal) => {
+				for field in self.table.members(nominal.symbol) {
+					if let Some(ty) = self.types.symbol_type(field) {
+						self.headers(ty, &format!("{path}.{}", self.table.symbol(field).name), headers);
+					}
+				}
+			}
+			_ => (),
+		}
+	}
+
+	/// Sets the next index of the header stacks in a value of a type to 0, e.g. of `hdr.mpls` for `hdr`.
+	fn start_stacks(&self, ty: &Type, path: &str, facts: &mut Facts) {
+		match ty {
+			Type::Stack(_, Some(size)) => {
+				facts.next.insert(path.to_string(), Next { min: 0, max: 0, size: *size, path: vec![] });
+			}
+			Type::Struct(nominal) => {
+				for field in self.table.members(nominal.symbol) {
+					if let Some(ty) = self.types.symbol_type(field) {
+						self.start_stacks(ty, &format!("{path}.{}", self.table.symbol(field).name), facts);
+					}
+				}
+			}
+			_ => (),
+		}
+	}
+
+	/// Analyzes a parser, control, action or function, returning the facts at its end.
+	fn block(&mut self, symbol: SymbolId, mut entry: Facts) -> Facts {
+		let Some(node) = self.table.symbol(symbol).decl.to_node(&self.root) else { return entry };
+		let outer = self.exits.take();
+		let exit = if let Some(parser) = ast::ParserDecl::cast(node.clone()) {
+			let name = parser.name().map(|name| name.text()).unwrap_or_default();
+			for param in parser.parameters().iter().filter(|param| param.direction() == Some(Direction::Out)) {
+				let Some((symbol, ty)) = self.parameter(param) else { continue };
+				let declared = self.table.symbol(symbol);
+				let mut headers = vec![];
+				self.headers(&ty, &declared.name, &mut headers);
+				for header in headers {
+					let message = format!("`{header}` is invalid when parser `{name}` starts");
+					let step = Step { range: declared.name_range.clone(), message };
+					entry.set(header, Validity::Invalid, vec![step]);
+				}
+				self.start_stacks(&ty, &declared.name, &mut entry);
+			}
+			self.parser(&parser, entry)
+		} else {
+			let mut facts = Some(entry);
+			if let Some(control) = ast::ControlDecl::cast(node.clone()) {
+				for decl in control.locals() {
+					self.local(&decl, &mut facts);
+				}
+				for statement in control.apply().iter().flat_map(|apply| apply.statements()) {
+					self.statement(&statement, &mut facts);
+				}
+			} else if let Some(action) = ast::ActionDecl::cast(node.clone()) {
+				for statement in action.body().iter().flat_map(|body| body.statements()) {
+					self.statement(&statement, &mut facts);
+				}
+			} else if let Some(function) = ast::FunctionDecl::cast(node) {
+				for statement in function.body().iter().flat_map(|body| body.statements()) {
+					self.statement(&statement, &mut facts);
+				}
+			}
+			Facts::join(facts, self.exits.take())
+		};
+		self.exits = outer;
+		exit.unwrap_or_default()
+	}
+
+	/// Analyzes the states of a parser until the facts at their starts no longer change, then reports the warnings
+	/// with those facts. Returns the facts when the parser accepts.
+	fn parser(&mut self, parser: &ast::ParserDecl, mut entry: Facts) -> Option<Facts> {
+		let graph = StateGraph::new(parser);
+		for decl in parser.locals() {
+			let mut facts = Some(entry);
+			self.local(&decl, &mut facts);
+			entry = facts.unwrap_or_default();
+		}
+
+		let states: Vec<_> = graph
+			.states()
+			.map(|(_, state)| state.decl.as_ref().and_then(|decl| ast::ParserState::cast(decl.to_node(&self.root)?)))
+			.collect();
+		let mut starts: Vec<Option<Facts>> = vec![None; states.len()];
+		let mut raises = vec![0; states.len()];
+		let start = graph.start()?;
+		starts[start.0] = Some(entry);
+
+		let report = std::mem::replace(&mut self.report, false);
+		let mut worklist = vec![start];
+		while let Some(id) = worklist.pop() {
+			let Some(state) = &states[id.0] else { continue };
+			let end = self.state(state, starts[id.0].clone());
+			for edge in graph.edges_from(id) {
+				let Some(to) = edge.to.filter(|&to| to != StateGraph::REJECT) else { continue };
+				if matches!(edge.label, Label::Implicit | Label::NoMatch { .. }) {
+					continue;
+				}
+				let mut facts = end.clone();
+				if let Some(facts) = &mut facts {
+					let message = format!("`{}` transitions to `{}`", graph.state(id).name, graph.state(to).name);
+					facts.step(&Step { range: edge.range.clone(), message });
+				}
+				let mut joined = Facts::join(starts[to.0].clone(), facts);
+				// A loop that extracts `stack.next` raises the highest index by one per pass, which would take as
+				// many passes as the stack has elements, so past as many passes as there are states it is taken to
+				// reach the end of the stack.
+				if let (Some(before), Some(after)) = (&starts[to.0], &mut joined) {
+					if after.next.iter().any(|(stack, next)| before.next.get(stack).map_or(false, |n| next.max > n.max))
+					{
+						raises[to.0] += 1;
+						if raises[to.0] > states.len() {
+							after.widen(before);
+						}
+					}
+				}
+				let changed = match (&starts[to.0], &joined) {
+					(Some(before), Some(after)) => !before.same(after),
+					(before, after) => before.is_none() != after.is_none(),
+				};
+				if changed {
+					starts[to.0] = joined;
+					worklist.push(to);
+				}
+			}
+		}
+		self.report = report;
+
+		for (state, facts) in states.iter().zip(&starts) {
+			if let (Some(state), Some(_)) = (state, facts) {
+				self.state(state, facts.clone());
+			}
+		}
+		starts[StateGraph::ACCEPT.0].take()
+	}
+
+	/// Analyzes the statements and the `select` of a state.
+	fn state(&mut self, state: &ast::ParserState, mut facts: Option<Facts>) -> Option<Facts> {
+		for statement in state.statements() {
+			self.statement(&statement, &mut facts);
+		}
+		let select = state.transition().and_then(|transition| transition.select());
+		if let (Some(select), Some(current)) = (select, &facts) {
+			for expr in select.expressions() {
+				self.reads(expr.syntax(), current);
+			}
+		}
+		facts
+	}
+
+	fn local(&mut self, decl: &ast::Declaration, facts: &mut Option<Facts>) {
+		match decl {
+			ast::Declaration::Variable(variable) => self.statement(&Statement::Variable(variable.clone()), facts),
+			ast::Declaration::Constant(constant) => self.statement(&Statement::Constant(constant.clone()), facts),
+			_ => (),
+		}
+	}
+
+	fn statement(&mut self, statement: &Statement, facts: &mut Option<Facts>) {
+		let Some(current) = facts.as_mut() else { return };
+		match statement {
+			Statement::Assignment(assignment) => {
+				if let Some(value) = assignment.value() {
+					self.reads(value.syntax(), current);
+				}
+				let Some(target) = assignment.target() else { return };
+				self.lvalue_reads(&target, current);
+				let Some((target, ty)) = self.lvalue_path(&target) else { return };
+				current.forget(&target);
+				match (&ty, assignment.value()) {
+					(Type::Header(_), Some(Expr::List(_) | Expr::Struct(_))) => {
+						current.set(target, Validity::Valid, vec![]);
+					}
+					(Type::Header(_), Some(value)) => {
+						if let Some(fact) = self.expr_path(&value).and_then(|(value, _)| current.headers.get(&value)) {
+							let fact = fact.clone();
+							current.headers.insert(target, fact);
+						}
+					}
+					_ => (),
+				}
+			}
+			Statement::MethodCall(call) => self.call(call, current),
+			Statement::Conditional(conditional) => {
+				let Some(condition) = conditional.condition() else { return };
+				self.reads(condition.syntax(), current);
+				let mut then_facts = Some(current.clone());
+				let mut else_facts = Some(current.clone());
+				self.refine(&condition, true, then_facts.as_mut().unwrap(), &condition);
+				self.refine(&condition, false, else_facts.as_mut().unwrap(), &condition);
+				if let Some(branch) = conditional.then_branch() {
+					self.statement(&branch, &mut then_facts);
+				}
+				if let Some(branch) = conditional.else_branch() {
+					self.statement(&branch, &mut else_facts);
+				}
+				*facts = Facts::join(then_facts, else_facts);
+			}
+			Statement::Block(block) => {
+				for statement in block.statements() {
+					self.statement(&statement, facts);
+				}
+			}
+			Statement::Exit(_) | Statement::Return(_) => {
+				if let Statement::Return(ret) = statement {
+					if let Some(value) = ret.value() {
+						self.reads(value.syntax(), current);
+					}
+				}
+				self.exits = Facts::join(self.exits.take(), facts.take());
+			}
+			Statement::Switch(switch) => {
+				if let Some(expr) = switch.expr() {
+					self.reads(expr.syntax(), current);
+				}
+				let entry = current.clone();
+				let mut joined = None;
+				let mut has_default = false;
+				for case in switch.cases() {
+					has_default |= case.label().map_or(false, |label| label.is_default());
+					let Some(body) = case.body() else { continue };
+					let mut branch = Some(entry.clone());
+					for statement in body.statements() {
+						self.statement(&statement, &mut branch);
+					}
+					joined = Facts::join(joined, branch);
+				}
+				if !has_default {
+					joined = Facts::join(joined, Some(entry));
+				}
+				*facts = joined;
+			}
+			Statement::Variable(variable) => {
+				if let Some(value) = variable.initializer() {
+					self.reads(value.syntax(), current);
+				}
+				let Some(name) = variable.name() else { return };
+				let Some(symbol) = self.table.declaration_at(name.text_range().start) else { return };
+				let Some(ty) = self.types.symbol_type(symbol) else { return };
+				let mut headers = vec![];
+				self.headers(ty, &name.text(), &mut headers);
+				current.forget(&name.text());
+				if variable.initializer().is_none() {
+					for header in headers {
+						let message = format!("`{header}` is invalid when it is declared");
+						let step = Step { range: name.text_range(), message };
+						current.set(header, Validity::Invalid, vec![step]);
+					}
+					self.start_stacks(ty, &name.text(), current);
+				}
+			}
+			Statement::Constant(constant) => {
+				if let Some(value) = constant.value() {
+					self.reads(value.syntax(), current);
+				}
+			}
+			Statement::Instantiation(_) | Statement::Empty(_) => (),
+		}
+	}
+
+	fn call(&mut self, call: &ast::MethodCallStatement, facts: &mut Facts) {
+		let Some(callee) = call.callee() else { return };
+		let args = call.arguments();
+		for value in args.iter().filter_map(|arg| arg.value()) {
+			self.reads(value.syntax(), facts);
+		}
+		self.lvalue_reads(&callee, facts);
+
+		let suffixes: Vec<_> = callee.suffixes().collect();
+		let (receiver, method) = match &suffixes[..] {
+			[receiver @ .., Suffix::Member(method)] => (self.path(callee.name(), receiver), method.name()),
+			_ => (None, None),
+		};
+		let method = method.map(|method| method.text()).unwrap_or_default();
+		let value = args.first().and_then(|arg| arg.value());
+		let argument = value.as_ref().and_then(|value| self.expr_path(value));
+		match (receiver, method.as_str(), argument) {
+			(Some((header, Type::Header(_))), "setValid", _) => {
+				let receiver = &suffixes[..suffixes.len() - 1];
+				self.make_valid(header, callee.name(), receiver, call.text_range(), facts);
+			}
+			(Some((header, Type::Header(_))), "setInvalid", _) => {
+				let step = Step { range: call.text_range(), message: format!("`{header}` is invalidated here") };
+				facts.set(header, Validity::Invalid, vec![step]);
+			}
+			(Some((union, ty @ Type::HeaderUnion(_))), "setInvalid", _) => {
+				let mut headers = vec![];
+				self.headers(&ty, &union, &mut headers);
+				for header in headers {
+					let step = Step { range: call.text_range(), message: format!("`{header}` is invalidated here") };
+					facts.set(header, Validity::Invalid, vec![step]);
+				}
+			}
+			(Some((_, Type::Extern(packet))), "extract", Some((header, Type::Header(_))))
+				if packet.name == "packet_in" =>
+			{
+				let (name, suffixes) = value.as_ref().and_then(parts).unwrap_or_default();
+				match self.stack_next(name.clone(), &suffixes) {
+					Some(stack) => self.extract_next(stack, call.text_range(), facts),
+					None => self.make_valid(header, name, &suffixes, call.text_range(), facts),
+				}
+			}
+			(Some((stack, Type::Stack(..))), "push_front" | "pop_front", _) => facts.forget(&stack),
+			(Some((_, Type::Extern(packet))), "emit", Some((header, ty))) if packet.name == "packet_out" => {
+				let mut headers = vec![];
+				self.headers(&ty, &header, &mut headers);
+				for header in headers {
+					let Some(fact) = facts.headers.get(&header).filter(|fact| fact.validity == Validity::Invalid) else {
+						continue;
+					};
+					let message = format!("`{header}` is invalid on every path to this `emit`, so it is never emitted");
+					let path = fact.path.clone();
+					self.warn(call.text_range(), message, path);
+				}
+			}
+			_ => {
+				// the headers passed to other methods may be changed by them
+				for value in args.iter().filter_map(|arg| arg.value()) {
+					if let Some((path, _)) = self.expr_path(&value) {
+						facts.forget(&path);
+					}
+				}
+			}
+		}
+	}
+
+	/// Makes a header valid, and the other members of the header union it is in, if any, invalid.
+	fn make_valid(
+		&self,
+		header: String,
+		name: Option<ast::Name>,
+		suffixes: &[Suffix],
+		range: Range<usize>,
+		facts: &mut Facts,
+	) {
+		for sibling in self.siblings(name, suffixes) {
+			let message = format!("`{sibling}` is invalidated by making `{header}` valid");
+			facts.set(sibling, Validity::Invalid, vec![Step { range: range.clone(), message }]);
+		}
+		facts.set(header, Validity::Valid, vec![]);
+	}
+
+	/// The other members of the header union a member is reached by, e.g. `u.b` for `u.a`. Only one member of a
+	/// union is valid at a time.
+	fn siblings(&self, name: Option<ast::Name>, suffixes: &[Suffix]) -> Vec<String> {
+		let [union @ .., Suffix::Member(member)] = suffixes else { return vec![] };
+		let Some((union, Type::HeaderUnion(nominal))) = self.path(name, union) else { return vec![] };
+		let member = member.name().map(|name| name.text()).unwrap_or_default();
+		let members = self.table.members(nominal.symbol).map(|field| &self.table.symbol(field).name);
+		members.filter(|&field| *field != member).map(|field| format!("{union}.{field}")).collect()
+	}
+
+	/// The path of the header stack whose `next` element is reached by a name and suffixes, e.g. `hdr.mpls` for
+	/// `hdr.mpls.next`.
+	fn stack_next(&self, name: Option<ast::Name>, suffixes: &[Suffix]) -> Option<String> {
+		let [stack @ .., Suffix::Member(member)] = suffixes else { return None };
+		if member.name()?.text() != "next" {
+			return None;
+		}
+		match self.path(name, stack)? {
+			(stack, Type::Stack(..)) => Some(stack),
+			_ => None,
+		}
+	}
+
+	/// Advances the next index of a header stack for an `extract` of `stack.next`, which fails if the stack is full.
+	fn extract_next(&mut self, stack: String, range: Range<usize>, facts: &mut Facts) {
+		let Some(next) = facts.next.get_mut(&stack) else { return };
+		let size = next.size;
+		if next.max >= size {
+			let message = if next.min >= size {
+				format!("`{stack}.next` is past the end of `{stack}`, which has {size} elements")
+			} else {
+				format!("`{stack}.next` may be past the end of `{stack}`, which has {size} elements")
+			};
+			self.warn(range.clone(), message, next.path.clone());
+		} else {
+			let message = format!("`{stack}[{}]` is extracted here", next.max);
+			next.path.push(Step { range, message });
+		}
+		next.min = (next.min + 1).min(size);
+		next.max = (next.max + 1).min(size);
+	}
+
+	/// Narrows the facts to the branch of a condition where it is true (or false), e.g. `hdr.ipv4.isValid()`.
+	fn refine(&self, expr: &Expr, positive: bool, facts: &mut Facts, condition: &Expr) {
+		match expr {
+			Expr::Paren(paren) => {
+				if let Some(inner) = paren.expr() {
+					self.refine(&inner, positive, facts, condition);
+				}
+			}
+			Expr::Unary(unary) if unary.op() == Some(UnaryOp::Not) => {
+				if let Some(operand) = unary.operand() {
+					self.refine(&operand, !positive, facts, condition);
+				}
+			}
+			Expr::Binary(binary) => {
+				let operators = binary.operators();
+				// both operands of `a && b` are true when it is, and both of `a || b` are false when it is
+				let conjunction = operators.iter().all(|&op| op == if positive { BinaryOp::And } else { BinaryOp::Or });
+				if conjunction && !operators.is_empty() {
+					for operand in binary.operands() {
+						self.refine(&operand, positive, facts, condition);
+					}
+				}
+			}
+			Expr::Postfix(postfix) => {
+				let suffixes: Vec<_> = postfix.suffixes().collect();
+				let [receiver @ .., Suffix::Member(method), Suffix::Call(_)] = &suffixes[..] else { return };
+				if method.name().map_or(true, |name| name.text() != "isValid") {
+					return;
+				}
+				let base = match postfix.base() {
+					Some(Expr::Name(name)) => name.name(),
+					_ => None,
+				};
+				let Some((header, Type::Header(_))) = self.path(base.clone(), receiver) else { return };
+				if positive {
+					for sibling in self.siblings(base, receiver) {
+						let message = format!("`{sibling}` is invalid when `{header}.isValid()` is true");
+						let step = Step { range: condition.text_range(), message };
+						facts.set(sibling, Validity::Invalid, vec![step]);
+					}
+					facts.set(header, Validity::Valid, vec![]);
+				} else {
+					let mut path = facts.headers.get(&header).map(|fact| fact.path.clone()).unwrap_or_default();
+					let message = format!("`{header}.isValid()` is false here");
+					path.push(Step { range: condition.text_range(), message });
+					facts.set(header, Validity::Invalid, path);
+				}
+			}
+			_ => (),
+		}
+	}
+
+	/// The path and type of a header or struct reached from a name by member accesses, e.g. `hdr.ipv4`.
+	fn path(&self, name: Option<ast::Name>, suffixes: &[Suffix]) -> Option<(String, Type)> {
+		let name = name?;
+		let mut path = name.text();
+		let mut ty = self.types.symbol_type(self.table.resolve(&name)?)?.clone();
+		for suffix in suffixes {
+			let Suffix::Member(member) = suffix else { return None };
+			let member = member.name()?;
+			path = format!("{path}.{}", member.text());
+			ty = self.types.type_of(member.syntax())?.clone();
+		}
+		Some((path, ty))
+	}
+
+	fn expr_path(&self, expr: &Expr) -> Option<(String, Type)> {
+		let (name, suffixes) = parts(expr)?;
+		self.path(name, &suffixes)
+	}
+
+	fn lvalue_path(&self, lvalue: &ast::LValue) -> Option<(String, Type)> {
+		self.path(lvalue.name(), &lvalue.suffixes().collect::<Vec<_>>())
+	}
+
+	/// Checks the expressions in the indices and slices of an l-value, which are read.
+	fn lvalue_reads(&mut self, lvalue: &ast::LValue, facts: &Facts) {
+		for suffix in lvalue.suffixes() {
+			if matches!(suffix, Suffix::Index(_) | Suffix::Slice(_)) {
+				self.reads(suffix.syntax(), facts);
+			}
+		}
+	}
+
+	/// Checks the fields of headers read by an expression, e.g. `hdr.ipv4.ttl`.
+	fn reads(&mut self, node: &SyntaxNode, facts: &Facts) {
+		for postfix in node.descendants().filter_map(ast::PostfixExpr::cast) {
+			let Some(Expr::Name(base)) = postfix.base() else { continue };
+			let suffixes: Vec<_> = postfix.suffixes().collect();
+			for (i, suffix) in suffixes.iter().enumerate() {
+				let Suffix::Member(field) = suffix else { break };
+				// a method like `isValid` rather than a field
+				if matches!(suffixes.get(i + 1), Some(Suffix::Call(_))) {
+					break;
+				}
+				let Some((header, Type::Header(_))) = self.path(base.name(), &suffixes[..i]) else { continue };
+				let Some(fact) = facts.headers.get(&header).filter(|fact| fact.validity != Validity::Valid) else {
+					continue;
+				};
+				let field = field.name().map(|name| name.text()).unwrap_or_default();
+				let message = match fact.validity {
+					Validity::Invalid => format!("`{header}` is invalid when `{field}` is read"),
+					_ => format!("`{header}` may be invalid when `{field}` is read"),
+				};
+				let range = postfix.text_range().start..suffix.text_range().end;
+				let path = fact.path.clone();
+				self.warn(range, message, path);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::p4_grammar::parse_and_check;
+	use pretty_assertions::assert_eq;
+
+	const HEADERS: &str = r#"
+		extern packet_in { void extract<T>(out T hdr); }
+		extern packet_out { void emit<T>(in T hdr); }
+		header ethernet_t { bit<16> type; }
+		header ipv4_t { bit<8> ttl; }
+		struct headers_t { ethernet_t eth; ipv4_t ipv4; }
+	"#;
+
+	fn check(input: &str) -> Vec<(&str, String, Vec<String>)> {
+		let (program, table, types) = parse_and_check(input);
+		let architecture = Architecture::new(&program, &table, &types);
+		let warnings = super::check(&program, &table, &types, architecture.as_ref());
+		warnings
+			.into_iter()
+			.map(|warning| {
+				let path = warning.path.into_iter().map(|step| step.message).collect();
+				(&input[warning.range], warning.message, path)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn parsers() {
+		let input = format!(
+			"{HEADERS}{}",
+			r#"
+			parser P(packet_in pkt, out headers_t hdr) {
+				state start {
+					pkt.extract(hdr.eth);
+					transition select(hdr.eth.type) { 0x800: parse_ipv4; default: skip; }
+				}
+				state parse_ipv4 { pkt.extract(hdr.ipv4); transition skip; }
+				state skip {
+					transition select(hdr.ipv4.ttl) { 0: accept; default: reject; }
+				}
+			}
+			"#
+		);
+		assert_eq!(
+			check(&input),
+			vec![(
+				"hdr.ipv4.ttl",
+				"`hdr.ipv4` may be invalid when `ttl` is read".to_string(),
+				vec![
+					"`hdr.ipv4` is invalid when parser `P` starts".to_string(),
+					"`start` transitions to `skip`".to_string()
+				]
+			)]
+		);
+	}
+
+	#[test]
+	fn controls() {
+		let input = format!(
+			"{HEADERS}{}",
+			r#"
+			control C(inout headers_t hdr, packet_out pkt) {
+				apply {
+					bit<8> ttl = hdr.ipv4.ttl;
+					if (!hdr.ipv4.isValid()) {
+						ttl = hdr.ipv4.ttl;
+						exit;
+					}
+					ttl = hdr.ipv4.ttl;
+					if (ttl == 0) {
+						hdr.eth.setInvalid();
+					}
+					ttl = (bit<8>) hdr.eth.type;
+					if (hdr.eth.isValid() && ttl == 1) {
+						ttl = (bit<8>) hdr.eth.type;
+					} else {
+						pkt.emit(hdr.eth);
+					}
+					ethernet_t local;
+					pkt.emit(local);
+				}
+			}
+			"#
+		);
+		assert_eq!(
+			check(&input),
+			vec![
+				(
+					"hdr.ipv4.ttl",
+					"`hdr.ipv4` is invalid when `ttl` is read".to_string(),
+					vec!["`hdr.ipv4.isValid()` is false here".to_string()]
+				),
+				(
+					"hdr.eth.type",
+					"`hdr.eth` may be invalid when `type` is read".to_string(),
+					vec!["`hdr.eth` is invalidated here".to_string()]
+				),
+				(
+					"pkt.emit(local);",
+					"`local` is invalid on every path to this `emit`, so it is never emitted".to_string(),
+					vec!["`local` is invalid when it is declared".to_string()]
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn pipelines() {
+		let input = format!(
+			"{HEADERS}{}",
+			r#"
+			parser Parser<H>(packet_in pkt, out H hdr);
+			control Ingress<H>(inout H hdr);
+			package Switch<H>(Parser<H> p, Ingress<H> ig);
+			parser P(packet_in pkt, out headers_t hdr) {
+				state start { pkt.extract(hdr.eth); transition accept; }
+			}
+			control I(inout headers_t hdr) {
+				apply { hdr.eth.type = (bit<16>) hdr.ipv4.ttl + hdr.eth.type; }
+			}
+			Switch(P(), I()) main;
+			"#
+		);
+		assert_eq!(
+			check(&input),
+			vec![(
+				"hdr.ipv4.ttl",
+				"`hdr.ipv4` is invalid when `ttl` is read".to_string(),
+				vec![
+					"`hdr.ipv4` is invalid when parser `P` starts".to_string(),
+					"`start` transitions to `accept`".to_string(),
+					"`hdr` is passed on from `P` to `I`".to_string(),
+				]
+			)]
+		);
+	}
+
+	#[test]
+	fn header_stacks() {
+		let input = format!(
+			"{HEADERS}{}",
+			r#"
+			header mpls_t { bit<1> bos; }
+			struct stack_t { mpls_t[2] mpls; }
+			parser Loop(packet_in pkt, out stack_t hdr) {
+				state start {
+					pkt.extract(hdr.mpls.next);
+					transition select(hdr.mpls.last.bos) { 0: start; default: accept; }
+				}
+			}
+			parser Straight(packet_in pkt, out stack_t hdr) {
+				state start { pkt.extract(hdr.mpls.next); pkt.extract(hdr.mpls.next); transition third; }
+				state third { pkt.extract(hdr.mpls.next); transition accept; }
+			}
+			"#
+		);
+		assert_eq!(
+			check(&input),
+			vec![
+				(
+					"pkt.extract(hdr.mpls.next);",
+					"`hdr.mpls.next` may be past the end of `hdr.mpls`, which has 2 elements".to_string(),
+					vec![
+						"`hdr.mpls[0]` is extracted here".to_string(),
+						"`start` transitions to `start`".to_string(),
+						"`hdr.mpls[1]` is extracted here".to_string(),
+						"`start` transitions to `start`".to_string(),
+					]
+				),
+				(
+					"pkt.extract(hdr.mpls.next);",
+					"`hdr.mpls.next` is past the end of `hdr.mpls`, which has 2 elements".to_string(),
+					vec![
+						"`hdr.mpls[0]` is extracted here".to_string(),
+						"`hdr.mpls[1]` is extracted here".to_string(),
+						"`start` transitions to `third`".to_string(),
+					]
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn long_header_stacks() {
+		let input = format!(
+			"{HEADERS}{}",
+			r#"
+			header vlan_t { bit<16> type; }
+			struct stack_t { vlan_t[4000000000] vlan; }
+			parser P(packet_in pkt, out stack_t hdr) {
+				state start { transition parse_vlan; }
+				state parse_vlan {
+					pkt.extract(hdr.vlan.next);
+					transition select(hdr.vlan.last.type) { 0x8100: parse_vlan; default: accept; }
+				}
+			}
+			"#
+		);
+		let warnings = check(&input);
+		assert_eq!(warnings.len(), 1);
+		assert_eq!(warnings[0].1, "`hdr.vlan.next` may be past the end of `hdr.vlan`, which has 4000000000 elements");
+	}
+
+	#[test]
+	fn header_unions() {
+		let input = format!(
+			"{HEADERS}{}",
+			r#"
+			header_union ip_t { ethernet_t eth; ipv4_t ipv4; }
+			struct union_t { ip_t ip; }
+			control C(inout union_t hdr) {
+				apply {
+					hdr.ip.eth.setValid();
+					bit<8> ttl = hdr.ip.ipv4.ttl;
+					if (hdr.ip.ipv4.isValid()) {
+						ttl = (bit<8>) hdr.ip.eth.type;
+					}
+				}
+			}
+			"#
+		);
+		assert_eq!(
+			check(&input),
+			vec![
+				(
+					"hdr.ip.ipv4.ttl",
+					"`hdr.ip.ipv4` is invalid when `ttl` is read".to_string(),
+					vec!["`hdr.ip.ipv4` is invalidated by making `hdr.ip.eth` valid".to_string()]
+				),
+				(
+					"hdr.ip.eth.type",
+					"`hdr.ip.eth` is invalid when `type` is read".to_string(),
+					vec!["`hdr.ip.eth` is invalid when `hdr.ip.ipv4.isValid()` is true".to_string()]
+				),
+			]
+		);
+	}
+}
@@ -0,0 +1,257 @@
+extern crate analyzer_core;
+
+use analyzer_core::*;
+use cancellation::CancellationTokenSource;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn parse_errors() {
+	let mut analyzer = Analyzer::new(|_, path| Ok(path.into()), |_| {});
+	let main = analyzer.file_id("main.p4");
+	let defs = analyzer.file_id("defs.p4");
+	analyzer.update(defs, "header h_t { bit<8> f; }\nstruct s_t { h_t h }\n".to_string());
+	analyzer.update(main, "#include \"defs.p4\"\nconst bit<8> x = ;\n".to_string());
+
+	let parsed = analyzer.parsed(main).unwrap();
+	let kinds: Vec<_> = parsed.syntax().descendants().map(|node| node.kind()).collect();
+	assert!(kinds.contains(&"headerTypeDeclaration"));
+
+	let diagnostics: Vec<_> = analyzer
+		.diagnostics(main)
+		.into_iter()
+		.map(|diagnostic| (analyzer.path(diagnostic.file), diagnostic.location, diagnostic.message))
+		.collect();
+	assert_eq!(
+		diagnostics,
+		vec![
+			("defs.p4".to_string(), 25..45, "Syntax error: unexpected `struct`".to_string()),
+			("main.p4".to_string(), 19..35, "Syntax error: unexpected `const`".to_string()),
+		]
+	);
+}
+
+#[test]
+fn reparsing() {
+	let mut analyzer = Analyzer::new(|_, path| Ok(path.into()), |_| {});
+	let main = analyzer.file_id("main.p4");
+	let defs = analyzer.file_id("defs.p4");
+	analyzer.update(defs, "header h_t { bit<8> f; }\n".to_string());
+	analyzer.update(main, "#include \"defs.p4\"\nconst bit<8> x = 1;\nconst bit<8> y = 2;\n".to_string());
+	assert!(analyzer.reparse(main, None));
+
+	// the file including the edited one is reparsed along with it, reusing the earlier match of its own tokens
+	analyzer.update(defs, "header h_t { bit<16> f; }\n".to_string());
+	assert!(analyzer.reparse(defs, None));
+	assert!(analyzer.parse_statistics(main).unwrap().reused > 0);
+	let tree = analyzer.parsed(main).unwrap().syntax().debug_tree();
+
+	let mut fresh = Analyzer::new(|_, path| Ok(path.into()), |_| {});
+	fresh.update(fresh.file_id("defs.p4"), analyzer.input(defs).unwrap().to_string());
+	fresh.update(fresh.file_id("main.p4"), analyzer.input(main).unwrap().to_string());
+	assert_eq!(fresh.parsed(fresh.file_id("main.p4")).unwrap().syntax().debug_tree(), tree);
+
+	// a file whose reparse was cancelled is parsed from scratch instead
+	let source = CancellationTokenSource::new();
+	source.cancel();
+	analyzer.update(main, "#include \"defs.p4\"\nconst bit<8> z = 3;\n".to_string());
+	assert!(!analyzer.reparse(main, Some(source.token().clone())));
+	assert!(analyzer.parsed(main).unwrap().syntax().to_string().contains("const bit<8> z = 3;"));
+
+	assert!(analyzer.reparse(main, None));
+	assert!(analyzer.parsed(main).unwrap().syntax().to_string().contains("const bit<8> z = 3;"));
+}
+
+#[test]
+fn edits() {
+	let mut analyzer = Analyzer::new(|_, path| Ok(path.into()), |_| {});
+	let main = analyzer.file_id("main.p4");
+	let defs = analyzer.file_id("defs.p4");
+	analyzer.update(defs, "const bit<8> a = 1;\n".to_string());
+	analyzer.update(main, "#include \"defs.p4\"\nconst bit<8> x = 1;\nconst bit<8> y = 2;\n".to_string());
+	analyzer.reparse(main, None);
+
+	// the syntax tree of the files as they are now, parsed from scratch
+	let from_scratch = |analyzer: &Analyzer| {
+		let mut fresh = Analyzer::new(|_, path| Ok(path.into()), |_| {});
+		for path in ["defs.p4", "main.p4"] {
+			fresh.update(fresh.file_id(path), analyzer.input(analyzer.file_id(path)).unwrap().to_string());
+		}
+		fresh.parsed(fresh.file_id("main.p4")).unwrap().syntax().debug_tree()
+	};
+
+	// each range is in the contents left by the previous edit
+	analyzer.edit(main, &[(32..33, "xx".to_string()), (57..58, "20".to_string())]).unwrap();
+	assert_eq!(analyzer.input(main), Some("#include \"defs.p4\"\nconst bit<8> xx = 1;\nconst bit<8> y = 20;\n"));
+	analyzer.reparse(main, None);
+	assert!(analyzer.parse_statistics(main).unwrap().retained > 0);
+	assert_eq!(analyzer.parsed(main).unwrap().syntax().debug_tree(), from_scratch(&analyzer));
+
+	// an edit that changes how the rest of the file is lexed, of an included file, and of a directive
+	analyzer.edit(main, &[(40..40, "/* ".to_string())]).unwrap();
+	analyzer.reparse(main, None);
+	assert_eq!(analyzer.parsed(main).unwrap().syntax().debug_tree(), from_scratch(&analyzer));
+	analyzer.edit(defs, &[(13..14, "b".to_string())]).unwrap();
+	analyzer.reparse(defs, None);
+	assert_eq!(analyzer.parsed(main).unwrap().syntax().debug_tree(), from_scratch(&analyzer));
+	analyzer.edit(main, &[(10..14, "none".to_string())]).unwrap();
+	analyzer.reparse(main, None);
+	assert_eq!(analyzer.parsed(main).unwrap().syntax().debug_tree(), from_scratch(&analyzer));
+
+	assert_eq!(analyzer.edit(main, &[(0..1000, String::new())]), None);
+}
+
+#[test]
+fn name_resolution() {
+	let mut analyzer = Analyzer::new(|_, path| Ok(path.into()), |_| {});
+	let main = analyzer.file_id("main.p4");
+	let defs = analyzer.file_id("defs.p4");
+	analyzer.update(defs, "header h_t { bit<8> f; }\n".to_string());
+	analyzer.update(main, "#include \"defs.p4\"\nstruct s_t { h_t h; g_t g; }\nconst bit<8> h_t = 1;\n".to_string());
+
+	let header = analyzer.resolve(main, 33).unwrap();
+	assert_eq!((header.name.as_str(), header.kind), ("h_t", symbols::SymbolKind::Header));
+	let (file, location) = analyzer.parsed(main).unwrap().source_map.locate_range(header.name_range.clone()).unwrap();
+	assert_eq!((analyzer.path(file), location), ("defs.p4".to_string(), 7..10));
+
+	let diagnostics: Vec<_> = analyzer
+		.diagnostics(main)
+		.into_iter()
+		.map(|diagnostic| (analyzer.path(diagnostic.file), diagnostic.location, diagnostic.message))
+		.collect();
+	assert_eq!(
+		diagnostics,
+		vec![
+			("main.p4".to_string(), 39..42, "Undeclared name `g_t`".to_string()),
+			("main.p4".to_string(), 61..64, "Constant `h_t` is never used".to_string()),
+			("main.p4".to_string(), 61..64, "Duplicate declaration of `h_t`".to_string()),
+		]
+	);
+}
+
+#[test]
+fn type_checking() {
+	let mut analyzer = Analyzer::new(|_, path| Ok(path.into()), |_| {});
+	let main = analyzer.file_id("main.p4");
+	let defs = analyzer.file_id("defs.p4");
+	analyzer.update(defs, "header h_t { bit<8> f; }\n".to_string());
+	analyzer.update(main, "#include \"defs.p4\"\nconst bit<16> x = 1;\nconst bit<8> y = x;\n".to_string());
+
+	assert_eq!(analyzer.type_at(main, 37).map(|ty| ty.to_string()), Some("bit<16>".to_string()));
+	assert_eq!(analyzer.type_at(main, 53).map(|ty| ty.to_string()), Some("bit<8>".to_string()));
+	assert_eq!(analyzer.value_at(main, 37).map(|value| value.to_string()), Some("1".to_string()));
+	assert_eq!(analyzer.value_at(main, 53).map(|value| value.to_string()), Some("1".to_string()));
+
+	let diagnostics: Vec<_> = analyzer
+		.diagnostics(main)
+		.into_iter()
+		.map(|diagnostic| (analyzer.path(diagnostic.file), diagnostic.location, diagnostic.message))
+		.collect();
+	assert_eq!(
+		diagnostics,
+		vec![
+			("main.p4".to_string(), 53..54, "Constant `y` is never used".to_string()),
+			("main.p4".to_string(), 57..58, "Type mismatch: expected `bit<8>`, found `bit<16>`".to_string()),
+		]
+	);
+}
+
+#[test]
+fn architectures() {
+	let mut analyzer = Analyzer::new(|_, path| Ok(path.into()), |_| {});
+	let main = analyzer.file_id("main.p4");
+	let arch = analyzer.file_id("my_arch.p4");
+	analyzer.update(arch, "control C<H>(inout H hdr);\npackage Pkg<H>(C<H> c);\n".to_string());
+	analyzer.update(
+		main,
+		"#include \"my_arch.p4\"\ncontrol MyC(in bit<8> hdr) { apply {} }\nPkg(MyC()) main;\n".to_string(),
+	);
+
+	let target = analyzer.architecture(main).and_then(|architecture| architecture.target.clone());
+	assert_eq!(target.map(|target| target.name), Some("my_arch".to_string()));
+
+	let diagnostics: Vec<_> = analyzer
+		.diagnostics(main)
+		.into_iter()
+		.map(|diagnostic| (analyzer.path(diagnostic.file), diagnostic.location, diagnostic.message))
+		.collect();
+	assert_eq!(
+		diagnostics,
+		vec![("main.p4".to_string(), 34..47, "Parameter `hdr` must be `inout`, as in `C<H>`".to_string())]
+	);
+}
+
+#[test]
+fn quick_fixes() {
+	let mut analyzer = Analyzer::new(|_, path| Ok(path.into()), |_| {});
+	let main = analyzer.file_id("main.p4");
+	analyzer.update(
+		main,
+		"action a() {\n\tbit<8> y;\n\ty = y + 1;\n}\naction b(out bit<8> z) {\n\tz = z + 1;\n}\n".to_string(),
+	);
+
+	let fixes = |range| {
+		let fixes = analyzer.quick_fixes(main, range);
+		fixes.into_iter().map(|fix| (fix.title, fix.location, fix.text)).collect::<Vec<_>>()
+	};
+	assert_eq!(fixes(29..30), vec![("Initialize `y` to 0".to_string(), 22..22, " = 0".to_string())]);
+	assert_eq!(fixes(68..68), vec![("Initialize `z` to 0".to_string(), 64..64, "z = 0;\n\t".to_string())]);
+	assert_eq!(fixes(0..10), vec![]);
+}
+
+#[test]
+fn definitions() {
+	let mut analyzer = Analyzer::new(|_, path| Ok(path.into()), |_| {});
+	let main = analyzer.file_id("main.p4");
+	let defs = analyzer.file_id("defs.p4");
+	analyzer.update(defs, "extern void f(in bit<8> x);\nextern void f(in bit<8> x, in bit<8> y);\n".to_string());
+	analyzer.update(main, "#include \"defs.p4\"\ncontrol C() { apply { f(1, 2); } }\n".to_string());
+
+	// the overload taking two arguments
+	let (file, location) = analyzer.definition(main, 41).unwrap();
+	assert_eq!((analyzer.path(file), location), ("defs.p4".to_string(), 40..41));
+}
+
+#[test]
+fn parameter_directions() {
+	let mut analyzer = Analyzer::new(|_, path| Ok(path.into()), |_| {});
+	let main = analyzer.file_id("main.p4");
+	let defs = analyzer.file_id("defs.p4");
+	analyzer.update(defs, "extern void f(out bit<8> x);\n".to_string());
+	analyzer.update(main, "#include \"defs.p4\"\ncontrol C() { apply { f(1); } }\n".to_string());
+
+	let diagnostics: Vec<_> = analyzer.diagnostics(main);
+	assert_eq!(diagnostics.len(), 1);
+	assert_eq!(
+		(analyzer.path(diagnostics[0].file), diagnostics[0].location.clone(), diagnostics[0].message.as_str()),
+		("main.p4".to_string(), 43..44, "The argument of the `out` parameter `x` must be an l-value")
+	);
+
+	// the parameter declaration is in the included file
+	let related: Vec<_> = diagnostics[0]
+		.related
+		.iter()
+		.map(|related| (analyzer.path(related.file), related.location.clone(), related.message.as_str()))
+		.collect();
+	assert_eq!(related, vec![("defs.p4".to_string(), 25..26, "`x` is declared here")]);
+}
+
+#[test]
+fn case_coverage() {
+	let mut analyzer = Analyzer::new(|_, path| Ok(path.into()), |_| {});
+	let main = analyzer.file_id("main.p4");
+	analyzer
+		.update(main, "parser P(in bool b) { state start { transition select(b) { true: accept; } } }\n".to_string());
+
+	let diagnostics: Vec<_> = analyzer
+		.diagnostics(main)
+		.into_iter()
+		.map(|diagnostic| (diagnostic.location, diagnostic.message))
+		.collect();
+	assert_eq!(
+		diagnostics,
+		vec![(
+			47..74,
+			"The select has no default case, so values that match no case are rejected, e.g. `false`".to_string()
+		)]
+	);
+}
@@ -168,6 +168,65 @@ fn long_comment() {
 	);
 }
 
+#[test]
+fn operators() {
+	use Token::*;
+
+	assert_eq!(
+		lex_str("a&&&b..c|+|d|-|e<<f>>g!=h<=i>=j==k&&l||m++n"),
+		vec![
+			Identifier("a".into()),
+			TripleAmpersand,
+			Identifier("b".into()),
+			DoubleDot,
+			Identifier("c".into()),
+			SaturatingPlus,
+			Identifier("d".into()),
+			SaturatingMinus,
+			Identifier("e".into()),
+			ShiftLeft,
+			Identifier("f".into()),
+			CloseChevron,
+			CloseChevron,
+			Identifier("g".into()),
+			NotEquals,
+			Identifier("h".into()),
+			LessEquals,
+			Identifier("i".into()),
+			GreaterEquals,
+			Identifier("j".into()),
+			DoubleEquals,
+			Identifier("k".into()),
+			DoubleAmpersand,
+			Identifier("l".into()),
+			DoublePipe,
+			Identifier("m".into()),
+			DoublePlus,
+			Identifier("n".into()),
+		]
+	);
+
+	assert_eq!(lex_str("@!~&|^%?"), vec![At, Exclamation, Tilde, Ampersand, Pipe, Caret, Percent, Question]);
+}
+
+#[test]
+fn string_literals() {
+	use Token::*;
+
+	assert_eq!(
+		lex_str(r#"@name("ingress.\"t\"\n") "a\\b""#),
+		vec![
+			At,
+			Identifier("name".into()),
+			OpenParen,
+			StringLiteral("ingress.\"t\"\n".into()),
+			CloseParen,
+			Whitespace,
+			StringLiteral("a\\b".into()),
+		]
+	);
+}
+
 #[test]
 fn unknown_directive() {
 	use Token::*;
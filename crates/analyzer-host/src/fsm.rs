@@ -1,5 +1,6 @@
 use analyzer_abstractions::{fs::AnyEnumerableFileSystem, tracing::info};
 use async_rwlock::RwLock as AsyncRwLock;
+use cancellation::CancellationToken;
 use std::{
 	collections::HashMap,
 	sync::{Arc, RwLock},
@@ -39,13 +40,14 @@ impl LspProtocolMachine {
 		trace_value: Option<TraceValueAccessor>,
 		request_manager: RequestManager,
 		file_system: Arc<AnyEnumerableFileSystem>,
+		cancel_token: Arc<CancellationToken>,
 	) -> Self {
 		let dispatchers = RwLock::new(LspProtocolMachine::create_dispatchers());
 
 		Self {
 			dispatchers,
 			current_state: LSP_STARTING_STATE,
-			state: Arc::new(AsyncRwLock::new(State::new(trace_value, request_manager, file_system))),
+			state: Arc::new(AsyncRwLock::new(State::new(trace_value, request_manager, file_system, cancel_token))),
 		}
 	}
 
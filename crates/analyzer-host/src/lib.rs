@@ -116,7 +116,12 @@ impl AnalyzerHost {
 		cancel_token: Arc<CancellationToken>,
 	) -> Result<(), OperationCanceled> {
 		{ // Scope: for `protocol_machine`.
-			let mut protocol_machine = LspProtocolMachine::new(self.trace_value.clone(), request_manager, file_system);
+			let mut protocol_machine = LspProtocolMachine::new(
+				self.trace_value.clone(),
+				request_manager,
+				file_system,
+				cancel_token.clone(),
+			);
 
 			while protocol_machine.is_active() && !cancel_token.is_canceled() {
 				match requests_receiver.recv().await {
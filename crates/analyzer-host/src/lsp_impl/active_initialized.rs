@@ -1,6 +1,9 @@
-use analyzer_core::base_abstractions::FileId;
+use analyzer_core::{
+	base_abstractions::FileId,
+	symbols::{Namespace, SymbolKind},
+};
 use async_rwlock::RwLock as AsyncRwLock;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use analyzer_abstractions::{
 	lsp_types::{
@@ -8,11 +11,12 @@ use analyzer_abstractions::{
 			DidChangeTextDocument, DidChangeWatchedFiles, DidCloseTextDocument, DidOpenTextDocument,
 			DidSaveTextDocument, Exit, SetTrace,
 		},
-		request::{Completion, HoverRequest, Shutdown},
-		CompletionItem, CompletionItemKind, CompletionList, CompletionParams, CompletionResponse,
-		DidChangeTextDocumentParams, DidChangeWatchedFilesParams, DidCloseTextDocumentParams,
-		DidOpenTextDocumentParams, DidSaveTextDocumentParams, FileChangeType, Hover, HoverContents, HoverParams,
-		MarkupContent, MarkupKind, Position, SetTraceParams, Url,
+		request::{CodeActionRequest, Completion, GotoDefinition, HoverRequest, Shutdown},
+		CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams, CodeActionResponse, CompletionItem,
+		CompletionItemKind, CompletionList, CompletionParams, CompletionResponse, DidChangeTextDocumentParams,
+		DidChangeWatchedFilesParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams, DidSaveTextDocumentParams,
+		FileChangeType, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents, HoverParams, Location,
+		MarkupContent, MarkupKind, Position, SetTraceParams, TextEdit, Url, WorkspaceEdit,
 	},
 	tracing::{error, info},
 };
@@ -38,6 +42,8 @@ pub(crate) fn create_dispatcher() -> LspServerStateDispatcher {
 			})
 			.for_request::<HoverRequest, _>(on_text_document_hover)
 			.for_request::<Completion, _>(on_text_document_completion)
+			.for_request::<GotoDefinition, _>(on_text_document_definition)
+			.for_request::<CodeActionRequest, _>(on_text_document_code_action)
 			.for_notification::<DidChangeTextDocument, _>(on_text_document_did_change)
 			.for_notification::<DidCloseTextDocument, _>(on_text_document_did_close)
 			.for_notification::<DidOpenTextDocument, _>(on_text_document_did_open)
@@ -56,20 +62,43 @@ async fn on_shutdown(_: LspServerState, _: (), _: Arc<AsyncRwLock<State>>) -> Ha
 async fn on_text_document_hover(
 	_: LspServerState,
 	params: HoverParams,
-	_: Arc<AsyncRwLock<State>>,
+	state: Arc<AsyncRwLock<State>>,
 ) -> HandlerResult<Option<Hover>> {
-	let line = params.text_document_position_params.position.line;
-	let character = params.text_document_position_params.position.character;
-
-	let hover = Hover {
-		range: None,
-		contents: HoverContents::Markup(MarkupContent {
-			kind: MarkupKind::Markdown,
-			value: format!("Hovering over Ln *{}*, Col *{}*.", line, character),
-		}),
-	};
+	let state = state.read().await;
+	let uri = params.text_document_position_params.text_document.uri;
+	let file = state.workspaces().get_file(uri.clone());
 
-	Ok(Some(hover))
+	match file.get_parsed_unit().await {
+		Ok(file_id) => {
+			let analyzer = state.analyzer.unwrap();
+			let Some(input) = analyzer.input(file_id) else { return Ok(None) };
+
+			// show the specialized signature of a callee, or else the type of the expression or declaration under the
+			// cursor and its value if it is a constant
+			let offset = position_to_byte_offset(input, params.text_document_position_params.position);
+			let contents = match analyzer.signature_at(file_id, offset) {
+				Some(signature) => Some(format!("```p4\n{signature}\n```")),
+				None => analyzer.type_at(file_id, offset).map(|ty| {
+					let value = match analyzer.value_at(file_id, offset) {
+						Some(value) => format!("\n\nValue: `{value}`"),
+						None => String::new(),
+					};
+					format!("```p4\n{ty}\n```{value}")
+				}),
+			};
+			let hover = contents.map(|value| Hover {
+				range: None,
+				contents: HoverContents::Markup(MarkupContent { kind: MarkupKind::Markdown, value }),
+			});
+
+			Ok(hover)
+		}
+		Err(err) => {
+			error!(file_uri = uri.as_str(), "Could not query hover. Index error: {}", err);
+
+			Err(HandlerError::new("Could not query hover for document."))
+		}
+	}
 }
 
 async fn on_text_document_completion(
@@ -77,8 +106,6 @@ async fn on_text_document_completion(
 	params: CompletionParams,
 	state: Arc<AsyncRwLock<State>>,
 ) -> HandlerResult<Option<CompletionResponse>> {
-	use itertools::Itertools;
-
 	let state = state.read().await;
 	let uri = params.text_document_position.text_document.uri;
 	let file = state.workspaces().get_file(uri.clone());
@@ -87,30 +114,35 @@ async fn on_text_document_completion(
 		Ok(file_id) => {
 			let analyzer = state.analyzer.unwrap();
 
-			let (input, lexed) = match (analyzer.input(file_id), analyzer.preprocessed(file_id)) {
-				(Some(i), Some(l)) => (i, l),
+			let (input, parsed, symbols) = match (
+				analyzer.input(file_id),
+				analyzer.preprocessed(file_id),
+				analyzer.parsed(file_id),
+				analyzer.symbols(file_id),
+			) {
+				(Some(i), Some(_), Some(p), Some(s)) => (i, p, s),
 				_ => return Ok(Some(CompletionResponse::Array(vec![]))),
 			};
 
-			let items = lexed
-				.iter()
-				.flat_map(|(_, token, _)| match token {
-					analyzer_core::lexer::Token::Identifier(name) => Some(name),
-					_ => None,
-				})
-				.unique()
-				.map(|label| CompletionItem {
-					label: label.to_string(),
-					kind: Some(CompletionItemKind::FILE),
+			// offer the names that are in scope at the cursor
+			let offset = position_to_byte_offset(input, params.text_document_position.position);
+			let items = parsed
+				.source_map
+				.tree_offset(file_id, offset)
+				.map(|offset| symbols.visible_at(offset))
+				.unwrap_or_default()
+				.into_iter()
+				.map(|id| symbols.symbol(id))
+				.filter(|symbol| symbol.namespace != Namespace::Error)
+				.map(|symbol| CompletionItem {
+					label: symbol.name.clone(),
+					kind: Some(completion_item_kind(symbol.kind)),
 					..Default::default()
 				})
 				.collect();
 
 			let data = CompletionList { is_incomplete: false, items };
 
-			let shown_tokens =
-				lexed.iter().map(|(file_id, tk, span)| format!("{tk:?}: {file_id:?} {span:?}")).collect::<Vec<_>>();
-			info!("input: {:?}\n{:?}", input, shown_tokens);
 			info!("files: {:?}", analyzer.files());
 
 			Ok(Some(CompletionResponse::List(data)))
@@ -123,6 +155,82 @@ async fn on_text_document_completion(
 	}
 }
 
+async fn on_text_document_definition(
+	_: LspServerState,
+	params: GotoDefinitionParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<GotoDefinitionResponse>> {
+	let state = state.read().await;
+	let uri = params.text_document_position_params.text_document.uri;
+	let file = state.workspaces().get_file(uri.clone());
+
+	match file.get_parsed_unit().await {
+		Ok(file_id) => {
+			let analyzer = state.analyzer.unwrap();
+			let Some(input) = analyzer.input(file_id) else { return Ok(None) };
+
+			// the declaration of the name under the cursor, which is the chosen overload for the callee of a call
+			let offset = position_to_byte_offset(input, params.text_document_position_params.position);
+			let location = analyzer.definition(file_id, offset).and_then(|(file, range)| {
+				let uri = Url::parse(&analyzer.path(file)).ok()?;
+				Some(Location { uri, range: byte_range_to_lsp_range(analyzer.input(file)?, range) })
+			});
+
+			Ok(location.map(GotoDefinitionResponse::Scalar))
+		}
+		Err(err) => {
+			error!(file_uri = uri.as_str(), "Could not query definition. Index error: {}", err);
+
+			Err(HandlerError::new("Could not query definition for document."))
+		}
+	}
+}
+
+async fn on_text_document_code_action(
+	_: LspServerState,
+	params: CodeActionParams,
+	state: Arc<AsyncRwLock<State>>,
+) -> HandlerResult<Option<CodeActionResponse>> {
+	let state = state.read().await;
+	let uri = params.text_document.uri;
+	let file = state.workspaces().get_file(uri.clone());
+
+	match file.get_parsed_unit().await {
+		Ok(file_id) => {
+			let analyzer = state.analyzer.unwrap();
+			let Some(input) = analyzer.input(file_id) else { return Ok(None) };
+
+			// offer the quick fixes for the diagnostics in the range
+			let range = lsp_range_to_byte_range(input, params.range);
+			let actions = analyzer
+				.quick_fixes(file_id, range)
+				.into_iter()
+				.filter_map(|fix| {
+					let uri = Url::parse(&analyzer.path(fix.file)).ok()?;
+					let range = byte_range_to_lsp_range(analyzer.input(fix.file)?, fix.location);
+					let edit = TextEdit { range, new_text: fix.text };
+					Some(CodeActionOrCommand::CodeAction(CodeAction {
+						title: fix.title,
+						kind: Some(CodeActionKind::QUICKFIX),
+						edit: Some(WorkspaceEdit {
+							changes: Some(HashMap::from([(uri, vec![edit])])),
+							..Default::default()
+						}),
+						..Default::default()
+					}))
+				})
+				.collect();
+
+			Ok(Some(actions))
+		}
+		Err(err) => {
+			error!(file_uri = uri.as_str(), "Could not query code actions. Index error: {}", err);
+
+			Err(HandlerError::new("Could not query code actions for document."))
+		}
+	}
+}
+
 async fn on_text_document_did_open(
 	_: LspServerState,
 	params: DidOpenTextDocumentParams,
@@ -135,6 +243,7 @@ async fn on_text_document_did_open(
 	let file_id = analyzer.file_id(params.text_document.uri.as_str());
 
 	analyzer.update(file_id, params.text_document.text);
+	state.analyzer.reparse_in_background(file_id, |_| {});
 
 	file.open_or_update(file_id);
 
@@ -160,28 +269,52 @@ async fn on_text_document_did_change(
 		}
 	};
 
+	// the byte ranges of the changes, unless one of them replaces the whole document
+	let mut edits = Some(vec![]);
 	for change in params.content_changes {
 		let analyzer_abstractions::lsp_types::TextDocumentContentChangeEvent { range, range_length: _, text } = change;
 		if let Some(range) = range {
 			let range = lsp_range_to_byte_range(&input, range);
 			info!("replacing range {:?} of {:?} with {:?}", range, &input[range.clone()], text);
-			input.replace_range(range, &text);
+			input.replace_range(range.clone(), &text);
+			if let Some(edits) = &mut edits {
+				edits.push((range, text));
+			}
 		} else {
 			input = text;
+			edits = None;
 		}
 	}
 
-	// TODO: avoid cloning
-	analyzer.update(file_id, input.clone());
+	// the analyzer keeps its contents if a range doesn't fit them, so hand it the new ones in full instead
+	if edits.and_then(|edits| analyzer.edit(file_id, &edits)).is_none() {
+		// TODO: avoid cloning
+		analyzer.update(file_id, input.clone());
+	}
 	file.open_or_update(file_id);
-	let diagnostics = process_diagnostics(&analyzer, file_id, &input);
-
-	// TODO: report diagnostics
-	// Ok(Some(PublishDiagnosticsParams {
-	// 	uri: params.text_document.uri,
-	// 	diagnostics,
-	// 	version: None,
-	// }))
+
+	let uri = uri.to_string();
+	state.analyzer.reparse_in_background(file_id, move |analyzer| {
+		if let Some(stats) = analyzer.parse_statistics(file_id) {
+			info!(
+				file_uri = uri.as_str(),
+				"Reparsed: {} memo table entries invalidated, {} retained, {} rule applications reused, {} evaluated.",
+				stats.invalidated,
+				stats.retained,
+				stats.reused,
+				stats.evaluated
+			);
+		}
+		let diagnostics = process_diagnostics(analyzer, file_id, analyzer.input(file_id).unwrap_or_default());
+
+		// TODO: report diagnostics
+		// Ok(Some(PublishDiagnosticsParams {
+		// 	uri: params.text_document.uri,
+		// 	diagnostics,
+		// 	version: None,
+		// }))
+	});
+
 	Ok(())
 }
 
@@ -215,6 +348,7 @@ async fn on_text_document_did_save(
 		let diagnostics = process_diagnostics(&analyzer, file_id, &text);
 		// TODO: report diagnostics, and process *after* the update below!
 		analyzer.update(file_id, text);
+		state.analyzer.reparse_in_background(file_id, |_| {});
 		file.open_or_update(file_id);
 	}
 
@@ -294,9 +428,21 @@ fn process_diagnostics(
 	diagnostics
 		.into_iter()
 		.map(|d| {
-			use analyzer_abstractions::lsp_types::{Diagnostic, DiagnosticSeverity};
+			use analyzer_abstractions::lsp_types::{
+				Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DiagnosticTag, Location, Url,
+			};
 			use analyzer_core::base_abstractions::Severity;
 
+			let related: Vec<_> = d
+				.related
+				.into_iter()
+				.filter_map(|related| {
+					let uri = Url::parse(&analyzer.path(related.file)).ok()?;
+					let range = byte_range_to_lsp_range(analyzer.input(related.file)?, related.location);
+					Some(DiagnosticRelatedInformation { location: Location { uri, range }, message: related.message })
+				})
+				.collect();
+
 			Diagnostic {
 				range: byte_range_to_lsp_range(input, d.location),
 				severity: Some(match d.severity {
@@ -306,12 +452,39 @@ fn process_diagnostics(
 					Severity::Error => DiagnosticSeverity::ERROR,
 				}),
 				message: d.message,
+				related_information: if related.is_empty() { None } else { Some(related) },
+				tags: if d.unnecessary { Some(vec![DiagnosticTag::UNNECESSARY]) } else { None },
 				..Default::default()
 			}
 		})
 		.collect()
 }
 
+fn completion_item_kind(kind: SymbolKind) -> CompletionItemKind {
+	match kind {
+		SymbolKind::Constant => CompletionItemKind::CONSTANT,
+		SymbolKind::Variable | SymbolKind::Parameter | SymbolKind::Instance | SymbolKind::ValueSet => {
+			CompletionItemKind::VARIABLE
+		}
+		SymbolKind::TypeParameter => CompletionItemKind::TYPE_PARAMETER,
+		SymbolKind::Extern | SymbolKind::Typedef | SymbolKind::NewType => CompletionItemKind::CLASS,
+		SymbolKind::ExternFunction | SymbolKind::Action | SymbolKind::Function => CompletionItemKind::FUNCTION,
+		SymbolKind::Method => CompletionItemKind::METHOD,
+		SymbolKind::Table => CompletionItemKind::VALUE,
+		SymbolKind::Parser
+		| SymbolKind::ParserType
+		| SymbolKind::Control
+		| SymbolKind::ControlType
+		| SymbolKind::Package => CompletionItemKind::MODULE,
+		SymbolKind::Header | SymbolKind::HeaderUnion | SymbolKind::Struct => CompletionItemKind::STRUCT,
+		SymbolKind::Enum => CompletionItemKind::ENUM,
+		SymbolKind::Field => CompletionItemKind::FIELD,
+		SymbolKind::State | SymbolKind::EnumMember | SymbolKind::ErrorMember | SymbolKind::MatchKind => {
+			CompletionItemKind::ENUM_MEMBER
+		}
+	}
+}
+
 fn lsp_range_to_byte_range(input: &str, range: analyzer_abstractions::lsp_types::Range) -> std::ops::Range<usize> {
 	let start = position_to_byte_offset(input, range.start);
 	let end = position_to_byte_offset(input, range.end);
@@ -2,11 +2,11 @@ use async_rwlock::RwLock as AsyncRwLock;
 use std::sync::Arc;
 
 use analyzer_abstractions::lsp_types::{
-	notification::Exit, request::Initialize, CompletionOptions, DeclarationCapability, HoverProviderCapability,
-	ImplementationProviderCapability, InitializeParams, InitializeResult, OneOf, SaveOptions, ServerCapabilities,
-	ServerInfo, SignatureHelpOptions, TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
-	TextDocumentSyncSaveOptions, TypeDefinitionProviderCapability, WindowClientCapabilities, WorkDoneProgressOptions,
-	WorkspaceFolder, WorkspaceFoldersServerCapabilities, WorkspaceServerCapabilities,
+	notification::Exit, request::Initialize, CodeActionProviderCapability, CompletionOptions, DeclarationCapability,
+	HoverProviderCapability, ImplementationProviderCapability, InitializeParams, InitializeResult, OneOf, SaveOptions,
+	ServerCapabilities, ServerInfo, SignatureHelpOptions, TextDocumentSyncCapability, TextDocumentSyncKind,
+	TextDocumentSyncOptions, TextDocumentSyncSaveOptions, TypeDefinitionProviderCapability, WindowClientCapabilities,
+	WorkDoneProgressOptions, WorkspaceFolder, WorkspaceFoldersServerCapabilities, WorkspaceServerCapabilities,
 };
 
 use crate::{
@@ -140,6 +140,7 @@ fn create_initialize_result(include_workspace_folders: bool) -> InitializeResult
 			type_definition_provider: Some(TypeDefinitionProviderCapability::Simple(true)),
 			implementation_provider: Some(ImplementationProviderCapability::Simple(true)),
 			references_provider: Some(OneOf::Left(true)),
+			code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
 			..Default::default()
 		},
 		server_info: Some(ServerInfo { name: String::from("P4 Analyzer"), version: Some(String::from("0.0.0")) }),
@@ -1,6 +1,7 @@
 use std::{
 	cell::{RefCell, RefMut},
-	sync::Arc,
+	collections::HashMap,
+	sync::{Arc, Mutex},
 };
 
 use analyzer_abstractions::{
@@ -12,6 +13,7 @@ use analyzer_abstractions::{
 };
 use analyzer_core::base_abstractions::FileId;
 use async_channel::{Receiver, Sender};
+use cancellation::{CancellationToken, CancellationTokenSource};
 use itertools::Itertools;
 
 use crate::{
@@ -28,13 +30,17 @@ use crate::{
 pub(crate) struct AnalyzerWrapper {
 	inner: RefCell<analyzer_core::Analyzer>,
 	background_queue: BackgroundQueue,
+	/// The host's cancellation token, which also cancels the queued reparses.
+	cancel_token: Arc<CancellationToken>,
+	/// The source of the cancellation token of the latest queued reparse of each file.
+	reparses: Mutex<HashMap<FileId, Arc<CancellationTokenSource>>>,
 }
 
 unsafe impl Sync for AnalyzerWrapper {}
 unsafe impl Send for AnalyzerWrapper {}
 
 impl AnalyzerWrapper {
-	pub fn new(background_channel: Sender<Url>) -> Self {
+	pub fn new(background_channel: Sender<Url>, cancel_token: Arc<CancellationToken>) -> Self {
 		let background_queue = BackgroundQueue(background_channel.clone());
 		let resolve_path = |absolute_base_url: &str, path: &str| {
 			if let Ok(absolute_target_url) = Url::parse(path) {
@@ -53,10 +59,61 @@ impl AnalyzerWrapper {
 		Self {
 			inner: RefCell::new(analyzer_core::Analyzer::new(resolve_path, require)),
 			background_queue: BackgroundQueue(background_channel.clone()),
+			cancel_token,
+			reparses: Mutex::new(HashMap::new()),
 		}
 	}
 
 	pub fn unwrap(&self) -> RefMut<analyzer_core::Analyzer> { self.inner.borrow_mut() }
+
+	/// Queues a reparse of a file that was changed in the IDE as background work, which then hands the analyzer to
+	/// `on_reparsed`.
+	///
+	/// Queuing the reparse cancels the one queued for an earlier change of the file, if that has not run yet. Cancelling
+	/// the host's token cancels the reparse too, even while it runs.
+	pub fn reparse_in_background<F>(self: &Arc<Self>, file_id: FileId, on_reparsed: F)
+	where
+		F: FnOnce(&analyzer_core::Analyzer) + Send + Sync + 'static,
+	{
+		let source = Arc::new(CancellationTokenSource::new());
+		if let Some(previous) = self.reparses.lock().unwrap().insert(file_id, source.clone()) {
+			previous.cancel();
+		}
+
+		self.clone().spawn_reparse(file_id, source, on_reparsed);
+	}
+
+	fn spawn_reparse<F>(self: Arc<Self>, file_id: FileId, source: Arc<CancellationTokenSource>, on_reparsed: F)
+	where
+		F: FnOnce(&analyzer_core::Analyzer) + Send + Sync + 'static,
+	{
+		AsyncPool::spawn_work(async move {
+			let cancel_token = source.token().clone();
+
+			// A newer change of the file has already queued a reparse of its own.
+			if cancel_token.is_canceled() {
+				return;
+			}
+
+			// The analyzer may still be borrowed by a request that is awaiting something, in which case the reparse
+			// is queued again behind the work that is already waiting.
+			let Ok(mut analyzer) = self.inner.try_borrow_mut() else {
+				self.clone().spawn_reparse(file_id, source, on_reparsed);
+				return;
+			};
+			let completed =
+				self.cancel_token.run(|| source.cancel(), || analyzer.reparse(file_id, Some(cancel_token)));
+
+			let mut reparses = self.reparses.lock().unwrap();
+			if reparses.get(&file_id).map_or(false, |latest| Arc::ptr_eq(latest, &source)) {
+				reparses.remove(&file_id);
+			}
+
+			if completed {
+				on_reparsed(&analyzer);
+			}
+		});
+	}
 }
 
 impl BackgroundLoad for AnalyzerWrapper {
@@ -94,13 +151,14 @@ impl State {
 		trace_value: Option<TraceValueAccessor>,
 		request_manager: RequestManager,
 		file_system: Arc<AnyEnumerableFileSystem>,
+		cancel_token: Arc<CancellationToken>,
 	) -> Self {
 		let background_parse_channel = async_channel::unbounded::<Url>();
 		let (sender, _) = background_parse_channel.clone();
 
 		Self {
 			trace_value,
-			analyzer: Arc::new(AnalyzerWrapper::new(sender)),
+			analyzer: Arc::new(AnalyzerWrapper::new(sender, cancel_token)),
 			file_system,
 			request_manager,
 			progress_manager: None,
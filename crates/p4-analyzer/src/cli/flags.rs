@@ -18,6 +18,28 @@ xflags::xflags! {
 			/// Use the 'stdio' transport (default).
 			optional --stdio
 		}
+
+		/// Parses a P4 file and reports the grammar rules that were tried where parsing failed.
+		cmd debug-parse {
+			/// The P4 file to parse.
+			required path: PathBuf
+
+			/// Print the full trace of rule attempts as JSON.
+			optional --json
+
+			/// Print the full trace of rule attempts as an indented tree.
+			optional --tree
+		}
+
+		/// Reports the unused declarations and unreachable code of a P4 program.
+		cmd dead-code {
+			/// The P4 file to analyze.
+			required path: PathBuf
+
+			/// A folder to search for the included files that are not next to the including file, e.g. of the
+			/// architecture. The dead code of these files is not reported.
+			repeated -I, --include dir: PathBuf
+		}
 	}
 }
 // generated start
@@ -33,6 +55,8 @@ pub struct P4Analyzer {
 #[derive(Debug)]
 pub enum P4AnalyzerCmd {
 	Server(Server),
+	DebugParse(DebugParse),
+	DeadCode(DeadCode),
 }
 
 #[derive(Debug)]
@@ -40,6 +64,21 @@ pub struct Server {
 	pub stdio: bool,
 }
 
+#[derive(Debug)]
+pub struct DebugParse {
+	pub path: PathBuf,
+
+	pub json: bool,
+	pub tree: bool,
+}
+
+#[derive(Debug)]
+pub struct DeadCode {
+	pub path: PathBuf,
+
+	pub include: Vec<PathBuf>,
+}
+
 impl P4Analyzer {
 	#[allow(dead_code)]
 	pub fn from_env_or_exit() -> Self { Self::from_env_or_exit_() }
@@ -5,6 +5,8 @@ use analyzer_host::tracing::tracing_subscriber::{registry::LookupSpan, Layer};
 use cancellation::CancellationToken;
 use thiserror::Error;
 
+pub(crate) mod dead_code;
+pub(crate) mod debug_parse;
 pub(crate) mod lsp_server;
 
 /// Defines a command invocation error.
@@ -14,6 +16,10 @@ pub enum CommandInvocationError {
 	#[error("The command was cancelled.")]
 	Cancelled,
 
+	/// The input to the command could not be processed.
+	#[error("{0}")]
+	InvalidInput(String),
+
 	/// An unexpected error.
 	#[error("An unexpected error occurred executing the command.")]
 	Unknown,
@@ -37,3 +43,11 @@ pub(crate) trait Command {
 		vec![] // Return an empty vector by default.
 	}
 }
+
+/// Converts a byte offset into a 1-based line and column.
+pub(crate) fn line_column(text: &str, offset: usize) -> (usize, usize) {
+	let preceding = &text[..offset.min(text.len())];
+	let line_start = preceding.rfind('\n').map(|i| i + 1).unwrap_or(0);
+
+	(preceding.matches('\n').count() + 1, preceding[line_start..].chars().count() + 1)
+}
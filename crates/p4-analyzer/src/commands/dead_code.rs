@@ -0,0 +1,93 @@
+use crate::{cli::flags::DeadCode, commands::line_column, Command, CommandInvocationError};
+use analyzer_abstractions::async_trait::async_trait;
+use analyzer_core::Analyzer;
+use cancellation::CancellationToken;
+use std::{collections::HashSet, fs, path::Path, sync::Arc};
+
+/// A P4 Analyzer command that reports the unused declarations and unreachable code of a program, e.g. to clean up a
+/// large legacy program.
+///
+/// Included files are read next to the including file, or else from the configured include folders. The dead code of
+/// the files found in the include folders, e.g. of the architecture, is not reported.
+pub struct DeadCodeCommand {
+	config: DeadCode,
+}
+
+impl DeadCodeCommand {
+	/// Initializes a new [`DeadCodeCommand`] instance.
+	pub fn new(config: DeadCode) -> Self { DeadCodeCommand { config } }
+}
+
+#[async_trait]
+impl Command for DeadCodeCommand {
+	/// Runs the command by analyzing the configured file and printing its dead code to `stdout`.
+	async fn run(&self, cancel_token: Arc<CancellationToken>) -> Result<(), CommandInvocationError> {
+		let path = self.config.path.to_string_lossy().to_string();
+		let contents = fs::read_to_string(&self.config.path)
+			.map_err(|err| CommandInvocationError::InvalidInput(format!("Unable to read '{path}': {err}")))?;
+
+		let include_dirs = self.config.include.clone();
+		let mut analyzer = Analyzer::new(
+			move |base, path| {
+				let dir = Path::new(base).parent().unwrap_or_else(|| Path::new(""));
+				let found = include_dirs.iter().map(|include_dir| include_dir.join(path)).find(|path| path.is_file());
+				let resolved =
+					if dir.join(path).is_file() { dir.join(path) } else { found.unwrap_or_else(|| dir.join(path)) };
+				Ok(resolved.to_string_lossy().to_string())
+			},
+			|_| {},
+		);
+		let file_id = analyzer.file_id(&path);
+		analyzer.update(file_id, contents);
+
+		// read the included files, and then the files they include
+		let mut read = HashSet::new();
+		loop {
+			if cancel_token.is_canceled() {
+				return Err(CommandInvocationError::Cancelled);
+			}
+			let unresolved: Vec<_> = analyzer
+				.include_dependencies(file_id)
+				.into_iter()
+				.filter(|dependency| !dependency.is_resolved && read.insert(dependency.file_id))
+				.collect();
+			if unresolved.is_empty() {
+				break;
+			}
+			for dependency in unresolved {
+				let include = analyzer.path(dependency.file_id);
+				let contents = fs::read_to_string(&include).map_err(|err| {
+					CommandInvocationError::InvalidInput(format!("Unable to read '{include}': {err}"))
+				})?;
+				analyzer.update(dependency.file_id, contents);
+			}
+		}
+
+		let unit = analyzer
+			.parsed(file_id)
+			.ok_or_else(|| CommandInvocationError::InvalidInput(format!("Unable to preprocess '{path}'.")))?;
+		let found: Vec<_> = analyzer
+			.unused(file_id)
+			.unwrap_or_default()
+			.iter()
+			.filter_map(|unused| {
+				let (file, location) = unit.source_map.locate_range(unused.range.clone())?;
+				Some((file, location.start, &unused.message))
+			})
+			.filter(|(file, ..)| {
+				!self.config.include.iter().any(|dir| Path::new(&analyzer.path(*file)).starts_with(dir))
+			})
+			.collect();
+
+		for (file, offset, message) in &found {
+			let (line, column) = line_column(analyzer.input(*file).unwrap_or_default(), *offset);
+			println!("{}:{line}:{column}: {message}", analyzer.path(*file));
+		}
+		match found.len() {
+			0 => println!("{path}: no dead code found."),
+			count => println!("{path}: found {count} unused declarations or unreachable code."),
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,98 @@
+use crate::{cli::flags::DebugParse, commands::line_column, Command, CommandInvocationError};
+use analyzer_abstractions::async_trait::async_trait;
+use analyzer_core::{
+	lexer::Token,
+	p4_grammar::is_trivia,
+	syntax::{SyntaxNode, ERROR},
+	Analyzer,
+};
+use cancellation::CancellationToken;
+use std::{fs, ops::Range, path::Path, sync::Arc};
+
+/// A P4 Analyzer command that parses a single file with tracing enabled, to help with writing and maintaining the
+/// P4 grammar.
+///
+/// By default, only the rules that were tried at the position where parsing first failed are printed. The full trace
+/// of rule attempts can be printed instead as JSON or as an indented tree.
+pub struct DebugParseCommand {
+	config: DebugParse,
+}
+
+impl DebugParseCommand {
+	/// Initializes a new [`DebugParseCommand`] instance.
+	pub fn new(config: DebugParse) -> Self { DebugParseCommand { config } }
+}
+
+#[async_trait]
+impl Command for DebugParseCommand {
+	/// Runs the command by parsing the configured file and printing the trace to `stdout`.
+	async fn run(&self, _cancel_token: Arc<CancellationToken>) -> Result<(), CommandInvocationError> {
+		let path = self.config.path.to_string_lossy().to_string();
+		let contents = fs::read_to_string(&self.config.path)
+			.map_err(|err| CommandInvocationError::InvalidInput(format!("Unable to read '{path}': {err}")))?;
+
+		// Included files are resolved relative to the including file, but are not read.
+		let mut analyzer = Analyzer::new(
+			|base, path| {
+				let dir = Path::new(base).parent().unwrap_or_else(|| Path::new(""));
+				Ok(dir.join(path).to_string_lossy().to_string())
+			},
+			|_| {},
+		);
+		let file_id = analyzer.file_id(&path);
+		analyzer.update(file_id, contents);
+
+		let trace = analyzer
+			.parse_trace(file_id)
+			.ok_or_else(|| CommandInvocationError::InvalidInput(format!("Unable to preprocess '{path}'.")))?;
+
+		if self.config.json {
+			println!("{}", trace.to_json());
+			return Ok(());
+		}
+
+		if self.config.tree {
+			print!("{trace}");
+		}
+
+		let tokens: Vec<_> = analyzer
+			.preprocessed(file_id)
+			.map(|preprocessed| preprocessed.iter().filter(|(_, tk, _)| !is_trivia(tk)).collect())
+			.unwrap_or_default();
+
+		// The grammar recovers from input it can't match by skipping it as an `error` node, so the match itself always
+		// succeeds. The rules that failed are found at the start of the first of these nodes instead, and usually
+		// stopped matching within it or at the token that ended it.
+		let Some(error) = analyzer.parsed(file_id).and_then(|unit| first_error(&unit.syntax())) else {
+			println!("{path}: parsed successfully.");
+			return Ok(());
+		};
+
+		let pos = trace.furthest_failure_in(error.start..=error.end, ERROR).unwrap_or(error.start);
+		match tokens.get(pos) {
+			Some((token_file_id, token, span)) => {
+				let token_path = analyzer.path(*token_file_id);
+				let (line, column) = line_column(analyzer.input(*token_file_id).unwrap_or_default(), span.start);
+				println!("{token_path}:{line}:{column}: parsing failed at {token:?}.");
+			}
+			None => println!("{path}: parsing failed at the end of the input."),
+		}
+
+		println!("Rules tried at this position:");
+		for node in trace.attempts_at(pos) {
+			let outcome = if node.succeeded() { "matched" } else { "failed" };
+			let memoized = if node.memoized { " (memo)" } else { "" };
+			println!("  {} {outcome}{memoized}", node.rule);
+		}
+
+		Ok(())
+	}
+}
+
+/// The positions of the non-trivia tokens of the first `error` node of a syntax tree, if any.
+fn first_error(root: &SyntaxNode<Token>) -> Option<Range<usize>> {
+	let error = root.descendants().find(|node| node.kind() == ERROR)?;
+	let start = error.text_range().start;
+	let before = root.tokens().filter(|tk| !is_trivia(tk.kind()) && tk.text_range().start < start).count();
+	Some(before..before + error.tokens().filter(|tk| !is_trivia(tk.kind())).count())
+}
@@ -11,7 +11,10 @@ use analyzer_host::tracing::tracing_subscriber::{
 };
 use cancellation::CancellationTokenSource;
 use cli::flags::{P4Analyzer, P4AnalyzerCmd};
-use commands::{lsp_server::LspServerCommand, Command, CommandInvocationError};
+use commands::{
+	dead_code::DeadCodeCommand, debug_parse::DebugParseCommand, lsp_server::LspServerCommand, Command,
+	CommandInvocationError,
+};
 use driver::DriverType;
 use std::{
 	env::current_exe,
@@ -32,20 +35,19 @@ pub async fn main() {
 	match P4Analyzer::from_env() {
 		Ok(cmd) => {
 			let default_logging_layer = create_default_logging_layer::<Registry>(&cmd);
-			let mut layers = if let Some((layer, _)) = default_logging_layer { vec![layer] } else { vec![] };
-
-			let cmd = match cmd.subcommand {
-				P4AnalyzerCmd::Server(config) => RunnableCommand(LspServerCommand::new(config, DriverType::Console)),
-				_ => unreachable!(),
+			let layers = if let Some((layer, _)) = default_logging_layer { vec![layer] } else { vec![] };
+
+			match cmd.subcommand {
+				P4AnalyzerCmd::Server(config) => {
+					RunnableCommand(LspServerCommand::new(config, DriverType::Console)).run_with_logging(layers).await
+				}
+				P4AnalyzerCmd::DebugParse(config) => {
+					RunnableCommand(DebugParseCommand::new(config)).run_with_logging(layers).await
+				}
+				P4AnalyzerCmd::DeadCode(config) => {
+					RunnableCommand(DeadCodeCommand::new(config)).run_with_logging(layers).await
+				}
 			};
-
-			layers.append(&mut cmd.logging_layers());
-
-			let subscriber = Registry::default().with(layers);
-
-			subscriber::set_global_default(subscriber).expect("Unable to set global tracing subscriber.");
-
-			cmd.run().await;
 		}
 		Err(err) => {
 			println!();
@@ -132,6 +134,18 @@ impl<C: Command> RunnableCommand<C> {
 		};
 	}
 
+	/// Installs the given logging layers, together with those of the underlying command, as the global '`Tracing`'
+	/// subscriber and then executes the adapted command.
+	async fn run_with_logging(&self, mut layers: Vec<Box<dyn Layer<Registry> + Send + Sync + 'static>>) {
+		layers.append(&mut self.logging_layers());
+
+		let subscriber = Registry::default().with(layers);
+
+		subscriber::set_global_default(subscriber).expect("Unable to set global tracing subscriber.");
+
+		self.run().await;
+	}
+
 	/// Retrieves any additional logging layers that have been configured by the underlying command.
 	fn logging_layers(&self) -> Vec<Box<dyn Layer<Registry> + Send + Sync + 'static>> {
 		let Self(cmd) = self;
@@ -159,3 +159,70 @@ mod lsp_server_tests {
 		assert!(res.is_err());
 	}
 }
+
+mod debug_parse_tests {
+	use cancellation::CancellationTokenSource;
+
+	use crate::{
+		cli::flags::{DebugParse, P4Analyzer, P4AnalyzerCmd},
+		commands::{debug_parse::DebugParseCommand, Command, CommandInvocationError},
+	};
+
+	#[test]
+	fn parses_flags() {
+		let cmd = P4Analyzer::from_vec(vec!["debug-parse".into(), "main.p4".into(), "--json".into()]).unwrap();
+		match cmd.subcommand {
+			P4AnalyzerCmd::DebugParse(config) => {
+				assert_eq!(config.path.to_str(), Some("main.p4"));
+				assert!(config.json);
+				assert!(!config.tree);
+			}
+			_ => unreachable!(),
+		}
+	}
+
+	#[tokio::test]
+	async fn command_fails_for_missing_file() {
+		let cmd = DebugParseCommand::new(DebugParse { path: "missing.p4".into(), json: false, tree: false });
+		let token = CancellationTokenSource::new();
+		let res = cmd.run(token.token().clone()).await;
+		assert!(matches!(res, Err(CommandInvocationError::InvalidInput(_))));
+	}
+}
+
+mod dead_code_tests {
+	use cancellation::CancellationTokenSource;
+
+	use crate::{
+		cli::flags::{DeadCode, P4Analyzer, P4AnalyzerCmd},
+		commands::{dead_code::DeadCodeCommand, Command, CommandInvocationError},
+	};
+
+	#[test]
+	fn parses_flags() {
+		let args = vec![
+			"dead-code".into(),
+			"main.p4".into(),
+			"-I".into(),
+			"p4include".into(),
+			"--include".into(),
+			"arch".into(),
+		];
+		let cmd = P4Analyzer::from_vec(args).unwrap();
+		match cmd.subcommand {
+			P4AnalyzerCmd::DeadCode(config) => {
+				assert_eq!(config.path.to_str(), Some("main.p4"));
+				assert_eq!(config.include, vec![std::path::PathBuf::from("p4include"), "arch".into()]);
+			}
+			_ => unreachable!(),
+		}
+	}
+
+	#[tokio::test]
+	async fn command_fails_for_missing_file() {
+		let cmd = DeadCodeCommand::new(DeadCode { path: "missing.p4".into(), include: vec![] });
+		let token = CancellationTokenSource::new();
+		let res = cmd.run(token.token().clone()).await;
+		assert!(matches!(res, Err(CommandInvocationError::InvalidInput(_))));
+	}
+}